@@ -0,0 +1,13 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use mclient_project::packet;
+
+// The first byte turns compression on or off, the rest is the frame
+fuzz_target!(|input: &[u8]| {
+    let Some((compression, frame)) = input.split_first() else {
+        return;
+    };
+    let threshold: i32 = if compression & 1 == 1 { 256 } else { -1 };
+    let _ = packet::decode_frame(frame, threshold);
+});
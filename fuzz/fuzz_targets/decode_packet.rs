@@ -0,0 +1,30 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use mclient_project::packet::{ClientboundPacket, ConfigurationPacket, LoginPacket};
+use mclient_project::protocol;
+
+// A protocol of every packet table
+const PROTOCOLS: [i32; 7] = [340, 754, 758, 763, 764, 765, 766];
+
+// The first byte picks the protocol and the state, the second is the packet ID and the
+// rest its data
+fuzz_target!(|input: &[u8]| {
+    let [selector, id, data @ ..] = input else {
+        return;
+    };
+    let protocol: i32 = PROTOCOLS[*selector as usize % PROTOCOLS.len()];
+    let id: i32 = i32::from(*id);
+    match *selector as usize / PROTOCOLS.len() % 3 {
+        0 => {
+            let _ = LoginPacket::decode(protocol, id, data);
+        }
+        1 => {
+            let _ = ConfigurationPacket::decode(protocol, id, data);
+        }
+        _ => {
+            let table = protocol::table_for(protocol).expect("every protocol has a table");
+            let _ = ClientboundPacket::decode(table, id, data);
+        }
+    }
+});
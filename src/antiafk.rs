@@ -0,0 +1,41 @@
+use std::time::Duration;
+
+// AFK plugins usually only look at rotation, swinging or sneaking, not at the
+// keep alives and position packets that are sent anyway.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AfkAction {
+    SwingArm,
+    /// Turns the head a few degrees, back and forth.
+    LookAround,
+    /// Sneaks for a moment.
+    Sneak,
+}
+
+impl AfkAction {
+    pub fn parse(name: &str) -> Option<AfkAction> {
+        match name {
+            "swing" => Some(AfkAction::SwingArm),
+            "look" => Some(AfkAction::LookAround),
+            "sneak" => Some(AfkAction::Sneak),
+            _ => None,
+        }
+    }
+}
+
+/// Keeps AFK-kick plugins from removing the client by doing something harmless every
+/// `interval`. The actions take turns.
+#[derive(Clone, Debug)]
+pub struct AntiAfk {
+    pub interval: Duration,
+    pub actions: Vec<AfkAction>,
+}
+
+impl Default for AntiAfk {
+    fn default() -> Self {
+        AntiAfk {
+            interval: Duration::from_secs(60),
+            actions: vec![AfkAction::SwingArm, AfkAction::LookAround, AfkAction::Sneak],
+        }
+    }
+}
@@ -0,0 +1,599 @@
+use std::collections::VecDeque;
+use std::error::Error;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use mclient_project::mojang::format_uuid;
+use mclient_project::{ClientError, Event, Latency};
+use serde_json::{Value, json};
+
+use crate::bots::{Bot, Bots};
+use crate::filter;
+use crate::lang::Language;
+use crate::ndjson;
+use crate::websocket::{self, Message as WebSocketMessage};
+
+// A small HTTP server (--api-listen) for dashboards and scripts. Everything is JSON:
+//
+//   POST /send       {"message": "...", "bot": "..."} sends a chat line or command
+//   GET  /messages   received chat, `?since=<id>` for only what came after that one
+//   GET  /players    the player list, of the bot in `?bot=` or the active one
+//   GET  /status     the bots, their servers and the time since startup
+//   GET  /events     a WebSocket streaming every event like --json does, which takes
+//                    the same objects as /send
+//
+// /send only takes a JSON body: browsers have to ask before sending one to another
+// site, so a web page can't make the bots chat. They don't ask before opening a
+// WebSocket though, so /events only lets in pages from this machine.
+
+// How many chat messages /messages keeps
+const KEPT_MESSAGES: usize = 1000;
+
+// Bigger requests are refused
+const MAX_BODY: usize = 64 * 1024;
+
+// Connections that send nothing are dropped after this, and event stream clients
+// that don't take what's sent to them
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+struct Message {
+    // Counts up from 1 and doesn't change when older messages are dropped
+    id: u64,
+    fields: Value,
+}
+
+// A client of /events
+struct Subscriber {
+    id: usize,
+    stream: TcpStream,
+}
+
+pub struct ApiServer {
+    bots: Arc<Bots>,
+    messages: Mutex<VecDeque<Message>>,
+    subscribers: Mutex<Vec<Subscriber>>,
+    started: Instant,
+}
+
+struct Request {
+    method: String,
+    path: String,
+    query: Vec<(String, String)>,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl ApiServer {
+    /// Listens on `address` and keeps the chat of every bot for /messages. Problems are
+    /// passed to `print`.
+    pub fn start(
+        address: SocketAddr,
+        bots: &Arc<Bots>,
+        language: &Arc<Language>,
+        print: fn(&str),
+    ) -> Result<(), Box<dyn Error>> {
+        let listener: TcpListener = match TcpListener::bind(address) {
+            Ok(listener) => listener,
+            Err(e) => return Err(format!("Couldn't listen for HTTP on {}: {}", address, e).into()),
+        };
+        let server: Arc<ApiServer> = Arc::new(ApiServer {
+            bots: Arc::clone(bots),
+            messages: Mutex::new(VecDeque::new()),
+            subscribers: Mutex::new(Vec::new()),
+            started: Instant::now(),
+        });
+
+        for bot in bots.all() {
+            server.record(&bot, language);
+            server.stream(&bot, language);
+        }
+
+        thread::spawn(move || {
+            for (id, stream) in listener.incoming().enumerate() {
+                let stream: TcpStream = match stream {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        print(&format!("[MClient] HTTP connection failed: {}", e));
+                        continue;
+                    }
+                };
+                let server: Arc<ApiServer> = Arc::clone(&server);
+                thread::spawn(move || server.serve(id, stream));
+            }
+        });
+        Ok(())
+    }
+
+    // Keeps the chat messages a bot receives
+    fn record(self: &Arc<Self>, bot: &Bot, language: &Arc<Language>) {
+        let server: Arc<ApiServer> = Arc::clone(self);
+        let name: String = bot.name.clone();
+        let client = Arc::downgrade(&bot.client);
+        let language: Arc<Language> = Arc::clone(language);
+
+        bot.client.on_event(move |event| {
+            let Event::ChatReceived {
+                message, sender, ..
+            } = event
+            else {
+                return;
+            };
+            let text: String = language.translate(message).to_string();
+            let author: Option<String> = match (sender, client.upgrade()) {
+                (Some(uuid), Some(client)) => client
+                    .online_players()
+                    .get(uuid)
+                    .map(|player| player.name.clone()),
+                _ => None,
+            }
+            .or_else(|| filter::tagged_author(&text).map(String::from));
+
+            let mut messages = server.messages.lock().unwrap();
+            let id: u64 = messages.back().map_or(1, |message| message.id + 1);
+            if messages.len() == KEPT_MESSAGES {
+                messages.pop_front();
+            }
+            messages.push_back(Message {
+                id,
+                fields: json!({
+                    "id": id,
+                    "time": unix_millis(),
+                    "bot": name,
+                    "sender": sender.map(format_uuid),
+                    "author": author,
+                    "text": text,
+                }),
+            });
+        });
+    }
+
+    // Sends every event of a bot to the /events clients
+    fn stream(self: &Arc<Self>, bot: &Bot, language: &Arc<Language>) {
+        let server: Arc<ApiServer> = Arc::clone(self);
+        let name: String = bot.name.clone();
+        let language: Arc<Language> = Arc::clone(language);
+        bot.client.on_event(move |event| {
+            if !server.subscribers.lock().unwrap().is_empty() {
+                server.publish(&ndjson::event_line(event, Some(&name), &language));
+            }
+        });
+
+        let server: Arc<ApiServer> = Arc::clone(self);
+        let name: String = bot.name.clone();
+        bot.client.on_disconnect(move |e: &ClientError| {
+            server.publish(&ndjson::disconnected_line(e, Some(&name)));
+        });
+    }
+
+    // Sends a text message to every /events client. Clients that can't be written to
+    // anymore are dropped.
+    fn publish(&self, text: &str) {
+        self.subscribers.lock().unwrap().retain_mut(|subscriber| {
+            websocket::write(
+                &mut subscriber.stream,
+                websocket::OPCODE_TEXT,
+                text.as_bytes(),
+            )
+            .is_ok()
+        });
+    }
+
+    // Sends a frame to one /events client
+    fn write_to(&self, id: usize, opcode: u8, payload: &[u8]) {
+        if let Some(subscriber) = self
+            .subscribers
+            .lock()
+            .unwrap()
+            .iter_mut()
+            .find(|subscriber| subscriber.id == id)
+        {
+            let _ = websocket::write(&mut subscriber.stream, opcode, payload);
+        }
+    }
+
+    fn serve(&self, id: usize, stream: TcpStream) {
+        let _ = stream.set_read_timeout(Some(READ_TIMEOUT));
+        let Ok(mut writer) = stream.try_clone() else {
+            return;
+        };
+        let mut reader: BufReader<TcpStream> = BufReader::new(stream);
+        let (status, body): (u16, Value) = match read_request(&mut reader) {
+            Ok(request) if request.path == "/events" => match self.upgrade(&request) {
+                Ok(key) => {
+                    self.subscribe(id, writer, reader, &key);
+                    return;
+                }
+                Err(error) => error,
+            },
+            Ok(request) => self.handle(&request),
+            Err((status, e)) => (status, json!({ "error": e })),
+        };
+        let body: String = body.to_string();
+        let _ = write!(
+            writer,
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            reason(status),
+            body.len(),
+            body
+        );
+    }
+
+    fn handle(&self, request: &Request) -> (u16, Value) {
+        match (request.method.as_str(), request.path.as_str()) {
+            ("POST", "/send") => self.send(request),
+            ("GET", "/messages") => self.messages(request),
+            ("GET", "/players") => self.players(request),
+            ("GET", "/status") => (200, self.status()),
+            (_, "/send" | "/messages" | "/players" | "/status" | "/events") => {
+                (405, json!({ "error": "Method not allowed" }))
+            }
+            _ => (404, json!({ "error": "No such endpoint" })),
+        }
+    }
+
+    // The bot called `name`, or the active one
+    fn bot(&self, name: Option<&str>) -> Result<Arc<Bot>, (u16, Value)> {
+        match name {
+            Some(name) => self
+                .bots
+                .all()
+                .into_iter()
+                .find(|bot| bot.name.eq_ignore_ascii_case(name))
+                .ok_or_else(|| {
+                    (
+                        404,
+                        json!({ "error": format!("There's no bot called {}", name) }),
+                    )
+                }),
+            None => Ok(self.bots.active()),
+        }
+    }
+
+    fn send(&self, request: &Request) -> (u16, Value) {
+        if !request.header("Content-Type").is_some_and(|content_type| {
+            content_type
+                .to_ascii_lowercase()
+                .starts_with("application/json")
+        }) {
+            return (415, json!({ "error": "Expected a JSON body" }));
+        }
+        self.send_json(&request.body, request.param("bot"))
+    }
+
+    // `{"message": "...", "bot": "..."}`, from /send or /events
+    fn send_json(&self, body: &[u8], bot: Option<&str>) -> (u16, Value) {
+        let Ok(body) = serde_json::from_slice::<Value>(body) else {
+            return (400, json!({ "error": "The body isn't valid JSON" }));
+        };
+        let Some(message) = body["message"]
+            .as_str()
+            .filter(|message| !message.is_empty())
+        else {
+            return (400, json!({ "error": "Expected a \"message\"" }));
+        };
+        let bot: Arc<Bot> = match self.bot(body["bot"].as_str().or(bot)) {
+            Ok(bot) => bot,
+            Err(error) => return error,
+        };
+        match bot.client.send_chat(message) {
+            Ok(()) => (200, json!({ "bot": bot.name })),
+            Err(e) => (500, json!({ "error": e.to_string() })),
+        }
+    }
+
+    fn messages(&self, request: &Request) -> (u16, Value) {
+        let since: u64 = match request.param("since").map(str::parse) {
+            Some(Ok(since)) => since,
+            Some(Err(_)) => return (400, json!({ "error": "since has to be a message id" })),
+            None => 0,
+        };
+        let messages = self.messages.lock().unwrap();
+        let last: u64 = messages.back().map_or(since, |message| message.id);
+        (
+            200,
+            json!({
+                "messages": messages
+                    .iter()
+                    .filter(|message| message.id > since)
+                    .map(|message| message.fields.clone())
+                    .collect::<Vec<Value>>(),
+                "last": last,
+            }),
+        )
+    }
+
+    fn players(&self, request: &Request) -> (u16, Value) {
+        let bot: Arc<Bot> = match self.bot(request.param("bot")) {
+            Ok(bot) => bot,
+            Err(error) => return error,
+        };
+        let mut players: Vec<(u128, String, i32, String)> = bot
+            .client
+            .online_players()
+            .into_iter()
+            .map(|(uuid, player)| {
+                let game_mode: String = format!("{:?}", player.game_mode).to_lowercase();
+                (uuid, player.name, player.latency, game_mode)
+            })
+            .collect();
+        players.sort_by_key(|(_, name, _, _)| name.to_lowercase());
+        (
+            200,
+            json!({
+                "bot": bot.name,
+                "players": players
+                    .into_iter()
+                    .map(|(uuid, name, latency, game_mode)| json!({
+                        "name": name,
+                        "uuid": format_uuid(uuid),
+                        "latency_ms": latency,
+                        "game_mode": game_mode,
+                    }))
+                    .collect::<Vec<Value>>(),
+            }),
+        )
+    }
+
+    fn status(&self) -> Value {
+        let bots: Vec<Value> = self
+            .bots
+            .all()
+            .iter()
+            .map(|bot| {
+                json!({
+                    "name": bot.name,
+                    "server": bot.address,
+                    "protocol": bot.protocol,
+                    "brand": bot.client.server_brand(),
+                    "players": bot.client.online_players().len(),
+                    "health": bot.client.health().map(|health| json!({
+                        "health": health.health,
+                        "food": health.food,
+                    })),
+                    "latency": latency_fields(&bot.client.latency()),
+                    "position": bot.client.position().map(|position| json!({
+                        "x": position.x,
+                        "y": position.y,
+                        "z": position.z,
+                    })),
+                })
+            })
+            .collect();
+        json!({
+            "bots": bots,
+            "active": self.bots.active().name,
+            "uptime_s": self.started.elapsed().as_secs(),
+        })
+    }
+
+    // Checks a request for /events, returning the client's Sec-WebSocket-Key
+    fn upgrade(&self, request: &Request) -> Result<String, (u16, Value)> {
+        if request.method != "GET" {
+            return Err((405, json!({ "error": "Method not allowed" })));
+        }
+        let websocket: bool = request
+            .header("Upgrade")
+            .is_some_and(|upgrade| upgrade.eq_ignore_ascii_case("websocket"));
+        let Some(key) = request.header("Sec-WebSocket-Key").filter(|_| websocket) else {
+            return Err((400, json!({ "error": "Expected a WebSocket handshake" })));
+        };
+        if !request.header("Origin").is_none_or(is_local_origin) {
+            return Err((
+                403,
+                json!({ "error": "Only pages from this machine can connect" }),
+            ));
+        }
+        Ok(String::from(key))
+    }
+
+    // Finishes the handshake and serves an /events client until it leaves
+    fn subscribe(
+        &self,
+        id: usize,
+        mut writer: TcpStream,
+        mut reader: BufReader<TcpStream>,
+        key: &str,
+    ) {
+        let handshake: String = format!(
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+            websocket::accept_key(key)
+        );
+        let mut session: Value = self.status();
+        session["event"] = json!("session");
+        if writer.write_all(handshake.as_bytes()).is_err()
+            || websocket::write(
+                &mut writer,
+                websocket::OPCODE_TEXT,
+                session.to_string().as_bytes(),
+            )
+            .is_err()
+        {
+            return;
+        }
+        // Events can be a long time apart, but a client that stops reading shouldn't
+        // hold the bots up
+        let _ = reader.get_ref().set_read_timeout(None);
+        let _ = writer.set_write_timeout(Some(READ_TIMEOUT));
+        self.subscribers
+            .lock()
+            .unwrap()
+            .push(Subscriber { id, stream: writer });
+
+        loop {
+            match websocket::read(&mut reader, MAX_BODY) {
+                Ok(WebSocketMessage::Text(text)) => {
+                    let (status, mut answer): (u16, Value) = self.send_json(text.as_bytes(), None);
+                    answer["event"] = json!(if status == 200 { "sent" } else { "error" });
+                    self.write_to(id, websocket::OPCODE_TEXT, answer.to_string().as_bytes());
+                }
+                Ok(WebSocketMessage::Binary) => {}
+                Ok(WebSocketMessage::Ping(payload)) => {
+                    self.write_to(id, websocket::OPCODE_PONG, &payload)
+                }
+                Ok(WebSocketMessage::Close) | Err(_) => {
+                    self.write_to(id, websocket::OPCODE_CLOSE, &[]);
+                    break;
+                }
+            }
+        }
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|subscriber| subscriber.id != id);
+    }
+}
+
+// Reads one request. Errors come with the status to answer with.
+fn read_request(reader: &mut BufReader<TcpStream>) -> Result<Request, (u16, String)> {
+    let mut line: String = String::new();
+    if reader.read_line(&mut line).is_err() {
+        return Err((400, String::from("Couldn't read the request")));
+    }
+    let mut words = line.split_whitespace();
+    let (Some(method), Some(target)) = (words.next(), words.next()) else {
+        return Err((400, String::from("Malformed request line")));
+    };
+    let method: String = String::from(method);
+    let (path, query): (&str, &str) = target.split_once('?').unwrap_or((target, ""));
+    let path: String = String::from(path);
+    let query: Vec<(String, String)> = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value): (&str, &str) = pair.split_once('=').unwrap_or((pair, ""));
+            (decode_component(key), decode_component(value))
+        })
+        .collect();
+
+    let mut headers: Vec<(String, String)> = Vec::new();
+    let mut content_length: usize = 0;
+    loop {
+        line.clear();
+        match reader.read_line(&mut line) {
+            Ok(0) | Err(_) => return Err((400, String::from("Couldn't read the headers"))),
+            Ok(_) => {}
+        }
+        let header: &str = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let Some((name, value)) = header.split_once(':') else {
+            continue;
+        };
+        let value: &str = value.trim();
+        if name.eq_ignore_ascii_case("Content-Length") {
+            let Ok(length) = value.parse() else {
+                return Err((400, String::from("Invalid Content-Length")));
+            };
+            content_length = length;
+        }
+        headers.push((String::from(name.trim()), String::from(value)));
+    }
+    if content_length > MAX_BODY {
+        return Err((413, String::from("The body is too big")));
+    }
+
+    let mut body: Vec<u8> = vec![0; content_length];
+    if reader.read_exact(&mut body).is_err() {
+        return Err((400, String::from("Couldn't read the body")));
+    }
+    Ok(Request {
+        method,
+        path,
+        query,
+        headers,
+        body,
+    })
+}
+
+// Undoes %XX escapes and `+` for spaces in a query parameter
+fn decode_component(text: &str) -> String {
+    let bytes: &[u8] = text.as_bytes();
+    let mut res: Vec<u8> = Vec::new();
+    let mut i: usize = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => res.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                match std::str::from_utf8(&bytes[i + 1..i + 3])
+                    .ok()
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                {
+                    Some(byte) => {
+                        res.push(byte);
+                        i += 2;
+                    }
+                    None => res.push(b'%'),
+                }
+            }
+            byte => res.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&res).into_owned()
+}
+
+fn latency_fields(latency: &Latency) -> Value {
+    let millis =
+        |duration: Option<Duration>| duration.map(|duration| duration.as_secs_f64() * 1000.0);
+    json!({
+        "last_ms": millis(latency.last),
+        "average_ms": millis(latency.average),
+        "min_ms": millis(latency.min),
+        "max_ms": millis(latency.max),
+        "samples": latency.samples,
+        "keep_alive_reply_ms": millis(latency.keep_alive_reply),
+    })
+}
+
+// Whether a page's Origin header is on localhost
+fn is_local_origin(origin: &str) -> bool {
+    let Some((_, host)) = origin.split_once("://") else {
+        return false;
+    };
+    let host: &str = match host.strip_prefix('[') {
+        Some(ipv6) => ipv6.split(']').next().unwrap_or_default(),
+        None => host.split(':').next().unwrap_or_default(),
+    };
+    matches!(host, "localhost" | "127.0.0.1" | "::1")
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Content Too Large",
+        415 => "Unsupported Media Type",
+        _ => "Internal Server Error",
+    }
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
@@ -0,0 +1,35 @@
+use azalea_chat::FormattedText;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BossBarColor {
+    Pink,
+    Blue,
+    Red,
+    Green,
+    Yellow,
+    Purple,
+    White,
+}
+
+impl BossBarColor {
+    pub(crate) fn from_id(id: i32) -> BossBarColor {
+        match id {
+            0 => BossBarColor::Pink,
+            1 => BossBarColor::Blue,
+            2 => BossBarColor::Red,
+            3 => BossBarColor::Green,
+            4 => BossBarColor::Yellow,
+            5 => BossBarColor::Purple,
+            _ => BossBarColor::White,
+        }
+    }
+}
+
+/// A boss bar currently shown at the top of the screen.
+#[derive(Clone, Debug)]
+pub struct BossBar {
+    pub title: FormattedText,
+    /// From 0.0 (empty) to 1.0 (full).
+    pub progress: f32,
+    pub color: BossBarColor,
+}
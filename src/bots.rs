@@ -0,0 +1,151 @@
+use std::cmp::Ordering;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use mclient_project::Client;
+
+// With --bot, one process runs several clients, each on the main server or one of its
+// own. Their output is prefixed with the bot's name, and the input line goes through
+// the active one, picked with `.switch`.
+
+/// How long after a bot logs in joins are ignored by the join messages, scripts and
+/// bridges: the server lists everyone who is already online right then.
+pub const JOIN_GRACE: Duration = Duration::from_secs(3);
+
+/// A `--bot` argument: `NAME[@HOST[:PORT]]`. Without a host the bot joins the main server.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BotSpec {
+    pub username: String,
+    pub server: Option<(String, Option<u16>)>,
+}
+
+impl BotSpec {
+    pub fn parse(text: &str) -> Result<BotSpec, Box<dyn Error>> {
+        let (username, server): (&str, Option<&str>) = match text.split_once('@') {
+            Some((username, server)) => (username, Some(server)),
+            None => (text, None),
+        };
+        if username.is_empty() {
+            return Err(format!("'{}' has no username, expected NAME[@HOST[:PORT]]", text).into());
+        }
+
+        // Bare IPv6 addresses have colons too, those need brackets to take a port
+        let server: Option<(String, Option<u16>)> = match server {
+            Some(server) => Some(match server.rsplit_once(':') {
+                Some((host, port)) if !host.contains(':') || host.ends_with(']') => {
+                    let Ok(port) = port.parse() else {
+                        return Err(format!("Invalid port in '{}'", text).into());
+                    };
+                    (String::from(host), Some(port))
+                }
+                _ => (String::from(server), None),
+            }),
+            None => None,
+        };
+
+        Ok(BotSpec {
+            username: String::from(username),
+            server: server.map(|(host, port)| {
+                (
+                    String::from(host.trim_start_matches('[').trim_end_matches(']')),
+                    port,
+                )
+            }),
+        })
+    }
+}
+
+pub struct Bot {
+    /// The name the server logged the bot in as.
+    pub name: String,
+    /// `host:port` of its server.
+    pub address: String,
+    pub protocol: i32,
+    pub client: Arc<Client>,
+}
+
+/// The connected bots, in the order they were started.
+pub struct Bots {
+    bots: Mutex<Vec<Arc<Bot>>>,
+    // Index into `bots`
+    active: Mutex<usize>,
+}
+
+impl Bots {
+    pub fn new(first: Bot) -> Bots {
+        Bots {
+            bots: Mutex::new(vec![Arc::new(first)]),
+            active: Mutex::new(0),
+        }
+    }
+
+    pub fn add(&self, bot: Bot) {
+        self.bots.lock().unwrap().push(Arc::new(bot));
+    }
+
+    pub fn all(&self) -> Vec<Arc<Bot>> {
+        self.bots.lock().unwrap().clone()
+    }
+
+    /// The bot the input line goes through.
+    pub fn active(&self) -> Arc<Bot> {
+        let bots = self.bots.lock().unwrap();
+        Arc::clone(&bots[*self.active.lock().unwrap()])
+    }
+
+    pub fn client(&self) -> Arc<Client> {
+        Arc::clone(&self.active().client)
+    }
+
+    /// Makes the bot called `name` (in any case) the active one. Returns its name, or
+    /// `None` if there's no such bot.
+    pub fn switch(&self, name: &str) -> Option<String> {
+        let bots = self.bots.lock().unwrap();
+        let index: usize = bots
+            .iter()
+            .position(|bot| bot.name.eq_ignore_ascii_case(name))?;
+        *self.active.lock().unwrap() = index;
+        Some(bots[index].name.clone())
+    }
+
+    /// Forgets a bot whose connection is gone. Returns the active bot if it was the one
+    /// removed and others are left, so the caller can say where input goes now.
+    pub fn remove(&self, name: &str) -> Removed {
+        let mut bots = self.bots.lock().unwrap();
+        let mut active = self.active.lock().unwrap();
+        let Some(index) = bots.iter().position(|bot| bot.name == name) else {
+            return Removed::Other;
+        };
+        if bots.len() == 1 {
+            return Removed::Last;
+        }
+
+        bots.remove(index);
+        match index.cmp(&*active) {
+            Ordering::Less => *active -= 1,
+            Ordering::Equal => {
+                *active = 0;
+                return Removed::Active(bots[0].name.clone());
+            }
+            Ordering::Greater => {}
+        }
+        Removed::Other
+    }
+}
+
+pub enum Removed {
+    /// It was the only bot left, which is kept.
+    Last,
+    /// The input line now goes through the bot with this name.
+    Active(String),
+    Other,
+}
+
+/// Put in front of a bot's lines, only when there's more than one.
+pub fn prefix(name: &str, several: bool) -> String {
+    match several {
+        true => format!("\x1b[1m[{}]\x1b[0m ", name),
+        false => String::new(),
+    }
+}
@@ -0,0 +1,196 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Weak};
+use std::thread;
+use std::time::Duration;
+
+use mclient_project::discord::{self, ChannelReader, DiscordMessage, Webhook};
+use mclient_project::{Client, Event};
+
+use crate::bots::{Bot, Bots};
+use crate::lang::Language;
+
+// Relays chat between bots and Discord channels (built with the `discord` feature):
+// what a bot sees goes to a channel webhook, and what people post in the channel is
+// sent through the bot.
+
+pub const DEFAULT_TO_DISCORD: &str = "%msg";
+pub const DEFAULT_TO_MINECRAFT: &str = "[Discord] %user: %msg";
+
+/// The `[discord]` table.
+#[derive(Clone, Debug)]
+pub struct DiscordConfig {
+    /// Bot token, needed to read the channels.
+    pub token: Option<String>,
+    pub poll_interval: Duration,
+    /// How a chat line is posted to Discord: %msg is the line and %bot the bot's name.
+    pub to_discord: String,
+    /// How a Discord message is sent in game: %user is the author and %msg the text.
+    pub to_minecraft: String,
+    pub channels: Vec<ChannelMapping>,
+}
+
+/// A `[discord.channels.<name>]` table, linking a bot to a channel.
+#[derive(Clone, Debug)]
+pub struct ChannelMapping {
+    pub name: String,
+    /// The bot's name; the one logged in with `--username` if not given.
+    pub bot: Option<String>,
+    /// Where the bot's chat is posted.
+    pub webhook: Option<String>,
+    /// ID of the channel whose messages the bot sends in game.
+    pub channel: Option<String>,
+}
+
+/// Starts relaying for every channel mapping. Problems are passed to `print`.
+pub fn start(config: &DiscordConfig, bots: &Bots, language: &Arc<Language>, print: fn(&str)) {
+    for mapping in &config.channels {
+        let bots: Vec<Arc<Bot>> = bots.all();
+        let bot: Option<&Arc<Bot>> = match &mapping.bot {
+            Some(name) => bots.iter().find(|bot| bot.name.eq_ignore_ascii_case(name)),
+            None => bots.first(),
+        };
+        let Some(bot) = bot else {
+            print(&format!(
+                "[MClient] [discord.channels.{}]: there's no bot called {}",
+                mapping.name,
+                mapping.bot.as_deref().unwrap_or_default()
+            ));
+            continue;
+        };
+
+        if let Some(webhook) = &mapping.webhook {
+            relay_to_discord(config, bot, Webhook::new(webhook), language, print);
+        }
+        match (&mapping.channel, &config.token) {
+            (Some(channel), Some(token)) => relay_to_minecraft(
+                config,
+                Arc::downgrade(&bot.client),
+                ChannelReader::new(token, channel),
+                print,
+            ),
+            (Some(_), None) => print(&format!(
+                "[MClient] [discord.channels.{}]: reading the channel needs a bot token",
+                mapping.name
+            )),
+            (None, _) => {}
+        }
+    }
+}
+
+fn relay_to_discord(
+    config: &DiscordConfig,
+    bot: &Bot,
+    webhook: Webhook,
+    language: &Arc<Language>,
+    print: fn(&str),
+) {
+    // Posting takes a moment, so lines that come in meanwhile are sent together
+    let (queue, lines): (Sender<String>, Receiver<String>) = mpsc::channel();
+    thread::spawn(move || {
+        while let Ok(line) = lines.recv() {
+            let mut content: String = line;
+            while let Ok(line) = lines.try_recv() {
+                if content.chars().count() + line.chars().count() >= discord::MAX_MESSAGE_LENGTH {
+                    post(&webhook, &content, print);
+                    content = line;
+                } else {
+                    content = format!("{}\n{}", content, line);
+                }
+            }
+            post(&webhook, &content, print);
+        }
+    });
+
+    let format: String = config
+        .to_discord
+        .replace("%bot", &escape_markdown(&bot.name));
+    let language: Arc<Language> = Arc::clone(language);
+    let own_uuid: u128 = bot.client.identity().uuid;
+    let own_tag: String = format!("<{}>", bot.name);
+    bot.client.on_event(move |event| {
+        let Event::ChatReceived {
+            message, sender, ..
+        } = event
+        else {
+            return;
+        };
+        let text: String = language.translate(message).to_string();
+        // That includes what it sent for Discord users, which would show up twice
+        if *sender == Some(own_uuid) || text.starts_with(&own_tag) {
+            return;
+        }
+        let _ = queue.send(format.replace("%msg", &escape_markdown(&text)));
+    });
+}
+
+fn post(webhook: &Webhook, content: &str, print: fn(&str)) {
+    if let Err(e) = webhook.send(content, None) {
+        print(&format!("[MClient] Couldn't post to Discord: {}", e));
+    }
+}
+
+fn relay_to_minecraft(
+    config: &DiscordConfig,
+    client: Weak<Client>,
+    mut reader: ChannelReader,
+    print: fn(&str),
+) {
+    let format: String = config.to_minecraft.clone();
+    let poll_interval: Duration = config.poll_interval;
+    thread::spawn(move || {
+        loop {
+            let messages: Vec<DiscordMessage> = match reader.poll() {
+                Ok(messages) => messages,
+                Err(e) => {
+                    print(&format!(
+                        "[MClient] Couldn't read the Discord channel: {}",
+                        e
+                    ));
+                    Vec::new()
+                }
+            };
+            let Some(client) = client.upgrade() else {
+                return;
+            };
+            // Its own posts come from the webhook
+            for message in messages.iter().filter(|message| !message.from_bot) {
+                let text: String = message
+                    .content
+                    .split_whitespace()
+                    .collect::<Vec<&str>>()
+                    .join(" ");
+                if text.is_empty() {
+                    continue;
+                }
+                let line: String = format
+                    .replace("%user", &message.author)
+                    .replace("%msg", &text);
+                // Discord users shouldn't be able to run commands as the bot
+                if let Err(e) = client.send_chat(line.trim_start_matches('/')) {
+                    print(&format!(
+                        "[MClient] Couldn't relay a Discord message: {}",
+                        e
+                    ));
+                }
+            }
+            drop(client);
+            thread::sleep(poll_interval);
+        }
+    });
+}
+
+// So chat lines show up in Discord as they are
+fn escape_markdown(text: &str) -> String {
+    let mut res: String = String::new();
+    // Quotes, headings and lists only start a line
+    if text.starts_with(['>', '#', '-']) {
+        res.push('\\');
+    }
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '_' | '~' | '`' | '|' | '[' | ']') {
+            res.push('\\');
+        }
+        res.push(c);
+    }
+    res
+}
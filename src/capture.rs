@@ -0,0 +1,246 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use mc_varint::{VarInt, VarIntWrite};
+
+use crate::error::ClientError;
+use crate::helper::PacketReader;
+use crate::types::McRead;
+
+// A capture file starts with MAGIC, a format version, the protocol version (i32) and the
+// time capturing started (u64, milliseconds since the Unix epoch). Every frame after
+// that is its time (u64, microseconds since the start), state (u8), whether compression
+// was on (u8), the frame as it arrived (u32 length and bytes, without the frame length)
+// and the packet it was unpacked to (u32 length and bytes, starting with the ID).
+// Numbers are big-endian.
+
+const MAGIC: &[u8; 5] = b"MCCAP";
+const FORMAT_VERSION: u8 = 1;
+
+/// The connection state a captured frame arrived in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CaptureState {
+    Login,
+    /// 1.20.2+ only.
+    Configuration,
+    Play,
+}
+
+impl CaptureState {
+    fn to_byte(self) -> u8 {
+        match self {
+            CaptureState::Login => 0,
+            CaptureState::Configuration => 1,
+            CaptureState::Play => 2,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Option<CaptureState> {
+        match byte {
+            0 => Some(CaptureState::Login),
+            1 => Some(CaptureState::Configuration),
+            2 => Some(CaptureState::Play),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for CaptureState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            CaptureState::Login => "login",
+            CaptureState::Configuration => "configuration",
+            CaptureState::Play => "play",
+        })
+    }
+}
+
+/// Writes every frame the server sends to a file, set as `ClientConfig::capture`.
+/// Clones write to the same file; reconnects keep adding to it.
+#[derive(Clone)]
+pub struct Capture {
+    path: PathBuf,
+    started: Instant,
+    file: Arc<Mutex<BufWriter<File>>>,
+}
+
+impl Capture {
+    /// Creates (or truncates) the capture file for a connection using `protocol`.
+    pub fn create(path: &Path, protocol: i32) -> Result<Capture, ClientError> {
+        let mut file: BufWriter<File> = BufWriter::new(File::create(path)?);
+        let started_at: u64 = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_millis() as u64)
+            .unwrap_or(0);
+        file.write_all(MAGIC)?;
+        file.write_all(&[FORMAT_VERSION])?;
+        file.write_all(&protocol.to_be_bytes())?;
+        file.write_all(&started_at.to_be_bytes())?;
+        file.flush()?;
+
+        Ok(Capture {
+            path: path.to_path_buf(),
+            started: Instant::now(),
+            file: Arc::new(Mutex::new(file)),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Flushed every time, the process may exit without dropping the writer. A frame that
+    // can't be written is left out rather than ending the session.
+    pub(crate) fn record(
+        &self,
+        state: CaptureState,
+        compressed: bool,
+        frame: &[u8],
+        packet: &[u8],
+    ) {
+        let mut record: Vec<u8> = Vec::with_capacity(14 + frame.len() + packet.len());
+        record.extend_from_slice(&(self.started.elapsed().as_micros() as u64).to_be_bytes());
+        record.push(state.to_byte());
+        record.push(compressed as u8);
+        for bytes in [frame, packet] {
+            record.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+            record.extend_from_slice(bytes);
+        }
+
+        let mut file = self.file.lock().unwrap();
+        let _ = file.write_all(&record).and_then(|()| file.flush());
+    }
+}
+
+impl fmt::Debug for Capture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Capture").field("path", &self.path).finish()
+    }
+}
+
+/// One frame from a capture file.
+#[derive(Clone, Debug)]
+pub struct CapturedFrame {
+    /// Since capturing started.
+    pub time: Duration,
+    pub state: CaptureState,
+    pub compressed: bool,
+    /// The frame as it arrived, after its length.
+    pub frame: Vec<u8>,
+    /// The decompressed packet, starting with its ID.
+    pub packet: Vec<u8>,
+}
+
+impl CapturedFrame {
+    /// The packet's ID and the data after it.
+    pub fn split(&self) -> Result<(i32, &[u8]), ClientError> {
+        let mut cursor: Cursor<&[u8]> = Cursor::new(&self.packet);
+        let id: i32 = cursor.read_varint()?;
+        Ok((id, &self.packet[cursor.position() as usize..]))
+    }
+
+    /// Runs the frame through the client's receiving code again, checking that it still
+    /// unpacks to the captured packet.
+    pub fn verify(&self) -> Result<(), ClientError> {
+        let mut stream: Vec<u8> = Vec::with_capacity(self.frame.len() + 3);
+        stream.write_var_int(VarInt::from(self.frame.len() as i32))?;
+        stream.extend_from_slice(&self.frame);
+
+        let threshold: i32 = if self.compressed { 0 } else { -1 };
+        let mut reader: PacketReader = PacketReader::new();
+        let (id, data): (i32, &[u8]) = reader.receive(&mut Cursor::new(stream), threshold)?;
+        if (id, data) != self.split()? {
+            return Err(format!(
+                "The frame unpacks to packet 0x{:02X} with {} bytes of data instead of the captured one",
+                id,
+                data.len()
+            )
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// Reads a capture file frame by frame.
+pub struct CaptureReader {
+    /// What the captured connection logged in with.
+    pub protocol: i32,
+    pub started_at: SystemTime,
+    file: BufReader<File>,
+}
+
+impl CaptureReader {
+    pub fn open(path: &Path) -> Result<CaptureReader, ClientError> {
+        let mut file: BufReader<File> = BufReader::new(File::open(path)?);
+        let mut header: [u8; 18] = [0; 18];
+        file.read_exact(&mut header)
+            .map_err(|_| ClientError::from("Not a capture file"))?;
+        if &header[..5] != MAGIC {
+            return Err("Not a capture file".into());
+        }
+        if header[5] != FORMAT_VERSION {
+            return Err(format!("Unsupported capture format version {}", header[5]).into());
+        }
+
+        Ok(CaptureReader {
+            protocol: i32::from_be_bytes(header[6..10].try_into().unwrap()),
+            started_at: UNIX_EPOCH
+                + Duration::from_millis(u64::from_be_bytes(header[10..18].try_into().unwrap())),
+            file,
+        })
+    }
+
+    fn read_frame(&mut self) -> Result<Option<CapturedFrame>, ClientError> {
+        // Ending between frames is the normal end of the file
+        if self.file.fill_buf()?.is_empty() {
+            return Ok(None);
+        }
+        let mut header: [u8; 10] = [0; 10];
+        self.file.read_exact(&mut header).map_err(truncated)?;
+        let state: CaptureState = CaptureState::from_byte(header[8]).ok_or_else(|| {
+            ClientError::from(format!("Unknown state {} in the capture", header[8]))
+        })?;
+        let frame: Vec<u8> = self.read_bytes()?;
+        let packet: Vec<u8> = self.read_bytes()?;
+
+        Ok(Some(CapturedFrame {
+            time: Duration::from_micros(u64::from_be_bytes(header[..8].try_into().unwrap())),
+            state,
+            compressed: header[9] != 0,
+            frame,
+            packet,
+        }))
+    }
+
+    fn read_bytes(&mut self) -> Result<Vec<u8>, ClientError> {
+        let mut length: [u8; 4] = [0; 4];
+        self.file.read_exact(&mut length).map_err(truncated)?;
+        let mut bytes: Vec<u8> = Vec::new();
+        (&mut self.file)
+            .take(u32::from_be_bytes(length) as u64)
+            .read_to_end(&mut bytes)?;
+        if bytes.len() != u32::from_be_bytes(length) as usize {
+            return Err(truncated(io::Error::from(io::ErrorKind::UnexpectedEof)));
+        }
+        Ok(bytes)
+    }
+}
+
+fn truncated(e: io::Error) -> ClientError {
+    match e.kind() {
+        io::ErrorKind::UnexpectedEof => "The capture ends in the middle of a frame".into(),
+        _ => e.into(),
+    }
+}
+
+impl Iterator for CaptureReader {
+    type Item = Result<CapturedFrame, ClientError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_frame().transpose()
+    }
+}
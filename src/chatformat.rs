@@ -0,0 +1,232 @@
+use azalea_chat::style::{ChatFormatting, Style, TextColor};
+use mclient_project::packet::ChatKind;
+
+use crate::clock::LocalTime;
+
+pub static DEFAULT_CHAT_FORMAT: &str = "[%H:%M] %msg";
+
+/// Put in front of incoming private messages so they stand out from public chat.
+pub static WHISPER_MARKER: &str = "\x1b[1;35m[Whisper]\x1b[0m ";
+
+/// Put in front of the player's own messages when the server echoes them back.
+pub static OWN_MESSAGE_MARKER: &str = "\x1b[2m[You]\x1b[0m ";
+
+// Colors used for player names, picked by the sender's UUID.
+const SENDER_COLORS: [u8; 12] = [39, 41, 45, 75, 81, 113, 141, 171, 178, 204, 209, 220];
+
+/// Renders chat lines from a template. `%H`, `%M`, `%S` (hour, minute, second),
+/// `%d`, `%m`, `%Y` (day, month, year) and `%msg` are replaced; `%%` is a literal `%`.
+pub struct ChatFormat {
+    template: String,
+}
+
+impl ChatFormat {
+    pub fn new(template: &str) -> ChatFormat {
+        ChatFormat {
+            template: String::from(template),
+        }
+    }
+
+    pub fn render(&self, message: &str) -> String {
+        let now: LocalTime = LocalTime::now();
+        let mut res: String = String::new();
+        let mut rest: &str = &self.template;
+
+        while let Some(i) = rest.find('%') {
+            res += &rest[..i];
+            rest = &rest[i + 1..];
+
+            if let Some(after) = rest.strip_prefix("msg") {
+                res += message;
+                rest = after;
+                continue;
+            }
+
+            let mut chars = rest.chars();
+            let replacement: String = match chars.next() {
+                Some('H') => format!("{:02}", now.hour),
+                Some('M') => format!("{:02}", now.minute),
+                Some('S') => format!("{:02}", now.second),
+                Some('d') => format!("{:02}", now.day),
+                Some('m') => format!("{:02}", now.month),
+                Some('Y') => format!("{:04}", now.year),
+                Some('%') => String::from("%"),
+                Some(c) => format!("%{}", c),
+                None => String::from("%"),
+            };
+            res += &replacement;
+            rest = chars.as_str();
+        }
+
+        res + rest
+    }
+}
+
+/// What happens to `&a`-style color codes typed in the input line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorCodes {
+    /// Sent as typed, for servers where a plugin translates them.
+    Keep,
+    /// Turned into `§` codes. Vanilla servers kick for `§` in chat.
+    Translate,
+    Strip,
+}
+
+impl ColorCodes {
+    pub fn parse(name: &str) -> Option<ColorCodes> {
+        match name {
+            "keep" => Some(ColorCodes::Keep),
+            "translate" => Some(ColorCodes::Translate),
+            "strip" => Some(ColorCodes::Strip),
+            _ => None,
+        }
+    }
+
+    /// Applies the mode to a line of input. `&&` stands for a literal `&` before a code
+    /// character.
+    pub fn apply(&self, input: &str) -> String {
+        if *self == ColorCodes::Keep {
+            return String::from(input);
+        }
+
+        let mut res: String = String::new();
+        let mut chars = input.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c != '&' {
+                res.push(c);
+                continue;
+            }
+            match chars.peek().copied() {
+                Some('&') => {
+                    chars.next();
+                    res.push('&');
+                }
+                Some(code) if is_format_code(code) => {
+                    chars.next();
+                    if *self == ColorCodes::Translate {
+                        res.push('§');
+                        res.push(code.to_ascii_lowercase());
+                    }
+                }
+                _ => res.push('&'),
+            }
+        }
+        res
+    }
+}
+
+// Colors 0-9 and a-f, k-o for obfuscated, bold, strikethrough, underline and italic,
+// r to reset
+fn is_format_code(c: char) -> bool {
+    matches!(c.to_ascii_lowercase(), '0'..='9' | 'a'..='f' | 'k'..='o' | 'r')
+}
+
+/// The style for text without its own color: system messages are dimmed to gray so
+/// that player chat stands out.
+pub fn base_style(kind: ChatKind) -> Style {
+    let color: ChatFormatting = match kind {
+        ChatKind::System => ChatFormatting::Gray,
+        ChatKind::Player | ChatKind::Disguised => ChatFormatting::White,
+    };
+    Style::default().color(TextColor::try_from(color).ok())
+}
+
+/// The style for text without its own color in a title: bold white.
+pub fn title_style() -> Style {
+    Style::default()
+        .color(TextColor::try_from(ChatFormatting::White).ok())
+        .bold(true)
+}
+
+/// Number of characters a line takes up on screen, without its ANSI escapes.
+pub fn visible_width(line: &str) -> usize {
+    strip_ansi(line).chars().count()
+}
+
+/// `line` without its ANSI escapes.
+pub fn strip_ansi(line: &str) -> String {
+    let mut res: String = String::new();
+    let mut in_escape: bool = false;
+    for c in line.chars() {
+        if in_escape {
+            in_escape = !c.is_ascii_alphabetic();
+        } else if c == '\x1b' {
+            in_escape = true;
+        } else {
+            res.push(c);
+        }
+    }
+    res
+}
+
+/// Draws a box around ANSI-formatted lines, centering each of them.
+pub fn boxed(lines: &[String]) -> String {
+    let width: usize = lines
+        .iter()
+        .map(|line| visible_width(line))
+        .max()
+        .unwrap_or(0)
+        + 2;
+    let mut res: String = format!("┌{}┐", "─".repeat(width));
+
+    for line in lines {
+        let padding: usize = width - visible_width(line);
+        res += &format!(
+            "\n│{}{}\x1b[0m{}│",
+            " ".repeat(padding / 2),
+            line,
+            " ".repeat(padding - padding / 2)
+        );
+    }
+
+    res + &format!("\n└{}┘", "─".repeat(width))
+}
+
+/// Colors the first `<name>` in an ANSI-formatted chat line with a color derived from
+/// `uuid`, then restores whatever style was active before it.
+pub fn colorize_sender(line: &str, name: &str, uuid: u128) -> String {
+    let color: u8 = SENDER_COLORS[(uuid % SENDER_COLORS.len() as u128) as usize];
+    replace_sender(line, name, &format!("\x1b[38;5;{}m{}", color, name))
+}
+
+/// Replaces the name in the first `<name>` of an ANSI-formatted chat line with
+/// `decorated` (the name with its team prefix and suffix, already in ANSI), then
+/// restores whatever style was active before it.
+pub fn replace_sender(line: &str, name: &str, decorated: &str) -> String {
+    let tag: String = format!("<{}>", name);
+    let Some(start) = line.find(&tag) else {
+        return String::from(line);
+    };
+
+    // Styles set since the last reset, so they can be re-applied after the name
+    let mut active_style: String = String::new();
+    let mut chars = line[..start].chars();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            continue;
+        }
+        let mut escape: String = String::from(c);
+        for next in chars.by_ref() {
+            escape.push(next);
+            if next.is_ascii_alphabetic() {
+                break;
+            }
+        }
+        if escape == "\x1b[m" || escape == "\x1b[0m" {
+            active_style.clear();
+        } else {
+            active_style += &escape;
+        }
+    }
+
+    let name_start: usize = start + 1;
+    let name_end: usize = name_start + name.len();
+
+    format!(
+        "{}{}\x1b[0m{}{}",
+        &line[..name_start],
+        decorated,
+        active_style,
+        &line[name_end..]
+    )
+}
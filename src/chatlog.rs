@@ -0,0 +1,47 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use azalea_chat::FormattedText;
+
+use crate::clock::LocalTime;
+
+// Appends every received chat message to `<dir>/chat-YYYY-MM-DD.log`, one line per
+// message: `[HH:MM:SS] plain text<TAB>raw JSON`. A new file is started every day.
+
+pub struct ChatLog {
+    dir: PathBuf,
+    current: Mutex<Option<(String, File)>>,
+}
+
+impl ChatLog {
+    pub fn open(dir: PathBuf) -> io::Result<ChatLog> {
+        fs::create_dir_all(&dir)?;
+
+        Ok(ChatLog {
+            dir,
+            current: Mutex::new(None),
+        })
+    }
+
+    pub fn log(&self, message: &FormattedText) -> io::Result<()> {
+        let now: LocalTime = LocalTime::now();
+        let date: String = now.date();
+
+        let mut current = self.current.lock().unwrap();
+        if current.as_ref().map(|(day, _)| day) != Some(&date) {
+            let path: PathBuf = self.dir.join(format!("chat-{}.log", date));
+            let file: File = OpenOptions::new().create(true).append(true).open(path)?;
+            *current = Some((date, file));
+        }
+
+        let raw: String = serde_json::to_string(message)?;
+        let plain: String = message.to_string().replace('\n', " ");
+
+        let Some((_, file)) = current.as_mut() else {
+            return Ok(());
+        };
+        writeln!(file, "[{}] {}\t{}", now.time(), plain, raw)
+    }
+}
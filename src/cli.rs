@@ -0,0 +1,632 @@
+use std::env;
+use std::error::Error;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use mclient_project::{
+    AfkAction, AntiAfk, ClientSettings, ConnectPolicy, IpPreference, ReconnectPolicy,
+    SocketOptions, Socks5Proxy, VirtualHost,
+};
+use tracing::Level;
+
+use crate::bots::BotSpec;
+#[cfg(feature = "discord")]
+use crate::bridge::DiscordConfig;
+use crate::chatformat::{ColorCodes, DEFAULT_CHAT_FORMAT};
+use crate::config::{Config, DEFAULT_CONFIG_PATH};
+use crate::logging;
+use crate::readline;
+use crate::responder::ResponderRule;
+use crate::schedule::ScheduleSpec;
+use crate::scrollback::DEFAULT_SCROLLBACK;
+use crate::seen;
+#[cfg(feature = "telegram")]
+use crate::telegrambridge::TelegramConfig;
+
+static USAGE: &str = "Usage: mc-chat [COMMAND] [OPTIONS]
+
+Commands:
+  chat                       Log in and chat interactively (the default)
+  send                       Log in, send --message (or a /command) once spawned,
+                             and disconnect. Exits with 0 if it was sent
+  status                     Print the server's status (MOTD, version, players, mods)
+                             and save its icon to server-icon.png
+  ping                       Measure the latency to the server with status pings
+  query                      Print the GS4 query response (full player list, plugins,
+                             map). Needs enable-query=true on the server
+  watch                      Ping the server every --interval seconds and print the
+                             player count, latency and MOTD changes
+  replay <FILE>              Run a --capture file through the packet parser again and
+                             print every packet. Exits with 1 if one fails to parse
+  proxy                      Listen on --listen, forward every connection to the
+                             server and print the packets going either way
+
+Options:
+      --message <TEXT>       What `send` sends
+      --count <N>            Pings `ping` sends [default: 4]
+      --servers <FILE>       Make `status` check every host[:port] listed in FILE at
+                             once and print a table instead
+      --interval <SECONDS>   Time between the pings of `watch` [default: 30]
+      --alert <COMMAND>      Shell command `watch` runs when the server goes down or
+                             comes back, with MC_STATE set to \"down\" or \"up\"
+      --listen <[HOST:]PORT> Where `proxy` listens [default: 127.0.0.1:25566]
+      --hex                  Make `proxy` dump the start of every packet
+      --hide <PACKET>        Leave a packet out of `proxy`'s output, by name (like
+                             \"keep alive\") or ID (like 0x21). Can be given more than
+                             once
+  -h, --host <HOST>          Server address [default: 127.0.0.1]
+  -p, --port <PORT>          Server port [default: the SRV record's, or 25565]
+      --handshake-host <HOST>
+                             Host name sent in the handshake, for proxies that route
+                             by it [default: --host]
+      --handshake-port <PORT>
+                             Port sent in the handshake [default: --port]
+  -u, --username <USERNAME>  Offline-mode username [default: Tester12]
+      --bot <NAME[@HOST[:PORT]]>
+                             Also log in as NAME, on this server or another one. Can
+                             be given more than once; .switch picks the bot that
+                             typed lines go through
+      --skip-status          Don't request the server status before logging in
+      --favicon-out <PATH>   Where the server icon is saved [default: server-icon.png]
+      --no-favicon           Don't save the server icon
+      --favicon-preview      Draw the server icon in the terminal (needs 24-bit color)
+      --protocol <VERSION>   Protocol version to log in with, instead of the one
+                             reported by the server [default: 754 without status]
+      --connect-timeout <SECONDS>
+                             How long each connection attempt waits for the server
+                             [default: 5]
+      --connect-attempts <N> Connection attempts before giving up [default: 5]
+      --retry-delay <SECONDS>
+                             Pause between connection attempts [default: 1]
+      --prefer-ipv4          Try the server's IPv4 addresses first
+      --prefer-ipv6          Try the server's IPv6 addresses first
+      --reconnect <RETRIES>  Reconnect up to RETRIES times when the connection drops
+                             [default: 0]
+      --backoff <SECONDS>    Delay before the first reconnect, doubled after every
+                             failed attempt [default: 1]
+      --max-backoff <SECONDS>
+                             Upper limit for the reconnect delay [default: 60]
+      --discover-lan         Look for worlds opened to LAN and pick one to join
+      --query-port <PORT>    The server's query.port [default: same as --port]
+      --timeout <SECONDS>    Drop the connection when the server hasn't sent a keep
+                             alive for this long, 0 to wait forever [default: 30]
+      --proxy <URL>          Connect through a SOCKS5 proxy
+                             (socks5://[user:pass@]host:port)
+      --tui                  Full-screen mode with a chat pane, player sidebar and a
+                             fixed input line (.up/.down [n] scroll the chat)
+      --join-messages        Print a line when a player joins or leaves the player list,
+                             for servers that hide the vanilla messages (.joins toggles)
+      --history-file <PATH>  Where sent messages are remembered between sessions
+                             [default: ~/.mc_chat_history]
+      --no-history           Don't save input history to disk
+      --seen-file <PATH>     Where .seen remembers when players were online
+                             [default: ~/.mc_chat_seen.json]
+      --no-seen              Only remember players seen during this session
+      --no-color             Print everything without colors or other ANSI escapes
+      --plain                Like --no-color, and print chat messages as bare text
+                             without the whisper, mention and link markers
+      --json                 Print every event (chat, joins, leaves, kicks, server
+                             status...) as one JSON object per line on stdout;
+                             client messages go to stderr
+      --chat-format <FORMAT> How chat lines are printed: %H, %M, %S, %d, %m, %Y and
+                             %msg are replaced [default: \"[%H:%M] %msg\"]
+      --lang <FILE>          Minecraft language file used for translated messages
+                             [default: bundled en_us]
+      --velocity-secret <SECRET>
+                             Answer Velocity's modern forwarding with this secret, to
+                             join a backend server behind the proxy directly
+      --bungee-spoof <IP>    Add BungeeCord's IP forwarding fields to the handshake,
+                             claiming to connect from IP, to test a backend server
+                             set up for BungeeCord
+      --anti-afk <SECONDS>   Swing the arm, look around or sneak every SECONDS so AFK
+                             plugins don't kick the client
+      --decline-resource-packs
+                             Decline the server's resource packs instead of pretending
+                             to load them
+      --color-codes <MODE>   What to do with &a-style codes in typed messages: keep,
+                             translate (to §, needs a server that allows it) or
+                             strip [default: keep]
+      --chat-rate <PER_SECOND>
+                             Send at most this many chat messages per second, queueing
+                             the rest; 0 for no limit [default: 1]
+      --brand <BRAND>        Client brand sent to the server [default: vanilla]
+      --debug-packets        Print every packet the client ignores with its name and
+                             the start of its data
+      --capture <FILE>       Write every packet the server sends to FILE, compressed
+                             and decompressed, with timestamps (see replay)
+  -v, --verbose              Also log connection details (-vv: every packet sent and
+                             received, with the connection and login they belong to)
+      --log-file <FILE>      Append the log to FILE instead of printing it between
+                             the chat lines
+      --highlight <WORD>     Also highlight chat lines containing WORD, besides the
+                             username. Can be given more than once
+      --notify               Ring the terminal bell and show a desktop notification
+                             when a chat line is highlighted
+      --scrollback <LINES>   Chat lines kept for .history and .search [default: 5000]
+      --log-dir <DIR>        Log received chat to DIR/chat-YYYY-MM-DD.log
+      --irc-listen <[HOST:]PORT>
+                             Run a small IRC server there (on 127.0.0.1 without a
+                             host), with the chat as the channel #minecraft
+      --irc-password <PASSWORD>
+                             Password IRC clients have to send
+      --api-listen <[HOST:]PORT>
+                             Serve a JSON HTTP API there (on 127.0.0.1 without a
+                             host) to send chat and read messages, players and status
+      --script <FILE>        Load an event script (see the README). Can be given more
+                             than once; .script loads, unloads and reloads them
+      --config <PATH>        Config file with client preferences and server profiles
+                             [default: config.toml]
+      --profile <NAME>       Use the [profiles.NAME] section of the config file.
+                             Command-line flags override config values
+      --help                 Print this message";
+
+/// What the binary does, picked by the first argument.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    Chat,
+    Send,
+    Status,
+    Ping,
+    Query,
+    Watch,
+    Replay,
+    Proxy,
+}
+
+impl Command {
+    fn parse(name: &str) -> Option<Command> {
+        match name {
+            "chat" => Some(Command::Chat),
+            "send" => Some(Command::Send),
+            "status" => Some(Command::Status),
+            "ping" => Some(Command::Ping),
+            "query" => Some(Command::Query),
+            "watch" => Some(Command::Watch),
+            "replay" => Some(Command::Replay),
+            "proxy" => Some(Command::Proxy),
+            _ => None,
+        }
+    }
+}
+
+pub struct Args {
+    pub command: Command,
+    pub host: String,
+    /// `None` unless a port was given; the SRV record is only looked up then.
+    pub port: Option<u16>,
+    pub username: String,
+    /// Logged in next to `username` in the chat session.
+    pub bots: Vec<BotSpec>,
+    pub skip_status: bool,
+    pub favicon_out: Option<PathBuf>,
+    pub favicon_preview: bool,
+    pub discover_lan: bool,
+    pub query_port: Option<u16>,
+    pub protocol: Option<i32>,
+    pub connect: ConnectPolicy,
+    pub virtual_host: VirtualHost,
+    pub socket: SocketOptions,
+    pub reconnect: ReconnectPolicy,
+    pub keep_alive_timeout: Option<Duration>,
+    pub proxy: Option<Socks5Proxy>,
+    pub tui: bool,
+    pub join_messages: bool,
+    pub history_file: Option<PathBuf>,
+    pub seen_file: Option<PathBuf>,
+    pub log_dir: Option<PathBuf>,
+    pub scripts: Vec<PathBuf>,
+    pub irc_listen: Option<SocketAddr>,
+    pub irc_password: Option<String>,
+    pub api_listen: Option<SocketAddr>,
+    /// From the config file only.
+    pub responders: Vec<ResponderRule>,
+    /// From the config file, more can be added with `.schedule add`.
+    pub schedules: Vec<ScheduleSpec>,
+    /// From the config file only.
+    #[cfg(feature = "discord")]
+    pub discord: Option<DiscordConfig>,
+    /// From the config file only.
+    #[cfg(feature = "telegram")]
+    pub telegram: Option<TelegramConfig>,
+    pub chat_format: String,
+    pub no_color: bool,
+    pub plain: bool,
+    pub json: bool,
+    /// What `send` sends.
+    pub message: Option<String>,
+    /// How many pings `ping` sends.
+    pub ping_count: usize,
+    /// Checked by `status` instead of `host`.
+    pub server_list: Option<PathBuf>,
+    pub watch_interval: Duration,
+    /// Run by `watch` when the server goes down or comes back.
+    pub alert_command: Option<String>,
+    /// Where `proxy` listens.
+    pub proxy_listen: SocketAddr,
+    /// Whether `proxy` dumps packets.
+    pub proxy_hex: bool,
+    /// Packets `proxy` doesn't print.
+    pub proxy_hide: Vec<String>,
+    pub lang_file: Option<PathBuf>,
+    pub settings: ClientSettings,
+    pub brand: String,
+    pub velocity_secret: Option<String>,
+    pub bungee_spoof: Option<IpAddr>,
+    pub anti_afk_interval: Option<Duration>,
+    pub anti_afk_actions: Vec<AfkAction>,
+    pub accept_resource_packs: bool,
+    pub highlight_words: Vec<String>,
+    pub notify: bool,
+    pub color_codes: ColorCodes,
+    pub chat_rate: Option<f64>,
+    pub debug_packets: bool,
+    pub capture: Option<PathBuf>,
+    /// Raised by `-v`, INFO by default.
+    pub log_level: Level,
+    pub log_file: Option<PathBuf>,
+    /// The capture file `replay` reads.
+    pub replay_file: Option<PathBuf>,
+    pub scrollback: usize,
+    pub ignored: Vec<String>,
+    pub filters: Vec<String>,
+    /// Where `.ignore` and `.filter` save their lists.
+    pub config_path: String,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Args {
+            command: Command::Chat,
+            host: String::from("127.0.0.1"),
+            port: None,
+            username: String::from("Tester12"),
+            bots: Vec::new(),
+            skip_status: false,
+            favicon_out: Some(PathBuf::from("server-icon.png")),
+            favicon_preview: false,
+            discover_lan: false,
+            query_port: None,
+            protocol: None,
+            connect: ConnectPolicy::default(),
+            virtual_host: VirtualHost::default(),
+            socket: SocketOptions::default(),
+            reconnect: ReconnectPolicy::default(),
+            keep_alive_timeout: Some(Duration::from_secs(30)),
+            proxy: None,
+            tui: false,
+            join_messages: false,
+            history_file: readline::default_history_path(),
+            seen_file: seen::default_path(),
+            log_dir: None,
+            scripts: Vec::new(),
+            irc_listen: None,
+            irc_password: None,
+            api_listen: None,
+            responders: Vec::new(),
+            schedules: Vec::new(),
+            #[cfg(feature = "discord")]
+            discord: None,
+            #[cfg(feature = "telegram")]
+            telegram: None,
+            chat_format: String::from(DEFAULT_CHAT_FORMAT),
+            no_color: false,
+            plain: false,
+            json: false,
+            message: None,
+            ping_count: 4,
+            server_list: None,
+            watch_interval: Duration::from_secs(30),
+            alert_command: None,
+            proxy_listen: SocketAddr::from(([127, 0, 0, 1], 25566)),
+            proxy_hex: false,
+            proxy_hide: Vec::new(),
+            lang_file: None,
+            settings: ClientSettings::default(),
+            brand: String::from("vanilla"),
+            velocity_secret: None,
+            bungee_spoof: None,
+            anti_afk_interval: None,
+            anti_afk_actions: AntiAfk::default().actions,
+            accept_resource_packs: true,
+            highlight_words: Vec::new(),
+            notify: false,
+            color_codes: ColorCodes::Keep,
+            chat_rate: Some(1.0),
+            debug_packets: false,
+            capture: None,
+            log_level: Level::INFO,
+            log_file: None,
+            replay_file: None,
+            scrollback: DEFAULT_SCROLLBACK,
+            ignored: Vec::new(),
+            filters: Vec::new(),
+            config_path: String::from(DEFAULT_CONFIG_PATH),
+        }
+    }
+}
+
+fn next_value(
+    args: &mut impl Iterator<Item = String>,
+    flag: &str,
+) -> Result<String, Box<dyn Error>> {
+    match args.next() {
+        Some(value) => Ok(value),
+        None => Err(format!("Missing value for {}", flag).into()),
+    }
+}
+
+/// Turns a number of seconds from a flag or the config file into a duration. Negative,
+/// NaN, infinite and too big values are an error instead of a panic in `Duration`.
+pub fn seconds(seconds: f64, name: &str) -> Result<Duration, Box<dyn Error>> {
+    Duration::try_from_secs_f64(seconds).map_err(|_| {
+        format!(
+            "{} must be a number of seconds, 0 or more (got {:?})",
+            name, seconds
+        )
+        .into()
+    })
+}
+
+/// Like [`seconds`], for durations that can't be 0.
+pub fn positive_seconds(value: f64, name: &str) -> Result<Duration, Box<dyn Error>> {
+    match seconds(value, name)? {
+        duration if duration.is_zero() => Err(format!("{} must be more than 0", name).into()),
+        duration => Ok(duration),
+    }
+}
+
+/// Turns a number of seconds into a timeout, with 0 meaning "no timeout".
+pub fn seconds_or_none(value: f64, name: &str) -> Result<Option<Duration>, Box<dyn Error>> {
+    match value == 0.0 {
+        true => Ok(None),
+        false => seconds(value, name).map(Some),
+    }
+}
+
+pub fn parse_color_codes(name: &str) -> Result<ColorCodes, Box<dyn Error>> {
+    match ColorCodes::parse(name) {
+        Some(color_codes) => Ok(color_codes),
+        None => Err(format!(
+            "Unknown color code mode '{}', expected \"keep\", \"translate\" or \"strip\"",
+            name
+        )
+        .into()),
+    }
+}
+
+/// `HOST:PORT`, or just a port on 127.0.0.1.
+pub fn parse_listen_address(text: &str) -> Result<SocketAddr, Box<dyn Error>> {
+    if let Ok(port) = text.parse::<u16>() {
+        return Ok(SocketAddr::from(([127, 0, 0, 1], port)));
+    }
+    match text.parse() {
+        Ok(address) => Ok(address),
+        Err(_) => Err(format!("Invalid address '{}', expected [HOST:]PORT", text).into()),
+    }
+}
+
+/// Turns a rate per second into a limit, with 0 meaning "no limit".
+pub fn rate_or_none(rate: f64, name: &str) -> Result<Option<f64>, Box<dyn Error>> {
+    if rate == 0.0 {
+        return Ok(None);
+    }
+    match rate > 0.0 && rate.is_finite() && Duration::try_from_secs_f64(1.0 / rate).is_ok() {
+        true => Ok(Some(rate)),
+        false => Err(format!(
+            "{} must be a number of messages per second, 0 for no limit (got {:?})",
+            name, rate
+        )
+        .into()),
+    }
+}
+
+impl Args {
+    pub fn parse() -> Result<Args, Box<dyn Error>> {
+        Args::parse_from(env::args().skip(1))
+    }
+
+    /// `host:port` for messages, with the default port if none was given.
+    pub fn address(&self) -> String {
+        format!(
+            "{}:{}",
+            self.host,
+            self.port.unwrap_or(mclient_project::DEFAULT_PORT)
+        )
+    }
+
+    pub fn parse_from(args: impl IntoIterator<Item = String>) -> Result<Args, Box<dyn Error>> {
+        let args: Vec<String> = args.into_iter().collect();
+        let mut res: Args = Args::default();
+
+        // The config file is applied first so that flags can override it
+        let mut config_path: Option<String> = None;
+        let mut profile: Option<String> = None;
+        for (i, arg) in args.iter().enumerate() {
+            match arg.as_str() {
+                "--config" => config_path = args.get(i + 1).cloned(),
+                "--profile" => profile = args.get(i + 1).cloned(),
+                _ => {}
+            }
+        }
+
+        let explicit: bool = config_path.is_some();
+        let config_path: String = config_path.unwrap_or(String::from(DEFAULT_CONFIG_PATH));
+        match Config::load(&config_path, explicit)? {
+            Some(config) => config.apply(profile.as_deref(), &mut res)?,
+            None if profile.is_some() => {
+                return Err(format!(
+                    "--profile needs a config file, {} doesn't exist",
+                    config_path
+                )
+                .into());
+            }
+            None => {}
+        }
+        res.config_path = config_path;
+
+        let mut args = args.into_iter().peekable();
+        if let Some(command) = args.peek().and_then(|arg| Command::parse(arg)) {
+            res.command = command;
+            args.next();
+        }
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--config" | "--profile" => {
+                    next_value(&mut args, &arg)?;
+                }
+                "-h" | "--host" => res.host = next_value(&mut args, &arg)?,
+                "-p" | "--port" => res.port = Some(next_value(&mut args, &arg)?.parse()?),
+                "--handshake-host" => res.virtual_host.host = Some(next_value(&mut args, &arg)?),
+                "--handshake-port" => {
+                    res.virtual_host.port = Some(next_value(&mut args, &arg)?.parse()?)
+                }
+                "-u" | "--username" => res.username = next_value(&mut args, &arg)?,
+                "--bot" => res
+                    .bots
+                    .push(BotSpec::parse(&next_value(&mut args, &arg)?)?),
+                "--skip-status" => res.skip_status = true,
+                "--favicon-out" => {
+                    res.favicon_out = Some(PathBuf::from(next_value(&mut args, &arg)?))
+                }
+                "--no-favicon" => res.favicon_out = None,
+                "--favicon-preview" => res.favicon_preview = true,
+                "--tui" => res.tui = true,
+                "--join-messages" => res.join_messages = true,
+                "--discover-lan" => res.discover_lan = true,
+                "--query-port" => res.query_port = Some(next_value(&mut args, &arg)?.parse()?),
+                "--history-file" => {
+                    res.history_file = Some(PathBuf::from(next_value(&mut args, &arg)?))
+                }
+                "--no-history" => res.history_file = None,
+                "--seen-file" => res.seen_file = Some(PathBuf::from(next_value(&mut args, &arg)?)),
+                "--no-seen" => res.seen_file = None,
+                "--chat-format" => res.chat_format = next_value(&mut args, &arg)?,
+                "--no-color" => res.no_color = true,
+                "--plain" => res.plain = true,
+                "--json" => res.json = true,
+                "--message" if res.command == Command::Send => {
+                    res.message = Some(next_value(&mut args, &arg)?)
+                }
+                "--count" if res.command == Command::Ping => {
+                    res.ping_count = next_value(&mut args, &arg)?.parse()?
+                }
+                "--servers" if res.command == Command::Status => {
+                    res.server_list = Some(PathBuf::from(next_value(&mut args, &arg)?))
+                }
+                "--interval" if res.command == Command::Watch => {
+                    res.watch_interval =
+                        positive_seconds(next_value(&mut args, &arg)?.parse()?, &arg)?
+                }
+                "--alert" if res.command == Command::Watch => {
+                    res.alert_command = Some(next_value(&mut args, &arg)?)
+                }
+                "--listen" if res.command == Command::Proxy => {
+                    res.proxy_listen = parse_listen_address(&next_value(&mut args, &arg)?)?
+                }
+                "--hex" if res.command == Command::Proxy => res.proxy_hex = true,
+                "--hide" if res.command == Command::Proxy => {
+                    res.proxy_hide.push(next_value(&mut args, &arg)?)
+                }
+                "--lang" => res.lang_file = Some(PathBuf::from(next_value(&mut args, &arg)?)),
+                "--velocity-secret" => res.velocity_secret = Some(next_value(&mut args, &arg)?),
+                "--bungee-spoof" => res.bungee_spoof = Some(next_value(&mut args, &arg)?.parse()?),
+                "--anti-afk" => {
+                    res.anti_afk_interval =
+                        seconds_or_none(next_value(&mut args, &arg)?.parse()?, &arg)?
+                }
+                "--decline-resource-packs" => res.accept_resource_packs = false,
+                "--highlight" => res.highlight_words.push(next_value(&mut args, &arg)?),
+                "--notify" => res.notify = true,
+                "--color-codes" => {
+                    res.color_codes = parse_color_codes(&next_value(&mut args, &arg)?)?
+                }
+                "--chat-rate" => {
+                    res.chat_rate = rate_or_none(next_value(&mut args, &arg)?.parse()?, &arg)?
+                }
+                "--debug-packets" => res.debug_packets = true,
+                "--capture" => res.capture = Some(PathBuf::from(next_value(&mut args, &arg)?)),
+                "-v" | "--verbose" => res.log_level = logging::more_verbose(res.log_level),
+                "-vv" => {
+                    res.log_level = logging::more_verbose(logging::more_verbose(res.log_level))
+                }
+                "--log-file" => res.log_file = Some(PathBuf::from(next_value(&mut args, &arg)?)),
+                "--scrollback" => res.scrollback = next_value(&mut args, &arg)?.parse()?,
+                "--brand" => res.brand = next_value(&mut args, &arg)?,
+                "--log-dir" => res.log_dir = Some(PathBuf::from(next_value(&mut args, &arg)?)),
+                "--irc-listen" => {
+                    res.irc_listen = Some(parse_listen_address(&next_value(&mut args, &arg)?)?)
+                }
+                "--irc-password" => res.irc_password = Some(next_value(&mut args, &arg)?),
+                "--api-listen" => {
+                    res.api_listen = Some(parse_listen_address(&next_value(&mut args, &arg)?)?)
+                }
+                "--script" => res
+                    .scripts
+                    .push(PathBuf::from(next_value(&mut args, &arg)?)),
+                "--protocol" => res.protocol = Some(next_value(&mut args, &arg)?.parse()?),
+                "--connect-timeout" => {
+                    // TcpStream::connect_timeout rejects a zero timeout
+                    res.connect.timeout =
+                        positive_seconds(next_value(&mut args, &arg)?.parse()?, &arg)?
+                }
+                "--connect-attempts" => {
+                    res.connect.attempts = next_value(&mut args, &arg)?.parse()?
+                }
+                "--retry-delay" => {
+                    res.connect.retry_delay = seconds(next_value(&mut args, &arg)?.parse()?, &arg)?
+                }
+                "--prefer-ipv4" => res.connect.prefer = IpPreference::Ipv4,
+                "--prefer-ipv6" => res.connect.prefer = IpPreference::Ipv6,
+                "--reconnect" => {
+                    res.reconnect.max_retries = next_value(&mut args, &arg)?.parse()?
+                }
+                "--backoff" => {
+                    res.reconnect.initial_backoff =
+                        seconds(next_value(&mut args, &arg)?.parse()?, &arg)?
+                }
+                "--max-backoff" => {
+                    res.reconnect.max_backoff =
+                        seconds(next_value(&mut args, &arg)?.parse()?, &arg)?
+                }
+                "--timeout" => {
+                    res.keep_alive_timeout =
+                        seconds_or_none(next_value(&mut args, &arg)?.parse()?, &arg)?
+                }
+                "--proxy" => res.proxy = Some(Socks5Proxy::parse(&next_value(&mut args, &arg)?)?),
+                "--help" => {
+                    println!("{}", USAGE);
+                    std::process::exit(0);
+                }
+                file if res.command == Command::Replay
+                    && res.replay_file.is_none()
+                    && !file.starts_with('-') =>
+                {
+                    res.replay_file = Some(PathBuf::from(file))
+                }
+                _ => return Err(format!("Unknown argument '{}'\n\n{}", arg, USAGE).into()),
+            }
+        }
+
+        if res.command == Command::Send && res.message.is_none() {
+            return Err(format!("send needs --message\n\n{}", USAGE).into());
+        }
+        if res.command == Command::Replay && res.replay_file.is_none() {
+            return Err(format!("replay needs a capture file\n\n{}", USAGE).into());
+        }
+
+        if res.plain && res.tui {
+            return Err("--plain can't be used with --tui".into());
+        }
+        if res.json && res.tui {
+            return Err("--json can't be used with --tui".into());
+        }
+
+        if res.username.is_empty() || res.username.len() > 16 {
+            return Err("The username must be between 1 and 16 characters long!".into());
+        }
+
+        Ok(res)
+    }
+}
@@ -0,0 +1,1536 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, Shutdown, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use azalea_chat::FormattedText;
+use tracing::{Span, debug, info, info_span, trace_span, warn};
+
+use crate::antiafk::{AfkAction, AntiAfk};
+use crate::bossbar::BossBar;
+use crate::capture::{Capture, CaptureState};
+use crate::commandtree::CommandTree;
+use crate::configuration;
+use crate::error::ClientError;
+use crate::event::Event;
+use crate::helper::{self, PacketReader};
+use crate::latency::{Latency, LatencyTracker};
+use crate::mojang;
+use crate::packet::{
+    self, BossBarAction, ClientboundPacket, PlayerInfoAction, ResourcePackStatus,
+    ServerboundPacket, TeamInfo, TitleAction,
+};
+use crate::player::{Health, Identity, PlayerInfo, Position};
+use crate::protocol::{self, PacketTable, Serverbound};
+use crate::proxy::Socks5Proxy;
+use crate::scoreboard::{Scoreboard, Sidebar};
+use crate::socket::SocketOptions;
+use crate::traffic::PacketCount;
+
+// Vanilla title times (fade in, stay, fade out) until the server sends its own
+const DEFAULT_TITLE_TICKS: i32 = 10 + 70 + 20;
+
+// Vanilla sends its position at least once a second, even when standing still
+const POSITION_INTERVAL: Duration = Duration::from_secs(1);
+
+// How long complete() waits for the server's suggestions
+const COMPLETION_TIMEOUT: Duration = Duration::from_secs(2);
+
+// How far the anti-AFK look turns the head, and how long it sneaks
+const AFK_LOOK_DEGREES: f32 = 15.0;
+const AFK_SNEAK_TIME: Duration = Duration::from_millis(500);
+
+// Longest chat message or command servers accept (1.11+)
+const MAX_CHAT_LENGTH: usize = 256;
+
+// How often the chat queue checks whether the client is closing while it's empty
+const QUEUE_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+// How often wait_for_spawn() checks whether the server has placed the player
+const SPAWN_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+// How long disconnect() waits for the writer thread to send what it was given
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Shared so the list can be copied and the lock released before they're called: a
+// handler may register another one
+type EventHandler = Arc<dyn Fn(&Event) + Send + Sync>;
+type DisconnectHandler = Arc<dyn Fn(&ClientError) + Send + Sync>;
+// The replaced range (if the version has one) and the matches from Command Suggestions
+type Suggestions = (Option<(usize, usize)>, Vec<String>);
+type LoginPluginHandler = Arc<dyn Fn(&[u8]) -> Option<Vec<u8>> + Send + Sync>;
+
+/// How connections to the server are opened, for the first login and every reconnect.
+#[derive(Clone, Debug)]
+pub struct ConnectPolicy {
+    /// How many times to try before giving up, at least once.
+    pub attempts: u32,
+    /// How long each attempt waits for the server to accept the connection.
+    pub timeout: Duration,
+    /// The pause between attempts.
+    pub retry_delay: Duration,
+    /// Which address family is tried first when the host has both.
+    pub prefer: IpPreference,
+}
+
+/// The order addresses are tried in. The families take turns either way, so a broken
+/// IPv6 route only delays the connection instead of failing it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IpPreference {
+    /// Start with the family of the first address the resolver returned.
+    #[default]
+    Any,
+    Ipv4,
+    Ipv6,
+}
+
+impl Default for ConnectPolicy {
+    fn default() -> Self {
+        ConnectPolicy {
+            attempts: 5,
+            timeout: Duration::from_secs(5),
+            retry_delay: Duration::from_secs(1),
+            prefer: IpPreference::Any,
+        }
+    }
+}
+
+/// The address written into the handshake, for servers behind proxies that route by
+/// host name. Anything left `None` is taken from the address connected to.
+#[derive(Clone, Debug, Default)]
+pub struct VirtualHost {
+    pub host: Option<String>,
+    pub port: Option<u16>,
+}
+
+impl VirtualHost {
+    pub(crate) fn address<'a>(&'a self, host: &'a str, port: u16) -> (&'a str, u16) {
+        (
+            self.host.as_deref().unwrap_or(host),
+            self.port.unwrap_or(port),
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ReconnectPolicy {
+    /// How many times to try reconnecting before giving up. 0 disables reconnecting.
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        ReconnectPolicy {
+            max_retries: 0,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChatMode {
+    Enabled,
+    CommandsOnly,
+    Hidden,
+}
+
+/// What the client tells the server about itself after logging in. Some servers don't
+/// treat the player as joined (no chat, no player list) until they've received this.
+#[derive(Clone, Debug)]
+pub struct ClientSettings {
+    /// e.g. `en_us`; servers use it to pick the language of their messages.
+    pub locale: String,
+    /// In chunks, 2 to 32.
+    pub view_distance: u8,
+    pub chat_mode: ChatMode,
+    pub chat_colors: bool,
+    /// Bit mask of the skin layers shown: cape, jacket, sleeves, pants legs and hat.
+    pub skin_parts: u8,
+    /// Whether the player shows up in the server list sample (1.18+).
+    pub allow_server_listings: bool,
+}
+
+impl Default for ClientSettings {
+    fn default() -> Self {
+        ClientSettings {
+            locale: String::from("en_us"),
+            view_distance: 2,
+            chat_mode: ChatMode::Enabled,
+            chat_colors: true,
+            skin_parts: 0x7F,
+            allow_server_listings: true,
+        }
+    }
+}
+
+/// Answers to the Login Plugin Requests that servers (and proxies in front of them) send
+/// during login. Channels without a handler get the "not understood" reply.
+#[derive(Clone, Default)]
+pub struct LoginPlugins {
+    handlers: Vec<(String, LoginPluginHandler)>,
+}
+
+impl LoginPlugins {
+    /// Registers `handler` for requests on `channel`. It gets the request payload and
+    /// returns the response payload, or `None` to reply that it isn't understood.
+    pub fn register<F>(&mut self, channel: &str, handler: F)
+    where
+        F: Fn(&[u8]) -> Option<Vec<u8>> + Send + Sync + 'static,
+    {
+        self.handlers
+            .push((String::from(channel), Arc::new(handler)));
+    }
+
+    pub(crate) fn answer(&self, channel: &str, data: &[u8]) -> Option<Vec<u8>> {
+        self.handlers
+            .iter()
+            .find(|entry| entry.0 == channel)
+            .and_then(|entry| (entry.1)(data))
+    }
+}
+
+impl fmt::Debug for LoginPlugins {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.handlers.iter().map(|entry| &entry.0))
+            .finish()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ClientConfig {
+    pub host: String,
+    /// `None` looks up the host's SRV record first, like the vanilla client, and falls
+    /// back to [`DEFAULT_PORT`](crate::DEFAULT_PORT).
+    pub port: Option<u16>,
+    pub username: String,
+    /// Protocol version sent in the handshake.
+    pub protocol: i32,
+    pub connect: ConnectPolicy,
+    pub virtual_host: VirtualHost,
+    pub socket: SocketOptions,
+    pub reconnect: ReconnectPolicy,
+    pub proxy: Option<Socks5Proxy>,
+    /// Drop the connection if the server hasn't sent a keep alive for this long.
+    /// `None` waits forever.
+    pub keep_alive_timeout: Option<Duration>,
+    pub settings: ClientSettings,
+    /// Sent to the server as the client's brand, like "vanilla" or "fabric".
+    pub brand: String,
+    pub login_plugins: LoginPlugins,
+    /// Claim to connect from this address through BungeeCord, by adding its legacy IP
+    /// forwarding fields to the handshake. Only backends with `bungeecord: true` accept it.
+    pub bungee_forwarding: Option<IpAddr>,
+    /// Respawn right away after dying instead of staying on the death screen.
+    pub auto_respawn: bool,
+    pub anti_afk: Option<AntiAfk>,
+    /// Tell the server that its resource packs were loaded, instead of declining them.
+    /// Nothing is downloaded either way.
+    pub accept_resource_packs: bool,
+    /// At most this many chat messages and commands are sent per second, the rest wait
+    /// in a queue. Vanilla kicks for spam at a sustained rate above one per second.
+    /// `None` sends everything right away.
+    pub chat_rate: Option<f64>,
+    /// Publish `Event::IgnoredPacket` for every play packet the client doesn't handle.
+    pub report_ignored_packets: bool,
+    /// Write every frame the server sends to a capture file, for `CaptureReader`.
+    pub capture: Option<Capture>,
+}
+
+impl ClientConfig {
+    pub fn new(host: &str, port: Option<u16>, username: &str) -> Self {
+        ClientConfig {
+            host: String::from(host),
+            port,
+            username: String::from(username),
+            protocol: helper::DEFAULT_PROTOCOL,
+            connect: ConnectPolicy::default(),
+            virtual_host: VirtualHost::default(),
+            socket: SocketOptions::default(),
+            reconnect: ReconnectPolicy::default(),
+            proxy: None,
+            keep_alive_timeout: Some(Duration::from_secs(30)),
+            settings: ClientSettings::default(),
+            brand: String::from("vanilla"),
+            login_plugins: LoginPlugins::default(),
+            bungee_forwarding: None,
+            auto_respawn: true,
+            anti_afk: None,
+            accept_resource_packs: true,
+            chat_rate: Some(1.0),
+            report_ignored_packets: false,
+            capture: None,
+        }
+    }
+}
+
+// What the writer thread is given to do, in order.
+enum Outgoing {
+    Packet {
+        id: i32,
+        data: Vec<u8>,
+    },
+    // The answer to a keep alive that arrived at `received`
+    KeepAlive {
+        id: i32,
+        data: Vec<u8>,
+        received: Instant,
+    },
+    // The connection to write to after reconnecting, with its compression threshold
+    Connection(TcpStream, i32),
+    // Answered once everything before it has been written
+    Flush(Sender<()>),
+    Stop,
+}
+
+// State shared between the Client handle and the reader thread.
+struct Shared {
+    config: ClientConfig,
+    packets: &'static PacketTable,
+    // Packets are written by the writer thread; this copy of the stream is only there to
+    // shut the connection down
+    socket: Mutex<TcpStream>,
+    outgoing: Sender<Outgoing>,
+    threshold: AtomicI32,
+    online_players: Mutex<HashMap<u128, PlayerInfo>>,
+    // In the order the server added them, like they are stacked in game
+    boss_bars: Mutex<Vec<(u128, BossBar)>>,
+    tab_list: Mutex<(FormattedText, FormattedText)>,
+    scoreboard: Mutex<Scoreboard>,
+    identity: Mutex<Identity>,
+    server_brand: Mutex<Option<String>>,
+    // Unknown until the server first teleports the player
+    position: Mutex<Option<Position>>,
+    health: Mutex<Option<Health>>,
+    entity_id: Mutex<Option<i32>>,
+    next_completion_id: AtomicI32,
+    // Requests waiting for their suggestions, oldest first
+    pending_completions: Mutex<Vec<(i32, Sender<Suggestions>)>>,
+    // Only sent by 1.13+ servers
+    commands: Mutex<Option<CommandTree>>,
+    latency: Mutex<LatencyTracker>,
+    // Play state packets received, by ID
+    packet_counts: Mutex<HashMap<i32, PacketCount>>,
+    // Chat messages and commands waiting for send_queued, and how many there are
+    chat_queue: Sender<ServerboundPacket>,
+    queued_chat: AtomicUsize,
+    event_handlers: Mutex<Vec<EventHandler>>,
+    disconnect_handlers: Mutex<Vec<DisconnectHandler>>,
+    closing: AtomicBool,
+}
+
+pub struct Client {
+    shared: Arc<Shared>,
+    reader: Mutex<Option<JoinHandle<()>>>,
+    writer: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Client {
+    /// Connects to the server, logs in with `username` and starts reading packets
+    /// on a background thread. `protocol` is the version number sent in the handshake.
+    pub fn connect(
+        ip: &str,
+        port: Option<u16>,
+        username: &str,
+        protocol: i32,
+    ) -> Result<Client, ClientError> {
+        let mut config: ClientConfig = ClientConfig::new(ip, port, username);
+        config.protocol = protocol;
+
+        Client::connect_with(config)
+    }
+
+    pub fn connect_with(config: ClientConfig) -> Result<Client, ClientError> {
+        let span: Span = info_span!(
+            "connection",
+            username = %config.username,
+            host = %config.host,
+            port = config.port.unwrap_or(helper::DEFAULT_PORT)
+        );
+        let packets: &'static PacketTable = protocol::require_table(config.protocol)?;
+        let chat_interval: Option<Duration> = chat_interval(config.chat_rate)?;
+        let (stream, logged_in): (TcpStream, LoggedIn) = span.in_scope(|| establish(&config))?;
+        let threshold: i32 = logged_in.threshold;
+        let (chat_queue, queued): (Sender<ServerboundPacket>, Receiver<ServerboundPacket>) =
+            mpsc::channel();
+        let (outgoing, to_write): (Sender<Outgoing>, Receiver<Outgoing>) = mpsc::channel();
+        let writer_stream: TcpStream = stream.try_clone()?;
+
+        let shared: Arc<Shared> = Arc::new(Shared {
+            packets,
+            scoreboard: Mutex::new(Scoreboard::new(config.protocol)),
+            config,
+            socket: Mutex::new(stream.try_clone()?),
+            outgoing,
+            threshold: AtomicI32::new(threshold),
+            online_players: Mutex::new(HashMap::new()),
+            boss_bars: Mutex::new(Vec::new()),
+            tab_list: Mutex::new((FormattedText::default(), FormattedText::default())),
+            identity: Mutex::new(logged_in.identity),
+            server_brand: Mutex::new(logged_in.server_brand),
+            position: Mutex::new(None),
+            health: Mutex::new(None),
+            entity_id: Mutex::new(None),
+            next_completion_id: AtomicI32::new(0),
+            pending_completions: Mutex::new(Vec::new()),
+            commands: Mutex::new(None),
+            latency: Mutex::new(LatencyTracker::default()),
+            packet_counts: Mutex::new(HashMap::new()),
+            chat_queue,
+            queued_chat: AtomicUsize::new(0),
+            event_handlers: Mutex::new(Vec::new()),
+            disconnect_handlers: Mutex::new(Vec::new()),
+            closing: AtomicBool::new(false),
+        });
+
+        let reader: JoinHandle<()> = {
+            let shared: Arc<Shared> = Arc::clone(&shared);
+            let span: Span = span.clone();
+            thread::spawn(move || span.in_scope(|| run(stream, &shared)))
+        };
+
+        let writer: JoinHandle<()> = {
+            let shared: Arc<Shared> = Arc::clone(&shared);
+            thread::spawn(move || {
+                span.in_scope(|| write_loop(writer_stream, threshold, &to_write, &shared))
+            })
+        };
+
+        {
+            let shared: Arc<Shared> = Arc::clone(&shared);
+            thread::spawn(move || send_positions(&shared));
+        }
+
+        {
+            let shared: Arc<Shared> = Arc::clone(&shared);
+            thread::spawn(move || send_queued(&shared, &queued, chat_interval));
+        }
+
+        if let Some(anti_afk) = shared.config.anti_afk.clone() {
+            let shared: Arc<Shared> = Arc::clone(&shared);
+            thread::spawn(move || prevent_afk(&shared, &anti_afk));
+        }
+
+        Ok(Client {
+            shared,
+            reader: Mutex::new(Some(reader)),
+            writer: Mutex::new(Some(writer)),
+        })
+    }
+
+    /// Sends a chat message (or a `/command`) to the server. On 1.19+ commands go in the
+    /// Chat Command packet, with empty argument signatures where the version wants them.
+    /// Messages longer than the server allows are split into several, at spaces where
+    /// possible. Everything goes through the queue limited by `ClientConfig::chat_rate`,
+    /// so a failed send shows up as a disconnect rather than here.
+    pub fn send_chat(&self, message: &str) -> Result<(), ClientError> {
+        if message.trim() == "/" {
+            return Err("The command is empty!".into());
+        }
+
+        // 1.19+ servers only accept commands through their own packet
+        let packets: Vec<ServerboundPacket> = match message.strip_prefix('/') {
+            // A command cut in two would run as two different commands
+            Some(_) if message.chars().count() > MAX_CHAT_LENGTH => {
+                return Err(format!(
+                    "The command can't be longer than {} characters!",
+                    MAX_CHAT_LENGTH
+                )
+                .into());
+            }
+            Some(command)
+                if self
+                    .shared
+                    .packets
+                    .serverbound_id(Serverbound::ChatCommand)
+                    .is_some() =>
+            {
+                vec![ServerboundPacket::ChatCommand {
+                    command: String::from(command),
+                }]
+            }
+            _ => split_message(message, MAX_CHAT_LENGTH)
+                .into_iter()
+                .map(|message| ServerboundPacket::ChatMessage { message })
+                .collect(),
+        };
+
+        for packet in packets {
+            self.shared.queued_chat.fetch_add(1, Ordering::SeqCst);
+            if self.shared.chat_queue.send(packet).is_err() {
+                return Err("The client is closing".into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Asks the server how `text` (a `/command` being typed) could continue, and returns
+    /// the completed lines. Waits up to two seconds; no answer means no completions.
+    pub fn complete(&self, text: &str) -> Result<Vec<String>, ClientError> {
+        Ok(match self.suggestions(text)? {
+            Some((range, matches)) => apply_suggestions(text, range, matches),
+            None => Vec::new(),
+        })
+    }
+
+    /// Measures a round trip to the server and back, by timing how long it takes to
+    /// answer a completion request for `/`. The result is also kept for `latency()`.
+    pub fn measure_latency(&self) -> Result<Duration, ClientError> {
+        let start: Instant = Instant::now();
+        if self.suggestions("/")?.is_none() {
+            return Err(ClientError::Timeout(String::from(
+                "the server didn't answer a completion request",
+            )));
+        }
+        let round_trip: Duration = start.elapsed();
+        self.shared.latency.lock().unwrap().add_echo(round_trip);
+        Ok(round_trip)
+    }
+
+    /// Returns the round trips measured so far and how fast keep alives are answered.
+    pub fn latency(&self) -> Latency {
+        self.shared.latency.lock().unwrap().latency()
+    }
+
+    /// Returns how many packets of each ID the server sent since connecting, sorted by
+    /// ID. Only the play state is counted, where nearly all the traffic is.
+    pub fn packet_counts(&self) -> Vec<PacketCount> {
+        let mut counts: Vec<PacketCount> = self
+            .shared
+            .packet_counts
+            .lock()
+            .unwrap()
+            .values()
+            .copied()
+            .collect();
+        counts.sort_by_key(|count| count.id);
+        counts
+    }
+
+    // Sends a Command Suggestions request and waits for the answer
+    fn suggestions(&self, text: &str) -> Result<Option<Suggestions>, ClientError> {
+        let id: i32 = self
+            .shared
+            .next_completion_id
+            .fetch_add(1, Ordering::SeqCst);
+        let (sender, receiver): (Sender<Suggestions>, Receiver<Suggestions>) = mpsc::channel();
+        self.shared
+            .pending_completions
+            .lock()
+            .unwrap()
+            .push((id, sender));
+
+        let res: Result<(), ClientError> = self.send(&ServerboundPacket::CommandSuggestions {
+            id,
+            text: String::from(text),
+        });
+        let suggestions: Option<Suggestions> = match res {
+            Ok(()) => receiver.recv_timeout(COMPLETION_TIMEOUT).ok(),
+            Err(_) => None,
+        };
+        self.shared
+            .pending_completions
+            .lock()
+            .unwrap()
+            .retain(|entry| entry.0 != id);
+        res?;
+        Ok(suggestions)
+    }
+
+    /// Sends a raw play state packet to the server. The packet is written by the writer
+    /// thread, so only encoding errors are returned here; a failed write shows up as a
+    /// disconnect.
+    pub fn send(&self, packet: &ServerboundPacket) -> Result<(), ClientError> {
+        self.shared.send(packet)
+    }
+
+    /// Registers a callback invoked for every event on the connection.
+    pub fn on_event<F>(&self, handler: F)
+    where
+        F: Fn(&Event) + Send + Sync + 'static,
+    {
+        self.shared
+            .event_handlers
+            .lock()
+            .unwrap()
+            .push(Arc::new(handler));
+    }
+
+    /// Registers a callback invoked for every chat message received from the server.
+    pub fn on_message<F>(&self, handler: F)
+    where
+        F: Fn(&FormattedText) + Send + Sync + 'static,
+    {
+        self.on_event(move |event| {
+            if let Event::ChatReceived { message, .. } = event {
+                handler(message);
+            }
+        });
+    }
+
+    /// Registers a callback invoked once if the connection is lost (or the server kicks
+    /// the client) and reconnecting failed. It isn't called after `disconnect()`.
+    pub fn on_disconnect<F>(&self, handler: F)
+    where
+        F: Fn(&ClientError) + Send + Sync + 'static,
+    {
+        self.shared
+            .disconnect_handlers
+            .lock()
+            .unwrap()
+            .push(Arc::new(handler));
+    }
+
+    /// Returns a snapshot of the player list, keyed by UUID.
+    pub fn online_players(&self) -> HashMap<u128, PlayerInfo> {
+        self.shared.online_players.lock().unwrap().clone()
+    }
+
+    /// Returns a snapshot of the boss bars shown at the top of the screen, top first.
+    pub fn boss_bars(&self) -> Vec<BossBar> {
+        self.shared
+            .boss_bars
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|entry| entry.1.clone())
+            .collect()
+    }
+
+    /// Returns the header and footer the server shows around the player list. Both are
+    /// empty until the server sets them.
+    pub fn tab_list(&self) -> (FormattedText, FormattedText) {
+        self.shared.tab_list.lock().unwrap().clone()
+    }
+
+    /// Returns the team `name` is on, with the prefix and suffix (ranks like "[Admin]") and
+    /// color the game shows around their name. Teams are kept up to date alongside the
+    /// player list.
+    pub fn team_of(&self, name: &str) -> Option<TeamInfo> {
+        self.shared
+            .scoreboard
+            .lock()
+            .unwrap()
+            .team_of(name)
+            .cloned()
+    }
+
+    /// Returns `name` between its team's prefix and suffix, in the team color. `None` if
+    /// the player isn't on a team.
+    pub fn decorated_name(&self, name: &str) -> Option<FormattedText> {
+        self.shared.scoreboard.lock().unwrap().decorate(name)
+    }
+
+    /// Returns the scoreboard objective shown in the sidebar, if there is one.
+    pub fn sidebar(&self) -> Option<Sidebar> {
+        self.shared.scoreboard.lock().unwrap().sidebar()
+    }
+
+    /// Returns the name and UUID the server logged the client in as.
+    pub fn identity(&self) -> Identity {
+        self.shared.identity.lock().unwrap().clone()
+    }
+
+    /// Returns the packet IDs used on this connection.
+    pub fn packets(&self) -> &'static PacketTable {
+        self.shared.packets
+    }
+
+    /// Returns the brand the server reported (e.g. "Paper"), if it sent one yet.
+    pub fn server_brand(&self) -> Option<String> {
+        self.shared.server_brand.lock().unwrap().clone()
+    }
+
+    /// Returns the commands the server declared, if it has (1.13+).
+    pub fn command_tree(&self) -> Option<CommandTree> {
+        self.shared.commands.lock().unwrap().clone()
+    }
+
+    /// Returns where the server last put the player, once it has.
+    pub fn position(&self) -> Option<Position> {
+        *self.shared.position.lock().unwrap()
+    }
+
+    /// Waits until the server has placed the player in the world, which is when vanilla
+    /// closes the loading screen and chat can be sent.
+    pub fn wait_for_spawn(&self, timeout: Duration) -> Result<(), ClientError> {
+        let start: Instant = Instant::now();
+        while self.position().is_none() {
+            if self.shared.closing.load(Ordering::SeqCst) {
+                return Err(ClientError::Protocol(String::from(
+                    "the connection was closed before spawning",
+                )));
+            }
+            if start.elapsed() > timeout {
+                return Err(ClientError::Timeout(format!(
+                    "not spawned after {} seconds",
+                    timeout.as_secs()
+                )));
+            }
+            thread::sleep(SPAWN_POLL_INTERVAL);
+        }
+        Ok(())
+    }
+
+    /// Returns the player's health, food and saturation, once the server has sent them.
+    pub fn health(&self) -> Option<Health> {
+        *self.shared.health.lock().unwrap()
+    }
+
+    /// Asks the server to respawn the player after dying.
+    pub fn respawn(&self) -> Result<(), ClientError> {
+        self.send(&ServerboundPacket::Respawn)
+    }
+
+    /// Sends what's left in the chat queue, closes the connection and waits for the
+    /// reader and writer threads to stop.
+    pub fn disconnect(&self) -> Result<(), ClientError> {
+        // Messages sent just before quitting (e.g. piped in) still go out
+        while self.shared.queued_chat.load(Ordering::SeqCst) > 0
+            && !self.shared.closing.load(Ordering::SeqCst)
+        {
+            thread::sleep(Duration::from_millis(50));
+        }
+
+        let (done, flushed): (Sender<()>, Receiver<()>) = mpsc::channel();
+        if self.shared.outgoing.send(Outgoing::Flush(done)).is_ok() {
+            let _ = flushed.recv_timeout(FLUSH_TIMEOUT);
+        }
+
+        self.close()
+    }
+
+    /// Like `disconnect`, but drops the queued chat instead of sending it. Nothing is
+    /// sent after this.
+    pub fn close(&self) -> Result<(), ClientError> {
+        self.shared.stop();
+        self.shared
+            .socket
+            .lock()
+            .unwrap()
+            .shutdown(Shutdown::Both)?;
+
+        for thread in [&self.reader, &self.writer] {
+            if let Some(thread) = thread.lock().unwrap().take() {
+                let _ = thread.join();
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Shared {
+    // Encodes the packet and hands it to the writer thread, without waiting for the write
+    fn send(&self, packet: &ServerboundPacket) -> Result<(), ClientError> {
+        let (id, data): (i32, Vec<u8>) = packet.encode(self.packets)?;
+        self.outgoing
+            .send(Outgoing::Packet { id, data })
+            .map_err(|_| ClientError::from("The client is closing"))
+    }
+
+    fn count_packet(&self, id: i32, length: usize) {
+        let mut counts = self.packet_counts.lock().unwrap();
+        let count: &mut PacketCount = counts.entry(id).or_insert(PacketCount {
+            id,
+            count: 0,
+            bytes: 0,
+        });
+        count.count += 1;
+        count.bytes += length as u64;
+    }
+
+    // Like send(), but the writer thread notes how long the answer took
+    fn answer_keep_alive(&self, id: i64, received: Instant) -> Result<(), ClientError> {
+        let (id, data): (i32, Vec<u8>) =
+            ServerboundPacket::KeepAlive { id }.encode(self.packets)?;
+        self.outgoing
+            .send(Outgoing::KeepAlive { id, data, received })
+            .map_err(|_| ClientError::from("The client is closing"))
+    }
+
+    // Nothing is sent after this, and the writer thread stops
+    fn stop(&self) {
+        self.closing.store(true, Ordering::SeqCst);
+        let _ = self.outgoing.send(Outgoing::Stop);
+    }
+
+    fn publish(&self, event: Event) {
+        let handlers: Vec<EventHandler> = self.event_handlers.lock().unwrap().clone();
+        for handler in handlers {
+            handler(&event);
+        }
+    }
+}
+
+// Puts each match in place of the part of `text` it completes
+fn apply_suggestions(
+    text: &str,
+    range: Option<(usize, usize)>,
+    matches: Vec<String>,
+) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let (start, end): (usize, usize) = match range {
+        Some((start, length)) => (start.min(chars.len()), (start + length).min(chars.len())),
+        None => (
+            chars.iter().rposition(|c| *c == ' ').map_or(0, |i| i + 1),
+            chars.len(),
+        ),
+    };
+
+    let before: String = chars[..start].iter().collect();
+    let after: String = chars[end..].iter().collect();
+    matches
+        .into_iter()
+        .map(|completion| format!("{}{}{}", before, completion, after))
+        .collect()
+}
+
+// Servers that require a pack kick clients that don't answer, or that decline it
+pub(crate) fn resource_pack_answer(
+    config: &ClientConfig,
+    url: &str,
+    hash: &str,
+) -> &'static [ResourcePackStatus] {
+    info!(
+        "The server sent a resource pack: {} (SHA-1: {}), {} it",
+        url,
+        if hash.is_empty() { "none" } else { hash },
+        if config.accept_resource_packs {
+            "accepting"
+        } else {
+            "declining"
+        }
+    );
+
+    if config.accept_resource_packs {
+        &[ResourcePackStatus::Accepted, ResourcePackStatus::Loaded]
+    } else {
+        &[ResourcePackStatus::Declined]
+    }
+}
+
+// What the server told the client while logging in
+struct LoggedIn {
+    threshold: i32,
+    identity: Identity,
+    // Only if it was sent during configuration
+    server_brand: Option<String>,
+}
+
+fn establish(config: &ClientConfig) -> Result<(TcpStream, LoggedIn), ClientError> {
+    let mut stream: TcpStream = helper::init_connection(
+        &config.host,
+        config.port,
+        config.proxy.as_ref(),
+        &config.connect,
+    )?;
+    config.socket.apply(&stream)?;
+
+    match log_in(&mut stream, config) {
+        Ok(logged_in) => Ok((stream, logged_in)),
+        Err(ClientError::Io(e))
+            if matches!(
+                e.kind(),
+                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+            ) =>
+        {
+            Err(ClientError::Timeout(String::from(
+                "the server stopped answering during login",
+            )))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+// Handshake, login and (1.20.2+) configuration, up to the play state
+fn log_in(stream: &mut TcpStream, config: &ClientConfig) -> Result<LoggedIn, ClientError> {
+    let _span = info_span!("login", protocol = config.protocol).entered();
+    let (host, port): (&str, u16) = config
+        .virtual_host
+        .address(&config.host, config.port.unwrap_or(helper::DEFAULT_PORT));
+    // BungeeCord passes the player's address, UUID and properties on after the host name
+    let server_address: String = match config.bungee_forwarding {
+        Some(address) => format!(
+            "{}\0{}\0{:032x}\0[]",
+            host,
+            address,
+            mojang::offline_uuid(&config.username)
+        ),
+        None => String::from(host),
+    };
+
+    // C -> S: Handshake
+    helper::send_handshake_packet(stream, &server_address, port, config.protocol, 2)?;
+    let (threshold, identity): (i32, Identity) = helper::login(
+        stream,
+        &config.username,
+        config.protocol,
+        &config.login_plugins,
+        config.capture.as_ref(),
+    )?;
+
+    if configuration::has_configuration(config.protocol) {
+        let server_brand: Option<String> = configuration::configure(stream, threshold, config)?;
+        return Ok(LoggedIn {
+            threshold,
+            identity,
+            server_brand,
+        });
+    }
+
+    // Older servers send their brand in the play state, it's picked up by read_loop
+    let packets: &PacketTable = protocol::require_table(config.protocol)?;
+    for packet in [
+        ServerboundPacket::ClientSettings(config.settings.clone()),
+        ServerboundPacket::PluginMessage {
+            channel: String::from(packet::brand_channel(config.protocol)),
+            data: packet::encode_brand(&config.brand)?,
+        },
+    ] {
+        let (packet_id, packet_buffer): (i32, Vec<u8>) = packet.encode(packets)?;
+        helper::send_packet(stream, packet_id, packet_buffer, threshold)?;
+    }
+
+    Ok(LoggedIn {
+        threshold,
+        identity,
+        server_brand: None,
+    })
+}
+
+fn run(mut stream: TcpStream, shared: &Shared) {
+    loop {
+        let e: ClientError = match read_loop(&mut stream, shared) {
+            Ok(()) => return,
+            Err(e) => e,
+        };
+
+        if shared.closing.load(Ordering::SeqCst) {
+            // disconnect() shut the stream down on purpose
+            return;
+        }
+
+        match reconnect(shared, &e) {
+            Some(new_stream) => stream = new_stream,
+            None => {
+                // Nothing is going to be sent anymore
+                shared.stop();
+                let disconnect_handlers: Vec<DisconnectHandler> =
+                    shared.disconnect_handlers.lock().unwrap().clone();
+                if disconnect_handlers.is_empty() {
+                    warn!("Connection closed: {}", e);
+                }
+                for handler in disconnect_handlers {
+                    handler(&e);
+                }
+                return;
+            }
+        }
+    }
+}
+
+// Writes the packets other threads send, so none of them waits for the network. A
+// failed write shuts the connection down, which the reader thread notices and handles
+// like any other lost connection.
+fn write_loop(
+    mut stream: TcpStream,
+    mut threshold: i32,
+    outgoing: &Receiver<Outgoing>,
+    shared: &Shared,
+) {
+    while let Ok(message) = outgoing.recv() {
+        if shared.closing.load(Ordering::SeqCst) {
+            return;
+        }
+
+        match message {
+            Outgoing::Packet { id, data } => {
+                if helper::send_packet(&mut stream, id, data, threshold).is_err() {
+                    let _ = stream.shutdown(Shutdown::Both);
+                }
+            }
+            Outgoing::KeepAlive { id, data, received } => {
+                match helper::send_packet(&mut stream, id, data, threshold) {
+                    Ok(_) => shared
+                        .latency
+                        .lock()
+                        .unwrap()
+                        .set_keep_alive_reply(received.elapsed()),
+                    Err(_) => {
+                        let _ = stream.shutdown(Shutdown::Both);
+                    }
+                }
+            }
+            Outgoing::Connection(new_stream, new_threshold) => {
+                stream = new_stream;
+                threshold = new_threshold;
+            }
+            Outgoing::Flush(done) => {
+                let _ = done.send(());
+            }
+            Outgoing::Stop => return,
+        }
+    }
+}
+
+// Keeps telling the server where the player is, so it isn't flagged as timed out
+fn send_positions(shared: &Shared) {
+    while !shared.closing.load(Ordering::SeqCst) {
+        thread::sleep(POSITION_INTERVAL);
+
+        let position: Option<Position> = *shared.position.lock().unwrap();
+        if let Some(position) = position {
+            // A failed send means the connection dropped, which the reader thread handles
+            let _ = shared.send(&ServerboundPacket::PlayerPosition {
+                position,
+                on_ground: true,
+            });
+        }
+    }
+}
+
+// Splits a chat message into pieces of at most `limit` characters, breaking at the last
+// space before the limit when there is one.
+fn split_message(message: &str, limit: usize) -> Vec<String> {
+    let mut res: Vec<String> = Vec::new();
+    let mut rest: &str = message.trim();
+
+    while rest.chars().count() > limit {
+        let cut: usize = rest
+            .char_indices()
+            .nth(limit)
+            .map_or(rest.len(), |(i, _)| i);
+        let end: usize = match rest[..cut].rfind(' ') {
+            _ if rest[cut..].starts_with(' ') => cut,
+            Some(space) if space > 0 => space,
+            _ => cut,
+        };
+        res.push(String::from(rest[..end].trim_end()));
+        rest = rest[end..].trim_start();
+    }
+    if !rest.is_empty() {
+        res.push(String::from(rest));
+    }
+    res
+}
+
+// The time between two queued messages. A rate so small that it doesn't fit in a
+// Duration is refused rather than panicking in the sender thread.
+fn chat_interval(chat_rate: Option<f64>) -> Result<Option<Duration>, ClientError> {
+    let Some(rate) = chat_rate.filter(|rate| *rate > 0.0) else {
+        return Ok(None);
+    };
+    match Duration::try_from_secs_f64(1.0 / rate) {
+        Ok(interval) => Ok(Some(interval)),
+        Err(_) => Err(format!("The chat rate {} is too small", rate).into()),
+    }
+}
+
+fn send_queued(shared: &Shared, queued: &Receiver<ServerboundPacket>, interval: Option<Duration>) {
+    let mut last_sent: Option<Instant> = None;
+
+    while !shared.closing.load(Ordering::SeqCst) {
+        let Ok(packet) = queued.recv_timeout(QUEUE_POLL_INTERVAL) else {
+            continue;
+        };
+
+        if let (Some(interval), Some(last_sent)) = (interval, last_sent) {
+            thread::sleep(interval.saturating_sub(last_sent.elapsed()));
+            if shared.closing.load(Ordering::SeqCst) {
+                return;
+            }
+        }
+        // Like positions, failed sends are left to the reader thread
+        let _ = shared.send(&packet);
+        shared.queued_chat.fetch_sub(1, Ordering::SeqCst);
+        last_sent = Some(Instant::now());
+    }
+}
+
+fn prevent_afk(shared: &Shared, anti_afk: &AntiAfk) {
+    let mut turn: usize = 0;
+    let mut look_right: bool = true;
+
+    while !shared.closing.load(Ordering::SeqCst) {
+        thread::sleep(anti_afk.interval);
+        let Some(action) = anti_afk.actions.get(turn % anti_afk.actions.len().max(1)) else {
+            return;
+        };
+        turn += 1;
+
+        // As with positions, failed sends are left to the reader thread
+        let _ = match action {
+            AfkAction::SwingArm => shared.send(&ServerboundPacket::SwingArm),
+            AfkAction::LookAround => {
+                let position: Option<Position> = {
+                    let mut position = shared.position.lock().unwrap();
+                    if let Some(position) = position.as_mut() {
+                        position.yaw += if look_right {
+                            AFK_LOOK_DEGREES
+                        } else {
+                            -AFK_LOOK_DEGREES
+                        };
+                    }
+                    *position
+                };
+                look_right = !look_right;
+
+                match position {
+                    Some(position) => shared.send(&ServerboundPacket::PlayerRotation {
+                        yaw: position.yaw,
+                        pitch: position.pitch,
+                        on_ground: true,
+                    }),
+                    None => Ok(()),
+                }
+            }
+            AfkAction::Sneak => {
+                let entity_id: Option<i32> = *shared.entity_id.lock().unwrap();
+                match entity_id {
+                    Some(entity_id) => shared
+                        .send(&ServerboundPacket::Sneak {
+                            entity_id,
+                            sneaking: true,
+                        })
+                        .and_then(|()| {
+                            thread::sleep(AFK_SNEAK_TIME);
+                            shared.send(&ServerboundPacket::Sneak {
+                                entity_id,
+                                sneaking: false,
+                            })
+                        }),
+                    None => Ok(()),
+                }
+            }
+        };
+    }
+}
+
+fn reconnect(shared: &Shared, e: &ClientError) -> Option<TcpStream> {
+    let policy: &ReconnectPolicy = &shared.config.reconnect;
+    let mut backoff: Duration = policy.initial_backoff;
+
+    for attempt in 1..=policy.max_retries {
+        warn!(
+            "{}. Reconnecting in {}s ({}/{})...",
+            e,
+            backoff.as_secs_f32(),
+            attempt,
+            policy.max_retries
+        );
+        thread::sleep(backoff);
+        backoff = (backoff * 2).min(policy.max_backoff);
+
+        if shared.closing.load(Ordering::SeqCst) {
+            return None;
+        }
+
+        match establish(&shared.config) {
+            Ok((stream, logged_in)) => {
+                let (Ok(reader_stream), Ok(writer_stream)) =
+                    (stream.try_clone(), stream.try_clone())
+                else {
+                    continue;
+                };
+
+                *shared.socket.lock().unwrap() = stream;
+                let _ = shared
+                    .outgoing
+                    .send(Outgoing::Connection(writer_stream, logged_in.threshold));
+                shared
+                    .threshold
+                    .store(logged_in.threshold, Ordering::SeqCst);
+                shared.online_players.lock().unwrap().clear();
+                shared.boss_bars.lock().unwrap().clear();
+                *shared.scoreboard.lock().unwrap() = Scoreboard::new(shared.config.protocol);
+                *shared.tab_list.lock().unwrap() =
+                    (FormattedText::default(), FormattedText::default());
+                *shared.identity.lock().unwrap() = logged_in.identity;
+                *shared.server_brand.lock().unwrap() = logged_in.server_brand;
+                *shared.position.lock().unwrap() = None;
+                *shared.health.lock().unwrap() = None;
+                *shared.entity_id.lock().unwrap() = None;
+                *shared.commands.lock().unwrap() = None;
+
+                debug!("Reconnected");
+                shared.publish(Event::Connected);
+                return Some(reader_stream);
+            }
+            Err(e) => warn!("Reconnect failed: {}", e),
+        }
+    }
+
+    None
+}
+
+fn read_loop(stream: &mut TcpStream, shared: &Shared) -> Result<(), ClientError> {
+    let threshold: i32 = shared.threshold.load(Ordering::SeqCst);
+    let timeout: Option<Duration> = shared.config.keep_alive_timeout;
+    let timed_out = |timeout: Duration| {
+        ClientError::Timeout(format!(
+            "no keep alive from the server in {} seconds",
+            timeout.as_secs()
+        ))
+    };
+
+    stream.set_read_timeout(timeout)?;
+    let mut last_keep_alive: Instant = Instant::now();
+    let mut subtitle: Option<FormattedText> = None;
+    let mut title_ticks: i32 = DEFAULT_TITLE_TICKS;
+
+    let mut reader: PacketReader =
+        PacketReader::capturing(shared.config.capture.as_ref(), CaptureState::Play);
+    loop {
+        let loop_packet: (i32, &[u8]) = match reader.receive(stream, threshold) {
+            Ok(loop_packet) => loop_packet,
+            Err(ClientError::Io(e))
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) && let Some(timeout) = timeout =>
+            {
+                return Err(timed_out(timeout));
+            }
+            Err(e) => return Err(e),
+        };
+
+        let (id, data): (i32, &[u8]) = loop_packet;
+        let _span = trace_span!("packet", id = format_args!("0x{:02X}", id)).entered();
+        shared.count_packet(id, data.len());
+        let packet: ClientboundPacket = match ClientboundPacket::decode(shared.packets, id, data) {
+            Ok(packet) => packet,
+            Err(e) => {
+                debug!("Malformed packet: {}", e);
+                // One bad chat component shouldn't end the session
+                shared.publish(Event::MalformedPacket {
+                    id,
+                    error: e.to_string(),
+                    payload: data.to_vec(),
+                });
+                continue;
+            }
+        };
+
+        if let ClientboundPacket::KeepAlive { .. } = packet {
+            last_keep_alive = Instant::now();
+        } else if let Some(timeout) = timeout
+            && last_keep_alive.elapsed() > timeout
+        {
+            // Other packets still arrive, but the server stopped checking on us
+            return Err(timed_out(timeout));
+        }
+
+        match packet {
+            ClientboundPacket::KeepAlive { id } => {
+                shared.answer_keep_alive(id, last_keep_alive)?;
+                shared.publish(Event::KeepAlive { id });
+            }
+            ClientboundPacket::ChatMessage {
+                message,
+                sender,
+                kind,
+                interactions,
+            } => {
+                shared.publish(Event::ChatReceived {
+                    message,
+                    sender,
+                    kind,
+                    interactions,
+                });
+            }
+            ClientboundPacket::ActionBar { text } => {
+                shared.publish(Event::ActionBar(text));
+            }
+            ClientboundPacket::Title(action) => match action {
+                TitleAction::SetTitle(title) => {
+                    shared.publish(Event::Title {
+                        title,
+                        subtitle: subtitle.take(),
+                        duration: Duration::from_millis(title_ticks.max(0) as u64 * 50),
+                    });
+                }
+                TitleAction::SetSubtitle(text) => subtitle = Some(text),
+                TitleAction::SetTimes {
+                    fade_in,
+                    stay,
+                    fade_out,
+                } => title_ticks = fade_in + stay + fade_out,
+                TitleAction::Hide => {}
+                TitleAction::Reset => {
+                    subtitle = None;
+                    title_ticks = DEFAULT_TITLE_TICKS;
+                }
+            },
+            ClientboundPacket::BossBar { uuid, action } => {
+                let mut boss_bars = shared.boss_bars.lock().unwrap();
+                let index: Option<usize> = boss_bars.iter().position(|entry| entry.0 == uuid);
+
+                match (action, index) {
+                    (
+                        BossBarAction::Add {
+                            title,
+                            progress,
+                            color,
+                        },
+                        None,
+                    ) => boss_bars.push((
+                        uuid,
+                        BossBar {
+                            title,
+                            progress,
+                            color,
+                        },
+                    )),
+                    (BossBarAction::Remove, Some(i)) => {
+                        boss_bars.remove(i);
+                    }
+                    (BossBarAction::UpdateProgress(progress), Some(i)) => {
+                        boss_bars[i].1.progress = progress
+                    }
+                    (BossBarAction::UpdateTitle(title), Some(i)) => boss_bars[i].1.title = title,
+                    (BossBarAction::UpdateColor(color), Some(i)) => boss_bars[i].1.color = color,
+                    _ => {}
+                }
+            }
+            ClientboundPacket::DisplayObjective { .. }
+            | ClientboundPacket::UpdateObjective { .. }
+            | ClientboundPacket::UpdateTeam { .. }
+            | ClientboundPacket::UpdateScore { .. }
+            | ClientboundPacket::ResetScore { .. } => {
+                shared.scoreboard.lock().unwrap().handle(packet);
+            }
+            ClientboundPacket::TabList { header, footer } => {
+                *shared.tab_list.lock().unwrap() = (header, footer);
+            }
+            ClientboundPacket::Disconnect { reason } => {
+                shared.publish(Event::Kicked(reason.clone()));
+                return Err(ClientError::Kicked(reason));
+            }
+            ClientboundPacket::PlayerInfo { entries } => {
+                let mut events: Vec<Event> = Vec::new();
+                {
+                    let mut players = shared.online_players.lock().unwrap();
+                    let mut joined: Vec<u128> = Vec::new();
+                    for (uuid, action) in entries {
+                        match action {
+                            PlayerInfoAction::AddPlayer {
+                                name,
+                                skin_url,
+                                game_mode,
+                                latency,
+                                display_name,
+                            } if !players.contains_key(&uuid) => {
+                                players.insert(
+                                    uuid,
+                                    PlayerInfo {
+                                        name,
+                                        skin_url,
+                                        game_mode,
+                                        latency,
+                                        display_name,
+                                    },
+                                );
+                                joined.push(uuid);
+                            }
+                            PlayerInfoAction::UpdateGameMode(game_mode) => {
+                                if let Some(player) = players.get_mut(&uuid) {
+                                    player.game_mode = game_mode;
+                                }
+                            }
+                            PlayerInfoAction::UpdateLatency(latency) => {
+                                if let Some(player) = players.get_mut(&uuid) {
+                                    player.latency = latency;
+                                }
+                            }
+                            PlayerInfoAction::UpdateDisplayName(display_name) => {
+                                if let Some(player) = players.get_mut(&uuid) {
+                                    player.display_name = display_name;
+                                }
+                            }
+                            PlayerInfoAction::RemovePlayer => {
+                                if let Some(player) = players.remove(&uuid) {
+                                    events.push(Event::PlayerLeft {
+                                        uuid,
+                                        name: player.name,
+                                        display_name: player.display_name,
+                                    });
+                                }
+                            }
+                            PlayerInfoAction::AddPlayer { .. } => {}
+                        }
+                    }
+                    // 1.19.3+ sends the display name as another action after adding the
+                    // player, so joins are reported once the whole packet is applied
+                    for uuid in joined {
+                        if let Some(player) = players.get(&uuid) {
+                            events.push(Event::PlayerJoined {
+                                uuid,
+                                name: player.name.clone(),
+                                display_name: player.display_name.clone(),
+                            });
+                        }
+                    }
+                }
+                for event in events {
+                    shared.publish(event);
+                }
+            }
+            ClientboundPacket::SynchronizePosition {
+                position,
+                relative,
+                teleport_id,
+            } => {
+                let position: Position = {
+                    let mut current = shared.position.lock().unwrap();
+                    let position: Position =
+                        current.unwrap_or_default().teleport(position, relative);
+                    *current = Some(position);
+                    position
+                };
+                // Vanilla confirms the teleport and then moves to the new position
+                shared.send(&ServerboundPacket::ConfirmTeleport { teleport_id })?;
+                shared.send(&ServerboundPacket::PlayerPosition {
+                    position,
+                    on_ground: true,
+                })?;
+            }
+            ClientboundPacket::JoinGame { entity_id } => {
+                *shared.entity_id.lock().unwrap() = Some(entity_id);
+            }
+            ClientboundPacket::ResourcePack { uuid, url, hash } => {
+                for status in resource_pack_answer(&shared.config, &url, &hash) {
+                    shared.send(&ServerboundPacket::ResourcePackStatus {
+                        uuid,
+                        status: *status,
+                    })?;
+                }
+            }
+            ClientboundPacket::CommandSuggestions { id, range, matches } => {
+                let mut pending = shared.pending_completions.lock().unwrap();
+                // 1.12 answers in order, without IDs
+                let index: Option<usize> = match id {
+                    Some(id) => pending.iter().position(|entry| entry.0 == id),
+                    None if pending.is_empty() => None,
+                    None => Some(0),
+                };
+                if let Some(index) = index {
+                    let (_, sender): (i32, Sender<Suggestions>) = pending.remove(index);
+                    let _ = sender.send((range, matches));
+                }
+            }
+            ClientboundPacket::Commands(tree) => {
+                *shared.commands.lock().unwrap() = Some(tree);
+            }
+            ClientboundPacket::SetHealth(health) => {
+                *shared.health.lock().unwrap() = Some(health);
+            }
+            ClientboundPacket::Death { message } => {
+                if shared.config.auto_respawn {
+                    shared.send(&ServerboundPacket::Respawn)?;
+                }
+                shared.publish(Event::Died(message));
+            }
+            ClientboundPacket::PluginMessage {
+                channel,
+                data: brand,
+            } if channel == packet::brand_channel(shared.config.protocol) => {
+                match packet::decode_brand(brand) {
+                    Ok(brand) => *shared.server_brand.lock().unwrap() = Some(brand),
+                    Err(e) => shared.publish(Event::MalformedPacket {
+                        id,
+                        error: e.to_string(),
+                        payload: data.to_vec(),
+                    }),
+                }
+            }
+            _ => {
+                if shared.config.report_ignored_packets {
+                    shared.publish(Event::IgnoredPacket {
+                        id,
+                        payload: data.to_vec(),
+                    });
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_messages_stay_whole() {
+        assert_eq!(split_message("  hello world ", 20), ["hello world"]);
+        assert_eq!(split_message("exactly10!", 10), ["exactly10!"]);
+        assert!(split_message("   ", 10).is_empty());
+    }
+
+    #[test]
+    fn long_messages_break_at_spaces() {
+        assert_eq!(
+            split_message("the quick brown fox jumps", 10),
+            ["the quick", "brown fox", "jumps"]
+        );
+        // The limit falls right on a space
+        assert_eq!(split_message("aaaa bbbb", 4), ["aaaa", "bbbb"]);
+        // Words longer than the limit are cut
+        assert_eq!(
+            split_message("abcdefghij xy", 4),
+            ["abcd", "efgh", "ij", "xy"]
+        );
+    }
+
+    #[test]
+    fn limits_count_characters() {
+        let pieces: Vec<String> = split_message("ééééé ééééé", 5);
+        assert_eq!(pieces, ["ééééé", "ééééé"]);
+        assert!(
+            split_message(&"ü".repeat(300), MAX_CHAT_LENGTH)
+                .iter()
+                .all(|piece| piece.chars().count() <= MAX_CHAT_LENGTH)
+        );
+    }
+
+    #[test]
+    fn suggestions_replace_the_last_word() {
+        let matches: Vec<String> = vec![String::from("Steve"), String::from("Stella")];
+        assert_eq!(
+            apply_suggestions("/msg St", None, matches.clone()),
+            ["/msg Steve", "/msg Stella"]
+        );
+        assert_eq!(apply_suggestions("St", None, matches), ["Steve", "Stella"]);
+    }
+
+    #[test]
+    fn suggestions_replace_the_range() {
+        // Ranges count characters, and text after the range is kept
+        assert_eq!(
+            apply_suggestions("/tp é 1 2", Some((4, 1)), vec![String::from("Alex")]),
+            ["/tp Alex 1 2"]
+        );
+        // Ranges past the end are clamped
+        assert_eq!(
+            apply_suggestions(
+                "/gamemode c",
+                Some((10, 40)),
+                vec![String::from("creative")]
+            ),
+            ["/gamemode creative"]
+        );
+        assert_eq!(
+            apply_suggestions("/time", Some((99, 1)), vec![String::from(" set")]),
+            ["/time set"]
+        );
+        assert!(apply_suggestions("/x", None, Vec::new()).is_empty());
+    }
+}
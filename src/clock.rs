@@ -0,0 +1,86 @@
+use std::os::raw::{c_char, c_int, c_long};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Local wall-clock time, via the C library's localtime_r so the system time zone is used.
+
+#[repr(C)]
+struct Tm {
+    tm_sec: c_int,
+    tm_min: c_int,
+    tm_hour: c_int,
+    tm_mday: c_int,
+    tm_mon: c_int,
+    tm_year: c_int,
+    tm_wday: c_int,
+    tm_yday: c_int,
+    tm_isdst: c_int,
+    tm_gmtoff: c_long,
+    tm_zone: *const c_char,
+}
+
+unsafe extern "C" {
+    fn localtime_r(time: *const i64, result: *mut Tm) -> *mut Tm;
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct LocalTime {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    /// 0 is Sunday.
+    pub weekday: u32,
+}
+
+impl LocalTime {
+    pub fn now() -> LocalTime {
+        LocalTime::at(SystemTime::now())
+    }
+
+    pub fn at(time: SystemTime) -> LocalTime {
+        let seconds: i64 = time
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_secs() as i64)
+            .unwrap_or(0);
+
+        let mut tm: Tm = Tm {
+            tm_sec: 0,
+            tm_min: 0,
+            tm_hour: 0,
+            tm_mday: 1,
+            tm_mon: 0,
+            tm_year: 70,
+            tm_wday: 0,
+            tm_yday: 0,
+            tm_isdst: 0,
+            tm_gmtoff: 0,
+            tm_zone: std::ptr::null(),
+        };
+        // SAFETY: both pointers are valid for the duration of the call
+        unsafe {
+            localtime_r(&seconds, &mut tm);
+        }
+
+        LocalTime {
+            year: tm.tm_year + 1900,
+            month: (tm.tm_mon + 1) as u32,
+            day: tm.tm_mday as u32,
+            hour: tm.tm_hour as u32,
+            minute: tm.tm_min as u32,
+            second: tm.tm_sec as u32,
+            weekday: tm.tm_wday as u32,
+        }
+    }
+
+    /// `YYYY-MM-DD`
+    pub fn date(&self) -> String {
+        format!("{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+
+    /// `HH:MM:SS`
+    pub fn time(&self) -> String {
+        format!("{:02}:{:02}:{:02}", self.hour, self.minute, self.second)
+    }
+}
@@ -0,0 +1,200 @@
+// Client commands are lines starting with `.` that the client handles itself instead
+// of sending them to the server, like `.list` or `.quit`.
+
+pub const COMMAND_PREFIX: char = '.';
+
+/// What the input loop should do after a command ran.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CommandFlow {
+    Continue,
+    /// Disconnect and exit.
+    Quit,
+}
+
+// Name, usage and help text of the built-in command list
+const HELP: (&str, &str, &str) = ("help", "", "This list");
+
+type CommandHandler = Box<dyn Fn(&str) -> CommandFlow + Send + Sync>;
+
+struct ClientCommand {
+    name: String,
+    usage: String,
+    help: String,
+    handler: CommandHandler,
+}
+
+/// The registered client commands, and a built-in `.help` that lists them. Names are
+/// matched case-insensitively.
+pub struct ClientCommands {
+    commands: Vec<ClientCommand>,
+    help_output: fn(&str),
+}
+
+impl Default for ClientCommands {
+    fn default() -> Self {
+        ClientCommands {
+            commands: Vec::new(),
+            help_output: |text| println!("{}", text),
+        }
+    }
+}
+
+impl ClientCommands {
+    pub fn new() -> ClientCommands {
+        ClientCommands::default()
+    }
+
+    /// Where `.help` prints the command list, stdout by default.
+    pub fn set_help_output(&mut self, print: fn(&str)) {
+        self.help_output = print;
+    }
+
+    /// Registers `.name`, replacing a command with the same name (`help` included).
+    /// `usage` is shown after the name in `.help` (e.g. `"<player>"`), and the handler
+    /// gets everything after the name, trimmed.
+    pub fn register(
+        &mut self,
+        name: &str,
+        usage: &str,
+        help: &str,
+        handler: impl Fn(&str) -> CommandFlow + Send + Sync + 'static,
+    ) {
+        self.commands
+            .retain(|command| !command.name.eq_ignore_ascii_case(name));
+        self.commands.push(ClientCommand {
+            name: String::from(name),
+            usage: String::from(usage),
+            help: String::from(help),
+            handler: Box::new(handler),
+        });
+    }
+
+    /// Runs `line` if it's a registered command. Returns `None` for anything else,
+    /// which should be sent as chat.
+    pub fn run(&self, line: &str) -> Option<CommandFlow> {
+        let line: &str = line.trim().strip_prefix(COMMAND_PREFIX)?;
+        let (name, rest): (&str, &str) = match line.split_once(char::is_whitespace) {
+            Some((name, rest)) => (name, rest.trim()),
+            None => (line, ""),
+        };
+
+        match self
+            .commands
+            .iter()
+            .find(|command| command.name.eq_ignore_ascii_case(name))
+        {
+            Some(command) => Some((command.handler)(rest)),
+            // Built when it runs, so commands registered at any time are listed
+            None if name.eq_ignore_ascii_case(HELP.0) => {
+                (self.help_output)(&format!("Client commands:\n{}", self.help()));
+                Some(CommandFlow::Continue)
+            }
+            None => None,
+        }
+    }
+
+    // The registered names and usages with their help texts, and the built-in `.help`
+    // unless it was replaced
+    fn entries(&self) -> Vec<(&str, &str, &str)> {
+        let mut entries: Vec<(&str, &str, &str)> = self
+            .commands
+            .iter()
+            .map(|command| {
+                (
+                    command.name.as_str(),
+                    command.usage.as_str(),
+                    command.help.as_str(),
+                )
+            })
+            .collect();
+        if !entries
+            .iter()
+            .any(|entry| entry.0.eq_ignore_ascii_case(HELP.0))
+        {
+            entries.push(HELP);
+        }
+        entries
+    }
+
+    /// One line per command, sorted by name.
+    pub fn help(&self) -> String {
+        let mut lines: Vec<(String, &str)> = self
+            .entries()
+            .into_iter()
+            .map(|(name, usage, help)| {
+                let mut syntax: String = format!("{}{}", COMMAND_PREFIX, name);
+                if !usage.is_empty() {
+                    syntax += &format!(" {}", usage);
+                }
+                (syntax, help)
+            })
+            .collect();
+        lines.sort();
+
+        let width: usize = lines
+            .iter()
+            .map(|(syntax, _)| syntax.len())
+            .max()
+            .unwrap_or(0);
+        lines
+            .iter()
+            .map(|(syntax, help)| format!("{:width$}  {}", syntax, help, width = width))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// Completes a command name, for lines like `.li`. Returns whole lines.
+    pub fn complete(&self, line: &str) -> Vec<String> {
+        let Some(partial) = line.strip_prefix(COMMAND_PREFIX) else {
+            return Vec::new();
+        };
+        if partial.contains(char::is_whitespace) {
+            return Vec::new();
+        }
+
+        let mut names: Vec<String> = self
+            .entries()
+            .into_iter()
+            .filter(|(name, _, _)| name.to_lowercase().starts_with(&partial.to_lowercase()))
+            .map(|(name, _, _)| format!("{}{}", COMMAND_PREFIX, name))
+            .collect();
+        names.sort();
+        names
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn help_lists_commands_registered_later() {
+        let mut commands: ClientCommands = ClientCommands::new();
+        commands.register("quit", "", "Disconnect and exit", |_| CommandFlow::Quit);
+        assert!(commands.help().contains(".help"));
+        commands.register("mine", "<x>", "Registered after the others", |_| {
+            CommandFlow::Continue
+        });
+
+        let help: String = commands.help();
+        assert!(
+            help.contains(".mine <x>  Registered after the others"),
+            "{}",
+            help
+        );
+        assert!(help.find(".help") < help.find(".mine"));
+        assert_eq!(commands.run(".HELP"), Some(CommandFlow::Continue));
+        assert_eq!(commands.run(".quit now"), Some(CommandFlow::Quit));
+        assert_eq!(commands.run(".unknown"), None);
+        assert_eq!(commands.run("hello"), None);
+    }
+
+    #[test]
+    fn help_can_be_replaced() {
+        let mut commands: ClientCommands = ClientCommands::new();
+        commands.register("help", "[topic]", "Mine", |_| CommandFlow::Quit);
+        assert_eq!(commands.run(".help"), Some(CommandFlow::Quit));
+        assert_eq!(commands.help().matches(".help").count(), 1);
+        assert_eq!(commands.complete(".he"), [".help"]);
+    }
+}
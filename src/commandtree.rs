@@ -0,0 +1,563 @@
+use std::io::Cursor;
+
+use crate::error::ClientError;
+use crate::mcstring::McString;
+use crate::types::McRead;
+
+// The command tree from Declare Commands (1.13+). Arguments are only known by their
+// parser, not parsed, so validation and completion stop at the command and literal
+// words (subcommands); whatever the arguments look like is left to the server.
+
+// Argument parsers by registry ID since 1.19. Only the ones with properties matter
+// for reading the packet, the rest just need a name.
+static PARSERS_1_19_4: &[&str] = &[
+    "brigadier:bool",
+    "brigadier:float",
+    "brigadier:double",
+    "brigadier:integer",
+    "brigadier:long",
+    "brigadier:string",
+    "minecraft:entity",
+    "minecraft:game_profile",
+    "minecraft:block_pos",
+    "minecraft:column_pos",
+    "minecraft:vec3",
+    "minecraft:vec2",
+    "minecraft:block_state",
+    "minecraft:block_predicate",
+    "minecraft:item_stack",
+    "minecraft:item_predicate",
+    "minecraft:color",
+    "minecraft:component",
+    "minecraft:message",
+    "minecraft:nbt_compound_tag",
+    "minecraft:nbt_tag",
+    "minecraft:nbt_path",
+    "minecraft:objective",
+    "minecraft:objective_criteria",
+    "minecraft:operation",
+    "minecraft:particle",
+    "minecraft:angle",
+    "minecraft:rotation",
+    "minecraft:scoreboard_slot",
+    "minecraft:score_holder",
+    "minecraft:swizzle",
+    "minecraft:team",
+    "minecraft:item_slot",
+    "minecraft:resource_location",
+    "minecraft:function",
+    "minecraft:entity_anchor",
+    "minecraft:int_range",
+    "minecraft:float_range",
+    "minecraft:dimension",
+    "minecraft:gamemode",
+    "minecraft:time",
+    "minecraft:resource_or_tag",
+    "minecraft:resource_or_tag_key",
+    "minecraft:resource",
+    "minecraft:resource_key",
+    "minecraft:template_mirror",
+    "minecraft:template_rotation",
+    "minecraft:heightmap",
+    "minecraft:uuid",
+];
+
+// 1.20.3 added style after component
+static PARSERS_1_20_3: &[&str] = &[
+    "brigadier:bool",
+    "brigadier:float",
+    "brigadier:double",
+    "brigadier:integer",
+    "brigadier:long",
+    "brigadier:string",
+    "minecraft:entity",
+    "minecraft:game_profile",
+    "minecraft:block_pos",
+    "minecraft:column_pos",
+    "minecraft:vec3",
+    "minecraft:vec2",
+    "minecraft:block_state",
+    "minecraft:block_predicate",
+    "minecraft:item_stack",
+    "minecraft:item_predicate",
+    "minecraft:color",
+    "minecraft:component",
+    "minecraft:style",
+    "minecraft:message",
+    "minecraft:nbt_compound_tag",
+    "minecraft:nbt_tag",
+    "minecraft:nbt_path",
+    "minecraft:objective",
+    "minecraft:objective_criteria",
+    "minecraft:operation",
+    "minecraft:particle",
+    "minecraft:angle",
+    "minecraft:rotation",
+    "minecraft:scoreboard_slot",
+    "minecraft:score_holder",
+    "minecraft:swizzle",
+    "minecraft:team",
+    "minecraft:item_slot",
+    "minecraft:resource_location",
+    "minecraft:function",
+    "minecraft:entity_anchor",
+    "minecraft:int_range",
+    "minecraft:float_range",
+    "minecraft:dimension",
+    "minecraft:gamemode",
+    "minecraft:time",
+    "minecraft:resource_or_tag",
+    "minecraft:resource_or_tag_key",
+    "minecraft:resource",
+    "minecraft:resource_key",
+    "minecraft:template_mirror",
+    "minecraft:template_rotation",
+    "minecraft:heightmap",
+    "minecraft:uuid",
+];
+
+// 1.20.5 added item_slots and the loot table arguments
+static PARSERS_1_20_5: &[&str] = &[
+    "brigadier:bool",
+    "brigadier:float",
+    "brigadier:double",
+    "brigadier:integer",
+    "brigadier:long",
+    "brigadier:string",
+    "minecraft:entity",
+    "minecraft:game_profile",
+    "minecraft:block_pos",
+    "minecraft:column_pos",
+    "minecraft:vec3",
+    "minecraft:vec2",
+    "minecraft:block_state",
+    "minecraft:block_predicate",
+    "minecraft:item_stack",
+    "minecraft:item_predicate",
+    "minecraft:color",
+    "minecraft:component",
+    "minecraft:style",
+    "minecraft:message",
+    "minecraft:nbt_compound_tag",
+    "minecraft:nbt_tag",
+    "minecraft:nbt_path",
+    "minecraft:objective",
+    "minecraft:objective_criteria",
+    "minecraft:operation",
+    "minecraft:particle",
+    "minecraft:angle",
+    "minecraft:rotation",
+    "minecraft:scoreboard_slot",
+    "minecraft:score_holder",
+    "minecraft:swizzle",
+    "minecraft:team",
+    "minecraft:item_slot",
+    "minecraft:item_slots",
+    "minecraft:resource_location",
+    "minecraft:function",
+    "minecraft:entity_anchor",
+    "minecraft:int_range",
+    "minecraft:float_range",
+    "minecraft:dimension",
+    "minecraft:gamemode",
+    "minecraft:time",
+    "minecraft:resource_or_tag",
+    "minecraft:resource_or_tag_key",
+    "minecraft:resource",
+    "minecraft:resource_key",
+    "minecraft:template_mirror",
+    "minecraft:template_rotation",
+    "minecraft:heightmap",
+    "minecraft:loot_table",
+    "minecraft:loot_predicate",
+    "minecraft:loot_modifier",
+    "minecraft:uuid",
+];
+
+#[derive(Clone, Debug)]
+pub enum NodeKind {
+    Root,
+    /// A fixed word, like a command name or subcommand.
+    Literal(String),
+    Argument {
+        name: String,
+        parser: String,
+        /// A string argument that takes the rest of the line.
+        greedy: bool,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct CommandNode {
+    pub kind: NodeKind,
+    pub children: Vec<usize>,
+    /// Aliases and `execute run` continue at another node.
+    pub redirect: Option<usize>,
+    /// Whether the command can end at this node.
+    pub executable: bool,
+}
+
+/// The commands the server lets the player use.
+#[derive(Clone, Debug)]
+pub struct CommandTree {
+    pub nodes: Vec<CommandNode>,
+    pub root: usize,
+}
+
+// Skips the optional min and max that follow number parsers
+fn skip_bounds(cursor: &mut Cursor<&[u8]>, size: usize) -> Result<(), ClientError> {
+    let flags: u8 = cursor.read_u8()?;
+    let count: usize = (flags & 0x01) as usize + ((flags >> 1) & 0x01) as usize;
+    cursor.read_bytes(size * count)?;
+    Ok(())
+}
+
+// Reads the parser of an argument node and skips its properties. Returns the parser
+// name and whether it's a greedy string.
+fn read_parser(cursor: &mut Cursor<&[u8]>, protocol: i32) -> Result<(String, bool), ClientError> {
+    let parser: String = if protocol >= 759 {
+        let parsers: &[&str] = match protocol {
+            766.. => PARSERS_1_20_5,
+            765 => PARSERS_1_20_3,
+            _ => PARSERS_1_19_4,
+        };
+        let id: i32 = cursor.read_varint()?;
+        match parsers.get(id as usize) {
+            Some(parser) => String::from(*parser),
+            None => return Err(format!("Unknown argument parser {}", id).into()),
+        }
+    } else {
+        McString::DEFAULT.read(cursor)?
+    };
+
+    let mut greedy: bool = false;
+    match parser.as_str() {
+        "brigadier:float" | "brigadier:integer" => skip_bounds(cursor, 4)?,
+        "brigadier:double" | "brigadier:long" => skip_bounds(cursor, 8)?,
+        "brigadier:string" => greedy = cursor.read_varint()? == 2,
+        "minecraft:entity" | "minecraft:score_holder" | "minecraft:range" => {
+            cursor.read_bytes(1)?;
+        }
+        // The minimum was added in 1.20.2
+        "minecraft:time" if protocol >= 764 => {
+            cursor.read_bytes(4)?;
+        }
+        "minecraft:resource_or_tag"
+        | "minecraft:resource_or_tag_key"
+        | "minecraft:resource"
+        | "minecraft:resource_key" => {
+            McString::DEFAULT.read(cursor)?; // registry
+        }
+        _ => {}
+    }
+
+    Ok((parser, greedy))
+}
+
+/// Reads the body of Declare Commands.
+pub(crate) fn read_command_tree(
+    cursor: &mut Cursor<&[u8]>,
+    protocol: i32,
+) -> Result<CommandTree, ClientError> {
+    let count: i32 = cursor.read_varint()?;
+    let mut nodes: Vec<CommandNode> = Vec::new();
+
+    for _ in 0..count {
+        let flags: u8 = cursor.read_u8()?;
+        let child_count: i32 = cursor.read_varint()?;
+        let mut children: Vec<usize> = Vec::new();
+        for _ in 0..child_count {
+            children.push(cursor.read_varint()? as usize);
+        }
+        let redirect: Option<usize> = if flags & 0x08 != 0 {
+            Some(cursor.read_varint()? as usize)
+        } else {
+            None
+        };
+
+        let kind: NodeKind = match flags & 0x03 {
+            1 => NodeKind::Literal(McString::DEFAULT.read(cursor)?),
+            2 => {
+                let name: String = McString::DEFAULT.read(cursor)?;
+                let (parser, greedy): (String, bool) = read_parser(cursor, protocol)?;
+                if flags & 0x10 != 0 {
+                    McString::DEFAULT.read(cursor)?; // suggestions type
+                }
+                NodeKind::Argument {
+                    name,
+                    parser,
+                    greedy,
+                }
+            }
+            _ => NodeKind::Root,
+        };
+
+        nodes.push(CommandNode {
+            kind,
+            children,
+            redirect,
+            executable: flags & 0x04 != 0,
+        });
+    }
+
+    let root: usize = cursor.read_varint()? as usize;
+    if root >= nodes.len() {
+        return Err("The command tree's root node doesn't exist".into());
+    }
+    // Walking the tree indexes `nodes` with these
+    if nodes
+        .iter()
+        .flat_map(|node| node.children.iter().chain(&node.redirect))
+        .any(|link| *link >= nodes.len())
+    {
+        return Err("The command tree links to a node that doesn't exist".into());
+    }
+
+    Ok(CommandTree { nodes, root })
+}
+
+// Where a line of words ends up in the tree
+enum Walk {
+    /// All words matched literals, ending at this node.
+    Node(usize),
+    /// The words up to this one matched; `usize` is the node they ended at.
+    Mismatch(usize, usize),
+    /// An argument was reached. Arguments can span several words (`~ ~ ~`, quoted
+    /// strings), so the rest of the line isn't followed.
+    Argument,
+}
+
+impl CommandTree {
+    /// The command names (without `/`), sorted.
+    pub fn commands(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.literal_children(self.root);
+        names.sort();
+        names
+    }
+
+    fn literal_children(&self, node: usize) -> Vec<String> {
+        self.children(node)
+            .iter()
+            .filter_map(|child| match &self.nodes.get(*child)?.kind {
+                NodeKind::Literal(name) => Some(name.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // A redirecting node without children of its own continues at the target
+    fn children(&self, node: usize) -> &[usize] {
+        let Some(current) = self.nodes.get(node) else {
+            return &[];
+        };
+        match current.redirect {
+            Some(target) if current.children.is_empty() && target != node => self.children(target),
+            _ => &current.children,
+        }
+    }
+
+    // Literals are matched by name; a word that isn't one is taken as the start of an
+    // argument if the node has any
+    fn walk(&self, words: &[&str]) -> Walk {
+        let mut node: usize = self.root;
+
+        for (i, word) in words.iter().enumerate() {
+            let children: &[usize] = self.children(node);
+            let literal: Option<usize> = children.iter().copied().find(|child| {
+                matches!(&self.nodes[*child].kind, NodeKind::Literal(name) if name.eq_ignore_ascii_case(word))
+            });
+            let has_argument: bool = children
+                .iter()
+                .any(|child| matches!(self.nodes[*child].kind, NodeKind::Argument { .. }));
+
+            node = match literal {
+                Some(literal) => literal,
+                // The command name itself has to be a literal
+                None if has_argument && i > 0 => return Walk::Argument,
+                None => return Walk::Mismatch(i, node),
+            };
+        }
+
+        Walk::Node(node)
+    }
+
+    /// Checks a `/command` line against the tree. Only the command name and the literal
+    /// words before the first argument are checked, arguments aren't parsed.
+    pub fn validate(&self, line: &str) -> Result<(), String> {
+        let line: &str = line.strip_prefix('/').unwrap_or(line);
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        match self.walk(&words) {
+            Walk::Mismatch(0, _) => Err(format!(
+                "Unknown command /{}",
+                words.first().copied().unwrap_or("")
+            )),
+            Walk::Mismatch(i, node) => {
+                let expected: Vec<String> = self.literal_children(node);
+                if expected.is_empty() {
+                    Err(format!("/{} has too many arguments", words[..i].join(" ")))
+                } else {
+                    Err(format!(
+                        "Unknown argument '{}', expected one of: {}",
+                        words[i],
+                        expected.join(", ")
+                    ))
+                }
+            }
+            Walk::Node(node) if !self.nodes[node].executable && node != self.root => {
+                Err(format!("/{} is incomplete", words.join(" ")))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Completes the last word of a `/command` line where the tree expects a literal.
+    /// Returns whole lines; empty once the line has reached an argument.
+    pub fn complete(&self, line: &str) -> Vec<String> {
+        let Some(text) = line.strip_prefix('/') else {
+            return Vec::new();
+        };
+        let (done, partial): (&str, &str) = match text.rfind(' ') {
+            Some(i) => (&text[..=i], &text[i + 1..]),
+            None => ("", text),
+        };
+        let words: Vec<&str> = done.split_whitespace().collect();
+
+        let Walk::Node(node) = self.walk(&words) else {
+            return Vec::new();
+        };
+        let mut names: Vec<String> = self
+            .literal_children(node)
+            .into_iter()
+            .filter(|name| name.starts_with(&partial.to_lowercase()))
+            .collect();
+        names.sort();
+        names
+            .into_iter()
+            .map(|name| format!("/{}{}", done, name))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(text: &str) -> Vec<u8> {
+        let mut res: Vec<u8> = vec![text.len() as u8];
+        res.extend_from_slice(text.as_bytes());
+        res
+    }
+
+    // /gamemode <mode>, /gm redirecting to /gamemode and /team list, as a 1.12.2 server
+    // sends them
+    fn tree_bytes() -> Vec<u8> {
+        let mut bytes: Vec<u8> = vec![6];
+        bytes.extend_from_slice(&[0x00, 3, 1, 3, 4]); // root
+        bytes.extend_from_slice(&[0x01, 1, 2]);
+        bytes.extend(string("gamemode"));
+        bytes.extend_from_slice(&[0x06, 0]);
+        bytes.extend(string("mode"));
+        bytes.extend(string("brigadier:string"));
+        bytes.push(0); // a single word
+        bytes.extend_from_slice(&[0x09, 0, 1]);
+        bytes.extend(string("gm"));
+        bytes.extend_from_slice(&[0x01, 1, 5]);
+        bytes.extend(string("team"));
+        bytes.extend_from_slice(&[0x05, 0]);
+        bytes.extend(string("list"));
+        bytes.push(0); // the root's index
+        bytes
+    }
+
+    fn tree() -> CommandTree {
+        read_command_tree(&mut Cursor::new(&tree_bytes()[..]), 340).unwrap()
+    }
+
+    #[test]
+    fn reads_nodes() {
+        let tree: CommandTree = tree();
+        assert_eq!(tree.nodes.len(), 6);
+        assert_eq!(tree.root, 0);
+        assert!(matches!(
+            &tree.nodes[2].kind,
+            NodeKind::Argument { name, parser, greedy: false }
+                if name == "mode" && parser == "brigadier:string"
+        ));
+        assert_eq!(tree.nodes[3].redirect, Some(1));
+        assert!(tree.nodes[5].executable);
+        assert_eq!(tree.commands(), ["gamemode", "gm", "team"]);
+    }
+
+    #[test]
+    fn broken_trees_are_refused() {
+        let mut bytes: Vec<u8> = tree_bytes();
+        *bytes.last_mut().unwrap() = 6;
+        assert!(read_command_tree(&mut Cursor::new(&bytes[..]), 340).is_err());
+
+        // The root's second child points past the end
+        let mut bytes: Vec<u8> = tree_bytes();
+        bytes[4] = 9;
+        assert!(read_command_tree(&mut Cursor::new(&bytes[..]), 340).is_err());
+
+        let bytes: Vec<u8> = tree_bytes();
+        assert!(read_command_tree(&mut Cursor::new(&bytes[..bytes.len() - 1]), 340).is_err());
+    }
+
+    #[test]
+    fn parsers_by_id() {
+        // <count> <min> for brigadier:integer (ID 3) with both bounds, then the root
+        let mut bytes: Vec<u8> = vec![2, 0x00, 1, 1, 0x06, 0];
+        bytes.extend(string("count"));
+        bytes.extend_from_slice(&[3, 0x03, 0, 0, 0, 1, 0, 0, 0, 64, 0]);
+        let tree: CommandTree = read_command_tree(&mut Cursor::new(&bytes[..]), 766).unwrap();
+        assert!(matches!(
+            &tree.nodes[1].kind,
+            NodeKind::Argument { parser, .. } if parser == "brigadier:integer"
+        ));
+
+        let mut bytes: Vec<u8> = vec![2, 0x00, 1, 1, 0x06, 0];
+        bytes.extend(string("count"));
+        bytes.extend_from_slice(&[120, 0]);
+        assert!(read_command_tree(&mut Cursor::new(&bytes[..]), 766).is_err());
+    }
+
+    #[test]
+    fn validation() {
+        let tree: CommandTree = tree();
+        assert!(tree.validate("/gamemode creative").is_ok());
+        assert!(tree.validate("/GAMEMODE creative").is_ok());
+        assert!(tree.validate("/gm creative").is_ok());
+        assert!(tree.validate("/team list").is_ok());
+
+        assert_eq!(
+            tree.validate("/foo"),
+            Err(String::from("Unknown command /foo"))
+        );
+        assert_eq!(
+            tree.validate("/gamemode"),
+            Err(String::from("/gamemode is incomplete"))
+        );
+        assert_eq!(
+            tree.validate("/team add"),
+            Err(String::from(
+                "Unknown argument 'add', expected one of: list"
+            ))
+        );
+        assert_eq!(
+            tree.validate("/team list all"),
+            Err(String::from("/team list has too many arguments"))
+        );
+    }
+
+    #[test]
+    fn completion() {
+        let tree: CommandTree = tree();
+        assert_eq!(tree.complete("/g"), ["/gamemode", "/gm"]);
+        assert_eq!(tree.complete("/T"), ["/team"]);
+        assert_eq!(tree.complete("/team "), ["/team list"]);
+        assert!(tree.complete("/gamemode c").is_empty());
+        assert!(tree.complete("/nothing ").is_empty());
+        assert!(tree.complete("team").is_empty());
+    }
+}
@@ -0,0 +1,805 @@
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use mclient_project::{AfkAction, ChatMode, IpPreference, Socks5Proxy};
+use serde_json::{Map, Value};
+
+use crate::bots::BotSpec;
+#[cfg(feature = "discord")]
+use crate::bridge::{self, ChannelMapping, DiscordConfig};
+use crate::cli::{self, Args};
+use crate::logging;
+use crate::regex::Regex;
+use crate::responder::ResponderRule;
+use crate::schedule::{self, Cron, ScheduleSpec, When};
+#[cfg(feature = "telegram")]
+use crate::telegrambridge::{self, TelegramConfig};
+
+pub static DEFAULT_CONFIG_PATH: &str = "config.toml";
+
+// Just enough TOML for the config file: [tables], dotted table names, comments,
+// and string/integer/float/boolean/array values. Parsed into a serde_json tree.
+
+fn parse_value(raw: &str, line_number: usize) -> Result<Value, Box<dyn Error>> {
+    let raw: &str = raw.trim();
+
+    if let Some(inner) = raw
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+    {
+        let mut res: String = String::new();
+        let mut chars = inner.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                res.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('n') => res.push('\n'),
+                Some('t') => res.push('\t'),
+                Some('"') => res.push('"'),
+                Some('\\') => res.push('\\'),
+                _ => return Err(format!("Invalid escape on line {}", line_number).into()),
+            }
+        }
+        return Ok(Value::String(res));
+    }
+
+    if let Some(inner) = raw
+        .strip_prefix('\'')
+        .and_then(|rest| rest.strip_suffix('\''))
+    {
+        return Ok(Value::String(String::from(inner)));
+    }
+
+    if let Some(inner) = raw
+        .strip_prefix('[')
+        .and_then(|rest| rest.strip_suffix(']'))
+    {
+        let mut items: Vec<Value> = Vec::new();
+        for item in split_array(inner) {
+            if !item.trim().is_empty() {
+                items.push(parse_value(&item, line_number)?);
+            }
+        }
+        return Ok(Value::Array(items));
+    }
+
+    match raw {
+        "true" => return Ok(Value::Bool(true)),
+        "false" => return Ok(Value::Bool(false)),
+        _ => {}
+    }
+
+    if let Ok(number) = raw.replace('_', "").parse::<i64>() {
+        return Ok(Value::from(number));
+    }
+    if let Ok(number) = raw.parse::<f64>() {
+        return Ok(Value::from(number));
+    }
+
+    Err(format!("Invalid value '{}' on line {}", raw, line_number).into())
+}
+
+// Splits array items on commas that aren't inside a string.
+fn split_array(inner: &str) -> Vec<String> {
+    let mut items: Vec<String> = Vec::new();
+    let mut current: String = String::new();
+    let mut quote: Option<char> = None;
+    let mut escaped: bool = false;
+
+    for c in inner.chars() {
+        match quote {
+            Some(q) => {
+                if c == q && !escaped {
+                    quote = None;
+                }
+                escaped = c == '\\' && !escaped;
+            }
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == ',' => {
+                items.push(std::mem::take(&mut current));
+                continue;
+            }
+            None => {}
+        }
+        current.push(c);
+    }
+    items.push(current);
+    items
+}
+
+// Drops a trailing `# comment`, ignoring `#` inside strings.
+fn strip_comment(line: &str) -> &str {
+    let mut quote: Option<char> = None;
+    let mut escaped: bool = false;
+
+    for (i, c) in line.char_indices() {
+        match quote {
+            Some(q) => {
+                if c == q && !escaped {
+                    quote = None;
+                }
+                escaped = c == '\\' && !escaped;
+            }
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '#' => return &line[..i],
+            None => {}
+        }
+    }
+    line
+}
+
+pub fn parse_toml(text: &str) -> Result<Value, Box<dyn Error>> {
+    let mut root: Map<String, Value> = Map::new();
+    let mut table_path: Vec<String> = Vec::new();
+
+    for (i, line) in text.lines().enumerate() {
+        let line_number: usize = i + 1;
+        let line: &str = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(name) = line
+            .strip_prefix('[')
+            .and_then(|rest| rest.strip_suffix(']'))
+        {
+            table_path = name
+                .split('.')
+                .map(|part| String::from(part.trim().trim_matches('"')))
+                .collect();
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(format!("Expected 'key = value' on line {}", line_number).into());
+        };
+
+        let mut table: &mut Map<String, Value> = &mut root;
+        for part in &table_path {
+            let entry: &mut Value = table
+                .entry(part.clone())
+                .or_insert_with(|| Value::Object(Map::new()));
+            let Value::Object(inner) = entry else {
+                return Err(format!("'{}' is not a table (line {})", part, line_number).into());
+            };
+            table = inner;
+        }
+
+        table.insert(
+            String::from(key.trim().trim_matches('"')),
+            parse_value(value, line_number)?,
+        );
+    }
+
+    Ok(Value::Object(root))
+}
+
+// Writes a string the way parse_value reads it back
+fn format_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Sets `key` in the `[client]` table of the config file to an array of strings,
+/// creating the file or the table if needed. Other lines and comments are kept.
+pub fn save_client_list(path: &str, key: &str, values: &[String]) -> Result<(), Box<dyn Error>> {
+    let text: String = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(format!("Couldn't read {}: {}", path, e).into()),
+    };
+    let entry: String = format!(
+        "{} = [{}]",
+        key,
+        values
+            .iter()
+            .map(|value| format_string(value))
+            .collect::<Vec<String>>()
+            .join(", ")
+    );
+
+    let mut lines: Vec<String> = text.lines().map(String::from).collect();
+    let header: Option<usize> = lines.iter().position(|line| line.trim() == "[client]");
+    match header {
+        Some(header) => {
+            let end: usize = lines[header + 1..]
+                .iter()
+                .position(|line| line.trim_start().starts_with('['))
+                .map_or(lines.len(), |i| header + 1 + i);
+            let existing: Option<usize> = (header + 1..end).find(|i| {
+                strip_comment(&lines[*i])
+                    .split_once('=')
+                    .is_some_and(|(name, _)| name.trim().trim_matches('"') == key)
+            });
+            match existing {
+                Some(i) => lines[i] = entry,
+                None => {
+                    // After the table's last setting, before the blank lines
+                    let last: usize = (header..end)
+                        .rev()
+                        .find(|i| !lines[*i].trim().is_empty())
+                        .unwrap_or(header);
+                    lines.insert(last + 1, entry);
+                }
+            }
+        }
+        None => {
+            let mut table: Vec<String> = vec![String::from("[client]"), entry];
+            if !lines.is_empty() {
+                table.push(String::new());
+            }
+            lines.splice(0..0, table);
+        }
+    }
+
+    match fs::write(path, lines.join("\n") + "\n") {
+        Ok(()) => Ok(()),
+        Err(e) => Err(format!("Couldn't write {}: {}", path, e).into()),
+    }
+}
+
+pub struct Config {
+    root: Value,
+}
+
+impl Config {
+    /// Loads the config file. A missing file is only an error if it was asked for explicitly.
+    pub fn load(path: &str, explicit: bool) -> Result<Option<Config>, Box<dyn Error>> {
+        let text: String = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound && !explicit => return Ok(None),
+            Err(e) => return Err(format!("Couldn't read {}: {}", path, e).into()),
+        };
+
+        match parse_toml(&text) {
+            Ok(root) => Ok(Some(Config { root })),
+            Err(e) => Err(format!("Couldn't parse {}: {}", path, e).into()),
+        }
+    }
+
+    /// Applies the `[client]` preferences and then the chosen `[profiles.<name>]` to `args`.
+    pub fn apply(&self, profile: Option<&str>, args: &mut Args) -> Result<(), Box<dyn Error>> {
+        apply_table(&self.root["client"], args)?;
+        args.responders = responder_rules(&self.root["responders"])?;
+        args.schedules = schedules(&self.root["schedules"])?;
+        #[cfg(feature = "discord")]
+        {
+            args.discord = discord_config(&self.root["discord"])?;
+        }
+        #[cfg(not(feature = "discord"))]
+        if !self.root["discord"].is_null() {
+            return Err("[discord] needs mc-chat built with the discord feature (cargo build --features discord)".into());
+        }
+        #[cfg(feature = "telegram")]
+        {
+            args.telegram = telegram_config(&self.root["telegram"])?;
+        }
+        #[cfg(not(feature = "telegram"))]
+        if !self.root["telegram"].is_null() {
+            return Err("[telegram] needs mc-chat built with the telegram feature (cargo build --features telegram)".into());
+        }
+
+        if let Some(name) = profile {
+            let table: &Value = &self.root["profiles"][name];
+            if !table.is_object() {
+                return Err(format!("There is no [profiles.{}] in the config file", name).into());
+            }
+            apply_table(table, args)?;
+        }
+
+        Ok(())
+    }
+}
+
+// The [responders.<name>] tables
+fn responder_rules(tables: &Value) -> Result<Vec<ResponderRule>, Box<dyn Error>> {
+    let tables: &Map<String, Value> = match tables {
+        Value::Null => return Ok(Vec::new()),
+        Value::Object(tables) => tables,
+        _ => return Err("'responders' must be a table".into()),
+    };
+
+    let mut rules: Vec<ResponderRule> = Vec::new();
+    for (name, table) in tables {
+        let rule = || -> Result<ResponderRule, Box<dyn Error>> {
+            let (Some(pattern), Some(response)) =
+                (get_str(table, "pattern")?, get_str(table, "response")?)
+            else {
+                return Err("'pattern' and 'response' are required".into());
+            };
+            Ok(ResponderRule {
+                name: name.clone(),
+                pattern: String::from(pattern),
+                response: String::from(response),
+                cooldown: cli::seconds(get_float(table, "cooldown")?.unwrap_or(0.0), "cooldown")?,
+                allow: get_str_array(table, "allow")?
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(String::from)
+                    .collect(),
+            })
+        };
+        rules.push(rule().map_err(|e| format!("[responders.{}]: {}", name, e))?);
+    }
+    Ok(rules)
+}
+
+// The [schedules.<name>] tables
+fn schedules(tables: &Value) -> Result<Vec<ScheduleSpec>, Box<dyn Error>> {
+    let tables: &Map<String, Value> = match tables {
+        Value::Null => return Ok(Vec::new()),
+        Value::Object(tables) => tables,
+        _ => return Err("'schedules' must be a table".into()),
+    };
+
+    let mut specs: Vec<ScheduleSpec> = Vec::new();
+    for (name, table) in tables {
+        let spec = || -> Result<ScheduleSpec, Box<dyn Error>> {
+            let Some(message) = get_str(table, "message")? else {
+                return Err("'message' is required".into());
+            };
+            // `every` is seconds, or a string like "15m"
+            let when: When = match (&table["every"], get_str(table, "cron")?) {
+                (Value::Null, Some(cron)) => When::Cron(Cron::parse(cron)?),
+                (Value::String(every), None) => When::Every(schedule::parse_interval(every)?),
+                (every, None) if every.is_number() => {
+                    When::Every(schedule::parse_interval(&every.to_string())?)
+                }
+                _ => return Err("Needs either 'every' or 'cron'".into()),
+            };
+            Ok(ScheduleSpec {
+                name: Some(name.clone()),
+                when,
+                message: String::from(message),
+            })
+        };
+        specs.push(spec().map_err(|e| format!("[schedules.{}]: {}", name, e))?);
+    }
+    Ok(specs)
+}
+
+// The [discord] table with its [discord.channels.<name>] tables
+#[cfg(feature = "discord")]
+fn discord_config(table: &Value) -> Result<Option<DiscordConfig>, Box<dyn Error>> {
+    if table.is_null() {
+        return Ok(None);
+    }
+    let read = || -> Result<DiscordConfig, Box<dyn Error>> {
+        let mut channels: Vec<ChannelMapping> = Vec::new();
+        if let Some(tables) = table["channels"].as_object() {
+            for (name, channel) in tables {
+                let mapping = ChannelMapping {
+                    name: name.clone(),
+                    bot: get_str(channel, "bot")?.map(String::from),
+                    webhook: get_str(channel, "webhook")?.map(String::from),
+                    channel: get_str(channel, "channel")?.map(String::from),
+                };
+                if mapping.webhook.is_none() && mapping.channel.is_none() {
+                    return Err(format!(
+                        "[discord.channels.{}] needs a webhook or a channel",
+                        name
+                    )
+                    .into());
+                }
+                channels.push(mapping);
+            }
+        }
+        Ok(DiscordConfig {
+            token: get_str(table, "token")?.map(String::from),
+            poll_interval: cli::positive_seconds(
+                get_float(table, "poll_interval")?.unwrap_or(2.0),
+                "poll_interval",
+            )?,
+            to_discord: String::from(
+                get_str(table, "to_discord")?.unwrap_or(bridge::DEFAULT_TO_DISCORD),
+            ),
+            to_minecraft: String::from(
+                get_str(table, "to_minecraft")?.unwrap_or(bridge::DEFAULT_TO_MINECRAFT),
+            ),
+            channels,
+        })
+    };
+    read()
+        .map(Some)
+        .map_err(|e| format!("[discord]: {}", e).into())
+}
+
+// The [telegram] table
+#[cfg(feature = "telegram")]
+fn telegram_config(table: &Value) -> Result<Option<TelegramConfig>, Box<dyn Error>> {
+    if table.is_null() {
+        return Ok(None);
+    }
+    let read = || -> Result<TelegramConfig, Box<dyn Error>> {
+        let (Some(token), Some(chat)) = (get_str(table, "token")?, get_int(table, "chat")?) else {
+            return Err("'token' and 'chat' are required".into());
+        };
+        Ok(TelegramConfig {
+            token: String::from(token),
+            chat,
+            allow: get_str_array(table, "allow")?
+                .unwrap_or_default()
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            bot: get_str(table, "bot")?.map(String::from),
+            to_telegram: String::from(
+                get_str(table, "to_telegram")?.unwrap_or(telegrambridge::DEFAULT_TO_TELEGRAM),
+            ),
+            to_minecraft: String::from(
+                get_str(table, "to_minecraft")?.unwrap_or(telegrambridge::DEFAULT_TO_MINECRAFT),
+            ),
+            events: get_bool(table, "events")?.unwrap_or(true),
+        })
+    };
+    read()
+        .map(Some)
+        .map_err(|e| format!("[telegram]: {}", e).into())
+}
+
+fn get_str<'a>(table: &'a Value, key: &str) -> Result<Option<&'a str>, Box<dyn Error>> {
+    match &table[key] {
+        Value::Null => Ok(None),
+        Value::String(value) => Ok(Some(value)),
+        _ => Err(format!("'{}' must be a string", key).into()),
+    }
+}
+
+fn get_int(table: &Value, key: &str) -> Result<Option<i64>, Box<dyn Error>> {
+    match &table[key] {
+        Value::Null => Ok(None),
+        value => match value.as_i64() {
+            Some(value) => Ok(Some(value)),
+            None => Err(format!("'{}' must be an integer", key).into()),
+        },
+    }
+}
+
+fn get_float(table: &Value, key: &str) -> Result<Option<f64>, Box<dyn Error>> {
+    match &table[key] {
+        Value::Null => Ok(None),
+        value => match value.as_f64() {
+            Some(value) => Ok(Some(value)),
+            None => Err(format!("'{}' must be a number", key).into()),
+        },
+    }
+}
+
+fn get_bool(table: &Value, key: &str) -> Result<Option<bool>, Box<dyn Error>> {
+    match &table[key] {
+        Value::Null => Ok(None),
+        Value::Bool(value) => Ok(Some(*value)),
+        _ => Err(format!("'{}' must be true or false", key).into()),
+    }
+}
+
+fn get_str_array<'a>(table: &'a Value, key: &str) -> Result<Option<Vec<&'a str>>, Box<dyn Error>> {
+    match &table[key] {
+        Value::Null => Ok(None),
+        Value::Array(items) => items
+            .iter()
+            .map(|item| item.as_str())
+            .collect::<Option<Vec<&str>>>()
+            .map(Some)
+            .ok_or_else(|| format!("'{}' must be an array of strings", key).into()),
+        _ => Err(format!("'{}' must be an array of strings", key).into()),
+    }
+}
+
+fn apply_table(table: &Value, args: &mut Args) -> Result<(), Box<dyn Error>> {
+    if let Some(host) = get_str(table, "host")? {
+        args.host = String::from(host);
+    }
+    if let Some(port) = get_int(table, "port")? {
+        args.port = Some(u16::try_from(port)?);
+    }
+    if let Some(bots) = get_str_array(table, "bots")? {
+        args.bots = bots
+            .into_iter()
+            .map(BotSpec::parse)
+            .collect::<Result<Vec<BotSpec>, Box<dyn Error>>>()?;
+    }
+    if let Some(handshake_host) = get_str(table, "handshake_host")? {
+        args.virtual_host.host = Some(String::from(handshake_host));
+    }
+    if let Some(handshake_port) = get_int(table, "handshake_port")? {
+        args.virtual_host.port = Some(u16::try_from(handshake_port)?);
+    }
+    if let Some(username) = get_str(table, "username")? {
+        args.username = String::from(username);
+    }
+    if let Some(auth) = get_str(table, "auth")?
+        && auth != "offline"
+    {
+        return Err(format!("Unsupported auth mode '{}', only \"offline\" works", auth).into());
+    }
+    if let Some(protocol) = get_int(table, "protocol")? {
+        args.protocol = Some(i32::try_from(protocol)?);
+    }
+    if let Some(proxy) = get_str(table, "proxy")? {
+        args.proxy = Some(Socks5Proxy::parse(proxy)?);
+    }
+    if let Some(skip_status) = get_bool(table, "skip_status")? {
+        args.skip_status = skip_status;
+    }
+    if let Some(favicon_out) = get_str(table, "favicon_out")? {
+        args.favicon_out = Some(PathBuf::from(favicon_out));
+    }
+    if let Some(false) = get_bool(table, "favicon")? {
+        args.favicon_out = None;
+    }
+    if let Some(favicon_preview) = get_bool(table, "favicon_preview")? {
+        args.favicon_preview = favicon_preview;
+    }
+    if let Some(tui) = get_bool(table, "tui")? {
+        args.tui = tui;
+    }
+    if let Some(join_messages) = get_bool(table, "join_messages")? {
+        args.join_messages = join_messages;
+    }
+    if let Some(history_file) = get_str(table, "history_file")? {
+        args.history_file = match history_file {
+            "" => None,
+            path => Some(PathBuf::from(path)),
+        };
+    }
+    if let Some(seen_file) = get_str(table, "seen_file")? {
+        args.seen_file = match seen_file {
+            "" => None,
+            path => Some(PathBuf::from(path)),
+        };
+    }
+    if let Some(chat_format) = get_str(table, "chat_format")? {
+        args.chat_format = String::from(chat_format);
+    }
+    if let Some(lang) = get_str(table, "lang")? {
+        args.lang_file = Some(PathBuf::from(lang));
+    }
+    if let Some(log_dir) = get_str(table, "log_dir")? {
+        args.log_dir = match log_dir {
+            "" => None,
+            path => Some(PathBuf::from(path)),
+        };
+    }
+    if let Some(irc_listen) = get_str(table, "irc_listen")? {
+        args.irc_listen = Some(cli::parse_listen_address(irc_listen)?);
+    }
+    if let Some(irc_password) = get_str(table, "irc_password")? {
+        args.irc_password = Some(String::from(irc_password));
+    }
+    if let Some(api_listen) = get_str(table, "api_listen")? {
+        args.api_listen = Some(cli::parse_listen_address(api_listen)?);
+    }
+    if let Some(scripts) = get_str_array(table, "scripts")? {
+        args.scripts = scripts.into_iter().map(PathBuf::from).collect();
+    }
+    if let Some(connect_timeout) = get_float(table, "connect_timeout")? {
+        args.connect.timeout = cli::positive_seconds(connect_timeout, "connect_timeout")?;
+    }
+    if let Some(connect_attempts) = get_int(table, "connect_attempts")? {
+        args.connect.attempts = u32::try_from(connect_attempts)?;
+    }
+    if let Some(retry_delay) = get_float(table, "retry_delay")? {
+        args.connect.retry_delay = cli::seconds(retry_delay, "retry_delay")?;
+    }
+    if let Some(prefer_ip) = get_str(table, "prefer_ip")? {
+        args.connect.prefer = match prefer_ip {
+            "any" => IpPreference::Any,
+            "ipv4" => IpPreference::Ipv4,
+            "ipv6" => IpPreference::Ipv6,
+            _ => {
+                return Err(format!(
+                    "Unknown address family '{}', expected \"any\", \"ipv4\" or \"ipv6\"",
+                    prefer_ip
+                )
+                .into());
+            }
+        };
+    }
+    if let Some(read_timeout) = get_float(table, "read_timeout")? {
+        args.socket.read_timeout = cli::seconds_or_none(read_timeout, "read_timeout")?;
+    }
+    if let Some(write_timeout) = get_float(table, "write_timeout")? {
+        args.socket.write_timeout = cli::seconds_or_none(write_timeout, "write_timeout")?;
+    }
+    if let Some(tcp_nodelay) = get_bool(table, "tcp_nodelay")? {
+        args.socket.nodelay = tcp_nodelay;
+    }
+    if let Some(tcp_keepalive) = get_bool(table, "tcp_keepalive")? {
+        args.socket.keepalive = tcp_keepalive;
+    }
+    if let Some(reconnect) = get_int(table, "reconnect")? {
+        args.reconnect.max_retries = u32::try_from(reconnect)?;
+    }
+    if let Some(backoff) = get_float(table, "backoff")? {
+        args.reconnect.initial_backoff = cli::seconds(backoff, "backoff")?;
+    }
+    if let Some(timeout) = get_float(table, "timeout")? {
+        args.keep_alive_timeout = cli::seconds_or_none(timeout, "timeout")?;
+    }
+    if let Some(max_backoff) = get_float(table, "max_backoff")? {
+        args.reconnect.max_backoff = cli::seconds(max_backoff, "max_backoff")?;
+    }
+    if let Some(velocity_secret) = get_str(table, "velocity_secret")? {
+        args.velocity_secret = Some(String::from(velocity_secret));
+    }
+    if let Some(bungee_spoof) = get_str(table, "bungee_spoof")? {
+        args.bungee_spoof = Some(bungee_spoof.parse()?);
+    }
+    if let Some(anti_afk) = get_float(table, "anti_afk")? {
+        args.anti_afk_interval = cli::seconds_or_none(anti_afk, "anti_afk")?;
+    }
+    if let Some(names) = get_str_array(table, "anti_afk_actions")? {
+        let mut actions: Vec<AfkAction> = Vec::new();
+        for name in names {
+            match AfkAction::parse(name) {
+                Some(action) => actions.push(action),
+                None => {
+                    return Err(format!(
+                        "Unknown anti-AFK action '{}', expected \"swing\", \"look\" or \"sneak\"",
+                        name
+                    )
+                    .into());
+                }
+            }
+        }
+        args.anti_afk_actions = actions;
+    }
+    if let Some(accept_resource_packs) = get_bool(table, "accept_resource_packs")? {
+        args.accept_resource_packs = accept_resource_packs;
+    }
+    if let Some(words) = get_str_array(table, "highlight")? {
+        args.highlight_words = words.into_iter().map(String::from).collect();
+    }
+    if let Some(no_color) = get_bool(table, "no_color")? {
+        args.no_color = no_color;
+    }
+    if let Some(plain) = get_bool(table, "plain")? {
+        args.plain = plain;
+    }
+    if let Some(json) = get_bool(table, "json")? {
+        args.json = json;
+    }
+    if let Some(notify) = get_bool(table, "notify")? {
+        args.notify = notify;
+    }
+    if let Some(color_codes) = get_str(table, "color_codes")? {
+        args.color_codes = cli::parse_color_codes(color_codes)?;
+    }
+    if let Some(chat_rate) = get_float(table, "chat_rate")? {
+        args.chat_rate = cli::rate_or_none(chat_rate, "chat_rate")?;
+    }
+    if let Some(debug_packets) = get_bool(table, "debug_packets")? {
+        args.debug_packets = debug_packets;
+    }
+    if let Some(capture) = get_str(table, "capture")? {
+        args.capture = Some(PathBuf::from(capture));
+    }
+    if let Some(log_level) = get_str(table, "log_level")? {
+        args.log_level = logging::parse_level(log_level)?;
+    }
+    if let Some(log_file) = get_str(table, "log_file")? {
+        args.log_file = match log_file {
+            "" => None,
+            path => Some(PathBuf::from(path)),
+        };
+    }
+    if let Some(scrollback) = get_int(table, "scrollback")? {
+        args.scrollback = usize::try_from(scrollback)?;
+    }
+    if let Some(names) = get_str_array(table, "ignore")? {
+        args.ignored = names.into_iter().map(String::from).collect();
+    }
+    if let Some(patterns) = get_str_array(table, "filters")? {
+        for pattern in &patterns {
+            if let Err(e) = Regex::new(pattern) {
+                return Err(format!("Invalid filter '{}': {}", pattern, e).into());
+            }
+        }
+        args.filters = patterns.into_iter().map(String::from).collect();
+    }
+    if let Some(brand) = get_str(table, "brand")? {
+        args.brand = String::from(brand);
+    }
+    if let Some(locale) = get_str(table, "locale")? {
+        args.settings.locale = String::from(locale);
+    }
+    if let Some(view_distance) = get_int(table, "view_distance")? {
+        if !(2..=32).contains(&view_distance) {
+            return Err("'view_distance' must be between 2 and 32".into());
+        }
+        args.settings.view_distance = view_distance as u8;
+    }
+    if let Some(chat_mode) = get_str(table, "chat_mode")? {
+        args.settings.chat_mode = match chat_mode {
+            "enabled" => ChatMode::Enabled,
+            "commands" => ChatMode::CommandsOnly,
+            "hidden" => ChatMode::Hidden,
+            _ => {
+                return Err(format!(
+                    "Unknown chat mode '{}', expected \"enabled\", \"commands\" or \"hidden\"",
+                    chat_mode
+                )
+                .into());
+            }
+        };
+    }
+    if let Some(skin_parts) = get_int(table, "skin_parts")? {
+        args.settings.skin_parts = u8::try_from(skin_parts)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn tables_and_values() {
+        let text: &str = r##"
+# The server
+host = "mc.example.com"  # trailing comment
+port = 25_565
+
+[client]
+username = 'Bot'
+chat_rate = 2.5
+reconnect = true
+ignore = ["Spammer", 'Other, with a comma', "#notacomment"]
+
+[profiles."alt".client]
+"proxy" = "socks5://localhost:1080"
+"##;
+        assert_eq!(
+            parse_toml(text).unwrap(),
+            json!({
+                "host": "mc.example.com",
+                "port": 25565,
+                "client": {
+                    "username": "Bot",
+                    "chat_rate": 2.5,
+                    "reconnect": true,
+                    "ignore": ["Spammer", "Other, with a comma", "#notacomment"],
+                },
+                "profiles": { "alt": { "client": { "proxy": "socks5://localhost:1080" } } },
+            })
+        );
+    }
+
+    #[test]
+    fn strings() {
+        let parse = |raw: &str| parse_value(raw, 1).unwrap();
+        assert_eq!(parse(r#""a\tb\nc""#), json!("a\tb\nc"));
+        assert_eq!(parse(r#""say \"hi\" \\o/""#), json!("say \"hi\" \\o/"));
+        // Literal strings keep backslashes
+        assert_eq!(parse(r"'C:\logs'"), json!(r"C:\logs"));
+        assert_eq!(parse("[]"), json!([]));
+        assert_eq!(parse("[1, 2.5, [true]]"), json!([1, 2.5, [true]]));
+
+        for value in [r#""a \\ b \" c""#, r#""""#, r#""tab\there""#] {
+            let parsed: Value = parse(value);
+            assert_eq!(parse(&format_string(parsed.as_str().unwrap())), parsed);
+        }
+    }
+
+    #[test]
+    fn errors() {
+        for (text, message) in [
+            ("key", "Expected 'key = value' on line 1"),
+            ("\nkey = nope", "Invalid value 'nope' on line 2"),
+            (r#"key = "\q""#, "Invalid escape on line 1"),
+            (
+                "key = \"unterminated",
+                "Invalid value '\"unterminated' on line 1",
+            ),
+            ("a = 1\n[a]\nb = 2", "'a' is not a table (line 3)"),
+        ] {
+            assert_eq!(parse_toml(text).unwrap_err().to_string(), message);
+        }
+    }
+}
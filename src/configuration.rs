@@ -0,0 +1,258 @@
+use std::io::Cursor;
+use std::net::TcpStream;
+
+use azalea_chat::FormattedText;
+
+use mc_varint::{VarInt, VarIntWrite};
+use tracing::debug;
+
+use crate::capture::CaptureState;
+use crate::client::{self, ClientConfig};
+use crate::error::ClientError;
+use crate::helper::{PacketReader, send_packet};
+use crate::packet::{
+    brand_channel, decode_brand, encode_brand, read_component, read_plugin_message,
+    read_resource_pack, write_client_settings, write_plugin_message, write_resource_pack_status,
+};
+use crate::types::McRead;
+
+// The configuration state that 1.20.2+ servers go through between login and play.
+// Registries, tags and feature flags are only needed by a real game client, so they're
+// skipped; the client settings and brand are sent, keep alives, pings and resource packs
+// are answered, and known packs are declined so the server sends everything inline.
+
+struct ConfigIds {
+    // clientbound
+    disconnect: i32,
+    finish: i32,
+    keep_alive: i32,
+    ping: i32,
+    plugin_message: i32,
+    resource_pack: i32,
+    // serverbound
+    client_information: i32,
+    plugin_message_response: i32,
+    acknowledge_finish: i32,
+    keep_alive_response: i32,
+    pong: i32,
+    resource_pack_status: i32,
+    /// Select Known Packs and the client's answer (1.20.5+).
+    known_packs: Option<(i32, i32)>,
+}
+
+static CONFIG_1_20_2: ConfigIds = ConfigIds {
+    disconnect: 0x01,
+    finish: 0x02,
+    keep_alive: 0x03,
+    ping: 0x04,
+    plugin_message: 0x00,
+    resource_pack: 0x06,
+    client_information: 0x00,
+    plugin_message_response: 0x01,
+    acknowledge_finish: 0x02,
+    keep_alive_response: 0x03,
+    pong: 0x04,
+    resource_pack_status: 0x05,
+    known_packs: None,
+};
+
+// 1.20.3 added Remove Resource Pack before Add Resource Pack
+static CONFIG_1_20_3: ConfigIds = ConfigIds {
+    disconnect: 0x01,
+    finish: 0x02,
+    keep_alive: 0x03,
+    ping: 0x04,
+    plugin_message: 0x00,
+    resource_pack: 0x07,
+    client_information: 0x00,
+    plugin_message_response: 0x01,
+    acknowledge_finish: 0x02,
+    keep_alive_response: 0x03,
+    pong: 0x04,
+    resource_pack_status: 0x05,
+    known_packs: None,
+};
+
+// 1.20.5 added cookies before the other packets
+static CONFIG_1_20_5: ConfigIds = ConfigIds {
+    disconnect: 0x02,
+    finish: 0x03,
+    keep_alive: 0x04,
+    ping: 0x05,
+    plugin_message: 0x01,
+    resource_pack: 0x09,
+    client_information: 0x00,
+    plugin_message_response: 0x02,
+    acknowledge_finish: 0x03,
+    keep_alive_response: 0x04,
+    pong: 0x05,
+    resource_pack_status: 0x06,
+    known_packs: Some((0x0E, 0x07)),
+};
+
+fn ids_for(protocol: i32) -> &'static ConfigIds {
+    match protocol {
+        766.. => &CONFIG_1_20_5,
+        765 => &CONFIG_1_20_3,
+        _ => &CONFIG_1_20_2,
+    }
+}
+
+/// A packet the server sends during configuration (1.20.2+).
+#[derive(Clone, Debug)]
+pub enum ConfigurationPacket<'a> {
+    Disconnect {
+        reason: FormattedText,
+    },
+    /// Switches the connection to play once the client acknowledges it.
+    Finish,
+    KeepAlive {
+        id: i64,
+    },
+    Ping {
+        id: i32,
+    },
+    PluginMessage {
+        channel: String,
+        data: &'a [u8],
+    },
+    /// Like the play packet of the same name.
+    ResourcePack {
+        uuid: Option<u128>,
+        url: String,
+        hash: String,
+    },
+    /// The data packs the server would like the client to have already (1.20.5+).
+    SelectKnownPacks,
+    /// Registries, tags, feature flags...
+    Unknown {
+        id: i32,
+        data: &'a [u8],
+    },
+}
+
+impl<'a> ConfigurationPacket<'a> {
+    /// Decodes a configuration state packet sent to a client that uses `protocol`.
+    pub fn decode(
+        protocol: i32,
+        id: i32,
+        data: &'a [u8],
+    ) -> Result<ConfigurationPacket<'a>, ClientError> {
+        let ids: &ConfigIds = ids_for(protocol);
+        let mut cursor: Cursor<&[u8]> = Cursor::new(data);
+
+        let packet: ConfigurationPacket = match id {
+            id if id == ids.disconnect => ConfigurationPacket::Disconnect {
+                reason: read_component(&mut cursor, protocol)?,
+            },
+            id if id == ids.finish => ConfigurationPacket::Finish,
+            id if id == ids.keep_alive => ConfigurationPacket::KeepAlive {
+                id: cursor.read_long()?,
+            },
+            id if id == ids.ping => ConfigurationPacket::Ping {
+                id: cursor.read_int()?,
+            },
+            id if id == ids.plugin_message => {
+                let (channel, data): (String, &[u8]) = read_plugin_message(&mut cursor)?;
+                ConfigurationPacket::PluginMessage { channel, data }
+            }
+            id if id == ids.resource_pack => {
+                let (uuid, url, hash): (Option<u128>, String, String) =
+                    read_resource_pack(&mut cursor, protocol)?;
+                ConfigurationPacket::ResourcePack { uuid, url, hash }
+            }
+            id if ids.known_packs.map(|packets| packets.0) == Some(id) => {
+                ConfigurationPacket::SelectKnownPacks
+            }
+            _ => ConfigurationPacket::Unknown { id, data },
+        };
+
+        Ok(packet)
+    }
+}
+
+/// The IDs of Finish Configuration and the client's acknowledgement, which switch the
+/// connection to play.
+pub(crate) fn finish_ids(protocol: i32) -> (i32, i32) {
+    let ids: &ConfigIds = ids_for(protocol);
+    (ids.finish, ids.acknowledge_finish)
+}
+
+/// Whether `protocol` has a configuration state after login.
+pub(crate) fn has_configuration(protocol: i32) -> bool {
+    protocol >= 764
+}
+
+/// Runs the configuration state until the server switches to play. Returns the server's
+/// brand if it sent one.
+pub(crate) fn configure(
+    stream: &mut TcpStream,
+    threshold: i32,
+    config: &ClientConfig,
+) -> Result<Option<String>, ClientError> {
+    let protocol: i32 = config.protocol;
+    let ids: &ConfigIds = ids_for(protocol);
+
+    let mut packet_buffer: Vec<u8> = Vec::new();
+    write_client_settings(&mut packet_buffer, &config.settings, protocol)?;
+    send_packet(stream, ids.client_information, packet_buffer, threshold)?;
+
+    let mut packet_buffer: Vec<u8> = Vec::new();
+    write_plugin_message(
+        &mut packet_buffer,
+        brand_channel(protocol),
+        &encode_brand(&config.brand)?,
+    )?;
+    send_packet(
+        stream,
+        ids.plugin_message_response,
+        packet_buffer,
+        threshold,
+    )?;
+
+    let mut server_brand: Option<String> = None;
+
+    let mut reader: PacketReader =
+        PacketReader::capturing(config.capture.as_ref(), CaptureState::Configuration);
+    loop {
+        let (id, data): (i32, &[u8]) = reader.receive(stream, threshold)?;
+
+        match ConfigurationPacket::decode(protocol, id, data)? {
+            ConfigurationPacket::Disconnect { reason } => {
+                return Err(ClientError::Kicked(reason));
+            }
+            ConfigurationPacket::Finish => {
+                debug!("Configuration finished");
+                send_packet(stream, ids.acknowledge_finish, Vec::new(), threshold)?;
+                return Ok(server_brand);
+            }
+            ConfigurationPacket::KeepAlive { id } => {
+                let keep_alive_id: Vec<u8> = id.to_be_bytes().to_vec();
+                send_packet(stream, ids.keep_alive_response, keep_alive_id, threshold)?;
+            }
+            ConfigurationPacket::Ping { id } => {
+                send_packet(stream, ids.pong, id.to_be_bytes().to_vec(), threshold)?;
+            }
+            ConfigurationPacket::PluginMessage { channel, data } => {
+                if channel == brand_channel(protocol) {
+                    server_brand = Some(decode_brand(data)?);
+                }
+            }
+            ConfigurationPacket::ResourcePack { uuid, url, hash } => {
+                for status in client::resource_pack_answer(config, &url, &hash) {
+                    let mut packet_buffer: Vec<u8> = Vec::new();
+                    write_resource_pack_status(&mut packet_buffer, uuid, *status)?;
+                    send_packet(stream, ids.resource_pack_status, packet_buffer, threshold)?;
+                }
+            }
+            ConfigurationPacket::SelectKnownPacks => {
+                let mut packet_buffer: Vec<u8> = Vec::new();
+                packet_buffer.write_var_int(VarInt::from(0))?; // no known packs
+                send_packet(stream, ids.known_packs.unwrap().1, packet_buffer, threshold)?;
+            }
+            ConfigurationPacket::Unknown { .. } => {
+                // registry data, tags, feature flags...
+            }
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use std::thread;
+use std::time::Duration;
+
+use serde_json::{Value, json};
+
+use crate::error::ClientError;
+use crate::http;
+use crate::textlimit::truncate;
+
+// Just the two Discord REST endpoints a chat bridge needs: posting through a channel
+// webhook, and reading a channel's new messages with a bot token. The gateway would
+// need a WebSocket over TLS, so new messages are polled instead.
+
+const API_URL: &str = "https://discord.com/api/v10";
+
+/// Discord rejects longer messages.
+pub const MAX_MESSAGE_LENGTH: usize = 2000;
+
+// Discord answers 429 with how long to wait; this caps it in case it's absurd
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// A channel webhook, which posts messages without a bot account.
+#[derive(Clone, Debug)]
+pub struct Webhook {
+    url: String,
+}
+
+impl Webhook {
+    /// `url` is the webhook URL from the channel settings,
+    /// `https://discord.com/api/webhooks/<id>/<token>`.
+    pub fn new(url: &str) -> Webhook {
+        Webhook {
+            url: String::from(url),
+        }
+    }
+
+    /// Posts `content`, as `username` if given instead of the webhook's name. Mentions
+    /// in it don't ping anyone. Waits and tries again when rate limited.
+    pub fn send(&self, content: &str, username: Option<&str>) -> Result<(), ClientError> {
+        let mut body: Value = json!({
+            "content": truncate(content, MAX_MESSAGE_LENGTH),
+            "allowed_mentions": { "parse": [] },
+        });
+        if let Some(username) = username {
+            body["username"] = Value::from(username);
+        }
+        request("POST", &self.url, &[], Some(&body.to_string()))?;
+        Ok(())
+    }
+}
+
+/// A message posted in a Discord channel.
+#[derive(Clone, Debug)]
+pub struct DiscordMessage {
+    pub id: u64,
+    /// The display name, or the username if there is none.
+    pub author: String,
+    pub content: String,
+    /// Posted by a bot or a webhook, like the bridge's own messages.
+    pub from_bot: bool,
+}
+
+/// Reads new messages from a channel with a bot token. The bot needs the Message
+/// Content intent, or the messages come without their text.
+#[derive(Debug)]
+pub struct ChannelReader {
+    token: String,
+    channel: String,
+    last_id: Option<u64>,
+}
+
+impl ChannelReader {
+    pub fn new(token: &str, channel: &str) -> ChannelReader {
+        ChannelReader {
+            token: String::from(token),
+            channel: String::from(channel),
+            last_id: None,
+        }
+    }
+
+    /// Returns the messages posted since the last call, oldest first. The first call
+    /// only remembers where the channel is and returns nothing, so old messages aren't
+    /// replayed.
+    pub fn poll(&mut self) -> Result<Vec<DiscordMessage>, ClientError> {
+        let url: String = match self.last_id {
+            Some(last_id) => format!(
+                "{}/channels/{}/messages?limit=100&after={}",
+                API_URL, self.channel, last_id
+            ),
+            None => format!("{}/channels/{}/messages?limit=1", API_URL, self.channel),
+        };
+        let authorization: String = format!("Bot {}", self.token);
+        let body: Vec<u8> = request("GET", &url, &[("Authorization", &authorization)], None)?;
+
+        let json: Value = serde_json::from_slice(&body)?;
+        let Some(items) = json.as_array() else {
+            return Err("Discord didn't send a list of messages".into());
+        };
+        let mut messages: Vec<DiscordMessage> = items.iter().filter_map(parse_message).collect();
+        // Newest first
+        messages.sort_by_key(|message| message.id);
+
+        let first: bool = self.last_id.is_none();
+        if let Some(last) = messages.last() {
+            self.last_id = Some(last.id);
+        } else if first {
+            // An empty channel
+            self.last_id = Some(0);
+        }
+        match first {
+            true => Ok(Vec::new()),
+            false => Ok(messages),
+        }
+    }
+}
+
+fn parse_message(item: &Value) -> Option<DiscordMessage> {
+    let author: &Value = &item["author"];
+    Some(DiscordMessage {
+        id: item["id"].as_str()?.parse().ok()?,
+        author: String::from(
+            author["global_name"]
+                .as_str()
+                .or_else(|| author["username"].as_str())?,
+        ),
+        content: String::from(item["content"].as_str().unwrap_or_default()),
+        from_bot: author["bot"] == true || !item["webhook_id"].is_null(),
+    })
+}
+
+// Sends a JSON request to Discord, waiting out rate limits
+fn request(
+    method: &str,
+    url: &str,
+    headers: &[(&str, &str)],
+    body: Option<&str>,
+) -> Result<Vec<u8>, ClientError> {
+    let mut headers: Vec<(&str, &str)> = headers.to_vec();
+    headers.push(("Content-Type", "application/json"));
+
+    loop {
+        let (status, response): (u16, Vec<u8>) = http::request(method, url, &headers, body)?;
+        match status {
+            200..=299 => return Ok(response),
+            429 => {
+                let retry_after: f64 = serde_json::from_slice::<Value>(&response)
+                    .ok()
+                    .and_then(|json| json["retry_after"].as_f64())
+                    .unwrap_or(1.0);
+                // Clamped before the conversion, which panics on huge values
+                let seconds: f64 = match retry_after.is_nan() {
+                    true => 0.0,
+                    false => retry_after.clamp(0.0, MAX_RETRY_AFTER.as_secs_f64()),
+                };
+                thread::sleep(Duration::from_secs_f64(seconds));
+            }
+            _ => {
+                let message: String = serde_json::from_slice::<Value>(&response)
+                    .ok()
+                    .and_then(|json| json["message"].as_str().map(String::from))
+                    .unwrap_or_default();
+                return Err(
+                    format!("Discord answered with status {} {}", status, message)
+                        .trim_end()
+                        .into(),
+                );
+            }
+        }
+    }
+}
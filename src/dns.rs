@@ -0,0 +1,329 @@
+use std::collections::hash_map::RandomState;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpStream, UdpSocket};
+use std::time::Duration;
+
+// Minimal DNS client, only used to look up `_minecraft._tcp` SRV records. Answers are
+// only taken from the nameserver that was asked, with the random ID and the question
+// echoed back. Truncated UDP answers are asked for again over TCP.
+
+const TYPE_SRV: u16 = 33;
+const CLASS_IN: u16 = 1;
+
+const FLAG_RESPONSE: u16 = 0x8000;
+const FLAG_TRUNCATED: u16 = 0x0200;
+
+const DNS_TIMEOUT: Duration = Duration::from_secs(2);
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn nameserver() -> Option<IpAddr> {
+    let resolv_conf: String = fs::read_to_string("/etc/resolv.conf").ok()?;
+
+    resolv_conf
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("nameserver"))
+        .find_map(|address| address.trim().parse().ok())
+}
+
+// A different ID for every query, from the randomly keyed hasher std uses for HashMaps
+fn random_id() -> u16 {
+    RandomState::new().build_hasher().finish() as u16
+}
+
+fn build_query(id: u16, name: &str) -> io::Result<Vec<u8>> {
+    let mut query: Vec<u8> = Vec::new();
+
+    query.extend_from_slice(&id.to_be_bytes());
+    query.extend_from_slice(&0x0100u16.to_be_bytes()); // recursion desired
+    query.extend_from_slice(&1u16.to_be_bytes()); // questions
+    query.extend_from_slice(&[0u8; 6]); // answer, authority and additional counts
+
+    let name: &str = name.trim_end_matches('.');
+    if name.len() > 253 {
+        return Err(invalid("Domain name too long"));
+    }
+    for label in name.split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(invalid("Domain name labels must be 1 to 63 bytes long"));
+        }
+        query.push(label.len() as u8);
+        query.extend_from_slice(label.as_bytes());
+    }
+    query.push(0);
+
+    query.extend_from_slice(&TYPE_SRV.to_be_bytes());
+    query.extend_from_slice(&CLASS_IN.to_be_bytes());
+    Ok(query)
+}
+
+fn read_u16(message: &[u8], pos: usize) -> io::Result<u16> {
+    match message.get(pos..pos + 2) {
+        Some(bytes) => Ok(u16::from_be_bytes([bytes[0], bytes[1]])),
+        None => Err(invalid("Truncated DNS response")),
+    }
+}
+
+/// Reads a (possibly compressed) domain name and returns it with the position right after it.
+fn read_name(message: &[u8], mut pos: usize) -> io::Result<(String, usize)> {
+    let mut labels: Vec<String> = Vec::new();
+    let mut end: Option<usize> = None;
+    let mut jumps: u8 = 0;
+
+    loop {
+        let Some(&len) = message.get(pos) else {
+            return Err(invalid("Truncated DNS name"));
+        };
+
+        if len & 0xC0 == 0xC0 {
+            // Compression pointer
+            jumps += 1;
+            if jumps > 16 {
+                return Err(invalid("Too many DNS name pointers"));
+            }
+            end.get_or_insert(pos + 2);
+            pos = (read_u16(message, pos)? & 0x3FFF) as usize;
+            continue;
+        }
+
+        if len == 0 {
+            return Ok((labels.join("."), end.unwrap_or(pos + 1)));
+        }
+
+        let Some(label) = message.get(pos + 1..pos + 1 + len as usize) else {
+            return Err(invalid("Truncated DNS label"));
+        };
+        labels.push(String::from_utf8_lossy(label).into_owned());
+        pos += 1 + len as usize;
+    }
+}
+
+/// The answer to the query `id` for `name`, checked to be one: the response flag set,
+/// and the same ID and question.
+fn parse_srv_response(id: u16, name: &str, message: &[u8]) -> io::Result<Option<(String, u16)>> {
+    if read_u16(message, 0)? != id {
+        return Err(invalid("DNS response ID mismatch"));
+    }
+    let flags: u16 = read_u16(message, 2)?;
+    if flags & FLAG_RESPONSE == 0 {
+        return Err(invalid("Not a DNS response"));
+    }
+    if read_u16(message, 4)? != 1 {
+        return Err(invalid("The DNS response doesn't repeat the question"));
+    }
+    let (question, after): (String, usize) = read_name(message, 12)?;
+    if !question.eq_ignore_ascii_case(name.trim_end_matches('.'))
+        || read_u16(message, after)? != TYPE_SRV
+        || read_u16(message, after + 2)? != CLASS_IN
+    {
+        return Err(invalid("The DNS response is for another question"));
+    }
+    if flags & 0x000F != 0 {
+        // NXDOMAIN and friends: there's simply no record
+        return Ok(None);
+    }
+
+    let answers: u16 = read_u16(message, 6)?;
+    let mut pos: usize = after + 4;
+
+    // Lowest priority wins, then the highest weight
+    let mut best: Option<(u16, u16, String, u16)> = None;
+
+    for _ in 0..answers {
+        pos = read_name(message, pos)?.1;
+        let record_type: u16 = read_u16(message, pos)?;
+        let data_len: usize = read_u16(message, pos + 8)? as usize;
+        let data_pos: usize = pos + 10;
+        pos = data_pos + data_len;
+
+        if record_type != TYPE_SRV {
+            continue;
+        }
+
+        let priority: u16 = read_u16(message, data_pos)?;
+        let weight: u16 = read_u16(message, data_pos + 2)?;
+        let port: u16 = read_u16(message, data_pos + 4)?;
+        let (target, _) = read_name(message, data_pos + 6)?;
+
+        let is_better: bool = match &best {
+            Some((best_priority, best_weight, _, _)) => {
+                priority < *best_priority || (priority == *best_priority && weight > *best_weight)
+            }
+            None => true,
+        };
+        if is_better && !target.is_empty() {
+            best = Some((priority, weight, target, port));
+        }
+    }
+
+    Ok(best.map(|(_, _, target, port)| (target, port)))
+}
+
+/// Looks up the `_minecraft._tcp.<host>` SRV record and returns the target host and port.
+pub fn resolve_srv(host: &str) -> io::Result<Option<(String, u16)>> {
+    let Some(server) = nameserver() else {
+        return Ok(None);
+    };
+    let server: SocketAddr = SocketAddr::new(server, 53);
+    let name: String = format!("_minecraft._tcp.{}", host);
+
+    let id: u16 = random_id();
+    let query: Vec<u8> = build_query(id, &name)?;
+    let response: Vec<u8> = query_udp(server, &query)?;
+    if read_u16(&response, 2)? & FLAG_TRUNCATED == 0 {
+        return parse_srv_response(id, &name, &response);
+    }
+
+    // Too many records for 512 bytes
+    let id: u16 = random_id();
+    let response: Vec<u8> = query_tcp(server, &build_query(id, &name)?)?;
+    parse_srv_response(id, &name, &response)
+}
+
+fn query_udp(server: SocketAddr, query: &[u8]) -> io::Result<Vec<u8>> {
+    let bind_address: &str = if server.is_ipv4() {
+        "0.0.0.0:0"
+    } else {
+        "[::]:0"
+    };
+    let socket: UdpSocket = UdpSocket::bind(bind_address)?;
+    // Datagrams from anywhere else are dropped by the kernel
+    socket.connect(server)?;
+    socket.set_read_timeout(Some(DNS_TIMEOUT))?;
+    socket.send(query)?;
+
+    let mut buffer: [u8; 512] = [0u8; 512];
+    let len: usize = socket.recv(&mut buffer)?;
+    Ok(buffer[..len].to_vec())
+}
+
+// Over TCP, messages are prefixed with their length
+fn query_tcp(server: SocketAddr, query: &[u8]) -> io::Result<Vec<u8>> {
+    let mut stream: TcpStream = TcpStream::connect_timeout(&server, DNS_TIMEOUT)?;
+    stream.set_read_timeout(Some(DNS_TIMEOUT))?;
+    stream.set_write_timeout(Some(DNS_TIMEOUT))?;
+
+    let mut message: Vec<u8> = (query.len() as u16).to_be_bytes().to_vec();
+    message.extend_from_slice(query);
+    stream.write_all(&message)?;
+
+    let mut len: [u8; 2] = [0u8; 2];
+    stream.read_exact(&mut len)?;
+    let mut response: Vec<u8> = vec![0u8; u16::from_be_bytes(len) as usize];
+    stream.read_exact(&mut response)?;
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAME: &str = "_minecraft._tcp.example.com";
+
+    // A response to `build_query(id, NAME)` with the given flags and SRV answers
+    fn response(id: u16, flags: u16, answers: &[(u16, u16, u16, &str)]) -> Vec<u8> {
+        let mut message: Vec<u8> = build_query(id, NAME).unwrap();
+        message[2..4].copy_from_slice(&flags.to_be_bytes());
+        message[6..8].copy_from_slice(&(answers.len() as u16).to_be_bytes());
+
+        for (priority, weight, port, target) in answers {
+            message.extend_from_slice(&[0xC0, 12]); // pointer to the question's name
+            message.extend_from_slice(&TYPE_SRV.to_be_bytes());
+            message.extend_from_slice(&CLASS_IN.to_be_bytes());
+            message.extend_from_slice(&300u32.to_be_bytes());
+
+            let mut data: Vec<u8> = Vec::new();
+            data.extend_from_slice(&priority.to_be_bytes());
+            data.extend_from_slice(&weight.to_be_bytes());
+            data.extend_from_slice(&port.to_be_bytes());
+            for label in target.split('.') {
+                data.push(label.len() as u8);
+                data.extend_from_slice(label.as_bytes());
+            }
+            data.push(0);
+
+            message.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            message.extend_from_slice(&data);
+        }
+        message
+    }
+
+    #[test]
+    fn queries_are_encoded() {
+        let query: Vec<u8> = build_query(0x1234, "a.bc.").unwrap();
+        assert_eq!(
+            query,
+            [
+                0x12, 0x34, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0, // header
+                1, b'a', 2, b'b', b'c', 0, // name
+                0, 33, 0, 1, // SRV, IN
+            ]
+        );
+    }
+
+    #[test]
+    fn bad_labels_are_refused() {
+        assert!(build_query(1, &format!("{}.com", "a".repeat(63))).is_ok());
+        assert!(build_query(1, &format!("{}.com", "a".repeat(64))).is_err());
+        assert!(build_query(1, "a..com").is_err());
+        assert!(build_query(1, "").is_err());
+        assert!(build_query(1, &vec!["a".repeat(60); 5].join(".")).is_err());
+    }
+
+    #[test]
+    fn best_record_is_picked() {
+        let message: Vec<u8> = response(
+            7,
+            0x8180,
+            &[
+                (20, 100, 1, "backup.example.com"),
+                (10, 5, 2, "light.example.com"),
+                (10, 50, 25570, "mc.example.com"),
+            ],
+        );
+        assert_eq!(
+            parse_srv_response(7, NAME, &message).unwrap(),
+            Some((String::from("mc.example.com"), 25570))
+        );
+        // Names are compared case-insensitively
+        assert!(parse_srv_response(7, &NAME.to_uppercase(), &message).is_ok());
+    }
+
+    #[test]
+    fn missing_records() {
+        assert_eq!(
+            parse_srv_response(7, NAME, &response(7, 0x8180, &[])).unwrap(),
+            None
+        );
+        assert_eq!(
+            parse_srv_response(7, NAME, &response(7, 0x8183, &[])).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn foreign_messages_are_refused() {
+        let answer: [(u16, u16, u16, &str); 1] = [(0, 0, 25565, "mc.example.com")];
+
+        // Other ID
+        assert!(parse_srv_response(8, NAME, &response(7, 0x8180, &answer)).is_err());
+        // A query, not a response
+        assert!(parse_srv_response(7, NAME, &response(7, 0x0100, &answer)).is_err());
+        // Another question
+        assert!(
+            parse_srv_response(
+                7,
+                "_minecraft._tcp.example.org",
+                &response(7, 0x8180, &answer)
+            )
+            .is_err()
+        );
+        // Truncated
+        let message: Vec<u8> = response(7, 0x8180, &answer);
+        assert!(parse_srv_response(7, NAME, &message[..message.len() - 3]).is_err());
+    }
+}
@@ -0,0 +1,105 @@
+use std::fmt;
+use std::io;
+use std::string::FromUtf8Error;
+
+use azalea_chat::FormattedText;
+
+#[derive(Debug)]
+pub enum ClientError {
+    Io(io::Error),
+    Protocol(String),
+    Compression(io::Error),
+    Json(serde_json::Error),
+    Kicked(FormattedText),
+    AuthFailed(String),
+    Timeout(String),
+    /// Nothing was listening on the server's port.
+    Refused(String),
+    /// Wraps an error that happened while processing a specific packet.
+    Packet {
+        packet: String,
+        source: Box<ClientError>,
+    },
+}
+
+impl ClientError {
+    pub fn in_packet(self, packet: impl Into<String>) -> ClientError {
+        ClientError::Packet {
+            packet: packet.into(),
+            source: Box::new(self),
+        }
+    }
+
+    /// Returns the underlying error, skipping over any packet context.
+    pub fn kind(&self) -> &ClientError {
+        match self {
+            ClientError::Packet { source, .. } => source.kind(),
+            _ => self,
+        }
+    }
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Io(e) => write!(f, "I/O error: {}", e),
+            ClientError::Protocol(message) => write!(f, "Protocol error: {}", message),
+            ClientError::Compression(e) => write!(f, "Compression error: {}", e),
+            ClientError::Json(e) => write!(f, "Invalid JSON: {}", e),
+            ClientError::Kicked(reason) => write!(f, "Kicked from the server: {}", reason),
+            ClientError::AuthFailed(message) => write!(f, "Authentication failed: {}", message),
+            ClientError::Timeout(message) => write!(f, "Timed out: {}", message),
+            ClientError::Refused(message) => write!(f, "Connection refused: {}", message),
+            ClientError::Packet { packet, source } => {
+                write!(f, "{} (while processing {})", source, packet)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ClientError::Io(e) | ClientError::Compression(e) => Some(e),
+            ClientError::Json(e) => Some(e),
+            ClientError::Packet { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ClientError {
+    fn from(e: io::Error) -> Self {
+        ClientError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for ClientError {
+    fn from(e: serde_json::Error) -> Self {
+        ClientError::Json(e)
+    }
+}
+
+impl From<FromUtf8Error> for ClientError {
+    fn from(e: FromUtf8Error) -> Self {
+        ClientError::Protocol(format!("Invalid UTF-8 string: {}", e))
+    }
+}
+
+impl From<base64::DecodeError> for ClientError {
+    fn from(e: base64::DecodeError) -> Self {
+        ClientError::Protocol(format!("Invalid base64: {}", e))
+    }
+}
+
+impl From<&str> for ClientError {
+    fn from(message: &str) -> Self {
+        ClientError::Protocol(String::from(message))
+    }
+}
+
+impl From<String> for ClientError {
+    fn from(message: String) -> Self {
+        ClientError::Protocol(message)
+    }
+}
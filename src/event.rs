@@ -0,0 +1,67 @@
+use std::time::Duration;
+
+use azalea_chat::FormattedText;
+
+use crate::interaction::Interaction;
+use crate::packet::ChatKind;
+
+/// Something that happened on the connection, passed to the callbacks registered
+/// with `Client::on_event`.
+#[derive(Clone, Debug)]
+pub enum Event {
+    /// Logged in again after the connection was lost. The first login happens inside
+    /// `Client::connect`, before any callback can be registered.
+    Connected,
+    /// A chat message. `sender` is the UUID of the player who wrote it (1.16+ only);
+    /// system messages don't have one. `interactions` are the parts with hover text or
+    /// click actions, which `FormattedText` doesn't keep.
+    ChatReceived {
+        message: FormattedText,
+        sender: Option<u128>,
+        kind: ChatKind,
+        interactions: Vec<Interaction>,
+    },
+    /// Text shown above the hotbar. Servers often resend the same text every second.
+    ActionBar(FormattedText),
+    /// A title was shown, with the subtitle the server set before it. `duration` covers
+    /// fading in, staying and fading out.
+    Title {
+        title: FormattedText,
+        subtitle: Option<FormattedText>,
+        duration: Duration,
+    },
+    /// A player was added to the player list. The server sends everyone who is already
+    /// online right after login, so this fires for each of them too.
+    PlayerJoined {
+        uuid: u128,
+        name: String,
+        /// Shown in the player list instead of the name, if the server set one.
+        display_name: Option<FormattedText>,
+    },
+    PlayerLeft {
+        uuid: u128,
+        name: String,
+        display_name: Option<FormattedText>,
+    },
+    /// The server sent a keep alive; the client has already answered it.
+    KeepAlive {
+        id: i64,
+    },
+    /// The player died, with the message from the death screen. If the client respawns
+    /// automatically, it already has.
+    Died(FormattedText),
+    Kicked(FormattedText),
+    /// A packet that couldn't be parsed. It's skipped and the connection stays open.
+    /// `payload` is the packet's data after its ID.
+    MalformedPacket {
+        id: i32,
+        error: String,
+        payload: Vec<u8>,
+    },
+    /// A play packet the client doesn't handle. Only published with
+    /// `ClientConfig::report_ignored_packets`.
+    IgnoredPacket {
+        id: i32,
+        payload: Vec<u8>,
+    },
+}
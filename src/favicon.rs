@@ -0,0 +1,206 @@
+use std::error::Error;
+use std::io::Read;
+
+use flate2::read::ZlibDecoder;
+
+// Server icons are 64x64 PNGs. This decodes the non-interlaced 8-bit kinds that image
+// editors write, which is enough to preview them in the terminal with half blocks:
+// each character shows two pixels, the upper one as the foreground color of `▀` and
+// the lower one as the background.
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+// Pixels more transparent than this are left blank
+const MIN_ALPHA: u8 = 128;
+
+struct Image {
+    width: usize,
+    height: usize,
+    // RGBA, row by row
+    pixels: Vec<[u8; 4]>,
+}
+
+impl Image {
+    fn pixel(&self, x: usize, y: usize) -> Option<[u8; 4]> {
+        if y >= self.height {
+            return None;
+        }
+        Some(self.pixels[y * self.width + x]).filter(|pixel| pixel[3] >= MIN_ALPHA)
+    }
+}
+
+/// Renders a PNG with 24-bit ANSI colors, one character per pixel column and two pixel
+/// rows per line.
+pub fn preview(png: &[u8]) -> Result<String, Box<dyn Error>> {
+    let image: Image = decode_png(png)?;
+
+    let mut lines: Vec<String> = Vec::new();
+    for y in (0..image.height).step_by(2) {
+        let mut line: String = String::new();
+        for x in 0..image.width {
+            line += &match (image.pixel(x, y), image.pixel(x, y + 1)) {
+                (Some(top), Some(bottom)) => format!(
+                    "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m▀",
+                    top[0], top[1], top[2], bottom[0], bottom[1], bottom[2]
+                ),
+                (Some(top), None) => {
+                    format!("\x1b[38;2;{};{};{}m\x1b[49m▀", top[0], top[1], top[2])
+                }
+                (None, Some(bottom)) => {
+                    format!(
+                        "\x1b[38;2;{};{};{}m\x1b[49m▄",
+                        bottom[0], bottom[1], bottom[2]
+                    )
+                }
+                (None, None) => String::from("\x1b[0m "),
+            };
+        }
+        lines.push(line + "\x1b[0m");
+    }
+    Ok(lines.join("\n"))
+}
+
+fn decode_png(png: &[u8]) -> Result<Image, Box<dyn Error>> {
+    if !png.starts_with(&PNG_SIGNATURE) {
+        return Err("The icon isn't a PNG".into());
+    }
+
+    let mut header: Option<(usize, usize, u8, u8)> = None;
+    let mut palette: Vec<[u8; 4]> = Vec::new();
+    let mut compressed: Vec<u8> = Vec::new();
+
+    let mut rest: &[u8] = &png[PNG_SIGNATURE.len()..];
+    while rest.len() >= 12 {
+        let length: usize = u32::from_be_bytes(rest[..4].try_into()?) as usize;
+        let kind: &[u8] = &rest[4..8];
+        let Some(data) = rest.get(8..8 + length) else {
+            return Err("The icon is cut off".into());
+        };
+
+        match kind {
+            b"IHDR" if data.len() >= 13 => {
+                if data[12] != 0 {
+                    return Err("Interlaced icons aren't supported".into());
+                }
+                header = Some((
+                    u32::from_be_bytes(data[..4].try_into()?) as usize,
+                    u32::from_be_bytes(data[4..8].try_into()?) as usize,
+                    data[8],
+                    data[9],
+                ));
+            }
+            b"PLTE" => {
+                palette = data
+                    .chunks_exact(3)
+                    .map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+                    .collect();
+            }
+            // Alpha values for the first palette entries
+            b"tRNS" => {
+                for (entry, alpha) in palette.iter_mut().zip(data) {
+                    entry[3] = *alpha;
+                }
+            }
+            b"IDAT" => compressed.extend_from_slice(data),
+            b"IEND" => break,
+            _ => {}
+        }
+        // The chunk's CRC isn't checked
+        rest = &rest[(8 + length + 4).min(rest.len())..];
+    }
+
+    let Some((width, height, bit_depth, color_type)) = header else {
+        return Err("The icon has no header".into());
+    };
+    let channels: usize = match (bit_depth, color_type) {
+        (8, 0) | (8, 3) => 1,
+        (8, 4) => 2,
+        (8, 2) => 3,
+        (8, 6) => 4,
+        _ => {
+            return Err(format!(
+                "Icons with color type {} at {} bits aren't supported",
+                color_type, bit_depth
+            )
+            .into());
+        }
+    };
+
+    let mut data: Vec<u8> = Vec::new();
+    ZlibDecoder::new(compressed.as_slice()).read_to_end(&mut data)?;
+    let stride: usize = width * channels;
+    if data.len() < (stride + 1) * height {
+        return Err("The icon's image data is too short".into());
+    }
+
+    let mut pixels: Vec<[u8; 4]> = Vec::with_capacity(width * height);
+    let mut previous: Vec<u8> = vec![0; stride];
+    for row in data.chunks_exact(stride + 1).take(height) {
+        let current: Vec<u8> = unfilter(row[0], &row[1..], &previous, channels)?;
+        for value in current.chunks_exact(channels) {
+            pixels.push(match color_type {
+                0 => [value[0], value[0], value[0], 255],
+                3 => *palette.get(value[0] as usize).unwrap_or(&[0, 0, 0, 0]),
+                4 => [value[0], value[0], value[0], value[1]],
+                2 => [value[0], value[1], value[2], 255],
+                _ => [value[0], value[1], value[2], value[3]],
+            });
+        }
+        previous = current;
+    }
+
+    Ok(Image {
+        width,
+        height,
+        pixels,
+    })
+}
+
+// Undoes the filter PNG applies to each row, based on the bytes to the left (one pixel
+// back) and above
+fn unfilter(
+    filter: u8,
+    row: &[u8],
+    previous: &[u8],
+    bytes_per_pixel: usize,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut res: Vec<u8> = Vec::with_capacity(row.len());
+    for (i, byte) in row.iter().enumerate() {
+        let left: u8 = if i >= bytes_per_pixel {
+            res[i - bytes_per_pixel]
+        } else {
+            0
+        };
+        let up: u8 = previous[i];
+        let up_left: u8 = if i >= bytes_per_pixel {
+            previous[i - bytes_per_pixel]
+        } else {
+            0
+        };
+
+        let predicted: u8 = match filter {
+            0 => 0,
+            1 => left,
+            2 => up,
+            3 => ((left as u16 + up as u16) / 2) as u8,
+            4 => paeth(left, up, up_left),
+            _ => return Err(format!("Unknown PNG filter {}", filter).into()),
+        };
+        res.push(byte.wrapping_add(predicted));
+    }
+    Ok(res)
+}
+
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let estimate: i16 = left as i16 + up as i16 - up_left as i16;
+    let distance_left: i16 = (estimate - left as i16).abs();
+    let distance_up: i16 = (estimate - up as i16).abs();
+    let distance_up_left: i16 = (estimate - up_left as i16).abs();
+    if distance_left <= distance_up && distance_left <= distance_up_left {
+        left
+    } else if distance_up <= distance_up_left {
+        up
+    } else {
+        up_left
+    }
+}
@@ -0,0 +1,124 @@
+use std::error::Error;
+use std::sync::Mutex;
+
+use crate::config;
+use crate::regex::Regex;
+
+// Hides chat from ignored players and lines matching one of the filter patterns.
+// Both lists can be changed while the client runs and are written back to the
+// [client] table of the config file.
+
+pub struct ChatFilter {
+    ignored: Mutex<Vec<String>>,
+    filters: Mutex<Vec<(String, Regex)>>,
+    config_path: String,
+}
+
+/// The name in a `<name> message` line, for messages without a sender UUID (1.12,
+/// system messages from chat plugins).
+pub fn tagged_author(text: &str) -> Option<&str> {
+    let rest: &str = text.strip_prefix('<')?;
+    let end: usize = rest.find('>')?;
+    Some(&rest[..end])
+}
+
+impl ChatFilter {
+    pub fn new(
+        ignored: &[String],
+        patterns: &[String],
+        config_path: &str,
+    ) -> Result<ChatFilter, Box<dyn Error>> {
+        let mut filters: Vec<(String, Regex)> = Vec::new();
+        for pattern in patterns {
+            filters.push((pattern.clone(), compile(pattern)?));
+        }
+
+        Ok(ChatFilter {
+            ignored: Mutex::new(ignored.to_vec()),
+            filters: Mutex::new(filters),
+            config_path: String::from(config_path),
+        })
+    }
+
+    /// Whether a line should be hidden. `author` is the player who wrote it, if known.
+    pub fn hides(&self, author: Option<&str>, text: &str) -> bool {
+        if let Some(author) = author
+            && self.is_ignored(author)
+        {
+            return true;
+        }
+        self.filters
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|(_, regex)| regex.is_match(text))
+    }
+
+    pub fn is_ignored(&self, name: &str) -> bool {
+        self.ignored
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|ignored| ignored.eq_ignore_ascii_case(name))
+    }
+
+    pub fn ignored(&self) -> Vec<String> {
+        self.ignored.lock().unwrap().clone()
+    }
+
+    /// Ignores `name`, or stops ignoring them if they already are. Returns whether
+    /// they're ignored now.
+    pub fn toggle_ignore(&self, name: &str) -> Result<bool, Box<dyn Error>> {
+        let ignored: Vec<String> = {
+            let mut ignored = self.ignored.lock().unwrap();
+            match ignored
+                .iter()
+                .position(|entry| entry.eq_ignore_ascii_case(name))
+            {
+                Some(index) => {
+                    ignored.remove(index);
+                }
+                None => ignored.push(String::from(name)),
+            }
+            ignored.clone()
+        };
+
+        config::save_client_list(&self.config_path, "ignore", &ignored)?;
+        Ok(self.is_ignored(name))
+    }
+
+    pub fn patterns(&self) -> Vec<String> {
+        self.filters
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(pattern, _)| pattern.clone())
+            .collect()
+    }
+
+    pub fn add_filter(&self, pattern: &str) -> Result<(), Box<dyn Error>> {
+        let regex: Regex = compile(pattern)?;
+        self.filters
+            .lock()
+            .unwrap()
+            .push((String::from(pattern), regex));
+        config::save_client_list(&self.config_path, "filters", &self.patterns())
+    }
+
+    /// Removes the filter at `index` (counting from 1, like `.filter list` shows them).
+    pub fn remove_filter(&self, index: usize) -> Result<String, Box<dyn Error>> {
+        let pattern: String = {
+            let mut filters = self.filters.lock().unwrap();
+            if index == 0 || index > filters.len() {
+                return Err(format!("There is no filter {}", index).into());
+            }
+            filters.remove(index - 1).0
+        };
+        config::save_client_list(&self.config_path, "filters", &self.patterns())?;
+        Ok(pattern)
+    }
+}
+
+fn compile(pattern: &str) -> Result<Regex, Box<dyn Error>> {
+    Regex::new(pattern).map_err(|e| format!("Invalid filter '{}': {}", pattern, e).into())
+}
@@ -1,70 +1,35 @@
-use std::collections::HashMap;
-use std::error::Error;
-use std::fs;
-use std::io::{Cursor, Read, Write, stdin};
-use std::net::{Shutdown, TcpStream};
-use std::sync::{Arc, Mutex, MutexGuard};
+use std::io::{self, Cursor, Read, Write};
+use std::net::{IpAddr, Shutdown, SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Sender};
 use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use azalea_chat::FormattedText;
-use base64::{engine::Engine, prelude::BASE64_STANDARD};
-use flate2::{bufread::ZlibDecoder, write::ZlibEncoder};
-use mc_varint::{VarInt, VarIntRead, VarIntWrite};
-use serde::Deserialize;
-use serde_json::{Value, from_str};
-
-fn read_varint(stream: &mut TcpStream) -> Result<VarInt, Box<dyn Error>> {
-    // varianta clasica, doar pentru tcpstream
-    let res: VarInt = stream.read_var_int()?;
-
-    Ok(res)
-}
-
-fn read_varint_cursor(stream: &mut Cursor<Vec<u8>>) -> Result<VarInt, Box<dyn Error>> {
-    // varianta pentru cursor
-    let res: VarInt = stream.read_var_int()?;
-
-    Ok(res)
-}
-
-// Never used :(
-
-// fn read_array_dynamic(stream: &mut TcpStream) -> Result<Vec<u8>, Box<dyn Error>> {
-//     let result_size: i32 = i32::from(read_varint(stream)?);
-
-//     let mut result_buf: Vec<u8> = vec![0u8; result_size as usize];
-//     stream.read_exact(&mut result_buf)?;
-
-//     Ok(result_buf)
-// }
-
-// fn read_array_fixed(stream: &mut TcpStream, buf_size: usize) -> Result<Vec<u8>, Box<dyn Error>> {
-//     let mut result_buf: Vec<u8> = vec![0u8; buf_size];
-//     stream.read_exact(&mut result_buf)?;
-
-//     Ok(result_buf)
-// }
-
-fn read_array_dynamic_cursor(stream: &mut Cursor<Vec<u8>>) -> Result<Vec<u8>, Box<dyn Error>> {
-    let result_size: i32 = i32::from(read_varint_cursor(stream)?);
-
-    let mut result_buf: Vec<u8> = vec![0u8; result_size as usize];
-    stream.read_exact(&mut result_buf)?;
-
-    Ok(result_buf)
-}
-
-fn read_array_fixed_cursor(
-    stream: &mut Cursor<Vec<u8>>,
-    buf_size: usize,
-) -> Result<Vec<u8>, Box<dyn Error>> {
-    let mut result_buf: Vec<u8> = vec![0u8; buf_size];
-    stream.read_exact(&mut result_buf)?;
-
-    Ok(result_buf)
-}
-
-fn send_packet_raw(stream: &mut TcpStream, data: Vec<u8>) -> Result<(), Box<dyn Error>> {
+use azalea_chat::text_component;
+use flate2::write::ZlibEncoder;
+use flate2::{Decompress, FlushDecompress};
+use mc_varint::{VarInt, VarIntWrite};
+use serde_json::{Value, from_str, json};
+use tracing::{debug, info, trace};
+
+use crate::capture::{Capture, CaptureState};
+use crate::client::{ConnectPolicy, IpPreference, LoginPlugins, VirtualHost};
+use crate::configuration::has_configuration;
+use crate::dns;
+use crate::error::ClientError;
+use crate::mcstring::McString;
+use crate::packet::LoginPacket;
+use crate::player::Identity;
+use crate::proxy::Socks5Proxy;
+use crate::status::ServerStatus;
+use crate::types::McRead;
+
+// The vanilla limits: frames fit in a 3 byte VarInt, decompressed packets in 8 MiB.
+// Anything bigger is a broken or hostile server, and would be allocated up front.
+pub(crate) const MAX_PACKET_LENGTH: i32 = (1 << 21) - 1;
+const MAX_DECOMPRESSED_LENGTH: i32 = 1 << 23;
+
+fn send_packet_raw(stream: &mut TcpStream, data: Vec<u8>) -> Result<(), ClientError> {
     stream.write_var_int(VarInt::from(data.len() as i32))?;
     stream.write_all(&data)?;
     Ok(())
@@ -74,7 +39,7 @@ fn send_packet_compressed(
     stream: &mut TcpStream,
     data: Vec<u8>,
     initial_len: i32,
-) -> Result<(), Box<dyn Error>> {
+) -> Result<(), ClientError> {
     let mut final_packet: Vec<u8> = Vec::new();
     final_packet.write_var_int(VarInt::from(initial_len))?;
     final_packet.write_all(&data)?;
@@ -84,12 +49,17 @@ fn send_packet_compressed(
     Ok(())
 }
 
-fn send_packet(
+pub(crate) fn send_packet(
     stream: &mut TcpStream,
     packet_id: i32,
     data: Vec<u8>,
     threshold: i32,
-) -> Result<(), Box<dyn Error>> {
+) -> Result<(), ClientError> {
+    trace!(
+        id = format_args!("0x{:02X}", packet_id),
+        length = data.len(),
+        "Sending packet"
+    );
     let mut raw_packet: Vec<u8> = Vec::new();
     raw_packet.write_var_int(VarInt::from(packet_id))?;
     raw_packet.write_all(&data)?;
@@ -118,127 +88,521 @@ fn send_packet(
     }
 }
 
-fn read_packet(
-    cursor: &mut Cursor<Vec<u8>>,
-    packet_length: i32,
-) -> Result<(i32, Vec<u8>), Box<dyn Error>> {
-    let packet_id: i32 = i32::from(read_varint_cursor(cursor)?);
-    let curr_size: usize = (packet_length as u64 - cursor.position()) as usize;
-    let data: Vec<u8> = read_array_fixed_cursor(cursor, curr_size)?;
+/// Receives packets into buffers that are kept from one packet to the next, so a busy
+/// connection doesn't allocate (and zero) a frame and a decompression buffer for each.
+/// The data of a packet borrows the reader until the next one is received.
+pub(crate) struct PacketReader {
+    frame: Vec<u8>,
+    decompressed: Vec<u8>,
+    decompressor: Decompress,
+    // Where every frame is also written, with the state the connection is in
+    capture: Option<(Capture, CaptureState)>,
+}
+
+impl PacketReader {
+    pub(crate) fn new() -> PacketReader {
+        PacketReader {
+            frame: Vec::new(),
+            decompressed: Vec::new(),
+            decompressor: Decompress::new(true),
+            capture: None,
+        }
+    }
+
+    pub(crate) fn capturing(capture: Option<&Capture>, state: CaptureState) -> PacketReader {
+        PacketReader {
+            capture: capture.map(|capture| (capture.clone(), state)),
+            ..PacketReader::new()
+        }
+    }
 
-    Ok((packet_id, data))
+    /// Reads the next packet, returning its ID and the data after it.
+    pub(crate) fn receive(
+        &mut self,
+        stream: &mut impl Read,
+        threshold: i32,
+    ) -> Result<(i32, &[u8]), ClientError> {
+        let packet_length: i32 = stream.read_varint()?;
+        if !(1..=MAX_PACKET_LENGTH).contains(&packet_length) {
+            return Err(format!("Invalid packet length {}", packet_length).into());
+        }
+        // Reads into the spare capacity instead of zeroing the frame first
+        self.frame.clear();
+        stream
+            .take(packet_length as u64)
+            .read_to_end(&mut self.frame)?;
+        if self.frame.len() != packet_length as usize {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+
+        let packet: &[u8] = unpack(
+            &self.frame,
+            threshold,
+            &mut self.decompressed,
+            &mut self.decompressor,
+        )?;
+        if let Some((capture, state)) = &self.capture {
+            capture.record(*state, threshold >= 0, &self.frame, packet);
+        }
+        let (id, data): (i32, &[u8]) = split_packet(packet)?;
+        trace!(
+            id = format_args!("0x{:02X}", id),
+            length = data.len(),
+            frame_length = packet_length,
+            "Received packet"
+        );
+        Ok((id, data))
+    }
 }
 
-fn receive_packet(
-    stream: &mut TcpStream,
+// The packet (ID and data) inside a frame, decompressed into `decompressed` if needed
+pub(crate) fn unpack<'a>(
+    frame: &'a [u8],
     threshold: i32,
-) -> Result<(i32, Vec<u8>), Box<dyn Error>> {
-    let packet_length: i32 = i32::from(read_varint(stream)?);
-    let mut buffer: Vec<u8> = vec![0u8; packet_length as usize];
-    stream.read_exact(&mut buffer)?;
-    let mut cursor: Cursor<Vec<u8>> = Cursor::new(buffer);
-
+    decompressed: &'a mut Vec<u8>,
+    decompressor: &mut Decompress,
+) -> Result<&'a [u8], ClientError> {
     if threshold < 0 {
-        return read_packet(&mut cursor, packet_length);
+        return Ok(frame);
     }
 
-    let data_length: i32 = i32::from(read_varint_cursor(&mut cursor)?);
+    let mut cursor: Cursor<&[u8]> = Cursor::new(frame);
+    let data_length: i32 = cursor.read_varint()?;
+    if !(0..=MAX_DECOMPRESSED_LENGTH).contains(&data_length) {
+        return Err(format!("Invalid decompressed packet length {}", data_length).into());
+    }
+    let compressed: &[u8] = &frame[cursor.position() as usize..];
 
     if data_length == 0 {
-        read_packet(&mut cursor, packet_length)
-    } else {
-        let mut decoder: ZlibDecoder<Cursor<Vec<u8>>> = ZlibDecoder::new(cursor);
-        let mut decompressed_data: Vec<u8> = Vec::new();
-        decoder.read_to_end(&mut decompressed_data)?;
+        return Ok(compressed);
+    }
+
+    // Room for one byte more than announced is enough to tell that the length was a
+    // lie, without inflating the rest of a zip bomb
+    decompressed.clear();
+    decompressed.reserve(data_length as usize + 1);
+    decompressor.reset(true);
+    decompressor
+        .decompress_vec(compressed, decompressed, FlushDecompress::Finish)
+        .map_err(|e| ClientError::Compression(io::Error::new(io::ErrorKind::InvalidData, e)))?;
+    if decompressed.len() != data_length as usize {
+        return Err(format!(
+            "Packet decompressed to {} bytes instead of {}",
+            decompressed.len(),
+            data_length
+        )
+        .into());
+    }
+
+    Ok(decompressed)
+}
 
-        let mut data_cursor: Cursor<Vec<u8>> = Cursor::new(decompressed_data);
-        read_packet(&mut data_cursor, data_length)
+pub(crate) fn split_packet(packet: &[u8]) -> Result<(i32, &[u8]), ClientError> {
+    let mut cursor: Cursor<&[u8]> = Cursor::new(packet);
+    let packet_id: i32 = cursor.read_varint()?;
+
+    Ok((packet_id, &packet[cursor.position() as usize..]))
+}
+
+/// Unpacks a frame as it arrives after its length: decompresses it if compression is on
+/// (`threshold` isn't negative) and splits off the packet ID. Returns the ID and the data.
+/// Works on any bytes, with the same limits as packets read from a connection.
+pub fn decode_frame(frame: &[u8], threshold: i32) -> Result<(i32, Vec<u8>), ClientError> {
+    if frame.is_empty() || frame.len() > MAX_PACKET_LENGTH as usize {
+        return Err(format!("Invalid packet length {}", frame.len()).into());
     }
+
+    let mut decompressed: Vec<u8> = Vec::new();
+    let packet: &[u8] = unpack(
+        frame,
+        threshold,
+        &mut decompressed,
+        &mut Decompress::new(true),
+    )?;
+    let (id, data): (i32, &[u8]) = split_packet(packet)?;
+    Ok((id, data.to_vec()))
 }
 
-fn create_players_string(players: &HashMap<u128, String>) -> String {
-    let mut res: String = String::new();
-    res += "Online Players (";
-    res += &players.keys().count().to_string();
-    res += "): [";
+/// Looks for a `_minecraft._tcp` SRV record, like the vanilla client does when the
+/// server address has no explicit port. Falls back to `ip`:[`DEFAULT_PORT`] if there is
+/// none.
+fn resolve_address(ip: &str, port: Option<u16>, verbose: bool) -> (String, u16) {
+    if let Some(port) = port {
+        return (String::from(ip), port);
+    }
+    let port: u16 = DEFAULT_PORT;
+    if ip.parse::<IpAddr>().is_ok() {
+        return (String::from(ip), port);
+    }
 
-    let mut is_first: bool = true;
-    for player in players {
-        if !is_first {
-            res += ", ";
+    match dns::resolve_srv(ip) {
+        Ok(Some((target, target_port))) => {
+            match verbose {
+                true => info!("Found SRV record for {}: {}:{}", ip, target, target_port),
+                false => debug!("Found SRV record for {}: {}:{}", ip, target, target_port),
+            }
+            (target, target_port)
         }
-        res += player.1;
-        is_first = false;
+        _ => (String::from(ip), port),
     }
-    res += "]";
-    res
 }
 
-pub fn init_connection(ip: &str, port: u16) -> Result<TcpStream, Box<dyn Error>> {
-    let mut attempt: u16 = 1;
-    while attempt < 6 {
-        println!("Attempting to connect to {}:{}! ({})", ip, port, attempt);
-        match TcpStream::connect(format!("{}:{}", ip, port)) {
+/// Opens a TCP connection to the server, trying as often as `policy` allows. The caller
+/// should keep using the original `ip` in the handshake even if an SRV record points
+/// somewhere else. SRV records are only looked up if `port` is `None`.
+pub fn init_connection(
+    ip: &str,
+    port: Option<u16>,
+    proxy: Option<&Socks5Proxy>,
+    policy: &ConnectPolicy,
+) -> Result<TcpStream, ClientError> {
+    connect(ip, port, proxy, policy, true)
+}
+
+// Status requests log the attempts (and the SRV record) at debug level only
+pub(crate) fn connect(
+    ip: &str,
+    port: Option<u16>,
+    proxy: Option<&Socks5Proxy>,
+    policy: &ConnectPolicy,
+    verbose: bool,
+) -> Result<TcpStream, ClientError> {
+    // Through a proxy, the proxy resolves the hostname: looking up SRV records here
+    // would leak the destination to the local nameserver
+    let (ip, port): (String, u16) = match proxy {
+        Some(_) => (String::from(ip), port.unwrap_or(DEFAULT_PORT)),
+        None => resolve_address(ip, port, verbose),
+    };
+    let attempts: u32 = policy.attempts.max(1);
+
+    let mut last_error: Option<ClientError> = None;
+    for attempt in 1..=attempts {
+        if attempt > 1 {
+            thread::sleep(policy.retry_delay);
+        }
+        match verbose {
+            true => info!("Attempting to connect to {}:{}! ({})", ip, port, attempt),
+            false => debug!("Attempting to connect to {}:{}! ({})", ip, port, attempt),
+        }
+        let connection: Result<TcpStream, ClientError> = match proxy {
+            Some(proxy) => proxy.connect(&ip, port, policy),
+            None => {
+                connect_timeout(&ip, port, policy.timeout, policy.prefer).map_err(ClientError::from)
+            }
+        };
+        match connection {
             Ok(stream) => {
-                println!("Connected!");
+                match verbose {
+                    true => info!("Connected!"),
+                    false => debug!("Connected!"),
+                }
                 return Ok(stream);
             }
-            Err(_) => {
-                // sleep(Duration::from_secs(1)); // connect already hangs for ~3 seconds on fail
-                attempt += 1;
+            Err(e) => {
+                debug!("Attempt {} failed: {}", attempt, e);
+                last_error = Some(e);
+            }
+        }
+    }
+
+    let tried: String = match attempts {
+        1 => String::from("1 attempt"),
+        attempts => format!("{} attempts", attempts),
+    };
+    // Anything else (e.g. an unknown host) is passed on as it is
+    Err(match last_error.as_ref().map(ClientError::kind) {
+        Some(ClientError::Io(e)) if e.kind() == io::ErrorKind::ConnectionRefused => {
+            ClientError::Refused(format!(
+                "nothing is listening on {}:{} ({})",
+                ip, port, tried
+            ))
+        }
+        Some(ClientError::Io(e))
+            if matches!(
+                e.kind(),
+                io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+            ) =>
+        {
+            ClientError::Timeout(format!(
+                "no answer from {}:{} within {} seconds ({})",
+                ip,
+                port,
+                policy.timeout.as_secs_f32(),
+                tried
+            ))
+        }
+        _ => last_error.unwrap_or_else(|| "Couldn't connect to the server".into()),
+    })
+}
+
+// Happy Eyeballs (RFC 8305): when an attempt hasn't connected after this long, the next
+// address is tried alongside it
+const NEXT_ADDRESS_DELAY: Duration = Duration::from_millis(250);
+
+/// Connects to whichever address of `host` answers first. Attempts start one after
+/// another, alternating between IPv6 and IPv4, and each waits up to `timeout`.
+pub(crate) fn connect_timeout(
+    host: &str,
+    port: u16,
+    timeout: Duration,
+    prefer: IpPreference,
+) -> io::Result<TcpStream> {
+    let addresses: Vec<SocketAddr> = order_addresses((host, port).to_socket_addrs()?, prefer);
+    let mut last_error: io::Error = io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("{} has no addresses", host),
+    );
+
+    let (sender, results) = mpsc::channel::<io::Result<TcpStream>>();
+    let mut remaining = addresses.into_iter().peekable();
+    let mut pending: usize = 0;
+    loop {
+        match remaining.next() {
+            Some(address) => {
+                let sender: Sender<io::Result<TcpStream>> = sender.clone();
+                // A connection that loses the race is closed when the send fails
+                thread::spawn(move || {
+                    let _ = sender.send(TcpStream::connect_timeout(&address, timeout));
+                });
+                pending += 1;
+            }
+            None if pending == 0 => return Err(last_error),
+            None => {}
+        }
+
+        let result: Option<io::Result<TcpStream>> = match remaining.peek() {
+            Some(_) => results.recv_timeout(NEXT_ADDRESS_DELAY).ok(),
+            // Every attempt ends within `timeout`, so this doesn't wait forever
+            None => results.recv().ok(),
+        };
+        match result {
+            Some(Ok(stream)) => return Ok(stream),
+            // A failed attempt starts the next one right away
+            Some(Err(e)) => {
+                pending -= 1;
+                last_error = e;
             }
+            None => {}
         }
     }
-    Err("Couldn't connect to the server in 5 attempts!".into())
 }
 
-pub fn request_status(ip: &str, port: u16) -> Result<(), Box<dyn Error>> {
-    println!("Requesting status from server {}:{}!", ip, port);
-    let mut temp_connection: TcpStream = init_connection(ip, port)?;
+// Puts the preferred family first and lets the families take turns after that
+fn order_addresses(
+    addresses: impl Iterator<Item = SocketAddr>,
+    prefer: IpPreference,
+) -> Vec<SocketAddr> {
+    let addresses: Vec<SocketAddr> = addresses.collect();
+    let ipv6_first: bool = match prefer {
+        IpPreference::Any => addresses.first().is_some_and(SocketAddr::is_ipv6),
+        IpPreference::Ipv4 => false,
+        IpPreference::Ipv6 => true,
+    };
+    let (first, second): (Vec<SocketAddr>, Vec<SocketAddr>) = addresses
+        .into_iter()
+        .partition(|address| address.is_ipv6() == ipv6_first);
+
+    let mut res: Vec<SocketAddr> = Vec::with_capacity(first.len() + second.len());
+    let mut second = second.into_iter();
+    for address in first {
+        res.push(address);
+        res.extend(second.next());
+    }
+    res.extend(second);
+    res
+}
+
+pub static DEFAULT_PROTOCOL: i32 = 754; // 1.16.5
+
+/// The port used when none is given and there is no SRV record.
+pub static DEFAULT_PORT: u16 = 25565;
+
+const STATUS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Asks the server for its status without printing anything. Falls back to the legacy
+/// (pre-1.7) ping if the server doesn't answer the normal status request.
+pub fn fetch_status(
+    ip: &str,
+    port: Option<u16>,
+    proxy: Option<&Socks5Proxy>,
+    policy: &ConnectPolicy,
+    virtual_host: &VirtualHost,
+) -> Result<ServerStatus, ClientError> {
+    match request_modern_status(ip, port, proxy, policy, virtual_host) {
+        Ok(status) => Ok(status),
+        Err(e) => request_legacy_status(ip, port, proxy, policy, virtual_host).map_err(|_| e),
+    }
+}
+
+/// Prints the server status and returns it.
+pub fn request_status(
+    ip: &str,
+    port: Option<u16>,
+    proxy: Option<&Socks5Proxy>,
+    policy: &ConnectPolicy,
+    virtual_host: &VirtualHost,
+) -> Result<ServerStatus, ClientError> {
+    info!(
+        "Requesting status from server {}:{}!",
+        ip,
+        port.unwrap_or(DEFAULT_PORT)
+    );
+
+    let status: ServerStatus = fetch_status(ip, port, proxy, policy, virtual_host)?;
+
+    println!("{}", status);
+
+    Ok(status)
+}
 
-    send_handshake_packet(&mut temp_connection, ip, port, 1)?;
+fn request_modern_status(
+    ip: &str,
+    port: Option<u16>,
+    proxy: Option<&Socks5Proxy>,
+    policy: &ConnectPolicy,
+    virtual_host: &VirtualHost,
+) -> Result<ServerStatus, ClientError> {
+    let mut temp_connection: TcpStream = connect(ip, port, proxy, policy, false)?;
+    temp_connection.set_read_timeout(Some(STATUS_TIMEOUT))?;
+
+    let (host, port): (&str, u16) = virtual_host.address(ip, port.unwrap_or(DEFAULT_PORT));
+    send_handshake_packet(&mut temp_connection, host, port, DEFAULT_PROTOCOL, 1)?;
 
     send_status_request(&mut temp_connection)?;
 
-    let response_json: Value = from_str(receive_status_response(&mut temp_connection)?.as_str())?;
+    let mut status: ServerStatus =
+        from_str(receive_status_response(&mut temp_connection)?.as_str())?;
 
-    let Some(status) = response_json["description"]["text"].as_str() else {
-        return Err("Error while converting status string".into());
+    // Some servers and proxies close the connection instead of answering the ping; the
+    // status is still good
+    status.latency = match ping(&mut temp_connection) {
+        Ok(latency) => Some(latency),
+        Err(e) => {
+            debug!("No answer to the status ping: {}", e);
+            None
+        }
     };
 
-    println!("Server status: {}", status);
+    let _ = temp_connection.shutdown(Shutdown::Both);
 
-    let Some(favicon_string) = response_json["favicon"].as_str() else {
-        println!("The server does not have a server-icon!");
-        return Ok(());
-    };
+    Ok(status)
+}
 
-    let Some(comma_pos) = favicon_string.find(',') else {
-        return Err("The string does not contain a comma!".into());
+// Server List Ping as understood by 1.6 and older servers (and some proxies): 0xFE 0x01
+// plus the MC|PingHost plugin message. The answer is a kick packet (0xFF) holding either
+// `§1\0protocol\0version\0motd\0online\0max` (1.4+) or `motd§online§max` (older).
+fn request_legacy_status(
+    ip: &str,
+    port: Option<u16>,
+    proxy: Option<&Socks5Proxy>,
+    policy: &ConnectPolicy,
+    virtual_host: &VirtualHost,
+) -> Result<ServerStatus, ClientError> {
+    let mut stream: TcpStream = connect(ip, port, proxy, policy, false)?;
+    stream.set_read_timeout(Some(STATUS_TIMEOUT))?;
+
+    let utf16 = |text: &str| -> Vec<u8> {
+        let mut res: Vec<u8> = (text.encode_utf16().count() as u16).to_be_bytes().to_vec();
+        for unit in text.encode_utf16() {
+            res.extend_from_slice(&unit.to_be_bytes());
+        }
+        res
     };
 
-    let favicon_b64: Vec<u8> = BASE64_STANDARD.decode(&favicon_string[(comma_pos + 1)..])?;
+    let mut ping_host: Vec<u8> = vec![74]; // protocol version of 1.6.4
+    let (host, port): (&str, u16) = virtual_host.address(ip, port.unwrap_or(DEFAULT_PORT));
+    ping_host.extend_from_slice(&utf16(host));
+    ping_host.extend_from_slice(&(port as i32).to_be_bytes());
 
-    fs::write("server-icon.png", favicon_b64)?;
-    println!("The server image has been saved to server-icon.png!");
+    let mut request: Vec<u8> = vec![0xFE, 0x01, 0xFA];
+    request.extend_from_slice(&utf16("MC|PingHost"));
+    request.extend_from_slice(&(ping_host.len() as u16).to_be_bytes());
+    request.extend_from_slice(&ping_host);
 
-    temp_connection.shutdown(Shutdown::Both)?;
+    let start: Instant = Instant::now();
+    stream.write_all(&request)?;
 
-    Ok(())
+    let mut header: [u8; 3] = [0u8; 3];
+    stream.read_exact(&mut header)?;
+    let latency: Duration = start.elapsed();
+    if header[0] != 0xFF {
+        return Err("The server didn't answer the legacy ping".into());
+    }
+
+    let length: usize = u16::from_be_bytes([header[1], header[2]]) as usize;
+    let mut data: Vec<u8> = vec![0u8; length * 2];
+    stream.read_exact(&mut data)?;
+    let _ = stream.shutdown(Shutdown::Both);
+
+    let units: Vec<u16> = data
+        .chunks_exact(2)
+        .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+        .collect();
+
+    let mut status: ServerStatus = parse_legacy_status(&String::from_utf16_lossy(&units))?;
+    status.latency = Some(latency);
+    Ok(status)
+}
+
+// The text of the kick packet that answers a legacy ping
+fn parse_legacy_status(response: &str) -> Result<ServerStatus, ClientError> {
+    let (protocol, version, motd, online, max): (i32, String, String, &str, &str) =
+        match response.strip_prefix("\u{a7}1\0") {
+            Some(rest) => {
+                let fields: Vec<&str> = rest.split('\0').collect();
+                if fields.len() < 5 {
+                    return Err("Invalid legacy ping response".into());
+                }
+                let protocol: i32 = fields[0].parse().unwrap_or(0);
+                (
+                    protocol,
+                    String::from(fields[1]),
+                    String::from(fields[2]),
+                    fields[3],
+                    fields[4],
+                )
+            }
+            None => {
+                let fields: Vec<&str> = response.rsplitn(3, '\u{a7}').collect();
+                if fields.len() < 3 {
+                    return Err("Invalid legacy ping response".into());
+                }
+                (
+                    0,
+                    String::from("legacy"),
+                    String::from(fields[2]),
+                    fields[1],
+                    fields[0],
+                )
+            }
+        };
+
+    let status: Value = json!({
+        "version": { "name": version, "protocol": protocol },
+        "players": {
+            "online": online.parse::<i32>().unwrap_or(0),
+            "max": max.parse::<i32>().unwrap_or(0),
+        },
+    });
+    let mut status: ServerStatus = serde_json::from_value(status)?;
+    status.description =
+        FormattedText::Text(text_component::legacy_color_code_to_text_component(&motd));
+
+    Ok(status)
 }
 
-fn send_handshake_packet(
+pub(crate) fn send_handshake_packet(
     stream: &mut TcpStream,
     ip: &str,
     port: u16,
+    protocol: i32,
     intent: i32,
-) -> Result<(), Box<dyn Error>> {
+) -> Result<(), ClientError> {
     let mut packet_buffer: Vec<u8> = Vec::new();
 
-    packet_buffer.write_var_int(VarInt::from(754))?; // protocol version
+    packet_buffer.write_var_int(VarInt::from(protocol))?; // protocol version
 
-    packet_buffer.write_var_int(VarInt::from(ip.len() as i32))?;
-    packet_buffer.write_all(ip.as_bytes())?;
+    McString::max(255).write(&mut packet_buffer, ip)?;
 
     packet_buffer.write_all(&port.to_be_bytes())?;
 
@@ -249,7 +613,7 @@ fn send_handshake_packet(
     Ok(())
 }
 
-fn send_status_request(stream: &mut TcpStream) -> Result<(), Box<dyn Error>> {
+fn send_status_request(stream: &mut TcpStream) -> Result<(), ClientError> {
     let packet_buffer: Vec<u8> = Vec::new();
 
     send_packet(stream, 0x00, packet_buffer, -1)?; // Status Request packet
@@ -257,189 +621,170 @@ fn send_status_request(stream: &mut TcpStream) -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
-fn receive_status_response(stream: &mut TcpStream) -> Result<String, Box<dyn Error>> {
-    let packet: (i32, Vec<u8>) = receive_packet(stream, -1)?; // Status Response packet
-
-    let mut buf: Cursor<Vec<u8>> = Cursor::new(packet.1);
-    let packet_size: i32 = i32::from(read_varint_cursor(&mut buf)?);
-    let packet_data: Vec<u8> = read_array_fixed_cursor(&mut buf, packet_size as usize)?;
+// Sends a Ping with the current time and waits for the matching Pong.
+fn ping(stream: &mut TcpStream) -> Result<Duration, ClientError> {
+    let payload: i64 = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_millis() as i64)
+        .unwrap_or(0);
+    let start: Instant = Instant::now();
+
+    send_packet(stream, 0x01, payload.to_be_bytes().to_vec(), -1)?; // Ping packet
+
+    let mut reader: PacketReader = PacketReader::new();
+    let packet: (i32, &[u8]) = reader.receive(stream, -1)?; // Pong packet
+    let pong: i64 = Cursor::new(packet.1).read_long()?;
+    if packet.0 != 0x01 || pong != payload {
+        return Err("The server answered the ping with a different packet".into());
+    }
 
-    Ok(String::from_utf8(packet_data)?) //
+    Ok(start.elapsed())
 }
 
-fn send_keep_alive_packet(
-    stream: &Arc<Mutex<TcpStream>>,
-    cursor: &mut Cursor<Vec<u8>>,
-    threshold: i32,
-) -> Result<(), Box<dyn Error>> {
-    let packet_secret: Vec<u8> = read_array_fixed_cursor(cursor, 8)?;
-    let mut new_packet_buffer: Vec<u8> = Vec::new();
-
-    new_packet_buffer.write_all(&packet_secret).unwrap();
+fn receive_status_response(stream: &mut TcpStream) -> Result<String, ClientError> {
+    let mut reader: PacketReader = PacketReader::new();
+    let packet: (i32, &[u8]) = reader.receive(stream, -1)?; // Status Response packet
 
-    let mut guard = stream.lock().unwrap();
-    send_packet(&mut guard, 0x10, new_packet_buffer, threshold)?;
-    Ok(())
+    McString::DEFAULT.read(&mut Cursor::new(packet.1))
 }
 
-fn receive_chat_message(cursor: &mut Cursor<Vec<u8>>) -> Result<(), Box<dyn Error>> {
-    let response_buf: Vec<u8> = read_array_dynamic_cursor(cursor)?;
+pub(crate) fn login(
+    stream: &mut TcpStream,
+    username: &str,
+    protocol: i32,
+    plugins: &LoginPlugins,
+    capture: Option<&Capture>,
+) -> Result<(i32, Identity), ClientError> {
+    let mut packet_buffer: Vec<u8> = Vec::new();
 
-    let chat_message: String = String::from_utf8(response_buf)?;
-    let json_str: Value = serde_json::from_str(chat_message.as_str())?;
-    let text: FormattedText = FormattedText::deserialize(&json_str)?;
+    McString::USERNAME.write(&mut packet_buffer, username)?;
 
-    println!("{}", text.to_ansi());
+    // Offline-mode servers work out the UUID themselves, so none is sent where it's optional
+    match protocol {
+        759 => packet_buffer.write_all(&[0u8])?, // no signature data
+        760 => packet_buffer.write_all(&[0u8, 0u8])?, // no signature data, no UUID
+        761..=763 => packet_buffer.write_all(&[0u8])?, // no UUID
+        764.. => packet_buffer.write_all(&[0u8; 16])?,
+        _ => {}
+    }
 
-    Ok(())
-}
+    send_packet(stream, 0x00, packet_buffer, -1)?; // Login Start packet
 
-fn create_player_list(
-    cursor: &mut Cursor<Vec<u8>>,
-    online_players: &Arc<Mutex<HashMap<u128, String>>>,
-) -> Result<(), Box<dyn Error>> {
-    let action: i32 = i32::from(read_varint_cursor(cursor)?);
-    let number_of_players: i32 = i32::from(read_varint_cursor(cursor)?);
-    let mut players: MutexGuard<'_, HashMap<u128, String>> = online_players.lock().unwrap();
-
-    for _ in 0..number_of_players {
-        let uuid_arr: Vec<u8> = read_array_fixed_cursor(cursor, 16)?;
-        let uuid: u128 = u128::from_be_bytes(
-            uuid_arr
-                .try_into()
-                .expect("Vector doesn't have 16 characters!"),
-        );
-        if action == 0 {
-            let name: String = String::from_utf8(read_array_dynamic_cursor(cursor)?)?;
-            players.entry(uuid).or_insert(name);
-
-            let number_of_properties = i32::from(read_varint_cursor(cursor)?);
-            for _ in 0..number_of_properties {
-                let _ = read_array_dynamic_cursor(cursor)?; // name
-                let _ = read_array_dynamic_cursor(cursor)?; // value
-                let is_signed = read_array_fixed_cursor(cursor, 1)?;
-                if is_signed[0] == 1 {
-                    let _ = read_array_dynamic_cursor(cursor)?;
+    let mut threshold: i32 = -1;
+    let mut reader: PacketReader = PacketReader::capturing(capture, CaptureState::Login);
+    loop {
+        let (id, data): (i32, &[u8]) = reader.receive(stream, threshold)?;
+
+        match LoginPacket::decode(protocol, id, data)? {
+            LoginPacket::Disconnect { reason } => return Err(ClientError::Kicked(reason)),
+            LoginPacket::EncryptionRequest { public_key } => {
+                return Err(ClientError::AuthFailed(format!(
+                    "The server is in online mode (sent a {}-byte public key), which requires an authenticated Mojang session!",
+                    public_key.len()
+                )));
+            }
+            LoginPacket::LoginSuccess(identity) => {
+                debug!(
+                    uuid = format_args!("{:032x}", identity.uuid),
+                    "Logged in as {}", identity.username
+                );
+                if has_configuration(protocol) {
+                    send_packet(stream, 0x03, Vec::new(), threshold)?; // Login Acknowledged packet
                 }
+                return Ok((threshold, identity));
             }
-            let _ = read_varint_cursor(cursor)?;
-            let _ = read_varint_cursor(cursor)?;
-            let has_disply_name = read_array_fixed_cursor(cursor, 1)?;
-            if has_disply_name[0] == 1 {
-                let _ = read_array_dynamic_cursor(cursor)?;
+            LoginPacket::SetCompression {
+                threshold: new_threshold,
+            } => {
+                threshold = new_threshold;
+                debug!(threshold, "Compression enabled");
             }
-        }
-        if action == 1 || action == 2 {
-            let _ = read_varint_cursor(cursor)?;
-        }
-        if action == 3 {
-            let has_disply_name = read_array_fixed_cursor(cursor, 1)?;
-            if has_disply_name[0] == 1 {
-                let _ = read_array_dynamic_cursor(cursor)?;
+            LoginPacket::PluginRequest {
+                message_id,
+                channel,
+                data,
+            } => {
+                // The server waits for an answer to every one, so channels without a
+                // handler are answered as not understood
+                let mut packet_buffer: Vec<u8> = Vec::new();
+                packet_buffer.write_var_int(VarInt::from(message_id))?;
+                match plugins.answer(&channel, data) {
+                    Some(response) => {
+                        packet_buffer.write_all(&[1u8])?;
+                        packet_buffer.write_all(&response)?;
+                    }
+                    None => packet_buffer.write_all(&[0u8])?,
+                }
+                send_packet(stream, 0x02, packet_buffer, threshold)?; // Login Plugin Response packet
+            }
+            LoginPacket::Unknown { .. } => {
+                // ignore other packets
             }
-        }
-        if action == 4 {
-            players.remove(&uuid);
         }
     }
-    Ok(())
 }
 
-pub fn start(ip: &str, port: u16, username: &str) -> Result<(), Box<dyn Error>> {
-    let mut stream: TcpStream = init_connection(ip, port)?;
-    let online_players: Arc<Mutex<HashMap<u128, String>>> = Arc::new(Mutex::new(HashMap::new()));
-    let online_players_clone: Arc<Mutex<HashMap<u128, String>>> = Arc::clone(&online_players);
-    let mut threshold: i32 = -1;
-
-    send_handshake_packet(&mut stream, ip, port, 2)?; // C -> S: Handshake
-
-    let mut packet_buffer: Vec<u8> = Vec::new();
-
-    packet_buffer.write_var_int(VarInt::from(username.len() as i32))?;
-    packet_buffer.write_all(username.as_bytes())?;
-
-    send_packet(&mut stream, 0x00, packet_buffer, threshold)?; // Login Start packet
-
-    let packet: (i32, Vec<u8>) = receive_packet(&mut stream, -1)?;
-    if packet.0 == 0x03 {
-        let mut cursor: Cursor<Vec<u8>> = Cursor::new(packet.1);
-        threshold = i32::from(read_varint_cursor(&mut cursor)?);
-        println!(
-            "Compression packet received (new threshold: {}), compressing all packets...",
-            threshold
-        );
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::status::StatusPlayers;
+
+    #[test]
+    fn legacy_status_of_1_4_and_newer() {
+        let response: String = [
+            "\u{a7}1",
+            "127",
+            "1.6.4",
+            "A \u{a7}aMinecraft\u{a7}r Server",
+            "5",
+            "20",
+        ]
+        .join("\0");
+        let status: ServerStatus = parse_legacy_status(&response).unwrap();
+        assert_eq!(status.version.protocol, 127);
+        assert_eq!(status.version.name, "1.6.4");
+        assert_eq!(status.description.to_string(), "A Minecraft Server");
+        let players: StatusPlayers = status.players.unwrap();
+        assert_eq!((players.online, players.max), (5, 20));
+        assert_eq!(status.latency, None);
     }
-    let threshold_clone: i32 = threshold;
-
-    let shared_stream: Arc<Mutex<TcpStream>> = Arc::new(Mutex::new(
-        stream.try_clone().expect("Failed to copy stream."),
-    ));
-    let shared_stream_clone: Arc<Mutex<TcpStream>> = Arc::clone(&shared_stream);
-
-    thread::spawn(move || {
-        let mut buffer: String = String::new();
-
-        loop {
-            if stdin().read_line(&mut buffer).is_err() {
-                panic!("Error while reading from terminal!");
-            }
-
-            buffer = String::from(buffer.trim());
-
-            if buffer.len() > 255 {
-                println!("[MClient] The message can't be longer than 255 characters!");
-                buffer.clear();
-                continue;
-            }
-
-            if buffer.eq_ignore_ascii_case(".list") {
-                let players: MutexGuard<'_, HashMap<u128, String>> =
-                    online_players_clone.lock().unwrap();
-                println!("[MClient] {}", create_players_string(&players));
-                buffer.clear();
-                continue;
-            }
-
-            if buffer.eq_ignore_ascii_case(".quit") {
-                std::process::exit(0);
-            }
-
-            let mut packet_buffer: Vec<u8> = Vec::new();
-            packet_buffer
-                .write_var_int(VarInt::from(buffer.len() as i32))
-                .unwrap();
-            packet_buffer.write_all(buffer.as_bytes()).unwrap();
-
-            {
-                let mut guard = shared_stream_clone.lock().unwrap();
-                send_packet(&mut guard, 0x03, packet_buffer, threshold_clone).unwrap();
-            }
 
-            buffer.clear();
-        }
-    });
-
-    loop {
-        let loop_packet: (i32, Vec<u8>) = receive_packet(&mut stream, threshold)?;
+    #[test]
+    fn legacy_status_of_older_servers() {
+        // Section signs in the MOTD stay part of it
+        let status: ServerStatus = parse_legacy_status("A \u{a7}aServer\u{a7}3\u{a7}20").unwrap();
+        assert_eq!(status.version.protocol, 0);
+        assert_eq!(status.version.name, "legacy");
+        assert_eq!(status.description.to_string(), "A Server");
+        let players: StatusPlayers = status.players.unwrap();
+        assert_eq!((players.online, players.max), (3, 20));
+    }
 
-        let mut cursor: Cursor<Vec<u8>> = Cursor::new(loop_packet.1);
+    #[test]
+    fn broken_legacy_status() {
+        assert!(parse_legacy_status(&["\u{a7}1", "127", "1.6.4"].join("\0")).is_err());
+        assert!(parse_legacy_status("no separators").is_err());
+        // Unreadable numbers count as 0
+        let status: ServerStatus = parse_legacy_status("\u{a7}1\0x\0?\0motd\0many\0").unwrap();
+        assert_eq!(status.version.protocol, 0);
+        let players: StatusPlayers = status.players.unwrap();
+        assert_eq!((players.online, players.max), (0, 0));
+    }
 
-        match loop_packet.0 {
-            0x1F => {
-                // Keep alive packet
-                send_keep_alive_packet(&shared_stream, &mut cursor, threshold)?;
-            }
-            0x0E => {
-                // Receive chat message packet
-                receive_chat_message(&mut cursor)?;
-            }
-            0x32 => {
-                // Create list
-                create_player_list(&mut cursor, &online_players)?;
-            }
-            _ => {
-                // ignore other packets
-            }
-        }
+    #[test]
+    fn explicit_port_skips_srv() {
+        // A hostname with any given port, even the default one, is used as it is
+        assert_eq!(
+            resolve_address("mc.invalid", Some(25565), false),
+            (String::from("mc.invalid"), 25565)
+        );
+        assert_eq!(
+            resolve_address("mc.invalid", Some(25566), false),
+            (String::from("mc.invalid"), 25566)
+        );
+        assert_eq!(
+            resolve_address("127.0.0.1", None, false),
+            (String::from("127.0.0.1"), DEFAULT_PORT)
+        );
     }
 }
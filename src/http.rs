@@ -0,0 +1,196 @@
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::process::{Child, Command, Output, Stdio};
+use std::time::Duration;
+
+use crate::error::ClientError;
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Downloads `url`, failing unless the server answers 200 OK.
+pub(crate) fn get(url: &str) -> Result<Vec<u8>, ClientError> {
+    let (status, body): (u16, Vec<u8>) = get_with_status(url)?;
+    if status != 200 {
+        return Err(format!("{} answered with status {}", url, status).into());
+    }
+    Ok(body)
+}
+
+/// Downloads `url` and returns the status code with the body.
+pub(crate) fn get_with_status(url: &str) -> Result<(u16, Vec<u8>), ClientError> {
+    request("GET", url, &[], None)
+}
+
+/// Sends a request with extra headers and an optional body, and returns the status code
+/// with the response body.
+pub(crate) fn request(
+    method: &str,
+    url: &str,
+    headers: &[(&str, &str)],
+    body: Option<&str>,
+) -> Result<(u16, Vec<u8>), ClientError> {
+    match url.strip_prefix("http://") {
+        Some(rest) => request_plain(method, rest, headers, body),
+        None if url.starts_with("https://") => request_with_curl(method, url, headers, body),
+        None => Err(format!("Unsupported URL {}", url).into()),
+    }
+}
+
+// Writes a string for curl's config file syntax
+fn curl_quote(value: &str) -> String {
+    format!(
+        "\"{}\"",
+        value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+            .replace('\r', "\\r")
+    )
+}
+
+// There's no TLS implementation in the client, so HTTPS requests go through curl.
+// The URL, headers and body are passed as a config file on stdin, where other users
+// can't see them like they could see the arguments (some APIs have tokens in URLs).
+fn request_with_curl(
+    method: &str,
+    url: &str,
+    headers: &[(&str, &str)],
+    body: Option<&str>,
+) -> Result<(u16, Vec<u8>), ClientError> {
+    let mut config: String = format!("url = {}\n", curl_quote(url));
+    for (name, value) in headers {
+        config += &format!("header = {}\n", curl_quote(&format!("{}: {}", name, value)));
+    }
+    if let Some(body) = body {
+        config += &format!("data-raw = {}\n", curl_quote(body));
+    }
+
+    let mut child: Child = Command::new("curl")
+        .args(["--silent", "--show-error", "--location", "--max-time"])
+        .arg(HTTP_TIMEOUT.as_secs().to_string())
+        .args(["--user-agent", "mc-chat", "--write-out", "\n%{http_code}"])
+        .args(["--config", "-"])
+        .args(
+            ["--request", method]
+                .into_iter()
+                .filter(|_| method != "GET"),
+        )
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| ClientError::from(format!("Couldn't run curl for HTTPS requests: {}", e)))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(config.as_bytes())?;
+    }
+    let output: Output = child.wait_with_output()?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "curl failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+
+    // --write-out appends the status code on a line of its own
+    let mut body: Vec<u8> = output.stdout;
+    let Some(newline) = body.iter().rposition(|b| *b == b'\n') else {
+        return Err("curl didn't print a status code".into());
+    };
+    let status: u16 = String::from_utf8_lossy(&body[newline + 1..])
+        .trim()
+        .parse()
+        .map_err(|_| "curl didn't print a status code")?;
+    body.truncate(newline);
+
+    Ok((status, body))
+}
+
+// A bare HTTP/1.1 request, enough for Mojang's texture server
+fn request_plain(
+    method: &str,
+    rest: &str,
+    headers: &[(&str, &str)],
+    body: Option<&str>,
+) -> Result<(u16, Vec<u8>), ClientError> {
+    let (authority, path): (&str, &str) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    let (host, port): (&str, u16) = match authority.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse().map_err(|_| "Invalid port in URL")?),
+        None => (authority, 80),
+    };
+
+    let address = (host, port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| ClientError::from(format!("Couldn't resolve {}", host)))?;
+    let mut stream: TcpStream = TcpStream::connect_timeout(&address, HTTP_TIMEOUT)?;
+    stream.set_read_timeout(Some(HTTP_TIMEOUT))?;
+
+    let mut request: String = format!(
+        "{} {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: mc-chat\r\nConnection: close\r\n",
+        method, path, authority
+    );
+    for (name, value) in headers {
+        request += &format!("{}: {}\r\n", name, value);
+    }
+    if let Some(body) = body {
+        request += &format!("Content-Length: {}\r\n", body.len());
+    }
+    request += "\r\n";
+    request += body.unwrap_or_default();
+    stream.write_all(request.as_bytes())?;
+
+    let mut response: Vec<u8> = Vec::new();
+    stream.read_to_end(&mut response)?;
+
+    let Some(header_end) = response.windows(4).position(|window| window == b"\r\n\r\n") else {
+        return Err("Incomplete HTTP response".into());
+    };
+    let headers: String = String::from_utf8_lossy(&response[..header_end]).into_owned();
+    let body: &[u8] = &response[header_end + 4..];
+
+    let status: u16 = headers
+        .lines()
+        .next()
+        .and_then(|status_line| status_line.split_whitespace().nth(1))
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| ClientError::from(format!("Invalid HTTP response from {}", host)))?;
+
+    let chunked: bool = headers.lines().any(|line| {
+        line.to_ascii_lowercase()
+            .starts_with("transfer-encoding: chunked")
+    });
+    if chunked {
+        Ok((status, decode_chunked(body)?))
+    } else {
+        Ok((status, body.to_vec()))
+    }
+}
+
+fn decode_chunked(mut body: &[u8]) -> Result<Vec<u8>, ClientError> {
+    let mut res: Vec<u8> = Vec::new();
+
+    loop {
+        let Some(line_end) = body.windows(2).position(|window| window == b"\r\n") else {
+            return Err("Incomplete chunked HTTP body".into());
+        };
+        let size_line: String = String::from_utf8_lossy(&body[..line_end]).into_owned();
+        let size_hex: &str = size_line.split(';').next().unwrap_or_default().trim();
+        let size: usize =
+            usize::from_str_radix(size_hex, 16).map_err(|_| "Invalid chunk size in HTTP body")?;
+
+        body = &body[line_end + 2..];
+        if size == 0 {
+            return Ok(res);
+        }
+        if body.len() < size + 2 {
+            return Err("Incomplete chunked HTTP body".into());
+        }
+        res.extend_from_slice(&body[..size]);
+        body = &body[size + 2..];
+    }
+}
@@ -0,0 +1,182 @@
+use std::net::{SocketAddr, TcpListener};
+
+use mclient_project::mitm::{self, ConnectionState, Direction, ProxiedPacket, ProxyEvent};
+use mclient_project::packet::{ClientboundPacket, ConfigurationPacket, LoginPacket};
+use mclient_project::protocol;
+use mclient_project::{ClientError, ConnectPolicy, Socks5Proxy};
+
+// Decoded packets can be long (chunk data, player lists), only the start is shown
+const MAX_SUMMARY_CHARS: usize = 100;
+
+/// What the `proxy` subcommand prints and leaves out.
+pub struct InspectOptions {
+    /// Dumps the start of every packet's data, if given.
+    pub hex_dump: Option<fn(&[u8]) -> String>,
+    /// Packet names (case, spaces and underscores don't matter) or IDs like 0x21.
+    pub hide: Vec<String>,
+}
+
+/// Listens on `listen`, forwards every connection to `host`:`port` and prints a line for
+/// each packet in both directions. Only returns if it can't listen.
+pub fn run(
+    listen: SocketAddr,
+    host: &str,
+    port: Option<u16>,
+    proxy: Option<&Socks5Proxy>,
+    policy: &ConnectPolicy,
+    options: InspectOptions,
+    print: fn(&str),
+) -> Result<(), ClientError> {
+    let listener: TcpListener = TcpListener::bind(listen)?;
+    print(&format!(
+        "Listening on {}, forwarding to {}:{}. Point a client at it to see its packets",
+        listen,
+        host,
+        port.unwrap_or(mclient_project::DEFAULT_PORT)
+    ));
+    mitm::run(listener, host, port, proxy, policy, move |event| {
+        let line: String = match event {
+            ProxyEvent::Connected { connection, client } => {
+                format!("[#{}] {} connected", connection, client)
+            }
+            ProxyEvent::Packet(packet) => {
+                let name: String = packet_name(packet);
+                if is_hidden(&options.hide, packet.id, &name) {
+                    return;
+                }
+                let mut line: String = format!(
+                    "[#{}] {} {:<13} 0x{:02X} {:<34} {:>7} B {:>9}  {}",
+                    packet.connection,
+                    arrow(packet.direction),
+                    packet.state,
+                    packet.id,
+                    name,
+                    packet.data.len(),
+                    format!("({})", packet.frame_length),
+                    summary(packet)
+                );
+                if let Some(hex_dump) = options.hex_dump {
+                    line = format!("{}\n{}", line.trim_end(), hex_dump(packet.data));
+                }
+                line
+            }
+            ProxyEvent::Malformed {
+                connection,
+                direction,
+                error,
+            } => format!(
+                "[#{}] {} Couldn't unpack a frame, forwarded it anyway: {}",
+                connection,
+                arrow(*direction),
+                error
+            ),
+            ProxyEvent::Encrypted { connection } => format!(
+                "[#{}] The server turned on encryption (online mode), the rest of the connection is forwarded without decoding",
+                connection
+            ),
+            ProxyEvent::LegacyPing { connection } => format!(
+                "[#{}] Pre-1.7 server list ping, forwarded without decoding",
+                connection
+            ),
+            ProxyEvent::Closed { connection, reason } => {
+                format!("[#{}] Closed: {}", connection, reason)
+            }
+        };
+        print(line.trim_end());
+    });
+    Ok(())
+}
+
+fn arrow(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Serverbound => "C->S",
+        Direction::Clientbound => "S->C",
+    }
+}
+
+// The proxy names clientbound play packets itself, serverbound ones are looked up in the
+// client's own (shorter) list
+fn packet_name(packet: &ProxiedPacket) -> String {
+    if let Some(name) = packet.name {
+        return String::from(name);
+    }
+    match (packet.state, packet.direction) {
+        (ConnectionState::Play, Direction::Serverbound) => protocol::table_for(packet.protocol)
+            .and_then(|table| table.serverbound(packet.id))
+            .map(|packet| spaced(&format!("{:?}", packet)))
+            .unwrap_or_else(|| String::from("(unknown)")),
+        _ => String::from("(unknown)"),
+    }
+}
+
+// "ChatCommand" to "Chat Command", like the names of clientbound packets
+fn spaced(name: &str) -> String {
+    let mut res: String = String::new();
+    for c in name.chars() {
+        if c.is_ascii_uppercase() && !res.is_empty() {
+            res.push(' ');
+        }
+        res.push(c);
+    }
+    res
+}
+
+// Clientbound packets the client understands, as it parses them
+fn summary(packet: &ProxiedPacket) -> String {
+    if packet.direction != Direction::Clientbound {
+        return String::new();
+    }
+    let text: String = match decode(packet) {
+        Ok(Some(text)) => text,
+        Ok(None) => return String::new(),
+        Err(e) => format!("malformed: {}", e),
+    };
+    match text.char_indices().nth(MAX_SUMMARY_CHARS) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text,
+    }
+}
+
+fn decode(packet: &ProxiedPacket) -> Result<Option<String>, ClientError> {
+    let (protocol, id, data): (i32, i32, &[u8]) = (packet.protocol, packet.id, packet.data);
+    Ok(match packet.state {
+        ConnectionState::Login => match LoginPacket::decode(protocol, id, data)? {
+            LoginPacket::Unknown { .. } => None,
+            decoded => Some(format!("{:?}", decoded)),
+        },
+        ConnectionState::Configuration => match ConfigurationPacket::decode(protocol, id, data)? {
+            ConfigurationPacket::Unknown { .. } => None,
+            decoded => Some(format!("{:?}", decoded)),
+        },
+        ConnectionState::Play => {
+            let Some(table) = protocol::table_for(protocol) else {
+                return Ok(None);
+            };
+            match ClientboundPacket::decode(table, id, data)? {
+                ClientboundPacket::Unknown { .. } => None,
+                decoded => Some(format!("{:?}", decoded)),
+            }
+        }
+        ConnectionState::Handshaking | ConnectionState::Status => None,
+    })
+}
+
+fn is_hidden(hide: &[String], id: i32, name: &str) -> bool {
+    let name: String = normalize(name);
+    hide.iter().any(|pattern| {
+        match pattern
+            .strip_prefix("0x")
+            .or_else(|| pattern.strip_prefix("0X"))
+        {
+            Some(hex) => i32::from_str_radix(hex, 16) == Ok(id),
+            None => normalize(pattern) == name,
+        }
+    })
+}
+
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| !matches!(c, ' ' | '_' | '-'))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
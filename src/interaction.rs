@@ -0,0 +1,193 @@
+use std::fmt;
+
+use azalea_chat::FormattedText;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+// azalea-chat drops hover and click events when it parses a component, so they're
+// collected from the JSON (or NBT converted to JSON) before that.
+
+/// What clicking a part of a chat message does in game.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ClickAction {
+    /// Sends a chat message or command (with the leading `/`).
+    RunCommand(String),
+    /// Puts the text into the chat input without sending it.
+    SuggestCommand(String),
+    OpenUrl(String),
+    CopyToClipboard(String),
+    /// Anything else, like `change_page` in books.
+    Other {
+        action: String,
+        value: String,
+    },
+}
+
+impl ClickAction {
+    fn from_json(event: &Value) -> Option<ClickAction> {
+        let action: &str = event.get("action")?.as_str()?;
+        let value: String = match event.get("value")? {
+            Value::String(value) => value.clone(),
+            value => value.to_string(),
+        };
+        Some(match action {
+            "run_command" => ClickAction::RunCommand(value),
+            "suggest_command" => ClickAction::SuggestCommand(value),
+            "open_url" => ClickAction::OpenUrl(value),
+            "copy_to_clipboard" => ClickAction::CopyToClipboard(value),
+            _ => ClickAction::Other {
+                action: String::from(action),
+                value,
+            },
+        })
+    }
+}
+
+impl fmt::Display for ClickAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClickAction::RunCommand(command) => write!(f, "Runs {}", command),
+            ClickAction::SuggestCommand(command) => write!(f, "Suggests {}", command),
+            ClickAction::OpenUrl(url) => write!(f, "Opens {}", url),
+            ClickAction::CopyToClipboard(text) => write!(f, "Copies {}", text),
+            ClickAction::Other { action, value } => write!(f, "{}: {}", action, value),
+        }
+    }
+}
+
+/// A part of a chat message with hover text or a click action.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Interaction {
+    /// The part's own text, untranslated.
+    pub text: String,
+    /// Shown when hovering over the part. Entities and items are shown by name or ID.
+    pub hover: Option<FormattedText>,
+    pub click: Option<ClickAction>,
+}
+
+#[derive(Clone, Default, PartialEq)]
+struct Events {
+    hover: Option<Value>,
+    click: Option<Value>,
+}
+
+impl Events {
+    fn is_empty(&self) -> bool {
+        self.hover.is_none() && self.click.is_none()
+    }
+}
+
+/// The parts of a chat component that have hover or click events, in order. Children
+/// inherit the events of their parent, and neighbouring parts with the same events
+/// are merged.
+pub(crate) fn interactions(component: &Value) -> Vec<Interaction> {
+    let mut segments: Vec<(String, Events)> = Vec::new();
+    collect(component, &Events::default(), false, &mut segments);
+
+    let mut merged: Vec<(String, Events)> = Vec::new();
+    for (text, events) in segments {
+        match merged.last_mut() {
+            Some((last_text, last_events)) if *last_events == events => *last_text += &text,
+            _ => merged.push((text, events)),
+        }
+    }
+
+    merged
+        .into_iter()
+        .map(|(text, events)| Interaction {
+            text,
+            hover: events.hover.as_ref().and_then(hover_text),
+            click: events.click.as_ref().and_then(ClickAction::from_json),
+        })
+        .collect()
+}
+
+// `covered` means the parent already recorded text that includes this component
+// (translations with arguments), so only parts with their own events are added.
+fn collect(component: &Value, inherited: &Events, covered: bool, out: &mut Vec<(String, Events)>) {
+    let object: &Map<String, Value> = match component {
+        Value::Object(object) => object,
+        // The first element is the parent of the rest
+        Value::Array(parts) => {
+            if let Some((first, rest)) = parts.split_first() {
+                let events: Events = own_events(first, inherited);
+                collect(first, inherited, covered, out);
+                for part in rest {
+                    collect(part, &events, covered, out);
+                }
+            }
+            return;
+        }
+        Value::String(text) => {
+            if !inherited.is_empty() && !covered {
+                out.push((text.clone(), inherited.clone()));
+            }
+            return;
+        }
+        _ => return,
+    };
+
+    let events: Events = own_events(component, inherited);
+    let covered: bool = covered && events == *inherited;
+    let mut children_covered: bool = covered;
+    if !events.is_empty() && !covered {
+        out.push((own_text(object), events.clone()));
+        children_covered = true;
+    }
+
+    if let Some(Value::Array(arguments)) = object.get("with") {
+        for argument in arguments {
+            collect(argument, &events, children_covered, out);
+        }
+    }
+    if let Some(Value::Array(extra)) = object.get("extra") {
+        for child in extra {
+            collect(child, &events, covered, out);
+        }
+    }
+}
+
+fn own_events(component: &Value, inherited: &Events) -> Events {
+    Events {
+        hover: component
+            .get("hoverEvent")
+            .cloned()
+            .or_else(|| inherited.hover.clone()),
+        click: component
+            .get("clickEvent")
+            .cloned()
+            .or_else(|| inherited.click.clone()),
+    }
+}
+
+// The text of a component without its children; translations include their arguments.
+fn own_text(object: &Map<String, Value>) -> String {
+    if let Some(Value::String(text)) = object.get("text") {
+        return text.clone();
+    }
+    let mut own: Map<String, Value> = object.clone();
+    own.remove("extra");
+    FormattedText::deserialize(&Value::Object(own))
+        .map(|text| text.to_string())
+        .unwrap_or_default()
+}
+
+fn hover_text(event: &Value) -> Option<FormattedText> {
+    // 1.16 replaced `value` with `contents`
+    let contents: &Value = event.get("contents").or_else(|| event.get("value"))?;
+    let component: Value = match event.get("action")?.as_str()? {
+        "show_text" => contents.clone(),
+        "show_entity" => contents
+            .get("name")
+            .or_else(|| contents.get("type"))
+            .unwrap_or(contents)
+            .clone(),
+        "show_item" => contents.get("id").unwrap_or(contents).clone(),
+        _ => return None,
+    };
+    match component {
+        // Before 1.16 entities and items are SNBT strings, shown as they are
+        Value::String(text) => Some(FormattedText::from(text)),
+        component => FormattedText::deserialize(&component).ok(),
+    }
+}
@@ -0,0 +1,511 @@
+use std::error::Error;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use mclient_project::{Client, Event};
+
+use crate::bots::{Bot, Bots, JOIN_GRACE};
+use crate::filter;
+use crate::lang::Language;
+use crate::whisper::{self, Direction};
+
+// A tiny IRC server (--irc-listen) so any IRC client can be the frontend: each bot's
+// chat is a channel, players talk as IRC users, and what's said in the channel is sent
+// through the bot. Messages to a player's nick are whispered to them.
+
+const SERVER_NAME: &str = "mc-chat";
+
+// The channel for a bot: `#minecraft`, or `#<name>` when there are several
+fn channel_name(bot: &str, several: bool) -> String {
+    match several {
+        true => format!("#{}", nick(bot)),
+        false => String::from("#minecraft"),
+    }
+}
+
+// Player names are nearly always valid nicks, but the rest becomes `_`
+fn nick(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            'a'..='z'
+            | 'A'..='Z'
+            | '0'..='9'
+            | '_'
+            | '-'
+            | '['
+            | ']'
+            | '\\'
+            | '`'
+            | '^'
+            | '{'
+            | '}'
+            | '|' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+struct Channel {
+    name: String,
+    bot: Arc<Bot>,
+}
+
+struct Connection {
+    id: usize,
+    stream: TcpStream,
+    // Set once the client is registered
+    nick: Option<String>,
+}
+
+pub struct IrcServer {
+    channels: Vec<Channel>,
+    password: Option<String>,
+    connections: Mutex<Vec<Connection>>,
+}
+
+impl IrcServer {
+    /// Listens on `address` and relays the chat of every bot. Problems are passed to
+    /// `print`.
+    pub fn start(
+        address: SocketAddr,
+        password: Option<String>,
+        bots: &Bots,
+        language: &Arc<Language>,
+        print: fn(&str),
+    ) -> Result<(), Box<dyn Error>> {
+        let listener: TcpListener = match TcpListener::bind(address) {
+            Ok(listener) => listener,
+            Err(e) => return Err(format!("Couldn't listen for IRC on {}: {}", address, e).into()),
+        };
+        let several: bool = bots.all().len() > 1;
+        let server: Arc<IrcServer> = Arc::new(IrcServer {
+            channels: bots
+                .all()
+                .into_iter()
+                .map(|bot| Channel {
+                    name: channel_name(&bot.name, several),
+                    bot,
+                })
+                .collect(),
+            password,
+            connections: Mutex::new(Vec::new()),
+        });
+
+        for channel in &server.channels {
+            server.relay(channel, language);
+        }
+
+        let accepting: Arc<IrcServer> = Arc::clone(&server);
+        thread::spawn(move || {
+            for (id, stream) in listener.incoming().enumerate() {
+                let stream: TcpStream = match stream {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        print(&format!("[MClient] IRC connection failed: {}", e));
+                        continue;
+                    }
+                };
+                let server: Arc<IrcServer> = Arc::clone(&accepting);
+                thread::spawn(move || server.serve(id, stream));
+            }
+        });
+        Ok(())
+    }
+
+    // Forwards a bot's events to its channel
+    fn relay(self: &Arc<Self>, channel: &Channel, language: &Arc<Language>) {
+        let server: Arc<IrcServer> = Arc::clone(self);
+        let name: String = channel.name.clone();
+        let client = Arc::downgrade(&channel.bot.client);
+        let language: Arc<Language> = Arc::clone(language);
+        let own_uuid: u128 = channel.bot.client.identity().uuid;
+        let own_tag: String = format!("<{}>", channel.bot.name);
+        let connected_at: Mutex<Instant> = Mutex::new(Instant::now());
+
+        channel.bot.client.on_event(move |event| match event {
+            Event::ChatReceived {
+                message, sender, ..
+            } => {
+                let text: String = language.translate(message).to_string();
+                // IRC clients show what their user said themselves
+                if *sender == Some(own_uuid) || text.starts_with(&own_tag) {
+                    return;
+                }
+                match whisper::detect(message) {
+                    Some((Direction::Incoming, partner)) => {
+                        let text: String = whisper::text(message).unwrap_or(text);
+                        server.to_each(|nick| {
+                            format!(":{} PRIVMSG {} :{}", prefix(&partner), nick, text)
+                        });
+                    }
+                    Some((Direction::Outgoing, _)) => {}
+                    None => {
+                        let author: Option<String> = match (sender, client.upgrade()) {
+                            (Some(uuid), Some(client)) => client
+                                .online_players()
+                                .get(uuid)
+                                .map(|player| player.name.clone()),
+                            _ => None,
+                        }
+                        .or_else(|| filter::tagged_author(&text).map(String::from));
+                        match author {
+                            Some(author) => {
+                                let tag: String = format!("<{}> ", author);
+                                let text: &str = text.strip_prefix(&tag).unwrap_or(&text);
+                                server.broadcast(&format!(
+                                    ":{} PRIVMSG {} :{}",
+                                    prefix(&author),
+                                    name,
+                                    text
+                                ));
+                            }
+                            None => server.notice(&name, &text),
+                        }
+                    }
+                }
+            }
+            Event::PlayerJoined { name: player, .. }
+                if connected_at.lock().unwrap().elapsed() > JOIN_GRACE =>
+            {
+                server.broadcast(&format!(":{} JOIN {}", prefix(player), name));
+            }
+            Event::PlayerLeft { name: player, .. } => {
+                server.broadcast(&format!(":{} PART {}", prefix(player), name));
+            }
+            Event::Died(message) => {
+                server.notice(&name, &format!("You died: {}", language.translate(message)))
+            }
+            Event::Kicked(reason) => {
+                server.notice(&name, &format!("Kicked: {}", language.translate(reason)))
+            }
+            Event::Connected => {
+                *connected_at.lock().unwrap() = Instant::now();
+                server.notice(&name, "Connected to the server again");
+            }
+            _ => {}
+        });
+    }
+
+    fn notice(&self, channel: &str, text: &str) {
+        self.broadcast(&format!(":{} NOTICE {} :{}", SERVER_NAME, channel, text));
+    }
+
+    // Sends a line to every registered connection
+    fn broadcast(&self, line: &str) {
+        self.to_each(|_| String::from(line));
+    }
+
+    // Sends each registered connection the line made for its nick. Connections that
+    // can't be written to anymore are dropped.
+    fn to_each(&self, line: impl Fn(&str) -> String) {
+        self.connections.lock().unwrap().retain_mut(|connection| {
+            let Some(nick) = &connection.nick else {
+                return true;
+            };
+            let line: String = line(nick);
+            write!(connection.stream, "{}\r\n", line.replace(['\r', '\n'], " ")).is_ok()
+        });
+    }
+
+    fn serve(&self, id: usize, stream: TcpStream) {
+        let Ok(writer) = stream.try_clone() else {
+            return;
+        };
+        self.connections.lock().unwrap().push(Connection {
+            id,
+            stream: writer,
+            nick: None,
+        });
+
+        let mut session: Session = Session {
+            server: self,
+            id,
+            stream: match stream.try_clone() {
+                Ok(stream) => stream,
+                Err(_) => return,
+            },
+            nick: None,
+            user: false,
+            password_ok: self.password.is_none(),
+        };
+        for line in BufReader::new(stream).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if !session.handle(&line) {
+                break;
+            }
+        }
+        self.connections
+            .lock()
+            .unwrap()
+            .retain(|connection| connection.id != id);
+    }
+
+    fn channel(&self, name: &str) -> Option<&Channel> {
+        self.channels
+            .iter()
+            .find(|channel| channel.name.eq_ignore_ascii_case(name))
+    }
+}
+
+// `nick!user@host` for a player
+fn prefix(player: &str) -> String {
+    let nick: String = nick(player);
+    format!("{}!{}@minecraft", nick, nick)
+}
+
+// One IRC connection, from the reading side
+struct Session<'a> {
+    server: &'a IrcServer,
+    id: usize,
+    stream: TcpStream,
+    nick: Option<String>,
+    user: bool,
+    password_ok: bool,
+}
+
+impl Session<'_> {
+    fn send(&mut self, line: &str) {
+        let _ = write!(self.stream, "{}\r\n", line);
+    }
+
+    // A numeric reply
+    fn reply(&mut self, code: &str, text: &str) {
+        let nick: String = self.nick.clone().unwrap_or_else(|| String::from("*"));
+        self.send(&format!(":{} {} {} {}", SERVER_NAME, code, nick, text));
+    }
+
+    fn registered(&self) -> bool {
+        self.server
+            .connections
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|connection| connection.id == self.id && connection.nick.is_some())
+    }
+
+    /// Returns false when the connection should be closed.
+    fn handle(&mut self, line: &str) -> bool {
+        let (command, params): (String, Vec<String>) = parse_line(line);
+        match command.as_str() {
+            "PASS" => {
+                self.password_ok = match &self.server.password {
+                    Some(password) => params.first() == Some(password),
+                    None => true,
+                };
+            }
+            "NICK" => {
+                let Some(nick) = params.first() else {
+                    self.reply("431", ":No nickname given");
+                    return true;
+                };
+                self.nick = Some(nick.clone());
+                self.try_register();
+            }
+            "USER" => {
+                self.user = true;
+                self.try_register();
+            }
+            "PING" => {
+                let token: String = params.first().cloned().unwrap_or_default();
+                self.send(&format!(":{} PONG {} :{}", SERVER_NAME, SERVER_NAME, token));
+            }
+            "QUIT" => return false,
+            "CAP" => {
+                // No capabilities, but clients wait for an answer to LS
+                if params
+                    .first()
+                    .is_some_and(|sub| sub.eq_ignore_ascii_case("LS"))
+                {
+                    self.send(&format!(":{} CAP * LS :", SERVER_NAME));
+                }
+            }
+            _ if !self.registered() => self.reply("451", ":You have not registered"),
+            "JOIN" => {
+                for name in params
+                    .first()
+                    .map(|names| names.split(','))
+                    .into_iter()
+                    .flatten()
+                {
+                    match self
+                        .server
+                        .channel(name)
+                        .map(|channel| channel.name.clone())
+                    {
+                        Some(name) => self.join(&name),
+                        None => self.reply("403", &format!("{} :No such channel", name)),
+                    }
+                }
+            }
+            // The channels are always joined
+            "PART" => {}
+            "NAMES" => {
+                for channel in params
+                    .first()
+                    .map(|names| names.split(','))
+                    .into_iter()
+                    .flatten()
+                {
+                    self.names(channel);
+                }
+            }
+            "PRIVMSG" | "NOTICE" => {
+                let (Some(target), Some(text)) = (params.first(), params.get(1)) else {
+                    self.reply("412", ":No text to send");
+                    return true;
+                };
+                self.message(target, text, command == "PRIVMSG");
+            }
+            "MODE" => match params.first() {
+                Some(target) if target.starts_with('#') => {
+                    self.reply("324", &format!("{} +nt", target))
+                }
+                _ => {}
+            },
+            "WHO" => {
+                let target: String = params.first().cloned().unwrap_or_default();
+                self.reply("315", &format!("{} :End of WHO list", target));
+            }
+            _ => self.reply("421", &format!("{} :Unknown command", command)),
+        }
+        true
+    }
+
+    fn try_register(&mut self) {
+        let Some(nick) = self.nick.clone() else {
+            return;
+        };
+        if !self.user || self.registered() {
+            return;
+        }
+        if !self.password_ok {
+            self.reply("464", ":Password incorrect");
+            let _ = self.stream.shutdown(std::net::Shutdown::Both);
+            return;
+        }
+
+        self.reply("001", &format!(":Welcome to mc-chat, {}", nick));
+        self.reply("002", &format!(":Your host is {}", SERVER_NAME));
+        self.reply("004", &format!("{} mc-chat o nt", SERVER_NAME));
+        self.reply("422", ":MOTD File is missing");
+        if let Some(connection) = self
+            .server
+            .connections
+            .lock()
+            .unwrap()
+            .iter_mut()
+            .find(|connection| connection.id == self.id)
+        {
+            connection.nick = Some(nick);
+        }
+
+        for channel in &self.server.channels {
+            self.join(&channel.name);
+        }
+    }
+
+    fn join(&mut self, channel: &str) {
+        let Some(nick) = self.nick.clone() else {
+            return;
+        };
+        self.send(&format!(":{}!{}@localhost JOIN {}", nick, nick, channel));
+        if let Some(bot) = self
+            .server
+            .channel(channel)
+            .map(|channel| Arc::clone(&channel.bot))
+        {
+            self.reply(
+                "332",
+                &format!(
+                    "{} :Minecraft chat of {} on {}",
+                    channel, bot.name, bot.address
+                ),
+            );
+        }
+        self.names(channel);
+    }
+
+    fn names(&mut self, channel: &str) {
+        let Some(bot) = self
+            .server
+            .channel(channel)
+            .map(|channel| Arc::clone(&channel.bot))
+        else {
+            self.reply("366", &format!("{} :End of NAMES list", channel));
+            return;
+        };
+        let mut names: Vec<String> = bot
+            .client
+            .online_players()
+            .into_values()
+            .map(|player| nick(&player.name))
+            .filter(|name| !name.eq_ignore_ascii_case(&bot.name))
+            .collect();
+        names.sort_by_key(|name| name.to_lowercase());
+        names.insert(0, self.nick.clone().unwrap_or_default());
+
+        for chunk in names.chunks(20) {
+            self.reply("353", &format!("= {} :{}", channel, chunk.join(" ")));
+        }
+        self.reply("366", &format!("{} :End of NAMES list", channel));
+    }
+
+    // Sends what the IRC user wrote through the right bot
+    fn message(&mut self, target: &str, text: &str, reply_errors: bool) {
+        // CTCP; only ACTION (/me) has a Minecraft equivalent
+        let text: String = match text.strip_prefix("\x01ACTION ") {
+            Some(action) => format!("/me {}", action.trim_end_matches('\x01')),
+            None if text.starts_with('\x01') => return,
+            None => String::from(text),
+        };
+
+        let (client, line): (Arc<Client>, String) = if target.starts_with('#') {
+            match self.server.channel(target) {
+                Some(channel) => (Arc::clone(&channel.bot.client), text),
+                None => {
+                    if reply_errors {
+                        self.reply("403", &format!("{} :No such channel", target));
+                    }
+                    return;
+                }
+            }
+        } else {
+            // Whispers go through the first bot
+            match self.server.channels.first() {
+                Some(channel) => (
+                    Arc::clone(&channel.bot.client),
+                    format!("/msg {} {}", target, text),
+                ),
+                None => return,
+            }
+        };
+
+        if let Err(e) = client.send_chat(&line)
+            && reply_errors
+        {
+            self.send(&format!(":{} NOTICE {} :{}", SERVER_NAME, target, e));
+        }
+    }
+}
+
+// Splits `[:prefix] COMMAND params [:trailing]`
+fn parse_line(line: &str) -> (String, Vec<String>) {
+    let mut rest: &str = line.trim_end();
+    if rest.starts_with(':') {
+        rest = rest.split_once(' ').map_or("", |(_, rest)| rest);
+    }
+    let (words, trailing): (&str, Option<&str>) = match rest.split_once(" :") {
+        Some((words, trailing)) => (words, Some(trailing)),
+        None => (rest, None),
+    };
+    let mut words = words.split_whitespace();
+    let command: String = words.next().unwrap_or_default().to_ascii_uppercase();
+    let mut params: Vec<String> = words.map(String::from).collect();
+    params.extend(trailing.map(String::from));
+    (command, params)
+}
@@ -0,0 +1,80 @@
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+
+use crate::error::ClientError;
+
+// Worlds opened to LAN announce themselves every ~1.5 seconds on a multicast group
+// with `[MOTD]<motd>[/MOTD][AD]<port>[/AD]`.
+
+const LAN_GROUP: Ipv4Addr = Ipv4Addr::new(224, 0, 2, 60);
+const LAN_PORT: u16 = 4445;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LanServer {
+    pub motd: String,
+    pub address: IpAddr,
+    pub port: u16,
+}
+
+fn between<'a>(text: &'a str, start: &str, end: &str) -> Option<&'a str> {
+    let from: usize = text.find(start)? + start.len();
+    let to: usize = from + text[from..].find(end)?;
+    Some(&text[from..to])
+}
+
+fn parse_announcement(message: &str, address: IpAddr) -> Option<LanServer> {
+    let motd: &str = between(message, "[MOTD]", "[/MOTD]")?;
+    let ad: &str = between(message, "[AD]", "[/AD]")?;
+
+    // Old versions announced "ip:port", newer ones only the port
+    let port: u16 = match ad.rsplit_once(':') {
+        Some((_, port)) => port.parse().ok()?,
+        None => ad.parse().ok()?,
+    };
+
+    Some(LanServer {
+        motd: String::from(motd),
+        address,
+        port,
+    })
+}
+
+/// Listens for LAN world announcements for `duration` and returns every world heard.
+pub fn discover_lan(duration: Duration) -> Result<Vec<LanServer>, ClientError> {
+    let socket: UdpSocket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, LAN_PORT))?;
+    socket.join_multicast_v4(&LAN_GROUP, &Ipv4Addr::UNSPECIFIED)?;
+
+    let mut servers: Vec<LanServer> = Vec::new();
+    let deadline: Instant = Instant::now() + duration;
+    let mut buffer: [u8; 1024] = [0u8; 1024];
+
+    loop {
+        let remaining: Duration = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        socket.set_read_timeout(Some(remaining))?;
+
+        let (len, from): (usize, SocketAddr) = match socket.recv_from(&mut buffer) {
+            Ok(received) => received,
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                ) =>
+            {
+                break;
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let message: String = String::from_utf8_lossy(&buffer[..len]).into_owned();
+        if let Some(server) = parse_announcement(&message, from.ip())
+            && !servers.contains(&server)
+        {
+            servers.push(server);
+        }
+    }
+
+    Ok(servers)
+}
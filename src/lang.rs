@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use azalea_chat::FormattedText;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+// Resolves `translate` chat components into plain text components before they're
+// printed, so the arguments keep their own colors. Keys are looked up in the language
+// file given with --lang first, then in the bundled en_us table.
+
+pub struct Language {
+    entries: HashMap<String, String>,
+}
+
+impl Language {
+    pub fn bundled() -> Language {
+        Language {
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Loads a Minecraft language file (e.g. `de_de.json` from the client jar or a resource pack).
+    pub fn load(path: &Path) -> Result<Language, Box<dyn Error>> {
+        let text: String = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) => return Err(format!("Couldn't read {}: {}", path.display(), e).into()),
+        };
+        let entries: HashMap<String, String> = match serde_json::from_str(&text) {
+            Ok(entries) => entries,
+            Err(e) => return Err(format!("Couldn't parse {}: {}", path.display(), e).into()),
+        };
+
+        Ok(Language { entries })
+    }
+
+    fn get<'a>(&'a self, key: &'a str) -> Option<&'a str> {
+        match self.entries.get(key) {
+            Some(template) => Some(template),
+            None => azalea_language::get(key),
+        }
+    }
+
+    pub fn translate(&self, message: &FormattedText) -> FormattedText {
+        let Ok(json) = serde_json::to_value(message) else {
+            return message.clone();
+        };
+
+        match FormattedText::deserialize(&self.resolve(json)) {
+            Ok(resolved) => resolved,
+            Err(_) => message.clone(),
+        }
+    }
+
+    fn resolve(&self, component: Value) -> Value {
+        let mut object: Map<String, Value> = match component {
+            Value::Object(object) => object,
+            Value::Array(items) => {
+                return Value::Array(items.into_iter().map(|item| self.resolve(item)).collect());
+            }
+            other => return other,
+        };
+
+        let mut extra: Vec<Value> = match object.remove("extra") {
+            Some(Value::Array(items)) => items.into_iter().map(|item| self.resolve(item)).collect(),
+            _ => Vec::new(),
+        };
+
+        if let Some(Value::String(key)) = object.remove("translate") {
+            let args: Vec<Value> = match object.remove("with") {
+                Some(Value::Array(items)) => {
+                    items.into_iter().map(|item| self.resolve(item)).collect()
+                }
+                _ => Vec::new(),
+            };
+            let fallback: Option<Value> = object.remove("fallback");
+
+            let template: &str = match (self.get(&key), &fallback) {
+                (Some(template), _) => template,
+                (None, Some(Value::String(fallback))) => fallback,
+                (None, _) => &key,
+            };
+
+            let mut pieces: Vec<Value> = fill_template(template, &args);
+            pieces.append(&mut extra);
+            extra = pieces;
+            object.insert(String::from("text"), Value::String(String::new()));
+        }
+
+        if !extra.is_empty() {
+            object.insert(String::from("extra"), Value::Array(extra));
+        }
+        Value::Object(object)
+    }
+}
+
+/// Splits a template like `%s was slain by %2$s` into literal text and the matching arguments.
+fn fill_template(template: &str, args: &[Value]) -> Vec<Value> {
+    let mut pieces: Vec<Value> = Vec::new();
+    let mut literal: String = String::new();
+    let mut next_arg: usize = 0;
+    let mut rest: &str = template;
+
+    while let Some(i) = rest.find('%') {
+        literal += &rest[..i];
+        rest = &rest[i + 1..];
+
+        if let Some(after) = rest.strip_prefix('%') {
+            literal.push('%');
+            rest = after;
+            continue;
+        }
+
+        let digits: usize = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+        let index: usize;
+        if digits > 0 && rest[digits..].starts_with("$s") {
+            index = rest[..digits]
+                .parse::<usize>()
+                .unwrap_or(1)
+                .saturating_sub(1);
+            rest = &rest[digits + 2..];
+        } else if let Some(after) = rest.strip_prefix('s') {
+            index = next_arg;
+            next_arg += 1;
+            rest = after;
+        } else {
+            literal.push('%');
+            continue;
+        }
+
+        if !literal.is_empty() {
+            pieces.push(Value::String(std::mem::take(&mut literal)));
+        }
+        pieces.push(match args.get(index) {
+            Some(Value::String(text)) => Value::String(text.clone()),
+            Some(Value::Object(object)) => Value::Object(object.clone()),
+            Some(Value::Null) | None => Value::String(String::new()),
+            Some(other) => Value::String(other.to_string()),
+        });
+    }
+
+    literal += rest;
+    if !literal.is_empty() {
+        pieces.push(Value::String(literal));
+    }
+    pieces
+}
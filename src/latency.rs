@@ -0,0 +1,55 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+// How many echoes the average, fastest and slowest cover
+const KEPT_ECHOES: usize = 20;
+
+/// Round trip times of the connection, from `Client::latency`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Latency {
+    /// The last echo measured with `Client::measure_latency`.
+    pub last: Option<Duration>,
+    /// Over the last 20 echoes.
+    pub average: Option<Duration>,
+    pub min: Option<Duration>,
+    pub max: Option<Duration>,
+    /// How many echoes those cover.
+    pub samples: usize,
+    /// How long the last keep alive took from arriving to its answer being written.
+    pub keep_alive_reply: Option<Duration>,
+}
+
+#[derive(Default)]
+pub(crate) struct LatencyTracker {
+    // Oldest first
+    echoes: VecDeque<Duration>,
+    keep_alive_reply: Option<Duration>,
+}
+
+impl LatencyTracker {
+    pub(crate) fn add_echo(&mut self, round_trip: Duration) {
+        if self.echoes.len() == KEPT_ECHOES {
+            self.echoes.pop_front();
+        }
+        self.echoes.push_back(round_trip);
+    }
+
+    pub(crate) fn set_keep_alive_reply(&mut self, reply: Duration) {
+        self.keep_alive_reply = Some(reply);
+    }
+
+    pub(crate) fn latency(&self) -> Latency {
+        let samples: usize = self.echoes.len();
+        Latency {
+            last: self.echoes.back().copied(),
+            average: match samples {
+                0 => None,
+                _ => Some(self.echoes.iter().sum::<Duration>() / samples as u32),
+            },
+            min: self.echoes.iter().min().copied(),
+            max: self.echoes.iter().max().copied(),
+            samples,
+            keep_alive_reply: self.keep_alive_reply,
+        }
+    }
+}
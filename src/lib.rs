@@ -0,0 +1,63 @@
+mod antiafk;
+mod bossbar;
+mod capture;
+mod client;
+mod commands;
+mod commandtree;
+mod configuration;
+#[cfg(feature = "discord")]
+pub mod discord;
+mod dns;
+mod error;
+mod event;
+mod hash;
+mod helper;
+mod http;
+mod interaction;
+mod lan;
+mod latency;
+mod mcstring;
+pub mod mitm;
+pub mod mojang;
+mod nbt;
+pub mod packet;
+mod packetnames;
+mod player;
+pub mod protocol;
+mod proxy;
+pub mod query;
+mod scoreboard;
+mod socket;
+pub mod status;
+#[cfg(feature = "telegram")]
+pub mod telegram;
+#[cfg(feature = "testserver")]
+pub mod testserver;
+#[cfg(any(feature = "discord", feature = "telegram"))]
+mod textlimit;
+mod traffic;
+pub mod types;
+pub mod velocity;
+
+pub use antiafk::{AfkAction, AntiAfk};
+pub use bossbar::{BossBar, BossBarColor};
+pub use capture::{Capture, CaptureReader, CaptureState, CapturedFrame};
+pub use client::{
+    ChatMode, Client, ClientConfig, ClientSettings, ConnectPolicy, IpPreference, LoginPlugins,
+    ReconnectPolicy, VirtualHost,
+};
+pub use commands::{COMMAND_PREFIX, ClientCommands, CommandFlow};
+pub use commandtree::{CommandNode, CommandTree, NodeKind};
+pub use error::ClientError;
+pub use event::Event;
+pub use helper::{DEFAULT_PORT, DEFAULT_PROTOCOL, fetch_status, init_connection, request_status};
+pub use interaction::{ClickAction, Interaction};
+pub use lan::{LanServer, discover_lan};
+pub use latency::Latency;
+pub use player::{GameMode, Health, Identity, PlayerInfo, Position};
+pub use proxy::Socks5Proxy;
+pub use query::{QueryResponse, query};
+pub use scoreboard::{Sidebar, SidebarLine};
+pub use socket::SocketOptions;
+pub use status::ServerStatus;
+pub use traffic::PacketCount;
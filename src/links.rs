@@ -0,0 +1,128 @@
+use std::collections::VecDeque;
+use std::io;
+use std::process::{Child, Command};
+use std::sync::Mutex;
+use std::thread;
+
+use mclient_project::{ClickAction, Interaction};
+
+const MAX_LINKS: usize = 100;
+
+// Characters that end a sentence more often than a URL
+const TRAILING_PUNCTUATION: &[char] =
+    &['.', ',', ';', ':', '!', '?', ')', ']', '}', '>', '"', '\''];
+
+/// Links seen in chat, numbered in the order they arrived for `.open`.
+pub struct Links {
+    links: Mutex<VecDeque<(usize, String)>>,
+}
+
+impl Links {
+    pub fn new() -> Links {
+        Links {
+            links: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Numbers the links in a chat line, from its text and its `open_url` click
+    /// actions. A link that was seen before keeps its number.
+    pub fn collect(&self, text: &str, interactions: &[Interaction]) -> Vec<usize> {
+        let mut urls: Vec<String> = find_urls(text);
+        for interaction in interactions {
+            if let Some(ClickAction::OpenUrl(url)) = &interaction.click
+                && is_web_url(url)
+            {
+                urls.push(url.clone());
+            }
+        }
+
+        let mut links = self.links.lock().unwrap();
+        let mut numbers: Vec<usize> = Vec::new();
+        for url in urls {
+            let number: usize = match links.iter().find(|(_, known)| *known == url) {
+                Some((number, _)) => *number,
+                None => {
+                    let number: usize = links.back().map_or(1, |(number, _)| number + 1);
+                    if links.len() == MAX_LINKS {
+                        links.pop_front();
+                    }
+                    links.push_back((number, url));
+                    number
+                }
+            };
+            if !numbers.contains(&number) {
+                numbers.push(number);
+            }
+        }
+        numbers
+    }
+
+    pub fn get(&self, number: usize) -> Option<String> {
+        self.links
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(known, _)| *known == number)
+            .map(|(_, url)| url.clone())
+    }
+
+    /// The last `count` links with their numbers, oldest first.
+    pub fn last(&self, count: usize) -> Vec<(usize, String)> {
+        let links = self.links.lock().unwrap();
+        links
+            .iter()
+            .skip(links.len().saturating_sub(count))
+            .cloned()
+            .collect()
+    }
+}
+
+/// The http(s) URLs in plain text. Words starting with `www.` count as well.
+pub fn find_urls(text: &str) -> Vec<String> {
+    let mut urls: Vec<String> = Vec::new();
+    for word in text.split_whitespace() {
+        let start: Option<usize> = ["https://", "http://", "www."]
+            .iter()
+            .filter_map(|prefix| word.to_ascii_lowercase().find(prefix))
+            .min();
+        let Some(start) = start else {
+            continue;
+        };
+
+        let url: &str = word[start..].trim_end_matches(TRAILING_PUNCTUATION);
+        let url: String = match is_web_url(url) {
+            true => String::from(url),
+            false => format!("https://{}", url),
+        };
+        if has_domain(&url) && !urls.contains(&url) {
+            urls.push(url);
+        }
+    }
+    urls
+}
+
+// At least two labels, so "www." or "http://localhost" alone isn't taken for a link
+fn has_domain(url: &str) -> bool {
+    let rest: &str = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let host: &str = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    host.split('.').filter(|label| !label.is_empty()).count() >= 2
+}
+
+fn is_web_url(url: &str) -> bool {
+    let url: String = url.to_ascii_lowercase();
+    url.starts_with("https://") || url.starts_with("http://")
+}
+
+/// Opens `url` in the default browser.
+pub fn open(url: &str) -> io::Result<()> {
+    let mut child: Child = if cfg!(target_os = "macos") {
+        Command::new("open").arg(url).spawn()?
+    } else if cfg!(windows) {
+        // The empty title keeps `start` from taking a quoted URL as the window title
+        Command::new("cmd").args(["/C", "start", "", url]).spawn()?
+    } else {
+        Command::new("xdg-open").arg(url).spawn()?
+    };
+    thread::spawn(move || child.wait());
+    Ok(())
+}
@@ -0,0 +1,263 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::{self, Write as _};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Metadata, Subscriber};
+
+use crate::chatformat;
+use crate::clock::LocalTime;
+
+// Diagnostics from the library (connecting, logging in, packets) and the client go
+// through `tracing`. Without a log file they're printed like the client's own messages,
+// with a file they're written there with a timestamp and stay out of the chat.
+//
+//   terminal: [MClient] Attempting to connect to 127.0.0.1:25565! (1)
+//   -v:       [MClient] DEBUG connection{username=Tester12 host=127.0.0.1 port=25565}: Connected!
+//   file:     2025-10-15 18:00:00  INFO connection{...}:login{protocol=767}: Compression enabled threshold=256
+
+thread_local! {
+    // The spans entered on this thread, innermost last
+    static ENTERED: RefCell<Vec<u64>> = const { RefCell::new(Vec::new()) };
+}
+
+enum Sink {
+    Terminal(fn(&str)),
+    File(Mutex<File>),
+}
+
+struct SpanData {
+    name: &'static str,
+    fields: String,
+    parent: Option<u64>,
+    references: usize,
+}
+
+struct Logger {
+    level: Level,
+    sink: Sink,
+    spans: Mutex<HashMap<u64, SpanData>>,
+    next_id: AtomicU64,
+}
+
+/// Sends events at `level` and above to `log_file` (appended to), or to `print` if
+/// there is none. Events from other crates only show up from warnings on.
+pub fn init(level: Level, log_file: Option<&Path>, print: fn(&str)) -> io::Result<()> {
+    let sink: Sink = match log_file {
+        Some(path) => Sink::File(Mutex::new(
+            OpenOptions::new().create(true).append(true).open(path)?,
+        )),
+        None => Sink::Terminal(print),
+    };
+    let logger: Logger = Logger {
+        level,
+        sink,
+        spans: Mutex::new(HashMap::new()),
+        next_id: AtomicU64::new(1),
+    };
+    tracing::subscriber::set_global_default(logger).map_err(io::Error::other)
+}
+
+/// One `-v` more than `level`.
+pub fn more_verbose(level: Level) -> Level {
+    match level {
+        Level::ERROR => Level::WARN,
+        Level::WARN => Level::INFO,
+        Level::INFO => Level::DEBUG,
+        _ => Level::TRACE,
+    }
+}
+
+pub fn parse_level(level: &str) -> Result<Level, String> {
+    level.parse().map_err(|_| {
+        format!(
+            "Unknown log level '{}' (error, warn, info, debug or trace)",
+            level
+        )
+    })
+}
+
+impl Logger {
+    // `connection{host=...}:login{...}` for the spans around an event
+    fn scope(&self, mut current: Option<u64>) -> String {
+        let spans = self.spans.lock().unwrap();
+        let mut names: Vec<String> = Vec::new();
+        while let Some(span) = current.and_then(|id| spans.get(&id)) {
+            names.push(match span.fields.is_empty() {
+                true => String::from(span.name),
+                false => format!("{}{{{}}}", span.name, span.fields.trim_start()),
+            });
+            current = span.parent;
+        }
+        names.reverse();
+        names.join(":")
+    }
+
+    fn write(&self, level: Level, scope: &str, message: &str) {
+        let scope: String = match scope.is_empty() {
+            true => String::new(),
+            false => format!("{}: ", scope),
+        };
+        match &self.sink {
+            Sink::Terminal(print) => match self.level > Level::INFO {
+                true => print(&format!("[MClient] {} {}{}", level, scope, message)),
+                false => print(&format!("[MClient] {}", message)),
+            },
+            Sink::File(file) => {
+                let now: LocalTime = LocalTime::now();
+                let line: String = format!(
+                    "{} {} {:>5} {}{}",
+                    now.date(),
+                    now.time(),
+                    level,
+                    scope,
+                    chatformat::strip_ansi(message)
+                );
+                // Nowhere left to report a failing log file
+                let _ = writeln!(file.lock().unwrap(), "{}", line);
+            }
+        }
+    }
+}
+
+fn current_span() -> Option<u64> {
+    ENTERED.with(|entered| entered.borrow().last().copied())
+}
+
+impl Subscriber for Logger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        let ours: bool = metadata.target().starts_with("mclient_project")
+            || metadata.target().starts_with("mc_chat");
+        *metadata.level() <= self.level && (ours || *metadata.level() <= Level::WARN)
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        Some(LevelFilter::from_level(self.level))
+    }
+
+    fn new_span(&self, attributes: &Attributes<'_>) -> Id {
+        let id: u64 = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut fields: Fields = Fields::default();
+        attributes.record(&mut fields);
+        let parent: Option<u64> = match attributes.parent() {
+            Some(parent) => Some(parent.into_u64()),
+            None if attributes.is_contextual() => current_span(),
+            None => None,
+        };
+        let mut spans = self.spans.lock().unwrap();
+        // Children keep their parents around, so the scope can still be printed
+        if let Some(parent) = parent.and_then(|parent| spans.get_mut(&parent)) {
+            parent.references += 1;
+        }
+        spans.insert(
+            id,
+            SpanData {
+                name: attributes.metadata().name(),
+                fields: fields.fields,
+                parent,
+                references: 1,
+            },
+        );
+        Id::from_u64(id)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        let mut fields: Fields = Fields::default();
+        values.record(&mut fields);
+        if let Some(span) = self.spans.lock().unwrap().get_mut(&span.into_u64()) {
+            span.fields += &fields.fields;
+        }
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields: Fields = Fields::default();
+        event.record(&mut fields);
+        let parent: Option<u64> = match event.parent() {
+            Some(parent) => Some(parent.into_u64()),
+            None if event.is_contextual() => current_span(),
+            None => None,
+        };
+        let scope: String = self.scope(parent);
+        self.write(
+            *event.metadata().level(),
+            &scope,
+            &format!("{}{}", fields.message, fields.fields),
+        );
+    }
+
+    fn enter(&self, span: &Id) {
+        ENTERED.with(|entered| entered.borrow_mut().push(span.into_u64()));
+    }
+
+    fn exit(&self, span: &Id) {
+        ENTERED.with(|entered| {
+            let mut entered = entered.borrow_mut();
+            if let Some(i) = entered.iter().rposition(|id| *id == span.into_u64()) {
+                entered.remove(i);
+            }
+        });
+    }
+
+    fn clone_span(&self, span: &Id) -> Id {
+        if let Some(data) = self.spans.lock().unwrap().get_mut(&span.into_u64()) {
+            data.references += 1;
+        }
+        span.clone()
+    }
+
+    fn try_close(&self, span: Id) -> bool {
+        let mut spans = self.spans.lock().unwrap();
+        let mut closing: Option<u64> = Some(span.into_u64());
+        let mut closed: bool = false;
+        while let Some(id) = closing {
+            let Some(data) = spans.get_mut(&id) else {
+                break;
+            };
+            data.references -= 1;
+            if data.references > 0 {
+                break;
+            }
+            closing = spans.remove(&id).and_then(|data| data.parent);
+            closed |= id == span.into_u64();
+        }
+        closed
+    }
+}
+
+// The message on its own, the other fields as ` name=value`
+#[derive(Default)]
+struct Fields {
+    message: String,
+    fields: String,
+}
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "message" => self.message += value,
+            name => {
+                let _ = write!(self.fields, " {}={}", name, value);
+            }
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        match field.name() {
+            "message" => {
+                let _ = write!(self.message, "{:?}", value);
+            }
+            name => {
+                let _ = write!(self.fields, " {}={:?}", name, value);
+            }
+        }
+    }
+}
@@ -1,15 +1,2493 @@
-mod helper;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Write, stdin, stdout};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
-static IP: &str = "127.0.0.1";
-static PORT: u16 = 25565;
-static USERNAME: &str = "Tester12";
+use azalea_chat::FormattedText;
+use mclient_project::mojang::{self, Profile};
+use mclient_project::packet::ChatKind;
+use mclient_project::protocol::PacketTable;
+use mclient_project::velocity;
+use mclient_project::{
+    AntiAfk, BossBar, BossBarColor, COMMAND_PREFIX, Capture, ClickAction, Client, ClientCommands,
+    ClientConfig, ClientError, CommandFlow, DEFAULT_PORT, Event, Identity, Interaction, LanServer,
+    Latency, PacketCount, PlayerInfo, ServerStatus, Sidebar, VirtualHost,
+};
+use tracing::{info, warn};
+
+mod api;
+mod bots;
+#[cfg(feature = "discord")]
+mod bridge;
+mod chatformat;
+mod chatlog;
+mod cli;
+mod clock;
+mod config;
+mod favicon;
+mod filter;
+mod inspect;
+mod irc;
+mod lang;
+mod links;
+mod logging;
+mod mention;
+mod ndjson;
+mod readline;
+mod regex;
+mod replay;
+mod responder;
+mod schedule;
+mod script;
+mod scrollback;
+mod seen;
+mod serverlist;
+mod shutdown;
+#[cfg(feature = "telegram")]
+mod telegrambridge;
+mod tui;
+mod websocket;
+mod whisper;
+
+use api::ApiServer;
+use bots::{Bot, BotSpec, Bots, JOIN_GRACE, Removed};
+use chatformat::ChatFormat;
+use chatlog::ChatLog;
+use cli::{Args, Command};
+use clock::LocalTime;
+use filter::ChatFilter;
+use inspect::InspectOptions;
+use irc::IrcServer;
+use lang::Language;
+use links::Links;
+use mention::Mentions;
+use readline::LineEditor;
+use regex::Regex;
+use responder::AutoResponder;
+use schedule::{Cron, ScheduleSpec, Scheduler, When};
+use script::{Scripts, Source};
+use scrollback::Scrollback;
+use seen::{PlayerHistory, Sighting};
+use serverlist::ServerEntry;
+use tui::Tui;
+use whisper::{Conversation, Direction};
+
+const EXIT_CONNECTION_LOST: i32 = 1;
+const EXIT_KICKED: i32 = 3;
+// `replay` found a packet that doesn't parse
+const EXIT_REPLAY_FAILED: i32 = 1;
+
+// `.list` fills this many rows per page, in as many columns as fit the width
+const PLAYER_LIST_ROWS: usize = 20;
+const PLAYER_LIST_WIDTH: usize = 80;
+
+// How long `send` waits for the server to spawn the player
+const SPAWN_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Longer payloads of malformed packets are cut off in the dump
+const MAX_HEX_DUMP_BYTES: usize = 256;
+
+// Ignored packets come often, so --debug-packets only dumps the start of each
+const MAX_IGNORED_DUMP_BYTES: usize = 64;
+
+// How often the input loop checks for Ctrl-C or SIGTERM while waiting for a line
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// How often the round trip to the server is measured in the background
+const LATENCY_INTERVAL: Duration = Duration::from_secs(30);
+
+// Set by --no-color and --plain
+static NO_COLOR: AtomicBool = AtomicBool::new(false);
+// Set by --json, which keeps stdout for events
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+/// Prints a line to the terminal, or to the chat pane when the TUI is running.
+fn output(line: &str) {
+    print_line(line, false);
+}
+
+// Log events without --log-file: between the chat lines in the TUI and the line editor,
+// on stderr otherwise, so chat piped somewhere else stays clean
+fn diagnostic(line: &str) {
+    print_line(line, true);
+}
+
+fn print_line(line: &str, diagnostic: bool) {
+    let stripped: String;
+    let line: &str = match NO_COLOR.load(Ordering::Relaxed) {
+        true => {
+            stripped = chatformat::strip_ansi(line);
+            &stripped
+        }
+        false => line,
+    };
+
+    if JSON_OUTPUT.load(Ordering::Relaxed) {
+        eprintln!("{}", line);
+    } else if let Some(tui) = tui::get() {
+        tui.push_line(line);
+    } else if let Some(editor) = readline::get() {
+        editor.print_above(line);
+    } else if diagnostic {
+        eprintln!("{}", line);
+    } else {
+        println!("{}", line);
+    }
+}
+
+/// Reads a line from the line editor, or straight from stdin if it isn't a terminal.
+/// Returns `None` once the input is closed.
+fn read_input() -> Option<String> {
+    if let Some(editor) = readline::get() {
+        return editor.read_line();
+    }
+
+    let mut buffer: String = String::new();
+    match stdin().read_line(&mut buffer) {
+        Ok(0) => None,
+        Ok(_) => Some(buffer),
+        Err(_) => panic!("Error while reading from terminal!"),
+    }
+}
+
+fn restore_terminal() {
+    if let Some(tui) = tui::get() {
+        tui.stop();
+    }
+    if let Some(editor) = readline::get() {
+        editor.stop();
+    }
+}
+
+fn exit_with_error(e: &ClientError) -> ! {
+    restore_terminal();
+
+    // stdout only carries JSON objects with --json
+    if JSON_OUTPUT.load(Ordering::Relaxed) {
+        println!("{}", ndjson::disconnected_line(e, None));
+        eprintln!("{}", disconnect_message(e));
+    } else {
+        println!("{}", disconnect_message(e));
+    }
+    match e.kind() {
+        ClientError::Kicked(_) => std::process::exit(EXIT_KICKED),
+        _ => std::process::exit(EXIT_CONNECTION_LOST),
+    }
+}
+
+fn disconnect_message(e: &ClientError) -> String {
+    match e.kind() {
+        ClientError::Kicked(reason) => {
+            format!("[MClient] Kicked from the server: {}", reason.to_ansi())
+        }
+        _ => format!("[MClient] Connection closed: {}", e),
+    }
+}
+
+/// The `status` subcommand.
+fn print_status(args: &Args, language: &Language) -> ! {
+    if let Some(path) = &args.server_list {
+        print_server_list(path, args, language);
+    }
+
+    let status: Result<ServerStatus, ClientError> = match args.json {
+        true => mclient_project::fetch_status(
+            &args.host,
+            args.port,
+            args.proxy.as_ref(),
+            &args.connect,
+            &args.virtual_host,
+        )
+        .inspect(|status| println!("{}", ndjson::status_line(status, language))),
+        false => mclient_project::request_status(
+            &args.host,
+            args.port,
+            args.proxy.as_ref(),
+            &args.connect,
+            &args.virtual_host,
+        )
+        .inspect(|status| show_favicon(status, args)),
+    };
+    if let Err(e) = status {
+        match args.json {
+            true => {
+                println!("{}", ndjson::down_line(&e));
+                eprintln!("[MClient] Status request failed: {}", e);
+            }
+            false => println!("[MClient] Status request failed: {}", e),
+        }
+        std::process::exit(EXIT_CONNECTION_LOST);
+    }
+    std::process::exit(0);
+}
+
+// Saves the server icon to --favicon-out and previews it with --favicon-preview
+fn show_favicon(status: &ServerStatus, args: &Args) {
+    let png: Vec<u8> = match status.favicon_png() {
+        Ok(Some(png)) => png,
+        Ok(None) => {
+            println!("The server does not have a server-icon!");
+            return;
+        }
+        Err(e) => {
+            println!("[MClient] Invalid server icon: {}", e);
+            return;
+        }
+    };
+
+    if let Some(path) = &args.favicon_out {
+        match fs::write(path, &png) {
+            Ok(()) => println!("The server image has been saved to {}!", path.display()),
+            Err(e) => println!("[MClient] Couldn't save the server icon: {}", e),
+        }
+    }
+    if args.favicon_preview {
+        match favicon::preview(&png) {
+            Ok(preview) => output(&preview),
+            Err(e) => println!("[MClient] Couldn't show the server icon: {}", e),
+        }
+    }
+}
+
+/// `status --servers`: checks every server in the file at once. Exits with 0 only if
+/// all of them answered.
+fn print_server_list(path: &Path, args: &Args, language: &Language) -> ! {
+    let servers: Vec<ServerEntry> = match serverlist::read_server_list(path) {
+        Ok(servers) => servers,
+        Err(e) => {
+            println!("[MClient] {}", e);
+            std::process::exit(2);
+        }
+    };
+
+    let results: Vec<Result<ServerStatus, ClientError>> =
+        serverlist::check_all(&servers, args.proxy.as_ref(), &args.connect);
+    if args.json {
+        for (server, result) in servers.iter().zip(&results) {
+            println!(
+                "{}",
+                ndjson::server_line(&server.address(), result, language)
+            );
+        }
+    } else {
+        output(&serverlist::create_table(&servers, &results));
+    }
+
+    match results.iter().all(Result::is_ok) {
+        true => std::process::exit(0),
+        false => std::process::exit(EXIT_CONNECTION_LOST),
+    }
+}
+
+/// The `ping` subcommand: `args.ping_count` status pings, a second apart.
+fn ping(args: &Args) -> ! {
+    let mut latencies: Vec<Duration> = Vec::new();
+    for i in 0..args.ping_count {
+        if i > 0 {
+            thread::sleep(Duration::from_secs(1));
+        }
+        match mclient_project::fetch_status(
+            &args.host,
+            args.port,
+            args.proxy.as_ref(),
+            &args.connect,
+            &args.virtual_host,
+        ) {
+            Ok(status) => match status.latency {
+                Some(latency) => {
+                    println!(
+                        "Reply from {}: time={} ms",
+                        args.address(),
+                        latency.as_millis()
+                    );
+                    latencies.push(latency);
+                }
+                None => println!(
+                    "Reply from {}: status only, no answer to the ping",
+                    args.address()
+                ),
+            },
+            Err(e) => println!("No reply from {}: {}", args.address(), e),
+        }
+    }
+
+    println!("{} pings, {} replies", args.ping_count, latencies.len());
+    let (Some(min), Some(max)) = (latencies.iter().min(), latencies.iter().max()) else {
+        std::process::exit(EXIT_CONNECTION_LOST);
+    };
+    let average: Duration = latencies.iter().sum::<Duration>() / latencies.len() as u32;
+    println!(
+        "min/avg/max = {}/{}/{} ms",
+        min.as_millis(),
+        average.as_millis(),
+        max.as_millis()
+    );
+    std::process::exit(0);
+}
+
+/// The `query` subcommand.
+fn print_query(args: &Args) -> ! {
+    let query_port: u16 = args.query_port.unwrap_or(args.port.unwrap_or(DEFAULT_PORT));
+    println!("Querying {}:{}!", args.host, query_port);
+    match mclient_project::query(&args.host, query_port) {
+        Ok(response) => println!("{}", response),
+        Err(e) => {
+            println!("[MClient] Query failed: {}", e);
+            std::process::exit(EXIT_CONNECTION_LOST);
+        }
+    }
+    std::process::exit(0);
+}
+
+/// The `replay` subcommand: exits with 1 if a captured packet doesn't parse anymore.
+fn replay(args: &Args) -> ! {
+    let path: &Path = args.replay_file.as_deref().unwrap_or(Path::new(""));
+    match replay::replay(path, output) {
+        Ok(0) => std::process::exit(0),
+        Ok(_) => std::process::exit(EXIT_REPLAY_FAILED),
+        Err(e) => {
+            output(&format!(
+                "[MClient] Couldn't replay {}: {}",
+                path.display(),
+                e
+            ));
+            std::process::exit(2);
+        }
+    }
+}
+
+/// The `proxy` subcommand, which runs until it's stopped.
+fn inspect_traffic(args: &Args) -> ! {
+    let options: InspectOptions = InspectOptions {
+        hex_dump: match args.proxy_hex {
+            true => Some(|data| create_hex_dump(data, MAX_IGNORED_DUMP_BYTES)),
+            false => None,
+        },
+        hide: args.proxy_hide.clone(),
+    };
+    if let Err(e) = inspect::run(
+        args.proxy_listen,
+        &args.host,
+        args.port,
+        args.proxy.as_ref(),
+        &args.connect,
+        options,
+        output,
+    ) {
+        output(&format!(
+            "[MClient] Couldn't listen on {}: {}",
+            args.proxy_listen, e
+        ));
+        std::process::exit(2);
+    }
+    std::process::exit(0);
+}
+
+/// The `watch` subcommand: asks for the status every `args.watch_interval` and runs
+/// `args.alert_command` whenever the server goes down or comes back.
+fn watch(args: &Args, language: &Language) -> ! {
+    let mut up: Option<bool> = None;
+    let mut last_motd: Option<String> = None;
+    loop {
+        let now: String = LocalTime::now().time();
+        match mclient_project::fetch_status(
+            &args.host,
+            args.port,
+            args.proxy.as_ref(),
+            &args.connect,
+            &args.virtual_host,
+        ) {
+            Ok(status) => {
+                if args.json {
+                    println!("{}", ndjson::status_line(&status, language));
+                } else {
+                    let players: String = match &status.players {
+                        Some(players) => format!("{}/{}", players.online, players.max),
+                        None => String::from("?"),
+                    };
+                    let latency: String = match status.latency {
+                        Some(latency) => format!("{} ms", latency.as_millis()),
+                        None => String::from("? ms"),
+                    };
+                    output(&format!("[{}] {} players, {}", now, players, latency));
+
+                    let motd: String = language.translate(&status.description).to_ansi();
+                    if last_motd.as_ref() != Some(&motd) {
+                        output(&format!("[{}] MOTD: {}\x1b[0m", now, motd));
+                        last_motd = Some(motd);
+                    }
+                }
+                if up == Some(false) {
+                    run_alert(args, "up");
+                }
+                up = Some(true);
+            }
+            Err(e) => {
+                match args.json {
+                    true => println!("{}", ndjson::down_line(&e)),
+                    false => output(&format!("[{}] Server is down: {}", now, e)),
+                }
+                if up == Some(true) {
+                    run_alert(args, "down");
+                }
+                up = Some(false);
+            }
+        }
+        thread::sleep(args.watch_interval);
+    }
+}
+
+// Runs --alert through the shell on its own thread, with MC_STATE, MC_HOST and MC_PORT set
+fn run_alert(args: &Args, state: &str) {
+    let Some(command) = &args.alert_command else {
+        return;
+    };
+    let mut shell: process::Command = match cfg!(windows) {
+        true => {
+            let mut shell: process::Command = process::Command::new("cmd");
+            shell.args(["/C", command]);
+            shell
+        }
+        false => {
+            let mut shell: process::Command = process::Command::new("sh");
+            shell.args(["-c", command]);
+            shell
+        }
+    };
+    shell
+        .env("MC_STATE", state)
+        .env("MC_HOST", &args.host)
+        .env("MC_PORT", args.port.unwrap_or(DEFAULT_PORT).to_string());
+
+    match shell.spawn() {
+        Ok(mut child) => {
+            thread::spawn(move || child.wait());
+        }
+        Err(e) => output(&format!("[MClient] Couldn't run the alert command: {}", e)),
+    }
+}
+
+// The login settings from `args`, for `username` on `host:port`
+fn create_config(
+    args: &Args,
+    host: &str,
+    port: Option<u16>,
+    username: &str,
+    protocol: i32,
+) -> ClientConfig {
+    let mut config: ClientConfig = ClientConfig::new(host, port, username);
+    config.protocol = protocol;
+    config.connect = args.connect.clone();
+    config.virtual_host = args.virtual_host.clone();
+    config.socket = args.socket.clone();
+    config.reconnect = args.reconnect.clone();
+    config.proxy = args.proxy.clone();
+    config.keep_alive_timeout = args.keep_alive_timeout;
+    config.settings = args.settings.clone();
+    config.brand = args.brand.clone();
+    config.bungee_forwarding = args.bungee_spoof;
+    config.accept_resource_packs = args.accept_resource_packs;
+    config.chat_rate = args.chat_rate;
+    config.report_ignored_packets = args.debug_packets;
+    config.anti_afk = args.anti_afk_interval.map(|interval| AntiAfk {
+        interval,
+        actions: args.anti_afk_actions.clone(),
+    });
+    if let Some(secret) = &args.velocity_secret {
+        velocity::register(
+            &mut config.login_plugins,
+            secret,
+            username,
+            IpAddr::from([127, 0, 0, 1]),
+        );
+    }
+    config
+}
+
+// Logs a --bot in. Bots on the main server use its protocol, the others ask their own
+// server unless the protocol was given.
+fn connect_bot(spec: &BotSpec, args: &Args, protocol: i32) -> Result<Bot, ClientError> {
+    let mut config: ClientConfig = match &spec.server {
+        None => create_config(args, &args.host, args.port, &spec.username, protocol),
+        Some((host, port)) => {
+            let protocol: i32 = match (args.protocol, args.skip_status) {
+                (Some(protocol), _) => protocol,
+                (None, true) => mclient_project::DEFAULT_PROTOCOL,
+                (None, false) => {
+                    mclient_project::fetch_status(
+                        host,
+                        *port,
+                        args.proxy.as_ref(),
+                        &args.connect,
+                        &VirtualHost::default(),
+                    )?
+                    .version
+                    .protocol
+                }
+            };
+            create_config(args, host, *port, &spec.username, protocol)
+        }
+    };
+    if spec.server.is_some() {
+        // --handshake-host is meant for the main server
+        config.virtual_host = VirtualHost::default();
+    }
+
+    let address: String = format!("{}:{}", config.host, config.port.unwrap_or(DEFAULT_PORT));
+    let protocol: i32 = config.protocol;
+    let client: Client = Client::connect_with(config)?;
+    if !args.json {
+        output(&logged_in_line(&client.identity(), &spec.username));
+    }
+    Ok(Bot {
+        name: client.identity().username,
+        address,
+        protocol,
+        client: Arc::new(client),
+    })
+}
+
+// Proxies and plugins may log the player in with another UUID
+fn logged_in_line(identity: &Identity, username: &str) -> String {
+    let origin: &str = match identity.uuid == mojang::offline_uuid(username) {
+        true => "offline UUID",
+        false => "UUID assigned by the server",
+    };
+    format!(
+        "[MClient] Logged in as {} ({} {})",
+        identity.username,
+        origin,
+        mojang::format_uuid(identity.uuid)
+    )
+}
+
+/// The `send` subcommand: sends `message` once the player has spawned, then exits.
+fn send_once(client: &Client, message: &str, args: &Args) -> ! {
+    client.on_disconnect(|e| exit_with_error(e));
+
+    match client.wait_for_spawn(SPAWN_TIMEOUT) {
+        Ok(()) => {}
+        Err(e @ ClientError::Timeout(_)) => exit_with_error(&e),
+        // The disconnect handler reports the reason and exits
+        Err(_) => loop {
+            thread::park();
+        },
+    }
+
+    let message: String = args.color_codes.apply(message);
+    if message.starts_with('/')
+        && let Some(Err(e)) = client.command_tree().map(|tree| tree.validate(&message))
+    {
+        println!("[MClient] {}", e);
+        std::process::exit(2);
+    }
+
+    if let Err(e) = client
+        .send_chat(&message)
+        .and_then(|()| client.disconnect())
+    {
+        exit_with_error(&e);
+    }
+    std::process::exit(0);
+}
+
+// The player with `own_uuid` isn't listed, nor those whose name or display name doesn't
+// contain `filter`. Players without a display name are shown with their team prefix and
+// suffix from `decorate`. Sorted by name down the columns, like `ls`
+fn create_players_string(
+    players: &HashMap<u128, PlayerInfo>,
+    own_uuid: u128,
+    filter: &str,
+    page: usize,
+    language: &Language,
+    decorate: impl Fn(&str) -> Option<FormattedText>,
+) -> String {
+    let lowercase: String = filter.to_lowercase();
+    let mut players: Vec<&PlayerInfo> = players
+        .iter()
+        .filter(|(uuid, _)| **uuid != own_uuid)
+        .map(|(_, player)| player)
+        .filter(|player| {
+            player.name.to_lowercase().contains(&lowercase)
+                || player.display_name.as_ref().is_some_and(|display_name| {
+                    let text: String = language.translate(display_name).to_string();
+                    text.to_lowercase().contains(&lowercase)
+                })
+        })
+        .collect();
+    players.sort_by_key(|player| player.name.to_lowercase());
+
+    let cells: Vec<String> = players
+        .iter()
+        .map(|player| {
+            let display_name: Option<FormattedText> = player
+                .display_name
+                .as_ref()
+                .map(|display_name| language.translate(display_name))
+                .filter(|display_name| display_name.to_string() != player.name);
+
+            let label: String = match (display_name, decorate(&player.name)) {
+                (Some(display_name), _) => {
+                    format!("{}\x1b[0m ({})", display_name.to_ansi(), player.name)
+                }
+                // The name is in there already
+                (None, Some(decorated)) => {
+                    format!("{}\x1b[0m", language.translate(&decorated).to_ansi())
+                }
+                (None, None) => player.name.clone(),
+            };
+            format!("{} - {}ms", label, player.latency)
+        })
+        .collect();
+
+    let cell_width: usize = cells
+        .iter()
+        .map(|cell| chatformat::visible_width(cell))
+        .max()
+        .unwrap_or(0)
+        + 2;
+    // The rows are indented by two spaces
+    let columns: usize = ((PLAYER_LIST_WIDTH - 2) / cell_width).max(1);
+    let per_page: usize = columns * PLAYER_LIST_ROWS;
+    let pages: usize = cells.len().div_ceil(per_page).max(1);
+    let page: usize = page.clamp(1, pages);
+
+    let mut res: String = match filter.is_empty() {
+        true => format!("Online Players ({} besides you)", cells.len()),
+        false => format!("Online Players matching \"{}\" ({})", filter, cells.len()),
+    };
+    if pages > 1 {
+        let command: String = match filter.is_empty() {
+            true => String::from(".list"),
+            false => format!(".list {}", filter),
+        };
+        res += &format!(", page {}/{} ({} page:<n>)", page, pages, command);
+    }
+    res += ":";
+
+    let on_page: Vec<&String> = cells
+        .iter()
+        .skip((page - 1) * per_page)
+        .take(per_page)
+        .collect();
+    let rows: usize = on_page.len().div_ceil(columns);
+    for row in 0..rows {
+        res += "\n  ";
+        let mut line: String = String::new();
+        for cell in on_page.iter().skip(row).step_by(rows) {
+            line += cell;
+            line += &" ".repeat(cell_width - chatformat::visible_width(cell));
+        }
+        res += line.trim_end();
+    }
+    res
+}
+
+// The display name with its colors, or the name if the server didn't set one
+fn player_label(name: &str, display_name: Option<&FormattedText>, language: &Language) -> String {
+    match display_name {
+        Some(display_name) => format!("{}\x1b[0m", language.translate(display_name).to_ansi()),
+        None => String::from(name),
+    }
+}
+
+/// Ends the session for `.seen`, with the players still online as the last ones seen.
+fn save_seen_players(history: &PlayerHistory, bot: &Bot) {
+    let online: Vec<(u128, String)> = bot
+        .client
+        .online_players()
+        .into_iter()
+        .map(|(uuid, player)| (uuid, player.name))
+        .collect();
+    if let Err(e) = history.save(&bot.address, &online) {
+        warn!("Couldn't save the players seen on {}: {}", bot.address, e);
+    }
+}
+
+fn create_seen_string(history: &PlayerHistory, bot: &Bot, name: &str) -> String {
+    let online: bool = bot
+        .client
+        .online_players()
+        .values()
+        .any(|player| player.name.eq_ignore_ascii_case(name));
+    match history.find(&bot.address, name) {
+        Some(Sighting {
+            name, first_seen, ..
+        }) if online => format!(
+            "{} is online now, first seen {}",
+            name,
+            seen::describe(first_seen)
+        ),
+        Some(sighting) => format!(
+            "{} was last seen {}, first seen {}",
+            sighting.name,
+            seen::describe(sighting.last_seen),
+            seen::describe(sighting.first_seen)
+        ),
+        None => format!("{} hasn't been seen on this server", name),
+    }
+}
+
+// Who joined and left between the end of the last session and now
+fn create_seen_diff_string(history: &PlayerHistory, bot: &Bot) -> String {
+    let Some((ended, before)) = history.last_session(&bot.address) else {
+        return String::from("No earlier session on this server to compare with");
+    };
+    let mut now: Vec<String> = bot
+        .client
+        .online_players()
+        .into_values()
+        .map(|player| player.name)
+        .collect();
+    now.sort_by_key(|name| name.to_lowercase());
+    let missing = |names: &[String], from: &[String]| -> Vec<String> {
+        names
+            .iter()
+            .filter(|name| !from.iter().any(|other| other.eq_ignore_ascii_case(name)))
+            .cloned()
+            .collect()
+    };
+    let joined: Vec<String> = missing(&now, &before);
+    let left: Vec<String> = missing(&before, &now);
+
+    let mut res: String = format!("Since the last session ended {}:", seen::describe(ended));
+    if joined.is_empty() && left.is_empty() {
+        res += " nobody joined or left";
+    }
+    for (label, names) in [("Joined", joined), ("Left", left)] {
+        if !names.is_empty() {
+            res += &format!("\n{} ({}): {}", label, names.len(), names.join(", "));
+        }
+    }
+    res
+}
+
+fn create_tab_list_string(
+    tab_list: &(FormattedText, FormattedText),
+    language: &Language,
+) -> String {
+    let mut res: String = String::from("Tab list:");
+    for (name, text) in [("Header", &tab_list.0), ("Footer", &tab_list.1)] {
+        if text.to_string().trim().is_empty() {
+            res += &format!("\n{}: (none)", name);
+        } else {
+            res += &format!("\n{}:\n{}", name, language.translate(text).to_ansi());
+        }
+    }
+    res
+}
+
+// Plain text lines of a tab list header or footer, without the empty ones
+fn plain_lines(text: &FormattedText, language: &Language) -> Vec<String> {
+    language
+        .translate(text)
+        .to_string()
+        .lines()
+        .map(|line| String::from(line.trim()))
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+fn create_interactions_string(interactions: &[Interaction], language: &Language) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    for interaction in interactions {
+        lines.push(format!("  \"{}\"", interaction.text.trim()));
+        if let Some(hover) = &interaction.hover {
+            for (i, hover_line) in language.translate(hover).to_ansi().lines().enumerate() {
+                let label: &str = if i == 0 { "Hover:" } else { "" };
+                lines.push(format!("    {:6} {}", label, hover_line));
+            }
+        }
+        if let Some(click) = &interaction.click {
+            lines.push(format!("    Click: {}", click));
+        }
+    }
+    lines.join("\n")
+}
+
+fn links_marker(numbers: &[usize]) -> String {
+    let numbers: Vec<String> = numbers.iter().map(|number| number.to_string()).collect();
+    format!(" \x1b[2m[link {}]\x1b[0m", numbers.join(", "))
+}
+
+fn numbered_line(number: usize, line: &str) -> String {
+    format!("\x1b[2m#{}\x1b[0m {}", number, line)
+}
+
+// Like `hexdump -C`: offset, 16 bytes in hex and the printable ones as ASCII
+fn create_hex_dump(data: &[u8], max_bytes: usize) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    for (i, row) in data.chunks(16).take(max_bytes / 16).enumerate() {
+        let hex: Vec<String> = row.iter().map(|byte| format!("{:02x}", byte)).collect();
+        let ascii: String = row
+            .iter()
+            .map(|byte| match byte {
+                0x20..=0x7E => *byte as char,
+                _ => '.',
+            })
+            .collect();
+        lines.push(format!(
+            "{:08x}  {:<47}  |{}|",
+            i * 16,
+            hex.join(" "),
+            ascii
+        ));
+    }
+    if data.len() > max_bytes {
+        lines.push(format!("... {} more bytes", data.len() - max_bytes));
+    }
+    if lines.is_empty() {
+        lines.push(String::from("(empty)"));
+    }
+    lines.join("\n")
+}
+
+fn create_sidebar_string(sidebar: Option<Sidebar>, language: &Language) -> String {
+    let Some(sidebar) = sidebar else {
+        return String::from("The server isn't showing a sidebar.");
+    };
+
+    let texts: Vec<FormattedText> = sidebar
+        .lines
+        .iter()
+        .map(|line| language.translate(&line.text))
+        .collect();
+    let width: usize = texts
+        .iter()
+        .map(|text| text.to_string().chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let mut res: String = format!("Sidebar: {}", language.translate(&sidebar.title).to_ansi());
+    for (line, text) in sidebar.lines.iter().zip(texts) {
+        let padding: usize = width - text.to_string().chars().count();
+        res += &format!("\n  {}{}", text.to_ansi(), " ".repeat(padding));
+        if let Some(score) = &line.score {
+            res += &format!("  \x1b[31m{}\x1b[0m", score);
+        }
+    }
+    res
+}
+
+fn boss_bar_ansi_color(color: BossBarColor) -> u8 {
+    match color {
+        BossBarColor::Pink => 213,
+        BossBarColor::Blue => 39,
+        BossBarColor::Red => 196,
+        BossBarColor::Green => 46,
+        BossBarColor::Yellow => 226,
+        BossBarColor::Purple => 129,
+        BossBarColor::White => 255,
+    }
+}
+
+fn create_boss_bars_string(boss_bars: &[BossBar], language: &Language) -> String {
+    let mut res: String = format!("Boss Bars ({}):", boss_bars.len());
+
+    for boss_bar in boss_bars {
+        let filled: usize = (boss_bar.progress.clamp(0.0, 1.0) * 20.0).round() as usize;
+        res += &format!(
+            "\n  \x1b[38;5;{}m[{}{}]\x1b[0m {:>3.0}% {}",
+            boss_bar_ansi_color(boss_bar.color),
+            "=".repeat(filled),
+            " ".repeat(20 - filled),
+            boss_bar.progress * 100.0,
+            language.translate(&boss_bar.title).to_ansi()
+        );
+    }
+    res
+}
+
+// Where a skin is saved. The name comes from the server, so anything but a valid
+// username (which could point outside the working directory) uses the UUID instead.
+fn skin_path(name: &str, uuid: u128) -> String {
+    match !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        true => format!("{}.png", name),
+        false => format!("{:032x}.png", uuid),
+    }
+}
+
+// Downloads a player's skin to <name>.png without blocking the input loop
+fn save_skin(client: &Client, name: &str) {
+    let player: Option<(u128, PlayerInfo)> = client
+        .online_players()
+        .into_iter()
+        .find(|(_, player)| player.name.eq_ignore_ascii_case(name));
+    let Some((uuid, player)) = player else {
+        output(&format!("[MClient] {} isn't online.", name));
+        return;
+    };
+
+    thread::spawn(move || {
+        let path: String = skin_path(&player.name, uuid);
+        match player.download_skin() {
+            Ok(png) => match fs::write(&path, png) {
+                Ok(()) => output(&format!(
+                    "[MClient] Saved the skin of {} to {}",
+                    player.name, path
+                )),
+                Err(e) => output(&format!("[MClient] Couldn't write {}: {}", path, e)),
+            },
+            Err(e) => output(&format!("[MClient] Couldn't download the skin: {}", e)),
+        }
+    });
+}
+
+// Looks up a name or UUID with the Mojang API in the background
+fn whois(query: &str) {
+    let query: String = String::from(query);
+    thread::spawn(move || {
+        let profile: Result<Option<Profile>, ClientError> = match mojang::parse_uuid(&query) {
+            Some(uuid) => mojang::lookup_uuid(uuid),
+            None => mojang::lookup_name(&query),
+        };
+        match profile {
+            Ok(Some(profile)) => output(&format!("[MClient] {}", profile)),
+            Ok(None) => output(&format!("[MClient] Mojang doesn't know {}.", query)),
+            Err(e) => output(&format!("[MClient] Lookup failed: {}", e)),
+        }
+    });
+}
+
+// Lists the LAN worlds that announce themselves and lets the user pick one to join.
+fn choose_lan_server(args: &mut Args) {
+    println!("[MClient] Looking for LAN worlds...");
+    let servers: Vec<LanServer> = match mclient_project::discover_lan(Duration::from_secs(5)) {
+        Ok(servers) => servers,
+        Err(e) => {
+            println!("[MClient] LAN discovery failed: {}", e);
+            std::process::exit(EXIT_CONNECTION_LOST);
+        }
+    };
+
+    if servers.is_empty() {
+        println!("[MClient] No LAN worlds found.");
+        std::process::exit(0);
+    }
+
+    for (i, server) in servers.iter().enumerate() {
+        println!(
+            "  {}) {} ({}:{})",
+            i + 1,
+            server.motd,
+            server.address,
+            server.port
+        );
+    }
+    println!("[MClient] Join which world? (number, empty to quit)");
+
+    let choice: Option<&LanServer> = read_input()
+        .and_then(|line| line.trim().parse::<usize>().ok())
+        .and_then(|number| servers.get(number.wrapping_sub(1)));
+    let Some(server) = choice else {
+        std::process::exit(0);
+    };
+
+    args.host = server.address.to_string();
+    args.port = Some(server.port);
+}
+
+fn add_schedule(scheduler: &Scheduler, when: When, words: &[&str]) {
+    let spec: ScheduleSpec = ScheduleSpec {
+        name: None,
+        when,
+        message: words.join(" "),
+    };
+    output(&format!(
+        "[MClient] Scheduled {} ({}).",
+        spec.message,
+        spec.when.describe()
+    ));
+    scheduler.add(spec);
+}
+
+// A table of the packets received per ID, most frequent (or biggest) first
+fn create_packets_string(counts: &[PacketCount], table: &PacketTable, by_bytes: bool) -> String {
+    let mut counts: Vec<PacketCount> = counts.to_vec();
+    match by_bytes {
+        true => counts.sort_by_key(|count| Reverse(count.bytes)),
+        false => counts.sort_by_key(|count| Reverse(count.count)),
+    }
+    let total_packets: u64 = counts.iter().map(|count| count.count).sum();
+    let total_bytes: u64 = counts.iter().map(|count| count.bytes).sum();
+
+    let mut res: String = format!(
+        "Packets from the server ({} in total, {}), sorted by {}:",
+        total_packets,
+        format_size(total_bytes),
+        match by_bytes {
+            true => "size",
+            false => "count (.packets bytes sorts by size)",
+        }
+    );
+    res += &format!(
+        "\n  {:<6} {:<38} {:>8} {:>10} {:>6}",
+        "ID", "Packet (* handled)", "Count", "Size", "Share"
+    );
+    for count in counts {
+        // Marked when the client reads it instead of skipping it
+        let name: String = format!(
+            "{}{}",
+            table.clientbound_name(count.id).unwrap_or("(unknown)"),
+            match table.clientbound(count.id) {
+                Some(_) => " *",
+                None => "",
+            }
+        );
+        res += &format!(
+            "\n  {:<6} {:<38} {:>8} {:>10} {:>5.1}%",
+            format!("0x{:02X}", count.id),
+            name,
+            count.count,
+            format_size(count.bytes),
+            count.bytes as f64 * 100.0 / total_bytes.max(1) as f64
+        );
+    }
+    res
+}
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..1024 => format!("{} B", bytes),
+        1024..1_048_576 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MiB", bytes as f64 / 1_048_576.0),
+    }
+}
+
+// The echoes measured so far, how fast keep alives are answered and what the server
+// itself measured
+fn create_latency_string(client: &Client) -> String {
+    let latency: Latency = client.latency();
+    let mut parts: Vec<String> = Vec::new();
+    if let (Some(min), Some(average), Some(max)) = (latency.min, latency.average, latency.max) {
+        parts.push(format!(
+            "min/avg/max = {}/{}/{} ms over {} {}",
+            min.as_millis(),
+            average.as_millis(),
+            max.as_millis(),
+            latency.samples,
+            if latency.samples == 1 {
+                "echo"
+            } else {
+                "echoes"
+            }
+        ));
+    }
+    if let Some(reply) = latency.keep_alive_reply {
+        parts.push(format!(
+            "keep alives answered in {:.1} ms",
+            reply.as_secs_f64() * 1000.0
+        ));
+    }
+    if let Some(player) = client.online_players().get(&client.identity().uuid) {
+        parts.push(format!("the server measures {} ms", player.latency));
+    }
+    match parts.is_empty() {
+        true => String::from("No measurements yet"),
+        false => parts.join(", "),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn register_commands(
+    bots: &Arc<Bots>,
+    language: &Arc<Language>,
+    join_messages: &Arc<AtomicBool>,
+    conversation: &Arc<Conversation>,
+    scrollback: &Arc<Scrollback>,
+    links: &Arc<Links>,
+    chat_filter: &Arc<ChatFilter>,
+    scripts: &Arc<Scripts>,
+    scheduler: &Arc<Scheduler>,
+    history: &Arc<PlayerHistory>,
+) -> ClientCommands {
+    let mut commands: ClientCommands = ClientCommands::new();
+    commands.set_help_output(|text| output(&format!("[MClient] {}", text)));
+
+    if tui::get().is_some() {
+        for (name, direction) in [("up", 1), ("down", -1)] {
+            commands.register(name, "[lines]", "Scroll the chat (10 lines)", move |rest| {
+                let lines: isize = rest.parse().unwrap_or(10);
+                if let Some(tui) = tui::get() {
+                    tui.scroll(lines * direction);
+                }
+                CommandFlow::Continue
+            });
+        }
+    }
+
+    {
+        let bots: Arc<Bots> = Arc::clone(bots);
+        let language: Arc<Language> = Arc::clone(language);
+        commands.register(
+            "list",
+            "[name] [page:<n>]",
+            "Online players with their ping, only those whose name contains [name] if given",
+            move |rest| {
+                let client: Arc<Client> = bots.client();
+                // The page is spelled out, so names made of digits can still be searched
+                let mut filter: &str = "";
+                let mut page: usize = 1;
+                for word in rest.split_whitespace() {
+                    match word.strip_prefix("page:").map(str::parse::<usize>) {
+                        Some(Ok(number)) => page = number,
+                        Some(Err(_)) => {
+                            output("[MClient] The page must be a number, like page:2");
+                            return CommandFlow::Continue;
+                        }
+                        None => filter = word,
+                    }
+                }
+                output(&format!(
+                    "[MClient] {}",
+                    create_players_string(
+                        &client.online_players(),
+                        client.identity().uuid,
+                        filter,
+                        page,
+                        &language,
+                        |name| client.decorated_name(name)
+                    )
+                ));
+                CommandFlow::Continue
+            },
+        );
+    }
+
+    {
+        let bots: Arc<Bots> = Arc::clone(bots);
+        let history: Arc<PlayerHistory> = Arc::clone(history);
+        commands.register(
+            "seen",
+            "[player]",
+            "When [player] was last online, or who joined and left since the last session",
+            move |rest| {
+                let bot: Arc<Bot> = bots.active();
+                let line: String = match rest.trim() {
+                    "" => create_seen_diff_string(&history, &bot),
+                    name => create_seen_string(&history, &bot, name),
+                };
+                output(&format!("[MClient] {}", line));
+                CommandFlow::Continue
+            },
+        );
+    }
+
+    {
+        let bots: Arc<Bots> = Arc::clone(bots);
+        commands.register(
+            "packets",
+            "[bytes]",
+            "How many packets of each kind the server sent (sorted by count or size)",
+            move |rest| {
+                let bot: Arc<Bot> = bots.active();
+                output(&format!(
+                    "[MClient] {}",
+                    create_packets_string(
+                        &bot.client.packet_counts(),
+                        bot.client.packets(),
+                        rest.eq_ignore_ascii_case("bytes")
+                    )
+                ));
+                CommandFlow::Continue
+            },
+        );
+    }
+
+    {
+        let bots: Arc<Bots> = Arc::clone(bots);
+        commands.register(
+            "ping",
+            "",
+            "Round trip time to the server, measured now and over time",
+            move |_| {
+                let client: Arc<Client> = bots.client();
+                match client.measure_latency() {
+                    Ok(round_trip) => output(&format!(
+                        "[MClient] Round trip: {} ms",
+                        round_trip.as_millis()
+                    )),
+                    Err(e) => output(&format!("[MClient] Couldn't measure the round trip: {}", e)),
+                }
+                output(&format!("[MClient] {}", create_latency_string(&client)));
+                CommandFlow::Continue
+            },
+        );
+    }
+
+    {
+        let bots: Arc<Bots> = Arc::clone(bots);
+        let language: Arc<Language> = Arc::clone(language);
+        commands.register("sidebar", "", "The scoreboard sidebar", move |_| {
+            let client: Arc<Client> = bots.client();
+            output(&format!(
+                "[MClient] {}",
+                create_sidebar_string(client.sidebar(), &language)
+            ));
+            CommandFlow::Continue
+        });
+    }
+
+    {
+        let bots: Arc<Bots> = Arc::clone(bots);
+        let language: Arc<Language> = Arc::clone(language);
+        commands.register("tab", "", "The player list header and footer", move |_| {
+            let client: Arc<Client> = bots.client();
+            output(&format!(
+                "[MClient] {}",
+                create_tab_list_string(&client.tab_list(), &language)
+            ));
+            CommandFlow::Continue
+        });
+    }
+
+    {
+        let bots: Arc<Bots> = Arc::clone(bots);
+        commands.register("health", "", "Health, food and saturation", move |_| {
+            let client: Arc<Client> = bots.client();
+            match client.health() {
+                Some(health) => output(&format!(
+                    "[MClient] Health: {:.1}/20, food: {}/20, saturation: {:.1}",
+                    health.health, health.food, health.saturation
+                )),
+                None => output("[MClient] The server hasn't sent the player's health yet."),
+            }
+            CommandFlow::Continue
+        });
+    }
+
+    {
+        let bots: Arc<Bots> = Arc::clone(bots);
+        commands.register(
+            "server",
+            "",
+            "The server address, protocol and brand",
+            move |_| {
+                let bot: Arc<Bot> = bots.active();
+                output(&format!(
+                    "[MClient] {} (protocol {}), brand: {}",
+                    bot.address,
+                    bot.protocol,
+                    bot.client
+                        .server_brand()
+                        .unwrap_or(String::from("(not sent)"))
+                ));
+                CommandFlow::Continue
+            },
+        );
+    }
+
+    {
+        let bots: Arc<Bots> = Arc::clone(bots);
+        let language: Arc<Language> = Arc::clone(language);
+        commands.register("bossbars", "", "The boss bars", move |_| {
+            let client: Arc<Client> = bots.client();
+            output(&format!(
+                "[MClient] {}",
+                create_boss_bars_string(&client.boss_bars(), &language)
+            ));
+            CommandFlow::Continue
+        });
+    }
+
+    commands.register(
+        "whois",
+        "<name or uuid>",
+        "Look an account up with the Mojang API",
+        |rest| {
+            match rest.split_whitespace().next() {
+                Some(query) => whois(query),
+                None => output("[MClient] Usage: .whois <name or uuid>"),
+            }
+            CommandFlow::Continue
+        },
+    );
+
+    {
+        let bots: Arc<Bots> = Arc::clone(bots);
+        commands.register(
+            "skin",
+            "<player>",
+            "Save a player's skin as <player>.png",
+            move |rest| {
+                let client: Arc<Client> = bots.client();
+                match rest.split_whitespace().next() {
+                    Some(name) => save_skin(&client, name),
+                    None => output("[MClient] Usage: .skin <player>"),
+                }
+                CommandFlow::Continue
+            },
+        );
+    }
+
+    {
+        let join_messages: Arc<AtomicBool> = Arc::clone(join_messages);
+        commands.register("joins", "", "Toggle join and leave messages", move |_| {
+            let enabled: bool = !join_messages.fetch_xor(true, Ordering::Relaxed);
+            output(&format!(
+                "[MClient] Join and leave messages {}.",
+                if enabled { "enabled" } else { "disabled" }
+            ));
+            CommandFlow::Continue
+        });
+    }
+
+    {
+        let bots: Arc<Bots> = Arc::clone(bots);
+        let conversation: Arc<Conversation> = Arc::clone(conversation);
+        commands.register(
+            "msg",
+            "<player> <text>",
+            "Whisper to a player",
+            move |rest| {
+                let client: Arc<Client> = bots.client();
+                match rest.split_once(' ') {
+                    Some((player, text)) => {
+                        conversation.set_partner(player);
+                        if let Err(e) = client.send_chat(&format!("/msg {} {}", player, text)) {
+                            output(&format!("[MClient] {}", e));
+                        }
+                    }
+                    None => output("[MClient] Usage: .msg <player> <text>"),
+                }
+                CommandFlow::Continue
+            },
+        );
+    }
+
+    {
+        let bots: Arc<Bots> = Arc::clone(bots);
+        let conversation: Arc<Conversation> = Arc::clone(conversation);
+        commands.register("r", "<text>", "Answer the last whisper", move |rest| {
+            let client: Arc<Client> = bots.client();
+            match conversation.partner() {
+                Some(_) if rest.is_empty() => output("[MClient] Usage: .r <text>"),
+                Some(player) => {
+                    if let Err(e) = client.send_chat(&format!("/msg {} {}", player, rest)) {
+                        output(&format!("[MClient] {}", e));
+                    }
+                }
+                None => output("[MClient] No one has whispered to you yet."),
+            }
+            CommandFlow::Continue
+        });
+    }
+
+    {
+        let scrollback: Arc<Scrollback> = Arc::clone(scrollback);
+        commands.register(
+            "history",
+            "[n]",
+            "The last n chat lines (20)",
+            move |rest| {
+                let count: usize = rest.parse().unwrap_or(20);
+                for (number, line) in scrollback.last(count) {
+                    output(&numbered_line(number, &line));
+                }
+                CommandFlow::Continue
+            },
+        );
+    }
+
+    {
+        let scrollback: Arc<Scrollback> = Arc::clone(scrollback);
+        commands.register(
+            "search",
+            "<regex>",
+            "Chat lines matching a pattern",
+            move |rest| {
+                match Regex::new(rest) {
+                    _ if rest.is_empty() => output("[MClient] Usage: .search <regex>"),
+                    Ok(regex) => {
+                        let matches: Vec<(usize, String)> = scrollback.search(&regex);
+                        for (number, line) in &matches {
+                            output(&numbered_line(*number, line));
+                        }
+                        output(&format!("[MClient] {} matching lines.", matches.len()));
+                    }
+                    Err(e) => output(&format!("[MClient] Invalid pattern: {}", e)),
+                }
+                CommandFlow::Continue
+            },
+        );
+    }
+
+    {
+        let scrollback: Arc<Scrollback> = Arc::clone(scrollback);
+        let language: Arc<Language> = Arc::clone(language);
+        commands.register(
+            "inspect",
+            "[line]",
+            "Hover texts and click actions of a chat line (the last one with any)",
+            move |rest| {
+                let number: Option<usize> = match rest.parse() {
+                    Ok(number) => Some(number),
+                    Err(_) if rest.is_empty() => None,
+                    Err(_) => {
+                        output("[MClient] Usage: .inspect [line]");
+                        return CommandFlow::Continue;
+                    }
+                };
+                match scrollback.interactions(number) {
+                    Some((number, interactions)) if interactions.is_empty() => output(&format!(
+                        "[MClient] Line #{} has no hover texts or click actions.",
+                        number
+                    )),
+                    Some((number, interactions)) => output(&format!(
+                        "[MClient] Line #{}:\n{}",
+                        number,
+                        create_interactions_string(&interactions, &language)
+                    )),
+                    None => output("[MClient] No such line in the scrollback."),
+                }
+                CommandFlow::Continue
+            },
+        );
+    }
+
+    {
+        let bots: Arc<Bots> = Arc::clone(bots);
+        let scrollback: Arc<Scrollback> = Arc::clone(scrollback);
+        commands.register(
+            "click",
+            "<line> [n]",
+            "Run the nth command a chat line runs when clicked (1)",
+            move |rest| {
+                let client: Arc<Client> = bots.client();
+                let mut words = rest.split_whitespace().map(str::parse::<usize>);
+                let (Some(Ok(number)), Ok(index)) = (words.next(), words.next().unwrap_or(Ok(1)))
+                else {
+                    output("[MClient] Usage: .click <line> [n]");
+                    return CommandFlow::Continue;
+                };
+                let Some((_, interactions)) = scrollback.interactions(Some(number)) else {
+                    output("[MClient] No such line in the scrollback.");
+                    return CommandFlow::Continue;
+                };
+
+                let command: Option<String> = interactions
+                    .into_iter()
+                    .filter_map(|interaction| match interaction.click {
+                        Some(ClickAction::RunCommand(command)) => Some(command),
+                        _ => None,
+                    })
+                    .nth(index.saturating_sub(1));
+                match command {
+                    Some(command) => {
+                        if let Err(e) = client.send_chat(&command) {
+                            output(&format!("[MClient] {}", e));
+                        }
+                    }
+                    None => output(&format!(
+                        "[MClient] Line #{} has no command to run there.",
+                        number
+                    )),
+                }
+                CommandFlow::Continue
+            },
+        );
+    }
+
+    {
+        let links: Arc<Links> = Arc::clone(links);
+        commands.register(
+            "open",
+            "[link]",
+            "Open a link from chat in the browser, or list the last ones",
+            move |rest| {
+                if rest.is_empty() {
+                    let recent: Vec<(usize, String)> = links.last(10);
+                    if recent.is_empty() {
+                        output("[MClient] No links in chat yet.");
+                    }
+                    for (number, url) in recent {
+                        output(&format!("[MClient] [{}] {}", number, url));
+                    }
+                    return CommandFlow::Continue;
+                }
+
+                match rest
+                    .trim_start_matches('#')
+                    .parse()
+                    .ok()
+                    .and_then(|number| links.get(number))
+                {
+                    Some(url) => match links::open(&url) {
+                        Ok(()) => output(&format!("[MClient] Opening {}", url)),
+                        Err(e) => output(&format!("[MClient] Couldn't open {}: {}", url, e)),
+                    },
+                    None => output("[MClient] No such link, see .open for the last ones."),
+                }
+                CommandFlow::Continue
+            },
+        );
+    }
+
+    {
+        let chat_filter: Arc<ChatFilter> = Arc::clone(chat_filter);
+        commands.register(
+            "ignore",
+            "[player]",
+            "Hide or unhide a player's messages, or list who's ignored",
+            move |rest| {
+                match rest.split_whitespace().next() {
+                    Some(name) => match chat_filter.toggle_ignore(name) {
+                        Ok(true) => output(&format!("[MClient] Ignoring {}.", name)),
+                        Ok(false) => output(&format!("[MClient] No longer ignoring {}.", name)),
+                        Err(e) => output(&format!("[MClient] {}", e)),
+                    },
+                    None => match chat_filter.ignored() {
+                        ignored if ignored.is_empty() => output("[MClient] No one is ignored."),
+                        ignored => output(&format!("[MClient] Ignored: {}", ignored.join(", "))),
+                    },
+                }
+                CommandFlow::Continue
+            },
+        );
+    }
+
+    {
+        let chat_filter: Arc<ChatFilter> = Arc::clone(chat_filter);
+        commands.register(
+            "filter",
+            "add <regex> | remove <number> | list",
+            "Hide lines matching a pattern",
+            move |rest| {
+                let mut parts = rest.splitn(2, ' ');
+                match (parts.next(), parts.next()) {
+                    (Some("add"), Some(pattern)) => match chat_filter.add_filter(pattern) {
+                        Ok(()) => output(&format!("[MClient] Hiding lines matching {}", pattern)),
+                        Err(e) => output(&format!("[MClient] {}", e)),
+                    },
+                    (Some("remove"), Some(index)) => {
+                        let res = match index.trim().parse::<usize>() {
+                            Ok(index) => chat_filter.remove_filter(index),
+                            Err(_) => Err(format!("'{}' is not a filter number", index).into()),
+                        };
+                        match res {
+                            Ok(pattern) => output(&format!("[MClient] Removed filter {}", pattern)),
+                            Err(e) => output(&format!("[MClient] {}", e)),
+                        }
+                    }
+                    (Some("list"), None) => {
+                        let patterns: Vec<String> = chat_filter.patterns();
+                        if patterns.is_empty() {
+                            output("[MClient] No filters.");
+                        }
+                        for (i, pattern) in patterns.iter().enumerate() {
+                            output(&format!("[MClient] {}. {}", i + 1, pattern));
+                        }
+                    }
+                    _ => output("[MClient] Usage: .filter add <regex> | remove <number> | list"),
+                }
+                CommandFlow::Continue
+            },
+        );
+    }
+
+    {
+        let bots: Arc<Bots> = Arc::clone(bots);
+        commands.register(
+            "commands",
+            "",
+            "The commands the server allows",
+            move |_| {
+                let client: Arc<Client> = bots.client();
+                match client.command_tree() {
+                    Some(tree) => output(&format!(
+                        "[MClient] Commands ({}): {}",
+                        tree.commands().len(),
+                        tree.commands().join(", ")
+                    )),
+                    None => output("[MClient] The server hasn't sent its commands (1.13+ only)."),
+                }
+                CommandFlow::Continue
+            },
+        );
+    }
+
+    {
+        let bots: Arc<Bots> = Arc::clone(bots);
+        commands.register(
+            "switch",
+            "[bot]",
+            "Send typed lines through another bot, or list the bots",
+            move |rest| {
+                if rest.is_empty() {
+                    let active: String = bots.active().name.clone();
+                    for bot in bots.all() {
+                        let marker: &str = if bot.name == active { "*" } else { " " };
+                        output(&format!(
+                            "[MClient] {} {} ({})",
+                            marker, bot.name, bot.address
+                        ));
+                    }
+                    return CommandFlow::Continue;
+                }
+                match bots.switch(rest) {
+                    Some(name) => {
+                        output(&format!("[MClient] Typed lines now go through {}.", name))
+                    }
+                    None => output(&format!("[MClient] There's no bot called {}.", rest)),
+                }
+                CommandFlow::Continue
+            },
+        );
+    }
+
+    {
+        let scripts: Arc<Scripts> = Arc::clone(scripts);
+        commands.register(
+            "script",
+            "[load|unload <file>|reload]",
+            "Load, unload or reload event scripts, or list them",
+            move |rest| {
+                let (action, file): (&str, &str) = match rest.split_once(' ') {
+                    Some((action, file)) => (action, file.trim()),
+                    None => (rest, ""),
+                };
+                match (action, file) {
+                    ("" | "list", _) => {
+                        let paths: Vec<PathBuf> = scripts.paths();
+                        if paths.is_empty() {
+                            output("[MClient] No scripts are loaded.");
+                        }
+                        for path in paths {
+                            output(&format!("[MClient] {}", path.display()));
+                        }
+                    }
+                    ("load", file) if !file.is_empty() => match scripts.load(Path::new(file)) {
+                        Ok(handlers) => output(&format!(
+                            "[MClient] Loaded {} ({} handlers).",
+                            file, handlers
+                        )),
+                        Err(e) => output(&format!("[MClient] {}", e)),
+                    },
+                    ("unload", file) if !file.is_empty() => match scripts.unload(Path::new(file)) {
+                        true => output(&format!("[MClient] Unloaded {}.", file)),
+                        false => output(&format!("[MClient] {} isn't loaded.", file)),
+                    },
+                    ("reload", _) => {
+                        for path in scripts.paths() {
+                            match scripts.load(&path) {
+                                Ok(handlers) => output(&format!(
+                                    "[MClient] Reloaded {} ({} handlers).",
+                                    path.display(),
+                                    handlers
+                                )),
+                                // The old handlers stay until the file is fixed
+                                Err(e) => output(&format!("[MClient] {}", e)),
+                            }
+                        }
+                    }
+                    _ => output("[MClient] Usage: .script [list|load <file>|unload <file>|reload]"),
+                }
+                CommandFlow::Continue
+            },
+        );
+    }
+
+    {
+        let scheduler: Arc<Scheduler> = Arc::clone(scheduler);
+        commands.register(
+            "schedule",
+            "add every <interval> | add cron <5 fields> <message> | remove <number> | list",
+            "Send a message or command repeatedly",
+            move |rest| {
+                let parts: Vec<&str> = rest.split_whitespace().collect();
+                match parts[..] {
+                    ["add", "every", interval, ref message @ ..] if !message.is_empty() => {
+                        match schedule::parse_interval(interval) {
+                            Ok(interval) => add_schedule(&scheduler, When::Every(interval), message),
+                            Err(e) => output(&format!("[MClient] {}", e)),
+                        }
+                    }
+                    ["add", "cron", ref fields @ ..] if fields.len() > 5 => {
+                        match Cron::parse(&fields[..5].join(" ")) {
+                            Ok(cron) => add_schedule(&scheduler, When::Cron(cron), &fields[5..]),
+                            Err(e) => output(&format!("[MClient] {}", e)),
+                        }
+                    }
+                    ["remove", index] => {
+                        let res = match index.parse::<usize>() {
+                            Ok(index) => scheduler.remove(index),
+                            Err(_) => Err(format!("'{}' is not a schedule number", index).into()),
+                        };
+                        match res {
+                            Ok(spec) => output(&format!(
+                                "[MClient] Removed the schedule {}: {}",
+                                spec.when.describe(),
+                                spec.message
+                            )),
+                            Err(e) => output(&format!("[MClient] {}", e)),
+                        }
+                    }
+                    [] | ["list"] => {
+                        let specs: Vec<ScheduleSpec> = scheduler.list();
+                        if specs.is_empty() {
+                            output("[MClient] No schedules.");
+                        }
+                        for (i, spec) in specs.iter().enumerate() {
+                            let name: String = spec
+                                .name
+                                .as_ref()
+                                .map(|name| format!(" ({})", name))
+                                .unwrap_or_default();
+                            output(&format!(
+                                "[MClient] {}. {}{}: {}",
+                                i + 1,
+                                spec.when.describe(),
+                                name,
+                                spec.message
+                            ));
+                        }
+                    }
+                    _ => output(
+                        "[MClient] Usage: .schedule add every <interval> <message> | add cron <minute> <hour> <day> <month> <weekday> <message> | remove <number> | list",
+                    ),
+                }
+                CommandFlow::Continue
+            },
+        );
+    }
+
+    commands.register("quit", "", "Disconnect and exit", |_| CommandFlow::Quit);
+
+    commands
+}
 
 fn main() {
-    if let Err(e) = helper::request_status(IP, PORT) {
-        panic!("Error while requesting status: {}", e);
+    let mut args: Args = match Args::parse() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+
+    NO_COLOR.store(args.no_color || args.plain, Ordering::Relaxed);
+    JSON_OUTPUT.store(args.json, Ordering::Relaxed);
+
+    if let Err(e) = logging::init(args.log_level, args.log_file.as_deref(), diagnostic) {
+        eprintln!("Couldn't open the log file: {}", e);
+        std::process::exit(2);
     }
 
-    if let Err(e) = helper::start(IP, PORT, USERNAME) {
-        panic!("Error while sending handshake packet: {}", e);
+    if args.discover_lan {
+        choose_lan_server(&mut args);
+    }
+
+    let language: Arc<Language> = Arc::new(match &args.lang_file {
+        Some(path) => match Language::load(path) {
+            Ok(language) => language,
+            Err(e) => {
+                warn!("{}", e);
+                Language::bundled()
+            }
+        },
+        None => Language::bundled(),
+    });
+
+    match args.command {
+        Command::Status => print_status(&args, &language),
+        Command::Ping => ping(&args),
+        Command::Query => print_query(&args),
+        Command::Watch => watch(&args, &language),
+        Command::Replay => replay(&args),
+        Command::Proxy => inspect_traffic(&args),
+        Command::Chat | Command::Send => {}
+    }
+
+    let mut protocol: i32 = mclient_project::DEFAULT_PROTOCOL;
+
+    if !args.skip_status {
+        // Only the interactive session shows the status
+        let status: Result<ServerStatus, ClientError> =
+            match args.json || args.command == Command::Send {
+                true => mclient_project::fetch_status(
+                    &args.host,
+                    args.port,
+                    args.proxy.as_ref(),
+                    &args.connect,
+                    &args.virtual_host,
+                ),
+                false => mclient_project::request_status(
+                    &args.host,
+                    args.port,
+                    args.proxy.as_ref(),
+                    &args.connect,
+                    &args.virtual_host,
+                )
+                .inspect(|status| show_favicon(status, &args)),
+            };
+        match status {
+            Ok(status) => {
+                protocol = status.version.protocol;
+                if args.json {
+                    println!("{}", ndjson::status_line(&status, &language));
+                }
+                if status.enforces_secure_chat {
+                    output(
+                        "[MClient] This server enforces secure chat. Messages from this client aren't signed and will be rejected!",
+                    );
+                }
+            }
+            Err(e) => exit_with_error(&e),
+        }
+    }
+
+    if let Some(protocol_override) = args.protocol {
+        protocol = protocol_override;
+    }
+
+    let mut config: ClientConfig =
+        create_config(&args, &args.host, args.port, &args.username, protocol);
+    // Only the first account is captured, the other bots would mix into the same file
+    if let Some(path) = &args.capture {
+        match Capture::create(path, protocol) {
+            Ok(capture) => config.capture = Some(capture),
+            Err(e) => {
+                output(&format!(
+                    "[MClient] Couldn't create {}: {}",
+                    path.display(),
+                    e
+                ));
+                std::process::exit(2);
+            }
+        }
+    }
+
+    let client: Arc<Client> = match Client::connect_with(config) {
+        Ok(client) => Arc::new(client),
+        Err(e) => exit_with_error(&e),
+    };
+
+    if let Some(message) = &args.message {
+        send_once(&client, message, &args);
+    }
+
+    let identity: Identity = client.identity();
+    if !args.json {
+        output(&logged_in_line(&identity, &args.username));
+    }
+
+    let bots: Arc<Bots> = Arc::new(Bots::new(Bot {
+        name: identity.username,
+        address: args.address(),
+        protocol,
+        client,
+    }));
+    for spec in &args.bots {
+        match connect_bot(spec, &args, protocol) {
+            Ok(bot) => bots.add(bot),
+            Err(e) => output(&format!(
+                "[MClient] {} couldn't log in: {}",
+                spec.username, e
+            )),
+        }
+    }
+    let several: bool = bots.all().len() > 1;
+
+    if args.json {
+        for bot in bots.all() {
+            let name: Option<String> = several.then(|| bot.name.clone());
+            // The first login happens before handlers can be registered
+            println!(
+                "{}",
+                ndjson::event_line(&Event::Connected, name.as_deref(), &language)
+            );
+            let language: Arc<Language> = Arc::clone(&language);
+            bot.client.on_event(move |event| {
+                println!("{}", ndjson::event_line(event, name.as_deref(), &language))
+            });
+        }
+    }
+
+    let json: bool = args.json;
+    let plain: bool = args.plain;
+    let join_messages: Arc<AtomicBool> = Arc::new(AtomicBool::new(args.join_messages));
+    let chat_filter: Arc<ChatFilter> =
+        match ChatFilter::new(&args.ignored, &args.filters, &args.config_path) {
+            Ok(chat_filter) => Arc::new(chat_filter),
+            Err(e) => {
+                println!("[MClient] {}", e);
+                std::process::exit(2);
+            }
+        };
+    let scrollback: Arc<Scrollback> = Arc::new(Scrollback::new(args.scrollback));
+    let links: Arc<Links> = Arc::new(Links::new());
+    let conversation: Arc<Conversation> = Arc::new(Conversation::new());
+    let history: Arc<PlayerHistory> = Arc::new(
+        PlayerHistory::open(args.seen_file.clone()).unwrap_or_else(|e| {
+            warn!("{}, .seen only remembers this session", e);
+            PlayerHistory::session_only()
+        }),
+    );
+    for bot in bots.all() {
+        let prefix: String = bots::prefix(&bot.name, several);
+        let chat_format: ChatFormat = ChatFormat::new(&args.chat_format);
+        let weak_client: Weak<Client> = Arc::downgrade(&bot.client);
+        let event_language: Arc<Language> = Arc::clone(&language);
+        let last_action_bar: Mutex<String> = Mutex::new(String::new());
+        let event_join_messages: Arc<AtomicBool> = Arc::clone(&join_messages);
+        let connected_at: Mutex<Instant> = Mutex::new(Instant::now());
+        let mentions: Mentions = Mentions::new(&bot.name, &args.highlight_words, args.notify);
+        let own_uuid: u128 = bot.client.identity().uuid;
+        let own_tag: String = format!("<{}>", bot.name);
+        let event_chat_filter: Arc<ChatFilter> = Arc::clone(&chat_filter);
+        let event_scrollback: Arc<Scrollback> = Arc::clone(&scrollback);
+        let event_links: Arc<Links> = Arc::clone(&links);
+        let event_conversation: Arc<Conversation> = Arc::clone(&conversation);
+        let packets: &PacketTable = bot.client.packets();
+        bot.client.on_event(move |event| {
+            let show = |line: &str| output(&format!("{}{}", prefix, line));
+            match event {
+                // Printed by the handler above instead
+                _ if json => {}
+                Event::ChatReceived {
+                    message,
+                    sender,
+                    kind,
+                    interactions,
+                } => {
+                    let translated: FormattedText = event_language.translate(message);
+                    let text: String = translated.to_string();
+                    let whisper: Option<(Direction, String)> = whisper::detect(message);
+
+                    let sender_name: Option<String> = match (sender, weak_client.upgrade()) {
+                        (Some(uuid), Some(client)) => client
+                            .online_players()
+                            .get(uuid)
+                            .map(|player| player.name.clone()),
+                        _ => None,
+                    };
+                    let author: Option<&str> = match &whisper {
+                        Some((Direction::Incoming, partner)) => Some(partner.as_str()),
+                        _ => sender_name
+                            .as_deref()
+                            .or_else(|| filter::tagged_author(&text)),
+                    };
+                    if event_chat_filter.hides(author, &text) {
+                        return;
+                    }
+
+                    // Plain output is just the text, without the markers added below
+                    let mut line: String = match plain {
+                        true => text.clone(),
+                        false => {
+                            translated.to_ansi_with_custom_style(&chatformat::base_style(*kind))
+                        }
+                    };
+                    if let (Some(uuid), Some(name), false) = (sender, &sender_name, plain) {
+                        // Team prefixes and suffixes (ranks) replace the sender color
+                        line = match weak_client
+                            .upgrade()
+                            .and_then(|client| client.decorated_name(name))
+                        {
+                            Some(decorated) => chatformat::replace_sender(
+                                &line,
+                                name,
+                                &event_language.translate(&decorated).to_ansi(),
+                            ),
+                            None => chatformat::colorize_sender(&line, name, *uuid),
+                        };
+                    }
+
+                    if let Some((direction, partner)) = &whisper {
+                        event_conversation.set_partner(partner);
+                        if let (Direction::Incoming, false) = (direction, plain) {
+                            line = format!("{}{}", chatformat::WHISPER_MARKER, line);
+                        }
+                    }
+
+                    // 1.12 has no sender, so there the name in front of the message has to do
+                    let own: bool = *sender == Some(own_uuid) || text.starts_with(&own_tag);
+                    if own && !plain {
+                        line = format!("{}{}", chatformat::OWN_MESSAGE_MARKER, line);
+                    }
+
+                    // The player's own messages contain their name too
+                    if !own && mentions.matches(&text) {
+                        if !plain {
+                            line = mentions.highlight(&line);
+                        }
+                        mentions.notify(&text);
+                    }
+
+                    let numbers: Vec<usize> = event_links.collect(&text, interactions);
+                    if !numbers.is_empty() && !plain {
+                        line += &links_marker(&numbers);
+                    }
+
+                    let line: String = format!("{}{}", prefix, chat_format.render(&line));
+                    event_scrollback.push(&line, &text, interactions.clone());
+                    output(&line);
+                }
+                Event::ActionBar(text) => {
+                    let text: FormattedText = event_language.translate(text);
+                    if let Some(tui) = tui::get() {
+                        tui.set_action_bar(&text.to_string());
+                        return;
+                    }
+
+                    // Servers resend the same action bar every second or so, only print changes
+                    let line: String = text.to_ansi();
+                    let mut last_action_bar = last_action_bar.lock().unwrap();
+                    if *last_action_bar != line {
+                        if !text.to_string().trim().is_empty() {
+                            show(&format!("\x1b[2m[Action Bar]\x1b[0m {}", line));
+                        }
+                        *last_action_bar = line;
+                    }
+                }
+                Event::Title {
+                    title, subtitle, ..
+                } => {
+                    let mut lines: Vec<String> = Vec::new();
+                    for (text, style) in [
+                        (Some(title), chatformat::title_style()),
+                        (subtitle.as_ref(), chatformat::base_style(ChatKind::Player)),
+                    ] {
+                        if let Some(text) = text.filter(|text| !text.to_string().trim().is_empty())
+                        {
+                            lines.push(
+                                event_language
+                                    .translate(text)
+                                    .to_ansi_with_custom_style(&style),
+                            );
+                        }
+                    }
+                    if !lines.is_empty() {
+                        show(&chatformat::boxed(&lines));
+                    }
+                }
+                Event::PlayerJoined {
+                    name, display_name, ..
+                } if event_join_messages.load(Ordering::Relaxed)
+                    && connected_at.lock().unwrap().elapsed() > JOIN_GRACE =>
+                {
+                    show(&chat_format.render(&format!(
+                        "\x1b[32m[+]\x1b[0m {} joined",
+                        player_label(name, display_name.as_ref(), &event_language)
+                    )));
+                }
+                Event::PlayerLeft {
+                    name, display_name, ..
+                } if event_join_messages.load(Ordering::Relaxed) => {
+                    show(&chat_format.render(&format!(
+                        "\x1b[31m[-]\x1b[0m {} left",
+                        player_label(name, display_name.as_ref(), &event_language)
+                    )));
+                }
+                Event::Died(message) => {
+                    show(&format!(
+                        "[MClient] You died: {}\x1b[0m (respawning)",
+                        event_language.translate(message).to_ansi()
+                    ));
+                }
+                Event::Connected => {
+                    *connected_at.lock().unwrap() = Instant::now();
+                    show("[MClient] Reconnected!");
+                }
+                Event::MalformedPacket { id, error, payload } => {
+                    warn!(
+                        "{}Skipped malformed packet 0x{:02X} ({}): {}\n\x1b[2m{}\x1b[0m",
+                        prefix,
+                        id,
+                        packets.clientbound_name(*id).unwrap_or("unknown"),
+                        error,
+                        create_hex_dump(payload, MAX_HEX_DUMP_BYTES)
+                    );
+                }
+                Event::IgnoredPacket { id, payload } => {
+                    info!(
+                        "{}Ignored packet 0x{:02X} {} ({} bytes)\n\x1b[2m{}\x1b[0m",
+                        prefix,
+                        id,
+                        packets.clientbound_name(*id).unwrap_or("(unknown)"),
+                        payload.len(),
+                        create_hex_dump(payload, MAX_IGNORED_DUMP_BYTES)
+                    );
+                }
+                _ => {}
+            }
+        });
+
+        {
+            let bots: Arc<Bots> = Arc::clone(&bots);
+            let name: String = bot.name.clone();
+            let prefix: String = bots::prefix(&bot.name, several);
+            let history: Arc<PlayerHistory> = Arc::clone(&history);
+            let weak_bot: Weak<Bot> = Arc::downgrade(&bot);
+            bot.client.on_disconnect(move |e| {
+                if let Some(bot) = weak_bot.upgrade() {
+                    save_seen_players(&history, &bot);
+                }
+                match bots.remove(&name) {
+                    Removed::Last => exit_with_error(e),
+                    removed => {
+                        output(&format!("{}{}", prefix, disconnect_message(e)));
+                        if let Removed::Active(active) = removed {
+                            output(&format!("[MClient] Typed lines now go through {}.", active));
+                        }
+                    }
+                }
+            });
+        }
+    }
+
+    // Who's in the player list, for .seen. The players already there when this is
+    // registered were listed before the handler could see them.
+    for bot in bots.all() {
+        for (uuid, player) in bot.client.online_players() {
+            history.saw(&bot.address, uuid, &player.name);
+        }
+        let history: Arc<PlayerHistory> = Arc::clone(&history);
+        let address: String = bot.address.clone();
+        bot.client.on_event(move |event| match event {
+            Event::PlayerJoined { uuid, name, .. } | Event::PlayerLeft { uuid, name, .. } => {
+                history.saw(&address, *uuid, name)
+            }
+            _ => {}
+        });
+    }
+
+    // Keeps measuring the round trip for .ping and the HTTP API
+    for bot in bots.all() {
+        let client: Weak<Client> = Arc::downgrade(&bot.client);
+        thread::spawn(move || {
+            loop {
+                thread::sleep(LATENCY_INTERVAL);
+                let Some(client) = client.upgrade() else {
+                    return;
+                };
+                let _ = client.measure_latency();
+            }
+        });
+    }
+
+    // Several bots get a directory each
+    for bot in bots.all() {
+        let Some(log_dir) = args.log_dir.clone() else {
+            break;
+        };
+        let log_dir: PathBuf = match several {
+            true => log_dir.join(&bot.name),
+            false => log_dir,
+        };
+        match ChatLog::open(log_dir) {
+            Ok(chat_log) => bot.client.on_event(move |event| {
+                if let Event::ChatReceived { message, .. } = event
+                    && let Err(e) = chat_log.log(message)
+                {
+                    warn!("Couldn't write the chat log: {}", e);
+                }
+            }),
+            Err(e) => warn!("Couldn't open the chat log: {}", e),
+        }
+    }
+
+    let responder: Arc<AutoResponder> = match AutoResponder::new(&args.responders) {
+        Ok(responder) => Arc::new(responder),
+        Err(e) => {
+            output(&format!("[MClient] {}", e));
+            std::process::exit(2);
+        }
+    };
+    for bot in bots.all().into_iter().filter(|_| !responder.is_empty()) {
+        let responder: Arc<AutoResponder> = Arc::clone(&responder);
+        let language: Arc<Language> = Arc::clone(&language);
+        let chat_filter: Arc<ChatFilter> = Arc::clone(&chat_filter);
+        let weak_client: Weak<Client> = Arc::downgrade(&bot.client);
+        let own_uuid: u128 = bot.client.identity().uuid;
+        let own_tag: String = format!("<{}>", bot.name);
+        bot.client.on_event(move |event| {
+            let Event::ChatReceived {
+                message, sender, ..
+            } = event
+            else {
+                return;
+            };
+            let Some(client) = weak_client.upgrade() else {
+                return;
+            };
+            let text: String = language.translate(message).to_string();
+            // Its own answers could match again
+            if *sender == Some(own_uuid) || text.starts_with(&own_tag) {
+                return;
+            }
+
+            let whisper: Option<(Direction, String)> = whisper::detect(message);
+            let author: Option<String> = match &whisper {
+                Some((Direction::Outgoing, _)) => return,
+                Some((Direction::Incoming, partner)) => Some(partner.clone()),
+                None => sender
+                    .and_then(|uuid| client.online_players().get(&uuid).map(|p| p.name.clone()))
+                    .or_else(|| filter::tagged_author(&text).map(String::from)),
+            };
+            if author
+                .as_deref()
+                .is_some_and(|author| chat_filter.is_ignored(author))
+            {
+                return;
+            }
+
+            let Some(answer) = responder.answer(author.as_deref(), &text) else {
+                return;
+            };
+            // Whispers are answered privately
+            let answer: String = match (&whisper, &author) {
+                (Some(_), Some(author)) => format!("/msg {} {}", author, answer),
+                _ => answer,
+            };
+            if let Err(e) = client.send_chat(&answer) {
+                warn!("Couldn't send the auto-response: {}", e);
+            }
+        });
+    }
+
+    if let Some(address) = args.irc_listen
+        && let Err(e) =
+            IrcServer::start(address, args.irc_password.clone(), &bots, &language, output)
+    {
+        output(&format!("[MClient] {}", e));
+        std::process::exit(2);
+    }
+
+    if let Some(address) = args.api_listen
+        && let Err(e) = ApiServer::start(address, &bots, &language, output)
+    {
+        output(&format!("[MClient] {}", e));
+        std::process::exit(2);
+    }
+
+    #[cfg(feature = "discord")]
+    if let Some(discord) = &args.discord {
+        bridge::start(discord, &bots, &language, output);
+    }
+
+    #[cfg(feature = "telegram")]
+    if let Some(telegram) = &args.telegram {
+        telegrambridge::start(telegram, &bots, &language, output);
+    }
+
+    let scripts: Arc<Scripts> = Arc::new(Scripts::new(output));
+    for path in &args.scripts {
+        if let Err(e) = scripts.load(path) {
+            output(&format!("[MClient] {}", e));
+            std::process::exit(2);
+        }
+    }
+    for bot in bots.all() {
+        let scripts: Arc<Scripts> = Arc::clone(&scripts);
+        let language: Arc<Language> = Arc::clone(&language);
+        let source: Source = Source::new(
+            &bot.name,
+            bot.client.identity().uuid,
+            Arc::downgrade(&bot.client),
+        );
+        bot.client
+            .on_event(move |event| scripts.dispatch(&source, event, &language));
+    }
+
+    if args.tui {
+        let tui: &'static Tui = Tui::start(&format!("mc-chat {}", args.address()));
+        let bots: Arc<Bots> = Arc::clone(&bots);
+        let language: Arc<Language> = Arc::clone(&language);
+
+        // Keep the player sidebar, boss bars (and the layout, if the window was resized) up
+        // to date, for the active bot
+        thread::spawn(move || {
+            loop {
+                let client: Arc<Client> = bots.client();
+                tui.resize();
+                tui.set_players(
+                    client
+                        .online_players()
+                        .into_values()
+                        .map(|player| player.name)
+                        .collect(),
+                );
+                let (header, footer): (FormattedText, FormattedText) = client.tab_list();
+                tui.set_tab_list(
+                    plain_lines(&header, &language),
+                    plain_lines(&footer, &language),
+                );
+                tui.set_boss_bars(
+                    client
+                        .boss_bars()
+                        .iter()
+                        .map(|boss_bar| {
+                            format!(
+                                "{} {:.0}%",
+                                language.translate(&boss_bar.title),
+                                boss_bar.progress * 100.0
+                            )
+                        })
+                        .collect(),
+                );
+                thread::sleep(Duration::from_secs(1));
+            }
+        });
+    }
+
+    let scheduler: Arc<Scheduler> = Arc::new(Scheduler::new(&args.schedules));
+    scheduler.start(&bots, output);
+
+    let commands: Arc<ClientCommands> = Arc::new(register_commands(
+        &bots,
+        &language,
+        &join_messages,
+        &conversation,
+        &scrollback,
+        &links,
+        &chat_filter,
+        &scripts,
+        &scheduler,
+        &history,
+    ));
+
+    if !args.json
+        && let Some(editor) = LineEditor::start(args.history_file.clone())
+    {
+        // Command names and subcommands come from the command tree when the server sent
+        // one, the rest is completed by the server like in the vanilla chat screen
+        let bots: Arc<Bots> = Arc::clone(&bots);
+        let commands: Arc<ClientCommands> = Arc::clone(&commands);
+        editor.set_completer(move |line| {
+            if line.starts_with(COMMAND_PREFIX) {
+                return commands.complete(line);
+            }
+            if !line.starts_with('/') {
+                return Vec::new();
+            }
+            let client: Arc<Client> = bots.client();
+            let local: Vec<String> = client
+                .command_tree()
+                .map(|tree| tree.complete(line))
+                .unwrap_or_default();
+            if !local.is_empty() {
+                return local;
+            }
+            client.complete(line).unwrap_or_default()
+        });
+    }
+
+    shutdown::install();
+
+    // Lines are read on their own thread so a shutdown is noticed while waiting for
+    // input. The thread waits for each line to be handled before reading the next one,
+    // so after `.quit` it isn't left blocked on the terminal.
+    let (line_sender, lines) = mpsc::channel::<String>();
+    let (next_sender, next) = mpsc::channel::<()>();
+    let input: JoinHandle<()> = thread::spawn(move || {
+        while let Some(line) = read_input() {
+            if line_sender.send(line).is_err() || next.recv().is_err() {
+                break;
+            }
+        }
+    });
+
+    // Only the end of piped input still sends what's left in the chat queue
+    let mut drain: bool = false;
+    let mut input_blocked: bool = false;
+    loop {
+        let line: String = match lines.recv_timeout(SHUTDOWN_POLL_INTERVAL) {
+            Ok(_) if shutdown::requested() => break,
+            Ok(line) => line,
+            Err(RecvTimeoutError::Timeout) if shutdown::requested() => {
+                input_blocked = true;
+                break;
+            }
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => {
+                drain = !shutdown::requested();
+                break;
+            }
+        };
+        let buffer: String = String::from(line.trim());
+
+        if let Some(tui) = tui::get() {
+            tui.prompt();
+        }
+
+        let flow: Option<CommandFlow> = commands.run(&buffer);
+        if flow == Some(CommandFlow::Quit) {
+            break;
+        }
+        let _ = next_sender.send(());
+        if flow.is_some() {
+            continue;
+        }
+
+        let buffer: String = args.color_codes.apply(&buffer);
+        let client: Arc<Client> = bots.client();
+
+        // Catch typos before the server has to
+        if buffer.starts_with('/')
+            && let Some(Err(e)) = client.command_tree().map(|tree| tree.validate(&buffer))
+        {
+            output(&format!("[MClient] {}", e));
+            continue;
+        }
+
+        if let Err(e) = client.send_chat(&buffer) {
+            output(&format!("[MClient] {}", e));
+        }
+    }
+
+    restore_terminal();
+    drop(next_sender);
+
+    for bot in bots.all() {
+        save_seen_players(&history, &bot);
+    }
+    // The others are still disconnected if one fails
+    let mut failed: bool = false;
+    for bot in bots.all() {
+        let closed: Result<(), ClientError> = match drain {
+            true => bot.client.disconnect(),
+            false => bot.client.close(),
+        };
+        if let Err(e) = closed {
+            output(&format!(
+                "[MClient] {} didn't disconnect cleanly: {}",
+                bot.name, e
+            ));
+            failed = true;
+        }
+    }
+
+    // A signal can come while the thread waits for a line, which can't be interrupted
+    if !input_blocked {
+        let _ = input.join();
+    }
+    let _ = stdout().flush();
+    if failed {
+        std::process::exit(EXIT_CONNECTION_LOST);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skin_path_stays_in_the_working_directory() {
+        assert_eq!(skin_path("Steve_2", 1), "Steve_2.png");
+        assert_eq!(
+            skin_path("../../.bashrc", 0xab),
+            format!("{:032x}.png", 0xab)
+        );
+        assert_eq!(skin_path("/etc/passwd", 1), format!("{:032x}.png", 1));
+        assert_eq!(skin_path("", 1), format!("{:032x}.png", 1));
     }
 }
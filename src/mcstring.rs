@@ -0,0 +1,61 @@
+use std::io::{Read, Write};
+
+use mc_varint::{VarInt, VarIntWrite};
+
+use crate::error::ClientError;
+use crate::types::McRead;
+
+// Protocol strings are a VarInt length in bytes followed by UTF-8, but their limits are
+// in characters. A character is up to 4 bytes, so the byte length is only checked
+// against that before decoding.
+
+const MAX_BYTES_PER_CHAR: usize = 4;
+
+/// A protocol string with a maximum length in characters.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct McString {
+    max_length: usize,
+}
+
+impl McString {
+    /// The limit for strings that don't have their own.
+    pub(crate) const DEFAULT: McString = McString::max(32767);
+    /// Chat components sent as JSON (before 1.20.3).
+    pub(crate) const COMPONENT: McString = McString::max(262144);
+    pub(crate) const USERNAME: McString = McString::max(16);
+    pub(crate) const CHAT: McString = McString::max(256);
+
+    pub(crate) const fn max(max_length: usize) -> McString {
+        McString { max_length }
+    }
+
+    pub(crate) fn read(&self, reader: &mut (impl Read + ?Sized)) -> Result<String, ClientError> {
+        let length: i32 = reader.read_varint()?;
+        if length < 0 || length as usize > self.max_length * MAX_BYTES_PER_CHAR {
+            return Err(format!("String of {} bytes is too long", length).into());
+        }
+
+        let text: String = String::from_utf8(reader.read_bytes(length as usize)?)?;
+        self.check(&text)?;
+        Ok(text)
+    }
+
+    pub(crate) fn write(&self, buffer: &mut impl Write, text: &str) -> Result<(), ClientError> {
+        self.check(text)?;
+        buffer.write_var_int(VarInt::from(text.len() as i32))?;
+        buffer.write_all(text.as_bytes())?;
+        Ok(())
+    }
+
+    fn check(&self, text: &str) -> Result<(), ClientError> {
+        let length: usize = text.chars().count();
+        if length > self.max_length {
+            return Err(format!(
+                "String of {} characters is longer than {}",
+                length, self.max_length
+            )
+            .into());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,82 @@
+use std::io::{self, Write};
+use std::process::{Child, Command};
+use std::thread;
+
+// Lines that mention the player (or one of the --highlight words) get a background
+// color, and with --notify a terminal bell and a desktop notification.
+
+const HIGHLIGHT_BACKGROUND: &str = "\x1b[48;5;58m";
+
+pub struct Mentions {
+    // Lowercase, the player's name first
+    words: Vec<String>,
+    notify: bool,
+}
+
+impl Mentions {
+    pub fn new(username: &str, keywords: &[String], notify: bool) -> Mentions {
+        let mut words: Vec<String> = vec![username.to_lowercase()];
+        words.extend(
+            keywords
+                .iter()
+                .map(|word| word.to_lowercase())
+                .filter(|word| !word.is_empty()),
+        );
+        Mentions { words, notify }
+    }
+
+    /// Whether the plain text of a chat line contains one of the words on its own,
+    /// not as part of a longer word.
+    pub fn matches(&self, text: &str) -> bool {
+        let text: String = text.to_lowercase();
+        self.words.iter().any(|word| {
+            text.match_indices(word.as_str()).any(|(start, _)| {
+                let end: usize = start + word.len();
+                !text[..start].ends_with(is_name_char) && !text[end..].starts_with(is_name_char)
+            })
+        })
+    }
+
+    /// Colors the background of an ANSI-formatted line, keeping it through the resets
+    /// inside the line.
+    pub fn highlight(&self, line: &str) -> String {
+        let reset_again: String = format!("\x1b[0m{}", HIGHLIGHT_BACKGROUND);
+        format!(
+            "{}{}\x1b[0m",
+            HIGHLIGHT_BACKGROUND,
+            line.replace("\x1b[0m", &reset_again)
+                .replace("\x1b[m", &reset_again)
+        )
+    }
+
+    /// Rings the terminal bell and shows a desktop notification, if enabled.
+    pub fn notify(&self, text: &str) {
+        if !self.notify {
+            return;
+        }
+        print!("\x07");
+        let _ = io::stdout().flush();
+
+        let text: String = String::from(text);
+        // Waited for on its own thread so the notifier doesn't hold up the chat
+        thread::spawn(move || {
+            let _ = desktop_notification(&text).and_then(|mut child| child.wait());
+        });
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn desktop_notification(text: &str) -> io::Result<Child> {
+    if cfg!(target_os = "macos") {
+        let script: String = format!(
+            "display notification {:?} with title \"mc-chat\"",
+            text.replace('\\', "")
+        );
+        Command::new("osascript").args(["-e", &script]).spawn()
+    } else {
+        Command::new("notify-send").args(["mc-chat", text]).spawn()
+    }
+}
@@ -0,0 +1,445 @@
+use std::fmt;
+use std::io::{self, Cursor, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use mc_varint::{VarInt, VarIntWrite};
+
+use crate::client::ConnectPolicy;
+use crate::configuration::{self, has_configuration};
+use crate::error::ClientError;
+use crate::helper::{self, MAX_PACKET_LENGTH};
+use crate::mcstring::McString;
+use crate::packet::{LoginPacket, decode_frame};
+use crate::protocol;
+use crate::proxy::Socks5Proxy;
+use crate::types::McRead;
+
+// A man-in-the-middle proxy for studying the protocol. A client connects to it, it
+// connects to the real server, and every frame is passed on unchanged except for the
+// handshake, whose address is replaced with the server's. A copy of each frame is
+// decompressed and split to report the packet in it. Encrypted (online-mode) connections
+// can't be followed past the Encryption Request; from there on the bytes are passed
+// on without being looked at, like legacy pings are.
+//
+// Going back from play to configuration (1.20.2+ proxies moving players between servers)
+// isn't followed. Packets are still split correctly, but reported as play packets.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// From the client to the server.
+    Serverbound,
+    Clientbound,
+}
+
+impl Direction {
+    fn index(self) -> usize {
+        match self {
+            Direction::Serverbound => 0,
+            Direction::Clientbound => 1,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConnectionState {
+    Handshaking,
+    Status,
+    Login,
+    /// 1.20.2+ only.
+    Configuration,
+    Play,
+}
+
+impl fmt::Display for ConnectionState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            ConnectionState::Handshaking => "handshake",
+            ConnectionState::Status => "status",
+            ConnectionState::Login => "login",
+            ConnectionState::Configuration => "configuration",
+            ConnectionState::Play => "play",
+        })
+    }
+}
+
+/// A packet passing through the proxy.
+pub struct ProxiedPacket<'a> {
+    /// Connections are numbered from 1 in the order they were accepted.
+    pub connection: usize,
+    pub direction: Direction,
+    pub state: ConnectionState,
+    /// From the client's handshake, 0 before it.
+    pub protocol: i32,
+    pub id: i32,
+    /// The data after the ID, decompressed.
+    pub data: &'a [u8],
+    /// The frame's size on the wire, after compression.
+    pub frame_length: usize,
+    /// For handshake, status and login packets, and every clientbound play packet.
+    pub name: Option<&'static str>,
+}
+
+pub enum ProxyEvent<'a> {
+    /// A client connected and the proxy reached the server for it.
+    Connected {
+        connection: usize,
+        client: SocketAddr,
+    },
+    Packet(ProxiedPacket<'a>),
+    /// A frame that couldn't be unpacked. It's passed on all the same.
+    Malformed {
+        connection: usize,
+        direction: Direction,
+        error: ClientError,
+    },
+    /// The server asked for encryption, the rest of the connection is passed on unread.
+    Encrypted {
+        connection: usize,
+    },
+    /// The client sent a pre-1.7 server list ping, which is passed on unread.
+    LegacyPing {
+        connection: usize,
+    },
+    Closed {
+        connection: usize,
+        reason: String,
+    },
+}
+
+type Handler = dyn Fn(&ProxyEvent) + Send + Sync;
+
+// What both directions of one connection need to know about it
+struct Session {
+    connection: usize,
+    state: Mutex<(ConnectionState, i32)>,
+    threshold: AtomicI32,
+    // Per direction: whether what follows can't be read anymore
+    opaque: [AtomicBool; 2],
+}
+
+impl Session {
+    fn state(&self) -> (ConnectionState, i32) {
+        *self.state.lock().unwrap()
+    }
+
+    fn set_state(&self, state: ConnectionState) {
+        self.state.lock().unwrap().0 = state;
+    }
+}
+
+/// Passes every connection `listener` accepts on to `host`:`port`, calling `handler` for
+/// each packet either way. Without a `port`, the host's SRV record is looked up first. Connections are handled on their own threads; this keeps
+/// accepting them for as long as the process runs.
+pub fn run(
+    listener: TcpListener,
+    host: &str,
+    port: Option<u16>,
+    proxy: Option<&Socks5Proxy>,
+    policy: &ConnectPolicy,
+    handler: impl Fn(&ProxyEvent) + Send + Sync + 'static,
+) {
+    let handler: Arc<Handler> = Arc::new(handler);
+    let connections: AtomicUsize = AtomicUsize::new(0);
+
+    for client in listener.incoming() {
+        let Ok(client) = client else {
+            continue;
+        };
+        let connection: usize = connections.fetch_add(1, Ordering::SeqCst) + 1;
+        let handler: Arc<Handler> = Arc::clone(&handler);
+        let (host, proxy, policy): (String, Option<Socks5Proxy>, ConnectPolicy) =
+            (String::from(host), proxy.cloned(), policy.clone());
+        thread::spawn(move || {
+            let reason: String = match helper::connect(&host, port, proxy.as_ref(), &policy, false)
+            {
+                Ok(server) => {
+                    let port: u16 = port.unwrap_or(helper::DEFAULT_PORT);
+                    forward(connection, client, server, &host, port, &handler)
+                }
+                Err(e) => format!("Couldn't reach the server: {}", e),
+            };
+            handler(&ProxyEvent::Closed { connection, reason });
+        });
+    }
+}
+
+// Runs both directions until one of them ends, returning why
+fn forward(
+    connection: usize,
+    client: TcpStream,
+    server: TcpStream,
+    host: &str,
+    port: u16,
+    handler: &Arc<Handler>,
+) -> String {
+    let (Ok(client_writer), Ok(server_writer)) = (client.try_clone(), server.try_clone()) else {
+        return String::from("Couldn't set the connection up");
+    };
+    if let Ok(address) = client.peer_addr() {
+        handler(&ProxyEvent::Connected {
+            connection,
+            client: address,
+        });
+    }
+
+    let session: Arc<Session> = Arc::new(Session {
+        connection,
+        state: Mutex::new((ConnectionState::Handshaking, 0)),
+        threshold: AtomicI32::new(-1),
+        opaque: [AtomicBool::new(false), AtomicBool::new(false)],
+    });
+
+    let clientbound = {
+        let session: Arc<Session> = Arc::clone(&session);
+        let handler: Arc<Handler> = Arc::clone(handler);
+        let (server, client): (TcpStream, TcpStream) = (server, client_writer);
+        thread::spawn(move || {
+            let res: Result<(), ClientError> = relay(
+                &server,
+                &client,
+                Direction::Clientbound,
+                &session,
+                &*handler,
+                None,
+            );
+            let _ = server.shutdown(Shutdown::Both);
+            let _ = client.shutdown(Shutdown::Both);
+            res
+        })
+    };
+
+    let serverbound: Result<(), ClientError> = relay(
+        &client,
+        &server_writer,
+        Direction::Serverbound,
+        &session,
+        &**handler,
+        Some((host, port)),
+    );
+    let _ = client.shutdown(Shutdown::Both);
+    let _ = server_writer.shutdown(Shutdown::Both);
+
+    match (serverbound, clientbound.join()) {
+        (Err(e), _) => format!("Client connection: {}", e),
+        (_, Ok(Err(e))) => format!("Server connection: {}", e),
+        _ => String::from("Connection closed"),
+    }
+}
+
+// Passes the frames from one side on to the other. `target` is the server's address for
+// the handshake, only given for the serverbound direction.
+fn relay(
+    mut from: &TcpStream,
+    mut to: &TcpStream,
+    direction: Direction,
+    session: &Session,
+    handler: &Handler,
+    target: Option<(&str, u16)>,
+) -> Result<(), ClientError> {
+    let mut frame: Vec<u8> = Vec::new();
+
+    loop {
+        let mut first: [u8; 1] = [0; 1];
+        if from.read(&mut first)? == 0 {
+            return Ok(());
+        }
+
+        let (state, protocol): (ConnectionState, i32) = session.state();
+        // Old clients ping with 0xFE instead of a handshake
+        if state == ConnectionState::Handshaking && first[0] == 0xFE {
+            for opaque in &session.opaque {
+                opaque.store(true, Ordering::SeqCst);
+            }
+            handler(&ProxyEvent::LegacyPing {
+                connection: session.connection,
+            });
+        }
+        if session.opaque[direction.index()].load(Ordering::SeqCst) {
+            to.write_all(&first)?;
+            io::copy(&mut from, &mut to)?;
+            return Ok(());
+        }
+
+        let frame_length: i32 = (&first[..]).chain(from).read_varint()?;
+        if !(1..=MAX_PACKET_LENGTH).contains(&frame_length) {
+            return Err(format!("Invalid packet length {}", frame_length).into());
+        }
+        frame.clear();
+        from.take(frame_length as u64).read_to_end(&mut frame)?;
+        if frame.len() != frame_length as usize {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+
+        let threshold: i32 = session.threshold.load(Ordering::SeqCst);
+        let (id, data): (i32, Vec<u8>) = match decode_frame(&frame, threshold) {
+            Ok(packet) => packet,
+            Err(error) => {
+                handler(&ProxyEvent::Malformed {
+                    connection: session.connection,
+                    direction,
+                    error,
+                });
+                write_frame(to, &frame)?;
+                continue;
+            }
+        };
+
+        let handshake: Option<Vec<u8>> = match (state, target) {
+            (ConnectionState::Handshaking, Some((host, port))) if id == 0x00 => {
+                let (handshake, protocol, next): (Vec<u8>, i32, ConnectionState) =
+                    rewrite_handshake(&data, host, port)?;
+                *session.state.lock().unwrap() = (ConnectionState::Handshaking, protocol);
+                report(session, direction, id, &data, frame.len(), handler);
+                session.set_state(next);
+                Some(handshake)
+            }
+            _ => {
+                report(session, direction, id, &data, frame.len(), handler);
+                None
+            }
+        };
+
+        // Changes take effect after the packet that causes them, but before it's passed
+        // on: the other side may answer in the new state right away
+        match (state, direction, id) {
+            (ConnectionState::Login, Direction::Clientbound, 0x01) => {
+                session.opaque[Direction::Clientbound.index()].store(true, Ordering::SeqCst);
+                handler(&ProxyEvent::Encrypted {
+                    connection: session.connection,
+                });
+            }
+            // Encryption Response, the last packet the client sends unencrypted
+            (ConnectionState::Login, Direction::Serverbound, 0x01) => {
+                session.opaque[Direction::Serverbound.index()].store(true, Ordering::SeqCst);
+            }
+            (ConnectionState::Login, Direction::Clientbound, 0x02)
+                if !has_configuration(protocol) =>
+            {
+                session.set_state(ConnectionState::Play);
+            }
+            (ConnectionState::Login, Direction::Clientbound, 0x03) => {
+                if let LoginPacket::SetCompression { threshold } =
+                    LoginPacket::decode(protocol, id, &data)?
+                {
+                    session.threshold.store(threshold, Ordering::SeqCst);
+                }
+            }
+            // Login Acknowledged
+            (ConnectionState::Login, Direction::Serverbound, 0x03)
+                if has_configuration(protocol) =>
+            {
+                session.set_state(ConnectionState::Configuration);
+            }
+            (ConnectionState::Configuration, Direction::Serverbound, id)
+                if id == configuration::finish_ids(protocol).1 =>
+            {
+                session.set_state(ConnectionState::Play);
+            }
+            _ => {}
+        }
+
+        write_frame(to, handshake.as_deref().unwrap_or(&frame))?;
+    }
+}
+
+fn write_frame(mut to: &TcpStream, frame: &[u8]) -> Result<(), ClientError> {
+    let mut buffer: Vec<u8> = Vec::with_capacity(frame.len() + 3);
+    buffer.write_var_int(VarInt::from(frame.len() as i32))?;
+    buffer.extend_from_slice(frame);
+    to.write_all(&buffer)?;
+    Ok(())
+}
+
+// The handshake packet (uncompressed, with its ID) pointing at `host`:`port` instead,
+// the client's protocol and the state it asks for. Anything after a NUL in the address
+// (Forge's marker, BungeeCord's forwarding) is kept.
+fn rewrite_handshake(
+    data: &[u8],
+    host: &str,
+    port: u16,
+) -> Result<(Vec<u8>, i32, ConnectionState), ClientError> {
+    let mut cursor: Cursor<&[u8]> = Cursor::new(data);
+    let protocol: i32 = cursor.read_varint()?;
+    let address: String = McString::DEFAULT.read(&mut cursor)?;
+    let _port: u16 = cursor.read_u16()?;
+    let intent: i32 = cursor.read_varint()?;
+
+    let next: ConnectionState = match intent {
+        1 => ConnectionState::Status,
+        // 3 is a transfer (1.20.5+), which logs in too
+        _ => ConnectionState::Login,
+    };
+    let suffix: &str = address.find('\0').map_or("", |start| &address[start..]);
+
+    let mut packet: Vec<u8> = Vec::new();
+    packet.write_var_int(VarInt::from(0x00))?;
+    packet.write_var_int(VarInt::from(protocol))?;
+    McString::DEFAULT.write(&mut packet, &format!("{}{}", host, suffix))?;
+    packet.write_all(&port.to_be_bytes())?;
+    packet.write_var_int(VarInt::from(intent))?;
+    Ok((packet, protocol, next))
+}
+
+fn report(
+    session: &Session,
+    direction: Direction,
+    id: i32,
+    data: &[u8],
+    frame_length: usize,
+    handler: &Handler,
+) {
+    let (state, protocol): (ConnectionState, i32) = session.state();
+    handler(&ProxyEvent::Packet(ProxiedPacket {
+        connection: session.connection,
+        direction,
+        state,
+        protocol,
+        id,
+        data,
+        frame_length,
+        name: packet_name(state, direction, protocol, id),
+    }));
+}
+
+fn packet_name(
+    state: ConnectionState,
+    direction: Direction,
+    protocol: i32,
+    id: i32,
+) -> Option<&'static str> {
+    Some(match (state, direction, id) {
+        (ConnectionState::Handshaking, Direction::Serverbound, 0x00) => "Handshake",
+        (ConnectionState::Status, Direction::Serverbound, 0x00) => "Status Request",
+        (ConnectionState::Status, Direction::Serverbound, 0x01) => "Ping",
+        (ConnectionState::Status, Direction::Clientbound, 0x00) => "Status Response",
+        (ConnectionState::Status, Direction::Clientbound, 0x01) => "Pong",
+        (ConnectionState::Login, Direction::Serverbound, 0x00) => "Login Start",
+        (ConnectionState::Login, Direction::Serverbound, 0x01) => "Encryption Response",
+        (ConnectionState::Login, Direction::Serverbound, 0x02) => "Login Plugin Response",
+        (ConnectionState::Login, Direction::Serverbound, 0x03) => "Login Acknowledged",
+        (ConnectionState::Login, Direction::Serverbound, 0x04) => "Cookie Response",
+        (ConnectionState::Login, Direction::Clientbound, 0x00) => "Disconnect",
+        (ConnectionState::Login, Direction::Clientbound, 0x01) => "Encryption Request",
+        (ConnectionState::Login, Direction::Clientbound, 0x02) => "Login Success",
+        (ConnectionState::Login, Direction::Clientbound, 0x03) => "Set Compression",
+        (ConnectionState::Login, Direction::Clientbound, 0x04) => "Login Plugin Request",
+        (ConnectionState::Login, Direction::Clientbound, 0x05) => "Cookie Request",
+        (ConnectionState::Configuration, Direction::Clientbound, id)
+            if id == configuration::finish_ids(protocol).0 =>
+        {
+            "Finish Configuration"
+        }
+        (ConnectionState::Configuration, Direction::Serverbound, id)
+            if id == configuration::finish_ids(protocol).1 =>
+        {
+            "Acknowledge Finish Configuration"
+        }
+        (ConnectionState::Play, Direction::Clientbound, id) => {
+            return protocol::table_for(protocol).and_then(|table| table.clientbound_name(id));
+        }
+        _ => return None,
+    })
+}
@@ -0,0 +1,138 @@
+use std::fmt;
+
+use base64::{engine::Engine, prelude::BASE64_STANDARD};
+use serde_json::Value;
+
+use crate::error::ClientError;
+use crate::hash::md5;
+use crate::http;
+
+// Public Mojang endpoints, no account needed. Players that only exist on offline-mode
+// servers aren't known to them.
+const PROFILE_BY_NAME_URL: &str = "https://api.mojang.com/users/profiles/minecraft/";
+const PROFILE_BY_UUID_URL: &str = "https://sessionserver.mojang.com/session/minecraft/profile/";
+
+/// A Minecraft account as known to Mojang.
+#[derive(Clone, Debug)]
+pub struct Profile {
+    pub uuid: u128,
+    pub name: String,
+    pub skin_url: Option<String>,
+    pub cape_url: Option<String>,
+    /// Whether the skin uses the slim (Alex) arm model.
+    pub slim: bool,
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Name: {}", self.name)?;
+        write!(f, "UUID: {}", format_uuid(self.uuid))?;
+        if let Some(skin_url) = &self.skin_url {
+            let model: &str = if self.slim { "slim" } else { "classic" };
+            write!(f, "\nSkin: {} ({})", skin_url, model)?;
+        }
+        if let Some(cape_url) = &self.cape_url {
+            write!(f, "\nCape: {}", cape_url)?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) struct Textures {
+    pub(crate) skin_url: Option<String>,
+    pub(crate) cape_url: Option<String>,
+    pub(crate) slim: bool,
+}
+
+/// Decodes the base64 `textures` property sent with profiles and player list entries.
+pub(crate) fn decode_textures(value: &str) -> Option<Textures> {
+    let json: Vec<u8> = BASE64_STANDARD.decode(value).ok()?;
+    let value: Value = serde_json::from_slice(&json).ok()?;
+    let textures: &Value = &value["textures"];
+
+    Some(Textures {
+        skin_url: textures["SKIN"]["url"].as_str().map(String::from),
+        cape_url: textures["CAPE"]["url"].as_str().map(String::from),
+        slim: textures["SKIN"]["metadata"]["model"] == "slim",
+    })
+}
+
+/// Formats a UUID with dashes, e.g. `069a79f4-44e9-4726-a5be-fca90e38aaf5`.
+pub fn format_uuid(uuid: u128) -> String {
+    let hex: String = format!("{:032x}", uuid);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// Parses a UUID with or without dashes.
+pub fn parse_uuid(text: &str) -> Option<u128> {
+    let hex: String = text.chars().filter(|c| *c != '-').collect();
+    if hex.len() != 32 {
+        return None;
+    }
+    u128::from_str_radix(&hex, 16).ok()
+}
+
+/// The UUID an offline-mode server gives `name`: a version 3 UUID of `OfflinePlayer:<name>`.
+pub fn offline_uuid(name: &str) -> u128 {
+    let mut hash: [u8; 16] = md5(format!("OfflinePlayer:{}", name).as_bytes());
+    hash[6] = (hash[6] & 0x0F) | 0x30;
+    hash[8] = (hash[8] & 0x3F) | 0x80;
+    u128::from_be_bytes(hash)
+}
+
+// Mojang answers unknown names and UUIDs with 204 or 404
+fn get_json(url: &str) -> Result<Option<Value>, ClientError> {
+    let (status, body): (u16, Vec<u8>) = http::get_with_status(url)?;
+    match status {
+        200 => Ok(Some(serde_json::from_slice(&body)?)),
+        204 | 404 => Ok(None),
+        _ => Err(format!("The Mojang API answered with status {}", status).into()),
+    }
+}
+
+/// Looks up a profile by its UUID. Returns `None` if Mojang doesn't know it.
+pub fn lookup_uuid(uuid: u128) -> Result<Option<Profile>, ClientError> {
+    let Some(json) = get_json(&format!("{}{:032x}", PROFILE_BY_UUID_URL, uuid))? else {
+        return Ok(None);
+    };
+
+    let name: String = match json["name"].as_str() {
+        Some(name) => String::from(name),
+        None => return Err("The Mojang API didn't send a name".into()),
+    };
+    let textures: Option<Textures> = json["properties"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|property| property["name"] == "textures")
+        .and_then(|property| property["value"].as_str())
+        .and_then(decode_textures);
+
+    Ok(Some(Profile {
+        uuid,
+        name,
+        skin_url: textures.as_ref().and_then(|t| t.skin_url.clone()),
+        cape_url: textures.as_ref().and_then(|t| t.cape_url.clone()),
+        slim: textures.is_some_and(|t| t.slim),
+    }))
+}
+
+/// Looks up a profile by player name (case-insensitive). Returns `None` if no account
+/// has that name.
+pub fn lookup_name(name: &str) -> Result<Option<Profile>, ClientError> {
+    let Some(json) = get_json(&format!("{}{}", PROFILE_BY_NAME_URL, name))? else {
+        return Ok(None);
+    };
+
+    match json["id"].as_str().and_then(parse_uuid) {
+        Some(uuid) => lookup_uuid(uuid),
+        None => Err("The Mojang API didn't send a valid UUID".into()),
+    }
+}
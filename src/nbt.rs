@@ -0,0 +1,137 @@
+use std::io::Cursor;
+
+use serde_json::{Map, Value};
+
+use crate::error::ClientError;
+use crate::types::McRead;
+
+// Reads the network NBT that 1.20.3+ uses for chat components (a root tag without a
+// name) and turns it into the equivalent JSON, so the usual component parser can be used.
+
+const TAG_END: u8 = 0;
+const TAG_BYTE: u8 = 1;
+const TAG_SHORT: u8 = 2;
+const TAG_INT: u8 = 3;
+const TAG_LONG: u8 = 4;
+const TAG_FLOAT: u8 = 5;
+const TAG_DOUBLE: u8 = 6;
+const TAG_BYTE_ARRAY: u8 = 7;
+const TAG_STRING: u8 = 8;
+const TAG_LIST: u8 = 9;
+const TAG_COMPOUND: u8 = 10;
+const TAG_INT_ARRAY: u8 = 11;
+const TAG_LONG_ARRAY: u8 = 12;
+
+// Style flags are stored as bytes in NBT but are booleans in JSON.
+const BOOLEAN_KEYS: [&str; 6] = [
+    "bold",
+    "italic",
+    "underlined",
+    "strikethrough",
+    "obfuscated",
+    "interpret",
+];
+
+fn read_length(cursor: &mut Cursor<&[u8]>) -> Result<usize, ClientError> {
+    let length: i32 = cursor.read_int()?;
+    if length < 0 {
+        return Err("Negative NBT length".into());
+    }
+    Ok(length as usize)
+}
+
+fn read_nbt_string(cursor: &mut Cursor<&[u8]>) -> Result<String, ClientError> {
+    let length: u16 = cursor.read_u16()?;
+    let bytes: Vec<u8> = cursor.read_bytes(length as usize)?;
+    // Java's modified UTF-8 only differs for NUL and supplementary characters
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+fn read_payload(cursor: &mut Cursor<&[u8]>, tag: u8, depth: usize) -> Result<Value, ClientError> {
+    if depth > 512 {
+        return Err("NBT is nested too deeply".into());
+    }
+
+    let value: Value = match tag {
+        TAG_BYTE => Value::from(cursor.read_byte()?),
+        TAG_SHORT => Value::from(cursor.read_short()?),
+        TAG_INT => Value::from(cursor.read_int()?),
+        TAG_LONG => Value::from(cursor.read_long()?),
+        TAG_FLOAT => Value::from(cursor.read_float()?),
+        TAG_DOUBLE => Value::from(cursor.read_double()?),
+        TAG_BYTE_ARRAY => {
+            let length: usize = read_length(cursor)?;
+            let bytes: Vec<u8> = cursor.read_bytes(length)?;
+            Value::Array(bytes.into_iter().map(|b| Value::from(b as i8)).collect())
+        }
+        TAG_STRING => Value::String(read_nbt_string(cursor)?),
+        TAG_LIST => {
+            let item_tag: u8 = cursor.read_u8()?;
+            let length: usize = read_length(cursor)?;
+            let mut items: Vec<Value> = Vec::new();
+            for _ in 0..length {
+                items.push(read_payload(cursor, item_tag, depth + 1)?);
+            }
+            Value::Array(items)
+        }
+        TAG_COMPOUND => {
+            let mut object: Map<String, Value> = Map::new();
+            loop {
+                let item_tag: u8 = cursor.read_u8()?;
+                if item_tag == TAG_END {
+                    break;
+                }
+                let name: String = read_nbt_string(cursor)?;
+                let mut item: Value = read_payload(cursor, item_tag, depth + 1)?;
+                if BOOLEAN_KEYS.contains(&name.as_str())
+                    && let Some(flag) = item.as_i64()
+                {
+                    item = Value::Bool(flag != 0);
+                }
+                object.insert(name, item);
+            }
+
+            // Lists can't mix tag types, so mixed component lists wrap plain strings as {"": "..."}
+            match object.remove("") {
+                Some(text) if object.is_empty() => {
+                    let mut wrapped: Map<String, Value> = Map::new();
+                    wrapped.insert(String::from("text"), text);
+                    Value::Object(wrapped)
+                }
+                Some(text) => {
+                    object.insert(String::from("text"), text);
+                    Value::Object(object)
+                }
+                None => Value::Object(object),
+            }
+        }
+        TAG_INT_ARRAY => {
+            let length: usize = read_length(cursor)?;
+            let mut items: Vec<Value> = Vec::new();
+            for _ in 0..length {
+                items.push(Value::from(cursor.read_int()?));
+            }
+            Value::Array(items)
+        }
+        TAG_LONG_ARRAY => {
+            let length: usize = read_length(cursor)?;
+            let mut items: Vec<Value> = Vec::new();
+            for _ in 0..length {
+                items.push(Value::from(cursor.read_long()?));
+            }
+            Value::Array(items)
+        }
+        _ => return Err(format!("Unknown NBT tag {}", tag).into()),
+    };
+
+    Ok(value)
+}
+
+/// Reads a nameless root tag, as sent over the network since 1.20.2.
+pub(crate) fn read_network_nbt(cursor: &mut Cursor<&[u8]>) -> Result<Value, ClientError> {
+    let tag: u8 = cursor.read_u8()?;
+    if tag == TAG_END {
+        return Ok(Value::Null);
+    }
+    read_payload(cursor, tag, 0)
+}
@@ -0,0 +1,169 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use azalea_chat::FormattedText;
+use mclient_project::mojang::format_uuid;
+use mclient_project::packet::ChatKind;
+use mclient_project::{ClientError, Event, ServerStatus};
+use serde_json::{Value, json};
+
+use crate::lang::Language;
+
+// `--json` prints one object per line on stdout. Every object has an "event" name and
+// a "time" in milliseconds since the Unix epoch; text is translated plain text, with
+// the chat component itself under "component" where there is one.
+
+/// The line for an event from the client. With several bots, `bot` names the one it
+/// came from.
+pub fn event_line(event: &Event, bot: Option<&str>, language: &Language) -> String {
+    let mut fields: Value = match event {
+        Event::Connected => json!({ "event": "connected" }),
+        Event::ChatReceived {
+            message,
+            sender,
+            kind,
+            ..
+        } => json!({
+            "event": "chat",
+            "kind": match kind {
+                ChatKind::Player => "player",
+                ChatKind::System => "system",
+                ChatKind::Disguised => "disguised",
+            },
+            "sender": sender.map(format_uuid),
+            "text": plain(message, language),
+            "component": component(message),
+        }),
+        Event::ActionBar(text) => json!({
+            "event": "action_bar",
+            "text": plain(text, language),
+        }),
+        Event::Title {
+            title,
+            subtitle,
+            duration,
+        } => json!({
+            "event": "title",
+            "title": plain(title, language),
+            "subtitle": subtitle.as_ref().map(|subtitle| plain(subtitle, language)),
+            "duration_ms": duration.as_millis() as u64,
+        }),
+        Event::PlayerJoined {
+            uuid,
+            name,
+            display_name,
+        } => json!({
+            "event": "join",
+            "uuid": format_uuid(*uuid),
+            "name": name,
+            "display_name": display_name.as_ref().map(|display_name| plain(display_name, language)),
+        }),
+        Event::PlayerLeft {
+            uuid,
+            name,
+            display_name,
+        } => json!({
+            "event": "leave",
+            "uuid": format_uuid(*uuid),
+            "name": name,
+            "display_name": display_name.as_ref().map(|display_name| plain(display_name, language)),
+        }),
+        Event::KeepAlive { id } => json!({ "event": "keep_alive", "id": id }),
+        Event::Died(message) => json!({
+            "event": "death",
+            "text": plain(message, language),
+        }),
+        Event::Kicked(reason) => json!({
+            "event": "kick",
+            "text": plain(reason, language),
+            "component": component(reason),
+        }),
+        Event::MalformedPacket { id, error, payload } => json!({
+            "event": "malformed_packet",
+            "id": id,
+            "reason": error,
+            "payload": payload.iter().map(|byte| format!("{:02x}", byte)).collect::<String>(),
+        }),
+        Event::IgnoredPacket { id, payload } => json!({
+            "event": "ignored_packet",
+            "id": id,
+            "payload": payload.iter().map(|byte| format!("{:02x}", byte)).collect::<String>(),
+        }),
+    };
+    if let Some(bot) = bot {
+        fields["bot"] = json!(bot);
+    }
+    line(fields)
+}
+
+/// The line for the status the server answered with before logging in.
+pub fn status_line(status: &ServerStatus, language: &Language) -> String {
+    line(status_fields(status, language))
+}
+
+/// The line for a status request that got no answer.
+pub fn down_line(e: &ClientError) -> String {
+    line(down_fields(e))
+}
+
+/// The line for one server of a server list, with its address under "server".
+pub fn server_line(
+    address: &str,
+    status: &Result<ServerStatus, ClientError>,
+    language: &Language,
+) -> String {
+    let mut fields: Value = match status {
+        Ok(status) => status_fields(status, language),
+        Err(e) => down_fields(e),
+    };
+    fields["server"] = json!(address);
+    line(fields)
+}
+
+fn status_fields(status: &ServerStatus, language: &Language) -> Value {
+    json!({
+        "event": "status",
+        "version": status.version.name,
+        "protocol": status.version.protocol,
+        "online": status.players.as_ref().map(|players| players.online),
+        "max": status.players.as_ref().map(|players| players.max),
+        "motd": plain(&status.description, language),
+        "latency_ms": status.latency.map(|latency| latency.as_millis() as u64),
+    })
+}
+
+fn down_fields(e: &ClientError) -> Value {
+    json!({
+        "event": "down",
+        "reason": e.to_string(),
+    })
+}
+
+/// The last line, once the connection is gone for good. With several bots, `bot` names
+/// the one it came from.
+pub fn disconnected_line(e: &ClientError, bot: Option<&str>) -> String {
+    let mut fields: Value = json!({
+        "event": "disconnected",
+        "reason": e.to_string(),
+    });
+    if let Some(bot) = bot {
+        fields["bot"] = json!(bot);
+    }
+    line(fields)
+}
+
+fn line(mut fields: Value) -> String {
+    let time: u128 = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    fields["time"] = json!(time as u64);
+    fields.to_string()
+}
+
+fn plain(text: &FormattedText, language: &Language) -> String {
+    language.translate(text).to_string()
+}
+
+fn component(text: &FormattedText) -> Value {
+    serde_json::to_value(text).unwrap_or(Value::Null)
+}
@@ -0,0 +1,1341 @@
+use std::io::{Cursor, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use azalea_chat::FormattedText;
+use azalea_chat::text_component::TextComponent;
+use mc_varint::{VarInt, VarIntWrite};
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::bossbar::BossBarColor;
+use crate::client::{ChatMode, ClientSettings};
+use crate::commandtree::{CommandTree, read_command_tree};
+use crate::error::ClientError;
+use crate::interaction::{Interaction, interactions};
+use crate::mcstring::McString;
+use crate::mojang::{decode_textures, parse_uuid};
+use crate::nbt::read_network_nbt;
+use crate::player::{GameMode, Health, Identity, Position};
+use crate::protocol::{Clientbound, PacketTable, Serverbound};
+use crate::types::McRead;
+
+pub use crate::configuration::ConfigurationPacket;
+pub use crate::helper::decode_frame;
+
+#[derive(Clone, Debug)]
+pub enum PlayerInfoAction {
+    AddPlayer {
+        name: String,
+        skin_url: Option<String>,
+        game_mode: GameMode,
+        latency: i32,
+        display_name: Option<FormattedText>,
+    },
+    UpdateGameMode(GameMode),
+    UpdateLatency(i32),
+    UpdateDisplayName(Option<FormattedText>),
+    RemovePlayer,
+}
+
+/// What the client tells the server about a resource pack it was sent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResourcePackStatus {
+    Loaded,
+    Declined,
+    Accepted,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChatKind {
+    /// Written by a player.
+    Player,
+    /// Sent by the server itself (command feedback, join messages, plugins...).
+    System,
+    /// A player message that the server sent without the player's signature (1.19.3+).
+    Disguised,
+}
+
+#[derive(Clone, Debug)]
+pub enum TitleAction {
+    SetTitle(FormattedText),
+    /// Shown together with the next title.
+    SetSubtitle(FormattedText),
+    /// Durations in ticks.
+    SetTimes {
+        fade_in: i32,
+        stay: i32,
+        fade_out: i32,
+    },
+    Hide,
+    /// Hides the title and forgets the subtitle and times.
+    Reset,
+}
+
+#[derive(Clone, Debug)]
+pub enum BossBarAction {
+    Add {
+        title: FormattedText,
+        progress: f32,
+        color: BossBarColor,
+    },
+    Remove,
+    UpdateProgress(f32),
+    UpdateTitle(FormattedText),
+    UpdateColor(BossBarColor),
+    UpdateFlags,
+}
+
+/// How a scoreboard number is drawn (1.20.3+).
+#[derive(Clone, Debug)]
+pub enum NumberFormat {
+    Blank,
+    /// The number in a style of its own, which isn't kept.
+    Styled,
+    Fixed(Box<FormattedText>),
+}
+
+#[derive(Clone, Debug)]
+pub enum ObjectiveAction {
+    /// Creates the objective or updates its display name.
+    Set {
+        display_name: FormattedText,
+        number_format: Option<NumberFormat>,
+    },
+    Remove,
+}
+
+#[derive(Clone, Debug)]
+pub struct TeamInfo {
+    /// Before 1.13 these are plain strings with `§` codes that run on into the name.
+    pub prefix: FormattedText,
+    pub suffix: FormattedText,
+    /// Formatting code index (0-15 are colors).
+    pub color: i32,
+}
+
+#[derive(Clone, Debug)]
+pub enum TeamAction {
+    Create {
+        info: TeamInfo,
+        members: Vec<String>,
+    },
+    Remove,
+    Update(TeamInfo),
+    AddMembers(Vec<String>),
+    RemoveMembers(Vec<String>),
+}
+
+#[derive(Clone, Debug)]
+pub enum ClientboundPacket<'a> {
+    ChatMessage {
+        message: FormattedText,
+        /// UUID of the player who sent the message, if the server says so.
+        sender: Option<u128>,
+        kind: ChatKind,
+        /// The parts of the message with hover text or click actions.
+        interactions: Vec<Interaction>,
+    },
+    /// Text shown above the hotbar.
+    ActionBar {
+        text: FormattedText,
+    },
+    Title(TitleAction),
+    BossBar {
+        uuid: u128,
+        action: BossBarAction,
+    },
+    /// Shows `objective` in a display slot (1 is the sidebar). Empty clears the slot.
+    DisplayObjective {
+        position: i32,
+        objective: String,
+    },
+    UpdateObjective {
+        name: String,
+        action: ObjectiveAction,
+    },
+    UpdateTeam {
+        name: String,
+        action: TeamAction,
+    },
+    UpdateScore {
+        entity: String,
+        objective: String,
+        value: i32,
+        display_name: Option<FormattedText>,
+        number_format: Option<NumberFormat>,
+    },
+    /// Removes the score of `entity` in `objective`, or in every objective if `None`.
+    ResetScore {
+        entity: String,
+        objective: Option<String>,
+    },
+    /// Text above and below the player list.
+    TabList {
+        header: FormattedText,
+        footer: FormattedText,
+    },
+    Disconnect {
+        reason: FormattedText,
+    },
+    KeepAlive {
+        id: i64,
+    },
+    PlayerInfo {
+        entries: Vec<(u128, PlayerInfoAction)>,
+    },
+    /// Custom data on a named channel, e.g. the server brand.
+    PluginMessage {
+        channel: String,
+        data: &'a [u8],
+    },
+    /// Teleports the player. Bits of `relative` mark the fields of `position` that are
+    /// offsets (see `Position`), and the teleport has to be confirmed with `teleport_id`.
+    SynchronizePosition {
+        position: Position,
+        relative: u8,
+        teleport_id: i32,
+    },
+    /// The first packet in the play state.
+    JoinGame {
+        /// The client's own entity ID.
+        entity_id: i32,
+    },
+    SetHealth(Health),
+    /// Completions for the text in a Command Suggestions Request. Before 1.13 there's no
+    /// `id`, and each match replaces the last word instead of the `range` of characters.
+    CommandSuggestions {
+        id: Option<i32>,
+        range: Option<(usize, usize)>,
+        matches: Vec<String>,
+    },
+    /// The server wants the client to use a resource pack. `uuid` identifies the pack
+    /// since 1.20.3, and `hash` is its SHA-1 (may be empty).
+    ResourcePack {
+        uuid: Option<u128>,
+        url: String,
+        hash: String,
+    },
+    /// The client's player died; the death screen shows `message`.
+    Death {
+        message: FormattedText,
+    },
+    /// The commands the player may use, sent on join and when permissions change.
+    Commands(CommandTree),
+    Unknown {
+        id: i32,
+        data: &'a [u8],
+    },
+}
+
+#[derive(Clone, Debug)]
+pub enum ServerboundPacket {
+    ChatMessage {
+        message: String,
+    },
+    /// A command without the leading `/`. Only exists on 1.19+.
+    ChatCommand {
+        command: String,
+    },
+    KeepAlive {
+        id: i64,
+    },
+    ClientSettings(ClientSettings),
+    PluginMessage {
+        channel: String,
+        data: Vec<u8>,
+    },
+    ConfirmTeleport {
+        teleport_id: i32,
+    },
+    PlayerPosition {
+        position: Position,
+        on_ground: bool,
+    },
+    Respawn,
+    SwingArm,
+    PlayerRotation {
+        yaw: f32,
+        pitch: f32,
+        on_ground: bool,
+    },
+    Sneak {
+        entity_id: i32,
+        sneaking: bool,
+    },
+    ResourcePackStatus {
+        uuid: Option<u128>,
+        status: ResourcePackStatus,
+    },
+    /// Asks for completions of `text`, which includes the leading `/`.
+    CommandSuggestions {
+        id: i32,
+        text: String,
+    },
+}
+
+// Everything left in the packet, for payloads that run to the end
+fn read_remaining<'a>(cursor: &mut Cursor<&'a [u8]>) -> &'a [u8] {
+    let data: &'a [u8] = cursor.get_ref();
+    let position: usize = (cursor.position() as usize).min(data.len());
+    cursor.set_position(data.len() as u64);
+    &data[position..]
+}
+
+/// The channel the client and server send their brand on; it was renamed in 1.13.
+pub(crate) fn brand_channel(protocol: i32) -> &'static str {
+    if protocol < 393 {
+        "MC|Brand"
+    } else {
+        "minecraft:brand"
+    }
+}
+
+/// Encodes a brand as the payload of a brand plugin message.
+pub(crate) fn encode_brand(brand: &str) -> Result<Vec<u8>, ClientError> {
+    let mut data: Vec<u8> = Vec::new();
+    McString::DEFAULT.write(&mut data, brand)?;
+    Ok(data)
+}
+
+/// Reads the brand out of a brand plugin message payload.
+pub(crate) fn decode_brand(data: &[u8]) -> Result<String, ClientError> {
+    McString::DEFAULT.read(&mut Cursor::new(data))
+}
+
+/// Reads the channel and payload of a plugin message, in play or configuration.
+pub(crate) fn read_plugin_message<'a>(
+    cursor: &mut Cursor<&'a [u8]>,
+) -> Result<(String, &'a [u8]), ClientError> {
+    let channel: String = McString::DEFAULT.read(cursor)?;
+    Ok((channel, read_remaining(cursor)))
+}
+
+pub(crate) fn read_chat(cursor: &mut Cursor<&[u8]>) -> Result<FormattedText, ClientError> {
+    let json_str: Value = serde_json::from_str(McString::COMPONENT.read(cursor)?.as_str())?;
+    Ok(FormattedText::deserialize(&json_str)?)
+}
+
+// Reads a chat component in the format `protocol` uses (JSON, or NBT since 1.20.3)
+// without parsing it yet.
+fn read_component_json(cursor: &mut Cursor<&[u8]>, protocol: i32) -> Result<Value, ClientError> {
+    if protocol < 765 {
+        return Ok(serde_json::from_str(
+            McString::COMPONENT.read(cursor)?.as_str(),
+        )?);
+    }
+    read_network_nbt(cursor)
+}
+
+/// Reads a chat component in the format `protocol` uses: JSON, or NBT since 1.20.3.
+pub(crate) fn read_component(
+    cursor: &mut Cursor<&[u8]>,
+    protocol: i32,
+) -> Result<FormattedText, ClientError> {
+    Ok(FormattedText::deserialize(&read_component_json(
+        cursor, protocol,
+    )?)?)
+}
+
+// Positions of the private message types in the vanilla chat type registry
+const MSG_COMMAND_INCOMING: i32 = 2;
+const MSG_COMMAND_OUTGOING: i32 = 3;
+
+// Chat types are registry IDs; since 1.20.5 the ID can also be followed by an inline definition.
+fn read_chat_type(cursor: &mut Cursor<&[u8]>, protocol: i32) -> Result<i32, ClientError> {
+    let chat_type: i32 = cursor.read_varint()?;
+    if protocol >= 766 {
+        if chat_type == 0 {
+            return Err("Inline chat type definitions aren't supported".into());
+        }
+        return Ok(chat_type - 1);
+    }
+    Ok(chat_type)
+}
+
+// Chat types come from the registry the server sent during configuration, which the
+// client doesn't keep. Private messages are recognized by their place in the vanilla
+// registry and shown with the vanilla translation keys. Other player messages are shown
+// like the vanilla "chat" type, or as "sender -> target: message" when there's a target
+// (team messages).
+fn player_message(
+    chat_type: i32,
+    sender_name: Value,
+    target_name: Option<Value>,
+    content: Value,
+) -> Value {
+    match (chat_type, target_name) {
+        (MSG_COMMAND_INCOMING, _) => json!({
+            "translate": "commands.message.display.incoming",
+            "with": [sender_name, content],
+            "color": "gray",
+            "italic": true,
+        }),
+        (MSG_COMMAND_OUTGOING, Some(target_name)) => json!({
+            "translate": "commands.message.display.outgoing",
+            "with": [target_name, content],
+            "color": "gray",
+            "italic": true,
+        }),
+        (_, Some(target_name)) => json!({
+            "translate": "mclient.chat.targeted",
+            "fallback": "%s -> %s: %s",
+            "with": [sender_name, target_name, content],
+        }),
+        (_, None) => json!({
+            "translate": "chat.type.text",
+            "with": [sender_name, content],
+        }),
+    }
+}
+
+fn chat_message(
+    component: Value,
+    sender: Option<u128>,
+    kind: ChatKind,
+) -> Result<ClientboundPacket<'static>, ClientError> {
+    Ok(ClientboundPacket::ChatMessage {
+        message: FormattedText::deserialize(&component)?,
+        sender,
+        kind,
+        interactions: interactions(&component),
+    })
+}
+
+// The 1.19.3+ layout. 1.19 to 1.19.2 signed messages differently, but there are no
+// packet tables for those versions.
+fn read_player_chat<'a>(
+    cursor: &mut Cursor<&'a [u8]>,
+    protocol: i32,
+) -> Result<ClientboundPacket<'a>, ClientError> {
+    let sender: u128 = cursor.read_uuid()?;
+    let _index: i32 = cursor.read_varint()?;
+    if cursor.read_bool()? {
+        let _signature: Vec<u8> = cursor.read_bytes(256)?;
+    }
+
+    let body: String = McString::DEFAULT.read(cursor)?;
+    let _timestamp_and_salt: Vec<u8> = cursor.read_bytes(16)?;
+
+    let previous_messages: i32 = cursor.read_varint()?;
+    for _ in 0..previous_messages {
+        if cursor.read_varint()? == 0 {
+            let _signature: Vec<u8> = cursor.read_bytes(256)?;
+        }
+    }
+
+    // Servers can replace what's shown (e.g. after a chat plugin reformatted it)
+    let mut content: Value = Value::String(body);
+    if cursor.read_bool()? {
+        content = read_component_json(cursor, protocol)?;
+    }
+
+    if cursor.read_varint()? == 2 {
+        // Partially filtered: skip the filter mask
+        let longs: i32 = cursor.read_varint()?;
+        let _mask: Vec<u8> = cursor.read_bytes(longs as usize * 8)?;
+    }
+
+    let chat_type: i32 = read_chat_type(cursor, protocol)?;
+    let sender_name: Value = read_component_json(cursor, protocol)?;
+    let target_name: Option<Value> =
+        cursor.read_optional(|cursor| read_component_json(cursor, protocol))?;
+
+    chat_message(
+        player_message(chat_type, sender_name, target_name, content),
+        Some(sender),
+        ChatKind::Player,
+    )
+}
+
+fn read_disguised_chat<'a>(
+    cursor: &mut Cursor<&'a [u8]>,
+    protocol: i32,
+) -> Result<ClientboundPacket<'a>, ClientError> {
+    let content: Value = read_component_json(cursor, protocol)?;
+    let chat_type: i32 = read_chat_type(cursor, protocol)?;
+    let sender_name: Value = read_component_json(cursor, protocol)?;
+    let target_name: Option<Value> =
+        cursor.read_optional(|cursor| read_component_json(cursor, protocol))?;
+
+    chat_message(
+        player_message(chat_type, sender_name, target_name, content),
+        None,
+        ChatKind::Disguised,
+    )
+}
+
+fn read_command_suggestions<'a>(
+    cursor: &mut Cursor<&'a [u8]>,
+    protocol: i32,
+) -> Result<ClientboundPacket<'a>, ClientError> {
+    if protocol < 393 {
+        let count: i32 = cursor.read_varint()?;
+        let mut matches: Vec<String> = Vec::new();
+        for _ in 0..count {
+            matches.push(McString::DEFAULT.read(cursor)?);
+        }
+        return Ok(ClientboundPacket::CommandSuggestions {
+            id: None,
+            range: None,
+            matches,
+        });
+    }
+
+    let id: i32 = cursor.read_varint()?;
+    let start: i32 = cursor.read_varint()?;
+    let length: i32 = cursor.read_varint()?;
+    let count: i32 = cursor.read_varint()?;
+
+    let mut matches: Vec<String> = Vec::new();
+    for _ in 0..count {
+        matches.push(McString::DEFAULT.read(cursor)?);
+        let _tooltip: Option<FormattedText> = read_optional_component(cursor, protocol)?;
+    }
+
+    Ok(ClientboundPacket::CommandSuggestions {
+        id: Some(id),
+        range: Some((start.max(0) as usize, length.max(0) as usize)),
+        matches,
+    })
+}
+
+/// Reads a resource pack request, which looks the same in play and configuration. The
+/// forced flag and prompt that follow since 1.17 aren't needed.
+/// Returns the pack's UUID (1.20.3+), URL and hash.
+pub(crate) fn read_resource_pack(
+    cursor: &mut Cursor<&[u8]>,
+    protocol: i32,
+) -> Result<(Option<u128>, String, String), ClientError> {
+    let uuid: Option<u128> = if protocol >= 765 {
+        Some(cursor.read_uuid()?)
+    } else {
+        None
+    };
+
+    Ok((
+        uuid,
+        McString::DEFAULT.read(cursor)?,
+        McString::DEFAULT.read(cursor)?,
+    ))
+}
+
+/// Writes the body of Resource Pack Status, which is also sent during configuration.
+pub(crate) fn write_resource_pack_status(
+    packet_buffer: &mut Vec<u8>,
+    uuid: Option<u128>,
+    status: ResourcePackStatus,
+) -> Result<(), ClientError> {
+    if let Some(uuid) = uuid {
+        packet_buffer.write_all(&uuid.to_be_bytes())?;
+    }
+    let status: i32 = match status {
+        ResourcePackStatus::Loaded => 0,
+        ResourcePackStatus::Declined => 1,
+        ResourcePackStatus::Accepted => 3,
+    };
+    packet_buffer.write_var_int(VarInt::from(status))?;
+    Ok(())
+}
+
+// 1.17 to 1.19.3 end the packet with a "dismount vehicle" flag, which isn't needed
+fn read_synchronize_position<'a>(
+    cursor: &mut Cursor<&'a [u8]>,
+) -> Result<ClientboundPacket<'a>, ClientError> {
+    let position: Position = Position {
+        x: cursor.read_double()?,
+        y: cursor.read_double()?,
+        z: cursor.read_double()?,
+        yaw: cursor.read_float()?,
+        pitch: cursor.read_float()?,
+    };
+
+    Ok(ClientboundPacket::SynchronizePosition {
+        position,
+        relative: cursor.read_u8()?,
+        teleport_id: cursor.read_varint()?,
+    })
+}
+
+fn read_boss_bar<'a>(
+    cursor: &mut Cursor<&'a [u8]>,
+    protocol: i32,
+) -> Result<ClientboundPacket<'a>, ClientError> {
+    let uuid: u128 = cursor.read_uuid()?;
+    let action: i32 = cursor.read_varint()?;
+
+    // The division and flags that follow only matter for drawing the bar in game
+    let action: BossBarAction = match action {
+        0 => BossBarAction::Add {
+            title: read_component(cursor, protocol)?,
+            progress: cursor.read_float()?,
+            color: BossBarColor::from_id(cursor.read_varint()?),
+        },
+        1 => BossBarAction::Remove,
+        2 => BossBarAction::UpdateProgress(cursor.read_float()?),
+        3 => BossBarAction::UpdateTitle(read_component(cursor, protocol)?),
+        4 => BossBarAction::UpdateColor(BossBarColor::from_id(cursor.read_varint()?)),
+        5 => BossBarAction::UpdateFlags,
+        _ => return Err(format!("Unknown boss bar action {}", action).into()),
+    };
+
+    Ok(ClientboundPacket::BossBar { uuid, action })
+}
+
+fn read_title_times(cursor: &mut Cursor<&[u8]>) -> Result<TitleAction, ClientError> {
+    Ok(TitleAction::SetTimes {
+        fade_in: cursor.read_int()?,
+        stay: cursor.read_int()?,
+        fade_out: cursor.read_int()?,
+    })
+}
+
+// The pre-1.17 Title packet, which also carried the action bar
+fn read_legacy_title<'a>(
+    cursor: &mut Cursor<&'a [u8]>,
+    protocol: i32,
+) -> Result<ClientboundPacket<'a>, ClientError> {
+    let action: i32 = cursor.read_varint()?;
+
+    let title: TitleAction = match action {
+        0 => TitleAction::SetTitle(read_component(cursor, protocol)?),
+        1 => TitleAction::SetSubtitle(read_component(cursor, protocol)?),
+        2 => {
+            return Ok(ClientboundPacket::ActionBar {
+                text: read_component(cursor, protocol)?,
+            });
+        }
+        3 => read_title_times(cursor)?,
+        4 => TitleAction::Hide,
+        5 => TitleAction::Reset,
+        _ => return Err(format!("Unknown title action {}", action).into()),
+    };
+
+    Ok(ClientboundPacket::Title(title))
+}
+
+// 1.13 turned scoreboard and team texts into chat components
+fn read_scoreboard_text(
+    cursor: &mut Cursor<&[u8]>,
+    protocol: i32,
+) -> Result<FormattedText, ClientError> {
+    if protocol < 393 {
+        Ok(FormattedText::from(McString::DEFAULT.read(cursor)?))
+    } else {
+        read_component(cursor, protocol)
+    }
+}
+
+fn read_number_format(
+    cursor: &mut Cursor<&[u8]>,
+    protocol: i32,
+) -> Result<Option<NumberFormat>, ClientError> {
+    if protocol < 765 || !cursor.read_bool()? {
+        return Ok(None);
+    }
+
+    let kind: i32 = cursor.read_varint()?;
+    let number_format: NumberFormat = match kind {
+        0 => NumberFormat::Blank,
+        1 => {
+            read_network_nbt(cursor)?;
+            NumberFormat::Styled
+        }
+        2 => NumberFormat::Fixed(Box::new(read_component(cursor, protocol)?)),
+        _ => return Err(format!("Unknown number format {}", kind).into()),
+    };
+
+    Ok(Some(number_format))
+}
+
+fn read_display_objective<'a>(
+    cursor: &mut Cursor<&'a [u8]>,
+    protocol: i32,
+) -> Result<ClientboundPacket<'a>, ClientError> {
+    // 1.20.2 turned the position byte into a varint
+    let position: i32 = if protocol < 764 {
+        i32::from(cursor.read_u8()?)
+    } else {
+        cursor.read_varint()?
+    };
+
+    Ok(ClientboundPacket::DisplayObjective {
+        position,
+        objective: McString::DEFAULT.read(cursor)?,
+    })
+}
+
+fn read_update_objective<'a>(
+    cursor: &mut Cursor<&'a [u8]>,
+    protocol: i32,
+) -> Result<ClientboundPacket<'a>, ClientError> {
+    let name: String = McString::DEFAULT.read(cursor)?;
+    let mode: u8 = cursor.read_u8()?;
+
+    // The render type (integer or hearts) after the display name is only for the player list
+    let action: ObjectiveAction = match mode {
+        1 => ObjectiveAction::Remove,
+        _ => {
+            let display_name: FormattedText;
+            if protocol < 393 {
+                display_name =
+                    FormattedText::from(TextComponent::new(McString::DEFAULT.read(cursor)?));
+                McString::DEFAULT.read(cursor)?;
+            } else {
+                display_name = read_component(cursor, protocol)?;
+                cursor.read_varint()?;
+            }
+            ObjectiveAction::Set {
+                display_name,
+                number_format: read_number_format(cursor, protocol)?,
+            }
+        }
+    };
+
+    Ok(ClientboundPacket::UpdateObjective { name, action })
+}
+
+fn read_team_members(cursor: &mut Cursor<&[u8]>) -> Result<Vec<String>, ClientError> {
+    let count: i32 = cursor.read_varint()?;
+    let mut members: Vec<String> = Vec::new();
+    for _ in 0..count {
+        members.push(McString::DEFAULT.read(cursor)?);
+    }
+    Ok(members)
+}
+
+fn read_team_info(cursor: &mut Cursor<&[u8]>, protocol: i32) -> Result<TeamInfo, ClientError> {
+    let _display_name: FormattedText = read_scoreboard_text(cursor, protocol)?;
+
+    if protocol < 393 {
+        let prefix: FormattedText = read_scoreboard_text(cursor, protocol)?;
+        let suffix: FormattedText = read_scoreboard_text(cursor, protocol)?;
+        let _flags: u8 = cursor.read_u8()?;
+        let _name_tag_visibility: String = McString::DEFAULT.read(cursor)?;
+        let _collision_rule: String = McString::DEFAULT.read(cursor)?;
+        let color: i32 = i32::from(cursor.read_byte()?);
+
+        return Ok(TeamInfo {
+            prefix,
+            suffix,
+            color,
+        });
+    }
+
+    let _flags: u8 = cursor.read_u8()?;
+    let _name_tag_visibility: String = McString::DEFAULT.read(cursor)?;
+    let _collision_rule: String = McString::DEFAULT.read(cursor)?;
+    let color: i32 = cursor.read_varint()?;
+
+    Ok(TeamInfo {
+        prefix: read_component(cursor, protocol)?,
+        suffix: read_component(cursor, protocol)?,
+        color,
+    })
+}
+
+fn read_update_team<'a>(
+    cursor: &mut Cursor<&'a [u8]>,
+    protocol: i32,
+) -> Result<ClientboundPacket<'a>, ClientError> {
+    let name: String = McString::DEFAULT.read(cursor)?;
+    let mode: u8 = cursor.read_u8()?;
+
+    let action: TeamAction = match mode {
+        0 => TeamAction::Create {
+            info: read_team_info(cursor, protocol)?,
+            members: read_team_members(cursor)?,
+        },
+        1 => TeamAction::Remove,
+        2 => TeamAction::Update(read_team_info(cursor, protocol)?),
+        3 => TeamAction::AddMembers(read_team_members(cursor)?),
+        4 => TeamAction::RemoveMembers(read_team_members(cursor)?),
+        _ => return Err(format!("Unknown team mode {}", mode).into()),
+    };
+
+    Ok(ClientboundPacket::UpdateTeam { name, action })
+}
+
+fn read_update_score<'a>(
+    cursor: &mut Cursor<&'a [u8]>,
+    protocol: i32,
+) -> Result<ClientboundPacket<'a>, ClientError> {
+    let entity: String = McString::DEFAULT.read(cursor)?;
+
+    // 1.20.3 moved removing scores into Reset Score and added display names
+    if protocol >= 765 {
+        let objective: String = McString::DEFAULT.read(cursor)?;
+        let value: i32 = cursor.read_varint()?;
+        let display_name: Option<FormattedText> = if cursor.read_bool()? {
+            Some(read_component(cursor, protocol)?)
+        } else {
+            None
+        };
+
+        return Ok(ClientboundPacket::UpdateScore {
+            entity,
+            objective,
+            value,
+            display_name,
+            number_format: read_number_format(cursor, protocol)?,
+        });
+    }
+
+    let action: i32 = cursor.read_varint()?;
+    let objective: String = McString::DEFAULT.read(cursor)?;
+    if action == 1 {
+        return Ok(ClientboundPacket::ResetScore {
+            entity,
+            objective: Some(objective).filter(|objective| !objective.is_empty()),
+        });
+    }
+
+    Ok(ClientboundPacket::UpdateScore {
+        entity,
+        objective,
+        value: cursor.read_varint()?,
+        display_name: None,
+        number_format: None,
+    })
+}
+
+fn read_optional_component(
+    cursor: &mut Cursor<&[u8]>,
+    protocol: i32,
+) -> Result<Option<FormattedText>, ClientError> {
+    cursor.read_optional(|cursor| read_component(cursor, protocol))
+}
+
+// Returns the skin URL, the other properties (capes) aren't kept
+fn read_properties(cursor: &mut Cursor<&[u8]>) -> Result<Option<String>, ClientError> {
+    let mut skin: Option<String> = None;
+
+    let number_of_properties: i32 = cursor.read_varint()?;
+    for _ in 0..number_of_properties {
+        let name: String = McString::DEFAULT.read(cursor)?;
+        let value: String = McString::DEFAULT.read(cursor)?;
+        if cursor.read_bool()? {
+            let _ = cursor.read_byte_array()?; // signature
+        }
+        if name == "textures" {
+            skin = decode_textures(&value).and_then(|textures| textures.skin_url);
+        }
+    }
+    Ok(skin)
+}
+
+fn read_game_mode(cursor: &mut Cursor<&[u8]>) -> Result<GameMode, ClientError> {
+    Ok(GameMode::from_id(cursor.read_varint()?))
+}
+
+// Player Info before 1.19.3: one action for every player in the packet
+fn read_player_info(
+    cursor: &mut Cursor<&[u8]>,
+    protocol: i32,
+) -> Result<Vec<(u128, PlayerInfoAction)>, ClientError> {
+    let action: i32 = cursor.read_varint()?;
+    let number_of_players: i32 = cursor.read_varint()?;
+    let mut entries: Vec<(u128, PlayerInfoAction)> = Vec::new();
+
+    for _ in 0..number_of_players {
+        let uuid: u128 = cursor.read_uuid()?;
+
+        let entry: PlayerInfoAction = match action {
+            0 => {
+                let name: String = McString::DEFAULT.read(cursor)?;
+                let skin_url: Option<String> = read_properties(cursor)?;
+
+                PlayerInfoAction::AddPlayer {
+                    name,
+                    skin_url,
+                    game_mode: read_game_mode(cursor)?,
+                    latency: cursor.read_varint()?,
+                    display_name: read_optional_component(cursor, protocol)?,
+                }
+            }
+            1 => PlayerInfoAction::UpdateGameMode(read_game_mode(cursor)?),
+            2 => PlayerInfoAction::UpdateLatency(cursor.read_varint()?),
+            3 => PlayerInfoAction::UpdateDisplayName(read_optional_component(cursor, protocol)?),
+            4 => PlayerInfoAction::RemovePlayer,
+            _ => return Err(format!("Unknown player info action {}", action).into()),
+        };
+
+        entries.push((uuid, entry));
+    }
+
+    Ok(entries)
+}
+
+// Player Info Update, 1.19.3+: a set of actions that applies to every player in the packet
+fn read_player_info_update(
+    cursor: &mut Cursor<&[u8]>,
+    protocol: i32,
+) -> Result<Vec<(u128, PlayerInfoAction)>, ClientError> {
+    const ADD_PLAYER: u8 = 0x01;
+    const INITIALIZE_CHAT: u8 = 0x02;
+    const UPDATE_GAME_MODE: u8 = 0x04;
+    const UPDATE_LISTED: u8 = 0x08;
+    const UPDATE_LATENCY: u8 = 0x10;
+    const UPDATE_DISPLAY_NAME: u8 = 0x20;
+
+    let actions: u8 = cursor.read_u8()?;
+    let number_of_players: i32 = cursor.read_varint()?;
+    let mut entries: Vec<(u128, PlayerInfoAction)> = Vec::new();
+
+    for _ in 0..number_of_players {
+        let uuid: u128 = cursor.read_uuid()?;
+
+        if actions & ADD_PLAYER != 0 {
+            let name: String = McString::DEFAULT.read(cursor)?;
+            let skin_url: Option<String> = read_properties(cursor)?;
+            entries.push((
+                uuid,
+                PlayerInfoAction::AddPlayer {
+                    name,
+                    skin_url,
+                    game_mode: GameMode::Survival,
+                    latency: 0,
+                    display_name: None,
+                },
+            ));
+        }
+        if actions & INITIALIZE_CHAT != 0 && cursor.read_bool()? {
+            let _session_id: u128 = cursor.read_uuid()?;
+            let _expires_at: i64 = cursor.read_long()?;
+            let _public_key: Vec<u8> = cursor.read_byte_array()?;
+            let _key_signature: Vec<u8> = cursor.read_byte_array()?;
+        }
+        if actions & UPDATE_GAME_MODE != 0 {
+            entries.push((
+                uuid,
+                PlayerInfoAction::UpdateGameMode(read_game_mode(cursor)?),
+            ));
+        }
+        if actions & UPDATE_LISTED != 0 {
+            let _listed: bool = cursor.read_bool()?;
+        }
+        if actions & UPDATE_LATENCY != 0 {
+            entries.push((uuid, PlayerInfoAction::UpdateLatency(cursor.read_varint()?)));
+        }
+        if actions & UPDATE_DISPLAY_NAME != 0 {
+            entries.push((
+                uuid,
+                PlayerInfoAction::UpdateDisplayName(read_optional_component(cursor, protocol)?),
+            ));
+        }
+    }
+
+    Ok(entries)
+}
+
+fn read_player_info_remove(
+    cursor: &mut Cursor<&[u8]>,
+) -> Result<Vec<(u128, PlayerInfoAction)>, ClientError> {
+    let number_of_players: i32 = cursor.read_varint()?;
+    let mut entries: Vec<(u128, PlayerInfoAction)> = Vec::new();
+    for _ in 0..number_of_players {
+        entries.push((cursor.read_uuid()?, PlayerInfoAction::RemovePlayer));
+    }
+    Ok(entries)
+}
+
+impl<'a> ClientboundPacket<'a> {
+    /// Decodes a play state packet, using `packets` to find out which packet `id` is.
+    pub fn decode(
+        packets: &PacketTable,
+        id: i32,
+        data: &'a [u8],
+    ) -> Result<ClientboundPacket<'a>, ClientError> {
+        let mut cursor: Cursor<&[u8]> = Cursor::new(data);
+
+        let packet: ClientboundPacket = match packets.clientbound(id) {
+            Some(Clientbound::ChatMessage) => {
+                let message: Value = read_component_json(&mut cursor, packets.protocol)?;
+                let mut sender: Option<u128> = None;
+                let mut kind: ChatKind = ChatKind::System;
+                let action_bar: bool;
+
+                // 1.19+ System Chat ends with an "overlay" flag instead of position and sender
+                if packets.protocol < 759 {
+                    let position: u8 = cursor.read_u8()?;
+                    if position == 0 {
+                        kind = ChatKind::Player;
+                    }
+                    action_bar = position == 2;
+                    if packets.protocol >= 735 {
+                        sender = Some(cursor.read_uuid()?).filter(|uuid| *uuid != 0);
+                    }
+                } else {
+                    action_bar = cursor.read_bool()?;
+                }
+
+                if action_bar {
+                    ClientboundPacket::ActionBar {
+                        text: FormattedText::deserialize(&message)?,
+                    }
+                } else {
+                    chat_message(message, sender, kind)?
+                }
+            }
+            Some(Clientbound::PlayerChat) => read_player_chat(&mut cursor, packets.protocol)?,
+            Some(Clientbound::DisguisedChat) => read_disguised_chat(&mut cursor, packets.protocol)?,
+            Some(Clientbound::ActionBar) => ClientboundPacket::ActionBar {
+                text: read_component(&mut cursor, packets.protocol)?,
+            },
+            Some(Clientbound::Title) => read_legacy_title(&mut cursor, packets.protocol)?,
+            Some(Clientbound::TitleText) => ClientboundPacket::Title(TitleAction::SetTitle(
+                read_component(&mut cursor, packets.protocol)?,
+            )),
+            Some(Clientbound::Subtitle) => ClientboundPacket::Title(TitleAction::SetSubtitle(
+                read_component(&mut cursor, packets.protocol)?,
+            )),
+            Some(Clientbound::TitleTimes) => {
+                ClientboundPacket::Title(read_title_times(&mut cursor)?)
+            }
+            Some(Clientbound::ClearTitles) => ClientboundPacket::Title(if cursor.read_bool()? {
+                TitleAction::Reset
+            } else {
+                TitleAction::Hide
+            }),
+            Some(Clientbound::BossBar) => read_boss_bar(&mut cursor, packets.protocol)?,
+            Some(Clientbound::DisplayObjective) => {
+                read_display_objective(&mut cursor, packets.protocol)?
+            }
+            Some(Clientbound::UpdateObjective) => {
+                read_update_objective(&mut cursor, packets.protocol)?
+            }
+            Some(Clientbound::UpdateTeam) => read_update_team(&mut cursor, packets.protocol)?,
+            Some(Clientbound::UpdateScore) => read_update_score(&mut cursor, packets.protocol)?,
+            Some(Clientbound::ResetScore) => ClientboundPacket::ResetScore {
+                entity: McString::DEFAULT.read(&mut cursor)?,
+                objective: if cursor.read_bool()? {
+                    Some(McString::DEFAULT.read(&mut cursor)?)
+                } else {
+                    None
+                },
+            },
+            Some(Clientbound::TabList) => ClientboundPacket::TabList {
+                header: read_component(&mut cursor, packets.protocol)?,
+                footer: read_component(&mut cursor, packets.protocol)?,
+            },
+            Some(Clientbound::Disconnect) => ClientboundPacket::Disconnect {
+                reason: read_component(&mut cursor, packets.protocol)?,
+            },
+            Some(Clientbound::KeepAlive) => ClientboundPacket::KeepAlive {
+                id: cursor.read_long()?,
+            },
+            Some(Clientbound::PlayerInfo) => ClientboundPacket::PlayerInfo {
+                entries: read_player_info(&mut cursor, packets.protocol)?,
+            },
+            Some(Clientbound::PlayerInfoUpdate) => ClientboundPacket::PlayerInfo {
+                entries: read_player_info_update(&mut cursor, packets.protocol)?,
+            },
+            Some(Clientbound::PlayerInfoRemove) => ClientboundPacket::PlayerInfo {
+                entries: read_player_info_remove(&mut cursor)?,
+            },
+            Some(Clientbound::PluginMessage) => {
+                let (channel, data): (String, &[u8]) = read_plugin_message(&mut cursor)?;
+                ClientboundPacket::PluginMessage { channel, data }
+            }
+            Some(Clientbound::SynchronizePosition) => read_synchronize_position(&mut cursor)?,
+            Some(Clientbound::JoinGame) => ClientboundPacket::JoinGame {
+                entity_id: cursor.read_int()?,
+            },
+            Some(Clientbound::ResourcePack) => {
+                let (uuid, url, hash): (Option<u128>, String, String) =
+                    read_resource_pack(&mut cursor, packets.protocol)?;
+                ClientboundPacket::ResourcePack { uuid, url, hash }
+            }
+            Some(Clientbound::CommandSuggestions) => {
+                read_command_suggestions(&mut cursor, packets.protocol)?
+            }
+            // A parser this client doesn't know shouldn't cost the connection, the tree
+            // is only a convenience
+            Some(Clientbound::Commands) => match read_command_tree(&mut cursor, packets.protocol) {
+                Ok(tree) => ClientboundPacket::Commands(tree),
+                Err(_) => ClientboundPacket::Unknown {
+                    id,
+                    data: cursor.into_inner(),
+                },
+            },
+            Some(Clientbound::SetHealth) => ClientboundPacket::SetHealth(Health {
+                health: cursor.read_float()?,
+                food: cursor.read_varint()?,
+                saturation: cursor.read_float()?,
+            }),
+            Some(Clientbound::CombatEvent) => {
+                // Only the "entity dead" action (2) matters, entering and leaving combat don't
+                if cursor.read_varint()? == 2 {
+                    let _player_id: i32 = cursor.read_varint()?;
+                    let _killer_id: i32 = cursor.read_int()?;
+                    ClientboundPacket::Death {
+                        message: read_component(&mut cursor, packets.protocol)?,
+                    }
+                } else {
+                    ClientboundPacket::Unknown {
+                        id,
+                        data: cursor.into_inner(),
+                    }
+                }
+            }
+            Some(Clientbound::CombatDeath) => {
+                let _player_id: i32 = cursor.read_varint()?;
+                // The killer's entity ID was dropped in 1.20
+                if packets.protocol < 763 {
+                    let _killer_id: i32 = cursor.read_int()?;
+                }
+                ClientboundPacket::Death {
+                    message: read_component(&mut cursor, packets.protocol)?,
+                }
+            }
+            None => ClientboundPacket::Unknown {
+                id,
+                data: cursor.into_inner(),
+            },
+        };
+
+        Ok(packet)
+    }
+}
+
+/// A packet the server sends during login.
+#[derive(Clone, Debug)]
+pub enum LoginPacket<'a> {
+    Disconnect {
+        reason: FormattedText,
+    },
+    /// The server is in online mode. Only the public key is kept.
+    EncryptionRequest {
+        public_key: Vec<u8>,
+    },
+    /// The last login packet; configuration (1.20.2+) or play follows.
+    LoginSuccess(Identity),
+    /// Frames from here on are compressed if their packet is at least `threshold` bytes.
+    SetCompression {
+        threshold: i32,
+    },
+    /// Has to be answered with a Login Plugin Response for `message_id`.
+    PluginRequest {
+        message_id: i32,
+        channel: String,
+        data: &'a [u8],
+    },
+    Unknown {
+        id: i32,
+        data: &'a [u8],
+    },
+}
+
+impl<'a> LoginPacket<'a> {
+    /// Decodes a login state packet sent to a client that uses `protocol`.
+    pub fn decode(protocol: i32, id: i32, data: &'a [u8]) -> Result<LoginPacket<'a>, ClientError> {
+        let mut cursor: Cursor<&[u8]> = Cursor::new(data);
+
+        let packet: LoginPacket = match id {
+            0x00 => LoginPacket::Disconnect {
+                reason: read_chat(&mut cursor)?,
+            },
+            0x01 => {
+                let _server_id: Vec<u8> = cursor.read_byte_array()?;
+                let public_key: Vec<u8> = cursor.read_byte_array()?;
+                let _verify_token: Vec<u8> = cursor.read_byte_array()?;
+                LoginPacket::EncryptionRequest { public_key }
+            }
+            0x02 => {
+                // 1.16+ sends the UUID as a number, older versions as text. Properties
+                // (1.19+) aren't needed.
+                let uuid: u128 = match protocol {
+                    735.. => cursor.read_uuid()?,
+                    _ => {
+                        let text: String = McString::max(36).read(&mut cursor)?;
+                        parse_uuid(&text).ok_or_else(|| {
+                            ClientError::Protocol(format!(
+                                "Invalid UUID '{}' in Login Success",
+                                text
+                            ))
+                        })?
+                    }
+                };
+                LoginPacket::LoginSuccess(Identity {
+                    uuid,
+                    username: McString::USERNAME.read(&mut cursor)?,
+                })
+            }
+            0x03 => LoginPacket::SetCompression {
+                threshold: cursor.read_varint()?,
+            },
+            0x04 => LoginPacket::PluginRequest {
+                message_id: cursor.read_varint()?,
+                channel: McString::DEFAULT.read(&mut cursor)?,
+                data: read_remaining(&mut cursor),
+            },
+            _ => LoginPacket::Unknown { id, data },
+        };
+
+        Ok(packet)
+    }
+}
+
+// Timestamp and salt of a 1.19.3+ chat message. Without a signature the salt is unused.
+fn write_unsigned_chat_fields(packet_buffer: &mut Vec<u8>) -> Result<(), ClientError> {
+    let timestamp: i64 = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_millis() as i64)
+        .unwrap_or(0);
+
+    packet_buffer.write_all(&timestamp.to_be_bytes())?;
+    packet_buffer.write_all(&0i64.to_be_bytes())?;
+    Ok(())
+}
+
+// Acknowledges no previously seen messages: message count 0 and an empty 20 bit set.
+fn write_last_seen(packet_buffer: &mut Vec<u8>) -> Result<(), ClientError> {
+    packet_buffer.write_var_int(VarInt::from(0))?;
+    packet_buffer.write_all(&[0u8; 3])?;
+    Ok(())
+}
+
+/// Writes the body of Client Settings, which is also sent during configuration.
+pub(crate) fn write_client_settings(
+    packet_buffer: &mut Vec<u8>,
+    settings: &ClientSettings,
+    protocol: i32,
+) -> Result<(), ClientError> {
+    let chat_mode: i32 = match settings.chat_mode {
+        ChatMode::Enabled => 0,
+        ChatMode::CommandsOnly => 1,
+        ChatMode::Hidden => 2,
+    };
+
+    McString::max(16).write(packet_buffer, &settings.locale)?;
+    packet_buffer.write_all(&[settings.view_distance])?;
+    packet_buffer.write_var_int(VarInt::from(chat_mode))?;
+    packet_buffer.write_all(&[settings.chat_colors as u8, settings.skin_parts])?;
+    packet_buffer.write_var_int(VarInt::from(1))?; // right main hand
+    if protocol >= 757 {
+        packet_buffer.write_all(&[0u8])?; // no text filtering
+        packet_buffer.write_all(&[settings.allow_server_listings as u8])?;
+    }
+    Ok(())
+}
+
+/// Writes the body of a plugin message, which is also sent during configuration.
+pub(crate) fn write_plugin_message(
+    packet_buffer: &mut Vec<u8>,
+    channel: &str,
+    data: &[u8],
+) -> Result<(), ClientError> {
+    McString::DEFAULT.write(packet_buffer, channel)?;
+    packet_buffer.write_all(data)?;
+    Ok(())
+}
+
+impl ServerboundPacket {
+    /// Encodes the packet body and returns it along with the packet ID for `packets`.
+    pub fn encode(&self, packets: &PacketTable) -> Result<(i32, Vec<u8>), ClientError> {
+        let mut packet_buffer: Vec<u8> = Vec::new();
+
+        let name: Serverbound = match self {
+            ServerboundPacket::ChatMessage { message } => {
+                McString::CHAT.write(&mut packet_buffer, message)?;
+                if packets.protocol >= 761 {
+                    write_unsigned_chat_fields(&mut packet_buffer)?;
+                    packet_buffer.write_all(&[0u8])?; // no signature
+                    write_last_seen(&mut packet_buffer)?;
+                }
+                Serverbound::ChatMessage
+            }
+            ServerboundPacket::ChatCommand { command } => {
+                McString::CHAT.write(&mut packet_buffer, command)?;
+                // Since 1.20.5 unsigned commands are only the command string
+                if packets.protocol < 766 {
+                    write_unsigned_chat_fields(&mut packet_buffer)?;
+                    packet_buffer.write_var_int(VarInt::from(0))?; // no argument signatures
+                    write_last_seen(&mut packet_buffer)?;
+                }
+                Serverbound::ChatCommand
+            }
+            ServerboundPacket::KeepAlive { id } => {
+                packet_buffer.write_all(&id.to_be_bytes())?;
+                Serverbound::KeepAlive
+            }
+            ServerboundPacket::ClientSettings(settings) => {
+                write_client_settings(&mut packet_buffer, settings, packets.protocol)?;
+                Serverbound::ClientSettings
+            }
+            ServerboundPacket::PluginMessage { channel, data } => {
+                write_plugin_message(&mut packet_buffer, channel, data)?;
+                Serverbound::PluginMessage
+            }
+            ServerboundPacket::ConfirmTeleport { teleport_id } => {
+                packet_buffer.write_var_int(VarInt::from(*teleport_id))?;
+                Serverbound::ConfirmTeleport
+            }
+            ServerboundPacket::PlayerPosition {
+                position,
+                on_ground,
+            } => {
+                packet_buffer.write_all(&position.x.to_be_bytes())?;
+                packet_buffer.write_all(&position.y.to_be_bytes())?;
+                packet_buffer.write_all(&position.z.to_be_bytes())?;
+                packet_buffer.write_all(&position.yaw.to_be_bytes())?;
+                packet_buffer.write_all(&position.pitch.to_be_bytes())?;
+                packet_buffer.write_all(&[*on_ground as u8])?;
+                Serverbound::PlayerPosition
+            }
+            ServerboundPacket::Respawn => {
+                packet_buffer.write_var_int(VarInt::from(0))?; // perform respawn
+                Serverbound::ClientStatus
+            }
+            ServerboundPacket::SwingArm => {
+                packet_buffer.write_var_int(VarInt::from(0))?; // main hand
+                Serverbound::SwingArm
+            }
+            ServerboundPacket::PlayerRotation {
+                yaw,
+                pitch,
+                on_ground,
+            } => {
+                packet_buffer.write_all(&yaw.to_be_bytes())?;
+                packet_buffer.write_all(&pitch.to_be_bytes())?;
+                packet_buffer.write_all(&[*on_ground as u8])?;
+                Serverbound::PlayerRotation
+            }
+            ServerboundPacket::Sneak {
+                entity_id,
+                sneaking,
+            } => {
+                packet_buffer.write_var_int(VarInt::from(*entity_id))?;
+                packet_buffer.write_var_int(VarInt::from(if *sneaking { 0 } else { 1 }))?;
+                packet_buffer.write_var_int(VarInt::from(0))?; // jump boost
+                Serverbound::PlayerCommand
+            }
+            ServerboundPacket::ResourcePackStatus { uuid, status } => {
+                write_resource_pack_status(&mut packet_buffer, *uuid, *status)?;
+                Serverbound::ResourcePackStatus
+            }
+            ServerboundPacket::CommandSuggestions { id, text } => {
+                // 1.12 has no transaction ID, but a flag for command blocks and a block position
+                if packets.protocol >= 393 {
+                    packet_buffer.write_var_int(VarInt::from(*id))?;
+                }
+                // 1.13 raised the limit for command blocks
+                let limit: McString = if packets.protocol >= 393 {
+                    McString::max(32500)
+                } else {
+                    McString::CHAT
+                };
+                limit.write(&mut packet_buffer, text)?;
+                if packets.protocol < 393 {
+                    packet_buffer.write_all(&[0u8, 0u8])?; // not a command block, no position
+                }
+                Serverbound::CommandSuggestions
+            }
+        };
+
+        let Some(packet_id) = packets.serverbound_id(name) else {
+            return Err(format!("{:?} isn't supported on {}!", name, packets.version).into());
+        };
+
+        Ok((packet_id, packet_buffer))
+    }
+}
@@ -0,0 +1,766 @@
+// Names of every clientbound play packet, indexed by ID, for the versions in
+// `protocol::TABLES`. The client only handles a few of them; the names are there so
+// the rest can be told apart when debugging.
+
+pub(crate) static CLIENTBOUND_1_12_2: &[&str] = &[
+    "Spawn Object",
+    "Spawn Experience Orb",
+    "Spawn Global Entity",
+    "Spawn Mob",
+    "Spawn Painting",
+    "Spawn Player",
+    "Animation",
+    "Statistics",
+    "Block Break Animation",
+    "Update Block Entity",
+    "Block Action",
+    "Block Change",
+    "Boss Bar",
+    "Server Difficulty",
+    "Tab-Complete",
+    "Chat Message",
+    "Multi Block Change",
+    "Confirm Transaction",
+    "Close Window",
+    "Open Window",
+    "Window Items",
+    "Window Property",
+    "Set Slot",
+    "Set Cooldown",
+    "Plugin Message",
+    "Named Sound Effect",
+    "Disconnect",
+    "Entity Status",
+    "Explosion",
+    "Unload Chunk",
+    "Change Game State",
+    "Keep Alive",
+    "Chunk Data",
+    "Effect",
+    "Particle",
+    "Join Game",
+    "Map",
+    "Entity",
+    "Entity Relative Move",
+    "Entity Look And Relative Move",
+    "Entity Look",
+    "Vehicle Move",
+    "Open Sign Editor",
+    "Craft Recipe Response",
+    "Player Abilities",
+    "Combat Event",
+    "Player List Item",
+    "Player Position And Look",
+    "Use Bed",
+    "Unlock Recipes",
+    "Destroy Entities",
+    "Remove Entity Effect",
+    "Resource Pack Send",
+    "Respawn",
+    "Entity Head Look",
+    "Select Advancement Tab",
+    "World Border",
+    "Camera",
+    "Held Item Change",
+    "Display Scoreboard",
+    "Entity Metadata",
+    "Attach Entity",
+    "Entity Velocity",
+    "Entity Equipment",
+    "Set Experience",
+    "Update Health",
+    "Scoreboard Objective",
+    "Set Passengers",
+    "Teams",
+    "Update Score",
+    "Spawn Position",
+    "Time Update",
+    "Title",
+    "Sound Effect",
+    "Player List Header And Footer",
+    "Collect Item",
+    "Entity Teleport",
+    "Advancements",
+    "Entity Properties",
+    "Entity Effect",
+];
+
+pub(crate) static CLIENTBOUND_1_16_5: &[&str] = &[
+    "Spawn Entity",
+    "Spawn Experience Orb",
+    "Spawn Living Entity",
+    "Spawn Painting",
+    "Spawn Player",
+    "Entity Animation",
+    "Statistics",
+    "Acknowledge Player Digging",
+    "Block Break Animation",
+    "Block Entity Data",
+    "Block Action",
+    "Block Change",
+    "Boss Bar",
+    "Server Difficulty",
+    "Chat Message",
+    "Tab-Complete",
+    "Declare Commands",
+    "Window Confirmation",
+    "Close Window",
+    "Window Items",
+    "Window Property",
+    "Set Slot",
+    "Set Cooldown",
+    "Plugin Message",
+    "Named Sound Effect",
+    "Disconnect",
+    "Entity Status",
+    "Explosion",
+    "Unload Chunk",
+    "Change Game State",
+    "Open Horse Window",
+    "Keep Alive",
+    "Chunk Data",
+    "Effect",
+    "Particle",
+    "Update Light",
+    "Join Game",
+    "Map Data",
+    "Trade List",
+    "Entity Position",
+    "Entity Position And Rotation",
+    "Entity Rotation",
+    "Entity Movement",
+    "Vehicle Move",
+    "Open Book",
+    "Open Window",
+    "Open Sign Editor",
+    "Craft Recipe Response",
+    "Player Abilities",
+    "Combat Event",
+    "Player Info",
+    "Face Player",
+    "Player Position And Look",
+    "Unlock Recipes",
+    "Destroy Entities",
+    "Remove Entity Effect",
+    "Resource Pack Send",
+    "Respawn",
+    "Entity Head Look",
+    "Multi Block Change",
+    "Select Advancement Tab",
+    "World Border",
+    "Camera",
+    "Held Item Change",
+    "Update View Position",
+    "Update View Distance",
+    "Spawn Position",
+    "Display Scoreboard",
+    "Entity Metadata",
+    "Attach Entity",
+    "Entity Velocity",
+    "Entity Equipment",
+    "Set Experience",
+    "Update Health",
+    "Scoreboard Objective",
+    "Set Passengers",
+    "Teams",
+    "Update Score",
+    "Time Update",
+    "Title",
+    "Entity Sound Effect",
+    "Sound Effect",
+    "Stop Sound",
+    "Player List Header And Footer",
+    "NBT Query Response",
+    "Collect Item",
+    "Entity Teleport",
+    "Advancements",
+    "Entity Properties",
+    "Entity Effect",
+    "Declare Recipes",
+    "Tags",
+];
+
+pub(crate) static CLIENTBOUND_1_18_2: &[&str] = &[
+    "Spawn Entity",
+    "Spawn Experience Orb",
+    "Spawn Living Entity",
+    "Spawn Painting",
+    "Spawn Player",
+    "Sculk Vibration Signal",
+    "Entity Animation",
+    "Statistics",
+    "Acknowledge Player Digging",
+    "Block Break Animation",
+    "Block Entity Data",
+    "Block Action",
+    "Block Change",
+    "Boss Bar",
+    "Server Difficulty",
+    "Chat Message",
+    "Clear Titles",
+    "Tab-Complete",
+    "Declare Commands",
+    "Close Window",
+    "Window Items",
+    "Window Property",
+    "Set Slot",
+    "Set Cooldown",
+    "Plugin Message",
+    "Named Sound Effect",
+    "Disconnect",
+    "Entity Status",
+    "Explosion",
+    "Unload Chunk",
+    "Change Game State",
+    "Open Horse Window",
+    "Initialize World Border",
+    "Keep Alive",
+    "Chunk Data And Update Light",
+    "Effect",
+    "Particle",
+    "Update Light",
+    "Join Game",
+    "Map Data",
+    "Trade List",
+    "Entity Position",
+    "Entity Position And Rotation",
+    "Entity Rotation",
+    "Vehicle Move",
+    "Open Book",
+    "Open Window",
+    "Open Sign Editor",
+    "Ping",
+    "Craft Recipe Response",
+    "Player Abilities",
+    "End Combat Event",
+    "Enter Combat Event",
+    "Death Combat Event",
+    "Player Info",
+    "Face Player",
+    "Player Position And Look",
+    "Unlock Recipes",
+    "Destroy Entities",
+    "Remove Entity Effect",
+    "Resource Pack Send",
+    "Respawn",
+    "Entity Head Look",
+    "Multi Block Change",
+    "Select Advancement Tab",
+    "Action Bar",
+    "World Border Center",
+    "World Border Lerp Size",
+    "World Border Size",
+    "World Border Warning Delay",
+    "World Border Warning Reach",
+    "Camera",
+    "Held Item Change",
+    "Update View Position",
+    "Update View Distance",
+    "Spawn Position",
+    "Display Scoreboard",
+    "Entity Metadata",
+    "Attach Entity",
+    "Entity Velocity",
+    "Entity Equipment",
+    "Set Experience",
+    "Update Health",
+    "Scoreboard Objective",
+    "Set Passengers",
+    "Teams",
+    "Update Score",
+    "Update Simulation Distance",
+    "Set Title Subtitle",
+    "Time Update",
+    "Set Title Text",
+    "Set Title Times",
+    "Entity Sound Effect",
+    "Sound Effect",
+    "Stop Sound",
+    "Player List Header And Footer",
+    "NBT Query Response",
+    "Collect Item",
+    "Entity Teleport",
+    "Advancements",
+    "Entity Properties",
+    "Entity Effect",
+    "Declare Recipes",
+    "Tags",
+];
+
+pub(crate) static CLIENTBOUND_1_20_1: &[&str] = &[
+    "Bundle Delimiter",
+    "Spawn Entity",
+    "Spawn Experience Orb",
+    "Spawn Player",
+    "Entity Animation",
+    "Award Statistics",
+    "Acknowledge Block Change",
+    "Set Block Destroy Stage",
+    "Block Entity Data",
+    "Block Action",
+    "Block Update",
+    "Boss Bar",
+    "Change Difficulty",
+    "Chunk Biomes",
+    "Clear Titles",
+    "Command Suggestions Response",
+    "Commands",
+    "Close Container",
+    "Set Container Content",
+    "Set Container Property",
+    "Set Container Slot",
+    "Set Cooldown",
+    "Chat Suggestions",
+    "Plugin Message",
+    "Damage Event",
+    "Delete Message",
+    "Disconnect",
+    "Disguised Chat Message",
+    "Entity Event",
+    "Explosion",
+    "Unload Chunk",
+    "Game Event",
+    "Open Horse Screen",
+    "Hurt Animation",
+    "Initialize World Border",
+    "Keep Alive",
+    "Chunk Data And Update Light",
+    "World Event",
+    "Particle",
+    "Update Light",
+    "Login",
+    "Map Data",
+    "Merchant Offers",
+    "Update Entity Position",
+    "Update Entity Position And Rotation",
+    "Update Entity Rotation",
+    "Move Vehicle",
+    "Open Book",
+    "Open Screen",
+    "Open Sign Editor",
+    "Ping",
+    "Place Ghost Recipe",
+    "Player Abilities",
+    "Player Chat Message",
+    "End Combat",
+    "Enter Combat",
+    "Combat Death",
+    "Player Info Remove",
+    "Player Info Update",
+    "Look At",
+    "Synchronize Player Position",
+    "Update Recipe Book",
+    "Remove Entities",
+    "Remove Entity Effect",
+    "Resource Pack",
+    "Respawn",
+    "Set Head Rotation",
+    "Update Section Blocks",
+    "Select Advancements Tab",
+    "Server Data",
+    "Set Action Bar Text",
+    "Set Border Center",
+    "Set Border Lerp Size",
+    "Set Border Size",
+    "Set Border Warning Delay",
+    "Set Border Warning Distance",
+    "Set Camera",
+    "Set Held Item",
+    "Set Center Chunk",
+    "Set Render Distance",
+    "Set Default Spawn Position",
+    "Display Objective",
+    "Set Entity Metadata",
+    "Link Entities",
+    "Set Entity Velocity",
+    "Set Equipment",
+    "Set Experience",
+    "Set Health",
+    "Update Objectives",
+    "Set Passengers",
+    "Update Teams",
+    "Update Score",
+    "Set Simulation Distance",
+    "Set Subtitle Text",
+    "Update Time",
+    "Set Title Text",
+    "Set Title Animation Times",
+    "Entity Sound Effect",
+    "Sound Effect",
+    "Stop Sound",
+    "System Chat Message",
+    "Set Tab List Header And Footer",
+    "Tag Query Response",
+    "Pickup Item",
+    "Teleport Entity",
+    "Update Advancements",
+    "Update Attributes",
+    "Feature Flags",
+    "Entity Effect",
+    "Update Recipes",
+    "Update Tags",
+];
+
+pub(crate) static CLIENTBOUND_1_20_2: &[&str] = &[
+    "Bundle Delimiter",
+    "Spawn Entity",
+    "Spawn Experience Orb",
+    "Entity Animation",
+    "Award Statistics",
+    "Acknowledge Block Change",
+    "Set Block Destroy Stage",
+    "Block Entity Data",
+    "Block Action",
+    "Block Update",
+    "Boss Bar",
+    "Change Difficulty",
+    "Chunk Batch Finished",
+    "Chunk Batch Start",
+    "Chunk Biomes",
+    "Clear Titles",
+    "Command Suggestions Response",
+    "Commands",
+    "Close Container",
+    "Set Container Content",
+    "Set Container Property",
+    "Set Container Slot",
+    "Set Cooldown",
+    "Chat Suggestions",
+    "Plugin Message",
+    "Damage Event",
+    "Delete Message",
+    "Disconnect",
+    "Disguised Chat Message",
+    "Entity Event",
+    "Explosion",
+    "Unload Chunk",
+    "Game Event",
+    "Open Horse Screen",
+    "Hurt Animation",
+    "Initialize World Border",
+    "Keep Alive",
+    "Chunk Data And Update Light",
+    "World Event",
+    "Particle",
+    "Update Light",
+    "Login",
+    "Map Data",
+    "Merchant Offers",
+    "Update Entity Position",
+    "Update Entity Position And Rotation",
+    "Update Entity Rotation",
+    "Move Vehicle",
+    "Open Book",
+    "Open Screen",
+    "Open Sign Editor",
+    "Ping",
+    "Ping Response",
+    "Place Ghost Recipe",
+    "Player Abilities",
+    "Player Chat Message",
+    "End Combat",
+    "Enter Combat",
+    "Combat Death",
+    "Player Info Remove",
+    "Player Info Update",
+    "Look At",
+    "Synchronize Player Position",
+    "Update Recipe Book",
+    "Remove Entities",
+    "Remove Entity Effect",
+    "Resource Pack",
+    "Respawn",
+    "Set Head Rotation",
+    "Update Section Blocks",
+    "Select Advancements Tab",
+    "Server Data",
+    "Set Action Bar Text",
+    "Set Border Center",
+    "Set Border Lerp Size",
+    "Set Border Size",
+    "Set Border Warning Delay",
+    "Set Border Warning Distance",
+    "Set Camera",
+    "Set Held Item",
+    "Set Center Chunk",
+    "Set Render Distance",
+    "Set Default Spawn Position",
+    "Display Objective",
+    "Set Entity Metadata",
+    "Link Entities",
+    "Set Entity Velocity",
+    "Set Equipment",
+    "Set Experience",
+    "Set Health",
+    "Update Objectives",
+    "Set Passengers",
+    "Update Teams",
+    "Update Score",
+    "Set Simulation Distance",
+    "Set Subtitle Text",
+    "Update Time",
+    "Set Title Text",
+    "Set Title Animation Times",
+    "Entity Sound Effect",
+    "Sound Effect",
+    "Start Configuration",
+    "Stop Sound",
+    "System Chat Message",
+    "Set Tab List Header And Footer",
+    "Tag Query Response",
+    "Pickup Item",
+    "Teleport Entity",
+    "Update Advancements",
+    "Update Attributes",
+    "Entity Effect",
+    "Update Recipes",
+    "Update Tags",
+];
+
+pub(crate) static CLIENTBOUND_1_20_4: &[&str] = &[
+    "Bundle Delimiter",
+    "Spawn Entity",
+    "Spawn Experience Orb",
+    "Entity Animation",
+    "Award Statistics",
+    "Acknowledge Block Change",
+    "Set Block Destroy Stage",
+    "Block Entity Data",
+    "Block Action",
+    "Block Update",
+    "Boss Bar",
+    "Change Difficulty",
+    "Chunk Batch Finished",
+    "Chunk Batch Start",
+    "Chunk Biomes",
+    "Clear Titles",
+    "Command Suggestions Response",
+    "Commands",
+    "Close Container",
+    "Set Container Content",
+    "Set Container Property",
+    "Set Container Slot",
+    "Set Cooldown",
+    "Chat Suggestions",
+    "Plugin Message",
+    "Damage Event",
+    "Delete Message",
+    "Disconnect",
+    "Disguised Chat Message",
+    "Entity Event",
+    "Explosion",
+    "Unload Chunk",
+    "Game Event",
+    "Open Horse Screen",
+    "Hurt Animation",
+    "Initialize World Border",
+    "Keep Alive",
+    "Chunk Data And Update Light",
+    "World Event",
+    "Particle",
+    "Update Light",
+    "Login",
+    "Map Data",
+    "Merchant Offers",
+    "Update Entity Position",
+    "Update Entity Position And Rotation",
+    "Update Entity Rotation",
+    "Move Vehicle",
+    "Open Book",
+    "Open Screen",
+    "Open Sign Editor",
+    "Ping",
+    "Ping Response",
+    "Place Ghost Recipe",
+    "Player Abilities",
+    "Player Chat Message",
+    "End Combat",
+    "Enter Combat",
+    "Combat Death",
+    "Player Info Remove",
+    "Player Info Update",
+    "Look At",
+    "Synchronize Player Position",
+    "Update Recipe Book",
+    "Remove Entities",
+    "Remove Entity Effect",
+    "Reset Score",
+    "Remove Resource Pack",
+    "Add Resource Pack",
+    "Respawn",
+    "Set Head Rotation",
+    "Update Section Blocks",
+    "Select Advancements Tab",
+    "Server Data",
+    "Set Action Bar Text",
+    "Set Border Center",
+    "Set Border Lerp Size",
+    "Set Border Size",
+    "Set Border Warning Delay",
+    "Set Border Warning Distance",
+    "Set Camera",
+    "Set Held Item",
+    "Set Center Chunk",
+    "Set Render Distance",
+    "Set Default Spawn Position",
+    "Display Objective",
+    "Set Entity Metadata",
+    "Link Entities",
+    "Set Entity Velocity",
+    "Set Equipment",
+    "Set Experience",
+    "Set Health",
+    "Update Objectives",
+    "Set Passengers",
+    "Update Teams",
+    "Update Score",
+    "Set Simulation Distance",
+    "Set Subtitle Text",
+    "Update Time",
+    "Set Title Text",
+    "Set Title Animation Times",
+    "Entity Sound Effect",
+    "Sound Effect",
+    "Start Configuration",
+    "Stop Sound",
+    "System Chat Message",
+    "Set Tab List Header And Footer",
+    "Tag Query Response",
+    "Pickup Item",
+    "Teleport Entity",
+    "Set Ticking State",
+    "Step Tick",
+    "Update Advancements",
+    "Update Attributes",
+    "Entity Effect",
+    "Update Recipes",
+    "Update Tags",
+];
+
+// The last three are only sent by 1.21 and 1.21.1
+pub(crate) static CLIENTBOUND_1_20_5: &[&str] = &[
+    "Bundle Delimiter",
+    "Spawn Entity",
+    "Spawn Experience Orb",
+    "Entity Animation",
+    "Award Statistics",
+    "Acknowledge Block Change",
+    "Set Block Destroy Stage",
+    "Block Entity Data",
+    "Block Action",
+    "Block Update",
+    "Boss Bar",
+    "Change Difficulty",
+    "Chunk Batch Finished",
+    "Chunk Batch Start",
+    "Chunk Biomes",
+    "Clear Titles",
+    "Command Suggestions Response",
+    "Commands",
+    "Close Container",
+    "Set Container Content",
+    "Set Container Property",
+    "Set Container Slot",
+    "Cookie Request",
+    "Set Cooldown",
+    "Chat Suggestions",
+    "Plugin Message",
+    "Damage Event",
+    "Debug Sample",
+    "Delete Message",
+    "Disconnect",
+    "Disguised Chat Message",
+    "Entity Event",
+    "Explosion",
+    "Unload Chunk",
+    "Game Event",
+    "Open Horse Screen",
+    "Hurt Animation",
+    "Initialize World Border",
+    "Keep Alive",
+    "Chunk Data And Update Light",
+    "World Event",
+    "Particle",
+    "Update Light",
+    "Login",
+    "Map Data",
+    "Merchant Offers",
+    "Update Entity Position",
+    "Update Entity Position And Rotation",
+    "Update Entity Rotation",
+    "Move Vehicle",
+    "Open Book",
+    "Open Screen",
+    "Open Sign Editor",
+    "Ping",
+    "Ping Response",
+    "Place Ghost Recipe",
+    "Player Abilities",
+    "Player Chat Message",
+    "End Combat",
+    "Enter Combat",
+    "Combat Death",
+    "Player Info Remove",
+    "Player Info Update",
+    "Look At",
+    "Synchronize Player Position",
+    "Update Recipe Book",
+    "Remove Entities",
+    "Remove Entity Effect",
+    "Reset Score",
+    "Remove Resource Pack",
+    "Add Resource Pack",
+    "Respawn",
+    "Set Head Rotation",
+    "Update Section Blocks",
+    "Select Advancements Tab",
+    "Server Data",
+    "Set Action Bar Text",
+    "Set Border Center",
+    "Set Border Lerp Size",
+    "Set Border Size",
+    "Set Border Warning Delay",
+    "Set Border Warning Distance",
+    "Set Camera",
+    "Set Held Item",
+    "Set Center Chunk",
+    "Set Render Distance",
+    "Set Default Spawn Position",
+    "Display Objective",
+    "Set Entity Metadata",
+    "Link Entities",
+    "Set Entity Velocity",
+    "Set Equipment",
+    "Set Experience",
+    "Set Health",
+    "Update Objectives",
+    "Set Passengers",
+    "Update Teams",
+    "Update Score",
+    "Set Simulation Distance",
+    "Set Subtitle Text",
+    "Update Time",
+    "Set Title Text",
+    "Set Title Animation Times",
+    "Entity Sound Effect",
+    "Sound Effect",
+    "Start Configuration",
+    "Stop Sound",
+    "Store Cookie",
+    "System Chat Message",
+    "Set Tab List Header And Footer",
+    "Tag Query Response",
+    "Pickup Item",
+    "Teleport Entity",
+    "Set Ticking State",
+    "Step Tick",
+    "Transfer",
+    "Update Advancements",
+    "Update Attributes",
+    "Entity Effect",
+    "Update Recipes",
+    "Update Tags",
+    "Projectile Power",
+    "Custom Report Details",
+    "Server Links",
+];
@@ -0,0 +1,94 @@
+use azalea_chat::FormattedText;
+
+use crate::error::ClientError;
+use crate::http;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameMode {
+    Survival,
+    Creative,
+    Adventure,
+    Spectator,
+}
+
+impl GameMode {
+    pub(crate) fn from_id(id: i32) -> GameMode {
+        match id {
+            1 => GameMode::Creative,
+            2 => GameMode::Adventure,
+            3 => GameMode::Spectator,
+            _ => GameMode::Survival,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Health {
+    /// 0 is dead, 20 is full.
+    pub health: f32,
+    /// 0 to 20.
+    pub food: i32,
+    pub saturation: f32,
+}
+
+/// Who the server logged the client in as, from the Login Success packet. Servers may
+/// change the name's case, and proxies may hand out another UUID than the offline one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Identity {
+    pub uuid: u128,
+    pub username: String,
+}
+
+/// Where the client's player is. Angles are in degrees.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Position {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+impl Position {
+    /// Applies a teleport from the server; each bit of `relative` (x, y, z, yaw, pitch)
+    /// marks a field that is an offset instead of an absolute value.
+    pub(crate) fn teleport(&self, target: Position, relative: u8) -> Position {
+        let offset = |bit: u8, current: f64, value: f64| {
+            if relative & bit != 0 {
+                current + value
+            } else {
+                value
+            }
+        };
+        Position {
+            x: offset(0x01, self.x, target.x),
+            y: offset(0x02, self.y, target.y),
+            z: offset(0x04, self.z, target.z),
+            yaw: offset(0x08, self.yaw as f64, target.yaw as f64) as f32,
+            pitch: offset(0x10, self.pitch as f64, target.pitch as f64) as f32,
+        }
+    }
+}
+
+/// An entry of the player list.
+#[derive(Clone, Debug)]
+pub struct PlayerInfo {
+    pub name: String,
+    /// From the `textures` property. Offline-mode servers usually don't send one.
+    pub skin_url: Option<String>,
+    pub game_mode: GameMode,
+    /// Round-trip time to the server in milliseconds, as measured by the server.
+    pub latency: i32,
+    /// Replaces the name in the player list if set.
+    pub display_name: Option<FormattedText>,
+}
+
+impl PlayerInfo {
+    /// Downloads the player's skin as a PNG image.
+    pub fn download_skin(&self) -> Result<Vec<u8>, ClientError> {
+        match &self.skin_url {
+            Some(url) => http::get(url),
+            None => Err(format!("{} doesn't have a skin", self.name).into()),
+        }
+    }
+}
@@ -0,0 +1,478 @@
+// Play state packet IDs for the protocol versions the client knows about.
+// Handshake, status and login IDs haven't moved between these versions, so they stay inline.
+
+use crate::error::ClientError;
+use crate::packetnames;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Clientbound {
+    /// Chat before 1.19, System Chat after.
+    ChatMessage,
+    PlayerChat,
+    DisguisedChat,
+    /// Set Action Bar Text, 1.17+.
+    ActionBar,
+    /// Title, subtitle, times and clearing in one packet, before 1.17.
+    Title,
+    TitleText,
+    Subtitle,
+    TitleTimes,
+    ClearTitles,
+    BossBar,
+    DisplayObjective,
+    UpdateObjective,
+    UpdateTeam,
+    UpdateScore,
+    /// 1.20.3+, older versions remove scores through Update Score.
+    ResetScore,
+    /// Player List Header And Footer.
+    TabList,
+    Disconnect,
+    KeepAlive,
+    /// Player Info before 1.19.3.
+    PlayerInfo,
+    PlayerInfoUpdate,
+    PlayerInfoRemove,
+    PluginMessage,
+    /// Player Position And Look before 1.19.4.
+    SynchronizePosition,
+    SetHealth,
+    /// Combat Event before 1.17, where the death is one of its actions.
+    CombatEvent,
+    CombatDeath,
+    /// Login (play) since 1.20.2.
+    JoinGame,
+    /// Add Resource Pack since 1.20.3, which can send more than one.
+    ResourcePack,
+    /// Tab-Complete before 1.19.4.
+    CommandSuggestions,
+    /// Declare Commands, the command tree (1.13+).
+    Commands,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Serverbound {
+    ChatMessage,
+    /// 1.19+ sends commands separately from chat.
+    ChatCommand,
+    KeepAlive,
+    /// Client Information since 1.20.2, where it's sent during configuration instead.
+    ClientSettings,
+    PluginMessage,
+    ConfirmTeleport,
+    /// Set Player Position And Rotation.
+    PlayerPosition,
+    /// Client Command since 1.19.4, used to respawn.
+    ClientStatus,
+    /// Animation before 1.19.4.
+    SwingArm,
+    /// Player Look before 1.19.4.
+    PlayerRotation,
+    /// Entity Action before 1.19.4, used to sneak.
+    PlayerCommand,
+    ResourcePackStatus,
+    /// Tab-Complete before 1.19.4.
+    CommandSuggestions,
+}
+
+pub struct PacketTable {
+    pub version: &'static str,
+    pub protocol: i32,
+    /// The newest protocol that still uses these IDs and layouts, usually `protocol`.
+    pub newest: i32,
+    clientbound: &'static [(Clientbound, i32)],
+    // Every clientbound play packet's name, indexed by ID
+    clientbound_names: &'static [&'static str],
+    serverbound: &'static [(Serverbound, i32)],
+}
+
+impl PacketTable {
+    /// Looks up the logical packet behind a clientbound packet ID.
+    pub fn clientbound(&self, id: i32) -> Option<Clientbound> {
+        self.clientbound
+            .iter()
+            .find(|entry| entry.1 == id)
+            .map(|entry| entry.0)
+    }
+
+    /// The name of any clientbound play packet, handled or not, like "Spawn Entity".
+    pub fn clientbound_name(&self, id: i32) -> Option<&'static str> {
+        usize::try_from(id)
+            .ok()
+            .and_then(|index| self.clientbound_names.get(index))
+            .copied()
+    }
+
+    pub fn clientbound_id(&self, packet: Clientbound) -> Option<i32> {
+        self.clientbound
+            .iter()
+            .find(|entry| entry.0 == packet)
+            .map(|entry| entry.1)
+    }
+
+    /// Looks up the logical packet behind a serverbound packet ID.
+    pub fn serverbound(&self, id: i32) -> Option<Serverbound> {
+        self.serverbound
+            .iter()
+            .find(|entry| entry.1 == id)
+            .map(|entry| entry.0)
+    }
+
+    pub fn serverbound_id(&self, packet: Serverbound) -> Option<i32> {
+        self.serverbound
+            .iter()
+            .find(|entry| entry.0 == packet)
+            .map(|entry| entry.1)
+    }
+}
+
+static TABLES: &[PacketTable] = &[
+    PacketTable {
+        version: "1.12.2",
+        protocol: 340,
+        newest: 340,
+        clientbound: &[
+            (Clientbound::ChatMessage, 0x0F),
+            (Clientbound::Disconnect, 0x1A),
+            (Clientbound::KeepAlive, 0x1F),
+            (Clientbound::DisplayObjective, 0x3B),
+            (Clientbound::UpdateObjective, 0x42),
+            (Clientbound::UpdateTeam, 0x44),
+            (Clientbound::UpdateScore, 0x45),
+            (Clientbound::TabList, 0x4A),
+            (Clientbound::BossBar, 0x0C),
+            (Clientbound::Title, 0x48),
+            (Clientbound::PlayerInfo, 0x2E),
+            (Clientbound::PluginMessage, 0x18),
+            (Clientbound::SynchronizePosition, 0x2F),
+            (Clientbound::JoinGame, 0x23),
+            (Clientbound::SetHealth, 0x41),
+            (Clientbound::CombatEvent, 0x2D),
+            (Clientbound::ResourcePack, 0x34),
+            (Clientbound::CommandSuggestions, 0x0E),
+        ],
+        clientbound_names: packetnames::CLIENTBOUND_1_12_2,
+        serverbound: &[
+            (Serverbound::ChatMessage, 0x02),
+            (Serverbound::KeepAlive, 0x0B),
+            (Serverbound::ClientSettings, 0x04),
+            (Serverbound::PluginMessage, 0x09),
+            (Serverbound::ConfirmTeleport, 0x00),
+            (Serverbound::PlayerPosition, 0x0E),
+            (Serverbound::ClientStatus, 0x03),
+            (Serverbound::SwingArm, 0x1D),
+            (Serverbound::PlayerRotation, 0x0F),
+            (Serverbound::PlayerCommand, 0x15),
+            (Serverbound::ResourcePackStatus, 0x18),
+            (Serverbound::CommandSuggestions, 0x01),
+        ],
+    },
+    PacketTable {
+        version: "1.16.5",
+        protocol: 754,
+        newest: 754,
+        clientbound: &[
+            (Clientbound::ChatMessage, 0x0E),
+            (Clientbound::Disconnect, 0x19),
+            (Clientbound::KeepAlive, 0x1F),
+            (Clientbound::DisplayObjective, 0x43),
+            (Clientbound::UpdateObjective, 0x4A),
+            (Clientbound::UpdateTeam, 0x4C),
+            (Clientbound::UpdateScore, 0x4D),
+            (Clientbound::TabList, 0x53),
+            (Clientbound::BossBar, 0x0C),
+            (Clientbound::Title, 0x4F),
+            (Clientbound::PlayerInfo, 0x32),
+            (Clientbound::PluginMessage, 0x17),
+            (Clientbound::SynchronizePosition, 0x34),
+            (Clientbound::JoinGame, 0x24),
+            (Clientbound::SetHealth, 0x49),
+            (Clientbound::CombatEvent, 0x31),
+            (Clientbound::ResourcePack, 0x38),
+            (Clientbound::CommandSuggestions, 0x0F),
+            (Clientbound::Commands, 0x10),
+        ],
+        clientbound_names: packetnames::CLIENTBOUND_1_16_5,
+        serverbound: &[
+            (Serverbound::ChatMessage, 0x03),
+            (Serverbound::KeepAlive, 0x10),
+            (Serverbound::ClientSettings, 0x05),
+            (Serverbound::PluginMessage, 0x0B),
+            (Serverbound::ConfirmTeleport, 0x00),
+            (Serverbound::PlayerPosition, 0x13),
+            (Serverbound::ClientStatus, 0x04),
+            (Serverbound::SwingArm, 0x2C),
+            (Serverbound::PlayerRotation, 0x14),
+            (Serverbound::PlayerCommand, 0x1C),
+            (Serverbound::ResourcePackStatus, 0x21),
+            (Serverbound::CommandSuggestions, 0x06),
+        ],
+    },
+    PacketTable {
+        version: "1.18.2",
+        protocol: 758,
+        newest: 758,
+        clientbound: &[
+            (Clientbound::ChatMessage, 0x0F),
+            (Clientbound::Disconnect, 0x1A),
+            (Clientbound::KeepAlive, 0x21),
+            (Clientbound::DisplayObjective, 0x4C),
+            (Clientbound::UpdateObjective, 0x53),
+            (Clientbound::UpdateTeam, 0x55),
+            (Clientbound::UpdateScore, 0x56),
+            (Clientbound::TabList, 0x5F),
+            (Clientbound::BossBar, 0x0D),
+            (Clientbound::TitleText, 0x5A),
+            (Clientbound::Subtitle, 0x58),
+            (Clientbound::TitleTimes, 0x5B),
+            (Clientbound::ClearTitles, 0x10),
+            (Clientbound::ActionBar, 0x41),
+            (Clientbound::PlayerInfo, 0x36),
+            (Clientbound::PluginMessage, 0x18),
+            (Clientbound::SynchronizePosition, 0x38),
+            (Clientbound::JoinGame, 0x26),
+            (Clientbound::SetHealth, 0x52),
+            (Clientbound::CombatDeath, 0x35),
+            (Clientbound::ResourcePack, 0x3C),
+            (Clientbound::CommandSuggestions, 0x11),
+            (Clientbound::Commands, 0x12),
+        ],
+        clientbound_names: packetnames::CLIENTBOUND_1_18_2,
+        serverbound: &[
+            (Serverbound::ChatMessage, 0x03),
+            (Serverbound::KeepAlive, 0x0F),
+            (Serverbound::ClientSettings, 0x05),
+            (Serverbound::PluginMessage, 0x0A),
+            (Serverbound::ConfirmTeleport, 0x00),
+            (Serverbound::PlayerPosition, 0x12),
+            (Serverbound::ClientStatus, 0x04),
+            (Serverbound::SwingArm, 0x2C),
+            (Serverbound::PlayerRotation, 0x13),
+            (Serverbound::PlayerCommand, 0x1B),
+            (Serverbound::ResourcePackStatus, 0x21),
+            (Serverbound::CommandSuggestions, 0x06),
+        ],
+    },
+    PacketTable {
+        version: "1.20.1",
+        protocol: 763,
+        newest: 763,
+        clientbound: &[
+            (Clientbound::ChatMessage, 0x64), // System Chat
+            (Clientbound::PlayerChat, 0x35),
+            (Clientbound::DisguisedChat, 0x1B),
+            (Clientbound::Disconnect, 0x1A),
+            (Clientbound::KeepAlive, 0x23),
+            (Clientbound::PlayerInfoUpdate, 0x3A),
+            (Clientbound::PlayerInfoRemove, 0x39),
+            (Clientbound::DisplayObjective, 0x51),
+            (Clientbound::UpdateObjective, 0x58),
+            (Clientbound::UpdateTeam, 0x5A),
+            (Clientbound::UpdateScore, 0x5B),
+            (Clientbound::TabList, 0x65),
+            (Clientbound::BossBar, 0x0B),
+            (Clientbound::TitleText, 0x5F),
+            (Clientbound::Subtitle, 0x5D),
+            (Clientbound::TitleTimes, 0x60),
+            (Clientbound::ClearTitles, 0x0E),
+            (Clientbound::ActionBar, 0x46),
+            (Clientbound::PluginMessage, 0x17),
+            (Clientbound::SynchronizePosition, 0x3C),
+            (Clientbound::JoinGame, 0x28),
+            (Clientbound::SetHealth, 0x57),
+            (Clientbound::CombatDeath, 0x38),
+            (Clientbound::ResourcePack, 0x40),
+            (Clientbound::CommandSuggestions, 0x0F),
+            (Clientbound::Commands, 0x10),
+            // Player Info Update (0x3A) has a different layout since 1.19.3
+        ],
+        clientbound_names: packetnames::CLIENTBOUND_1_20_1,
+        serverbound: &[
+            (Serverbound::ChatCommand, 0x04),
+            (Serverbound::ChatMessage, 0x05),
+            (Serverbound::KeepAlive, 0x12),
+            (Serverbound::ClientSettings, 0x08),
+            (Serverbound::PluginMessage, 0x0D),
+            (Serverbound::ConfirmTeleport, 0x00),
+            (Serverbound::PlayerPosition, 0x15),
+            (Serverbound::ClientStatus, 0x07),
+            (Serverbound::SwingArm, 0x2F),
+            (Serverbound::PlayerRotation, 0x16),
+            (Serverbound::PlayerCommand, 0x1E),
+            (Serverbound::ResourcePackStatus, 0x24),
+            (Serverbound::CommandSuggestions, 0x09),
+        ],
+    },
+    PacketTable {
+        version: "1.20.2",
+        protocol: 764,
+        newest: 764,
+        clientbound: &[
+            (Clientbound::ChatMessage, 0x67), // System Chat
+            (Clientbound::PlayerChat, 0x37),
+            (Clientbound::DisguisedChat, 0x1C),
+            (Clientbound::Disconnect, 0x1B),
+            (Clientbound::KeepAlive, 0x24),
+            (Clientbound::PlayerInfoUpdate, 0x3C),
+            (Clientbound::PlayerInfoRemove, 0x3B),
+            (Clientbound::DisplayObjective, 0x53),
+            (Clientbound::UpdateObjective, 0x5A),
+            (Clientbound::UpdateTeam, 0x5C),
+            (Clientbound::UpdateScore, 0x5D),
+            (Clientbound::TabList, 0x68),
+            (Clientbound::BossBar, 0x0A),
+            (Clientbound::TitleText, 0x61),
+            (Clientbound::Subtitle, 0x5F),
+            (Clientbound::TitleTimes, 0x62),
+            (Clientbound::ClearTitles, 0x0F),
+            (Clientbound::ActionBar, 0x48),
+            (Clientbound::PluginMessage, 0x18),
+            (Clientbound::SynchronizePosition, 0x3E),
+            (Clientbound::JoinGame, 0x29),
+            (Clientbound::SetHealth, 0x59),
+            (Clientbound::CombatDeath, 0x3A),
+            (Clientbound::ResourcePack, 0x42),
+            (Clientbound::CommandSuggestions, 0x10),
+            (Clientbound::Commands, 0x11),
+        ],
+        clientbound_names: packetnames::CLIENTBOUND_1_20_2,
+        serverbound: &[
+            (Serverbound::ChatCommand, 0x04),
+            (Serverbound::ChatMessage, 0x05),
+            (Serverbound::KeepAlive, 0x14),
+            (Serverbound::PluginMessage, 0x0F),
+            (Serverbound::ConfirmTeleport, 0x00),
+            (Serverbound::PlayerPosition, 0x17),
+            (Serverbound::ClientStatus, 0x08),
+            (Serverbound::SwingArm, 0x32),
+            (Serverbound::PlayerRotation, 0x18),
+            (Serverbound::PlayerCommand, 0x21),
+            (Serverbound::ResourcePackStatus, 0x27),
+            (Serverbound::CommandSuggestions, 0x0A),
+        ],
+    },
+    PacketTable {
+        version: "1.20.4",
+        protocol: 765,
+        newest: 765,
+        clientbound: &[
+            (Clientbound::ChatMessage, 0x69), // System Chat
+            (Clientbound::PlayerChat, 0x37),
+            (Clientbound::DisguisedChat, 0x1C),
+            (Clientbound::Disconnect, 0x1B),
+            (Clientbound::KeepAlive, 0x24),
+            (Clientbound::PlayerInfoUpdate, 0x3C),
+            (Clientbound::PlayerInfoRemove, 0x3B),
+            (Clientbound::DisplayObjective, 0x55),
+            (Clientbound::UpdateObjective, 0x5C),
+            (Clientbound::UpdateTeam, 0x5E),
+            (Clientbound::UpdateScore, 0x5F),
+            (Clientbound::ResetScore, 0x42),
+            (Clientbound::TabList, 0x6A),
+            (Clientbound::BossBar, 0x0A),
+            (Clientbound::TitleText, 0x63),
+            (Clientbound::Subtitle, 0x61),
+            (Clientbound::TitleTimes, 0x64),
+            (Clientbound::ClearTitles, 0x0F),
+            (Clientbound::ActionBar, 0x4A),
+            (Clientbound::PluginMessage, 0x18),
+            (Clientbound::SynchronizePosition, 0x3E),
+            (Clientbound::JoinGame, 0x29),
+            (Clientbound::SetHealth, 0x5B),
+            (Clientbound::CombatDeath, 0x3A),
+            (Clientbound::ResourcePack, 0x44),
+            (Clientbound::CommandSuggestions, 0x10),
+            (Clientbound::Commands, 0x11),
+        ],
+        clientbound_names: packetnames::CLIENTBOUND_1_20_4,
+        serverbound: &[
+            (Serverbound::ChatCommand, 0x04),
+            (Serverbound::ChatMessage, 0x05),
+            (Serverbound::KeepAlive, 0x15),
+            (Serverbound::PluginMessage, 0x10),
+            (Serverbound::ConfirmTeleport, 0x00),
+            (Serverbound::PlayerPosition, 0x18),
+            (Serverbound::ClientStatus, 0x08),
+            (Serverbound::SwingArm, 0x33),
+            (Serverbound::PlayerRotation, 0x19),
+            (Serverbound::PlayerCommand, 0x22),
+            (Serverbound::ResourcePackStatus, 0x28),
+            (Serverbound::CommandSuggestions, 0x0A),
+        ],
+    },
+    PacketTable {
+        version: "1.20.5",
+        protocol: 766,
+        newest: 767, // 1.20.6, 1.21 and 1.21.1 use the same IDs
+        clientbound: &[
+            (Clientbound::ChatMessage, 0x6C), // System Chat
+            (Clientbound::PlayerChat, 0x39),
+            (Clientbound::DisguisedChat, 0x1E),
+            (Clientbound::Disconnect, 0x1D),
+            (Clientbound::KeepAlive, 0x26),
+            (Clientbound::PlayerInfoUpdate, 0x3E),
+            (Clientbound::PlayerInfoRemove, 0x3D),
+            (Clientbound::DisplayObjective, 0x57),
+            (Clientbound::UpdateObjective, 0x5E),
+            (Clientbound::UpdateTeam, 0x60),
+            (Clientbound::UpdateScore, 0x61),
+            (Clientbound::ResetScore, 0x44),
+            (Clientbound::TabList, 0x6D),
+            (Clientbound::BossBar, 0x0A),
+            (Clientbound::TitleText, 0x65),
+            (Clientbound::Subtitle, 0x63),
+            (Clientbound::TitleTimes, 0x66),
+            (Clientbound::ClearTitles, 0x0F),
+            (Clientbound::ActionBar, 0x4C),
+            (Clientbound::PluginMessage, 0x19),
+            (Clientbound::SynchronizePosition, 0x40),
+            (Clientbound::JoinGame, 0x2B),
+            (Clientbound::SetHealth, 0x5D),
+            (Clientbound::CombatDeath, 0x3C),
+            (Clientbound::ResourcePack, 0x46),
+            (Clientbound::CommandSuggestions, 0x10),
+            (Clientbound::Commands, 0x11),
+        ],
+        clientbound_names: packetnames::CLIENTBOUND_1_20_5,
+        serverbound: &[
+            (Serverbound::ChatCommand, 0x04),
+            (Serverbound::ChatMessage, 0x06),
+            (Serverbound::KeepAlive, 0x18),
+            (Serverbound::PluginMessage, 0x12),
+            (Serverbound::ConfirmTeleport, 0x00),
+            (Serverbound::PlayerPosition, 0x1B),
+            (Serverbound::ClientStatus, 0x09),
+            (Serverbound::SwingArm, 0x36),
+            (Serverbound::PlayerRotation, 0x1C),
+            (Serverbound::PlayerCommand, 0x25),
+            (Serverbound::ResourcePackStatus, 0x2B),
+            (Serverbound::CommandSuggestions, 0x0B),
+        ],
+    },
+];
+
+/// The table for `protocol`, if the client knows its packet IDs. Versions in between
+/// have their own IDs and layouts, so there's no falling back to a neighbouring table.
+pub fn table_for(protocol: i32) -> Option<&'static PacketTable> {
+    TABLES
+        .iter()
+        .find(|table| (table.protocol..=table.newest).contains(&protocol))
+}
+
+/// Like [`table_for`], but fails with the versions that are supported.
+pub fn require_table(protocol: i32) -> Result<&'static PacketTable, ClientError> {
+    table_for(protocol).ok_or_else(|| {
+        let versions: Vec<String> = TABLES
+            .iter()
+            .map(|table| match table.protocol == table.newest {
+                true => format!("{} ({})", table.version, table.protocol),
+                false => format!("{} ({}-{})", table.version, table.protocol, table.newest),
+            })
+            .collect();
+        ClientError::Protocol(format!(
+            "Unsupported protocol {} (the client knows {})",
+            protocol,
+            versions.join(", ")
+        ))
+    })
+}
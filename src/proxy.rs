@@ -0,0 +1,144 @@
+use std::io::{Read, Write};
+use std::net::{IpAddr, TcpStream};
+
+use crate::client::ConnectPolicy;
+use crate::error::ClientError;
+use crate::helper;
+
+#[derive(Clone, Debug)]
+pub struct Socks5Proxy {
+    pub host: String,
+    pub port: u16,
+    pub credentials: Option<(String, String)>,
+}
+
+impl Socks5Proxy {
+    /// Parses `socks5://[user:pass@]host[:port]`. The port defaults to 1080.
+    pub fn parse(url: &str) -> Result<Socks5Proxy, ClientError> {
+        let Some(rest) = url
+            .strip_prefix("socks5://")
+            .or_else(|| url.strip_prefix("socks5h://"))
+        else {
+            return Err(format!("Unsupported proxy '{}', expected socks5://host:port", url).into());
+        };
+
+        let (credentials, address): (Option<(String, String)>, &str) = match rest.rsplit_once('@') {
+            Some((user_info, address)) => {
+                let Some((user, pass)) = user_info.split_once(':') else {
+                    return Err("Proxy credentials must look like user:pass".into());
+                };
+                (Some((String::from(user), String::from(pass))), address)
+            }
+            None => (None, rest),
+        };
+
+        let address: &str = address.trim_end_matches('/');
+        let (host, port): (&str, u16) = match address.rsplit_once(':') {
+            Some((host, port)) if !port.contains(']') => match port.parse() {
+                Ok(port) => (host, port),
+                Err(_) => return Err(format!("Invalid proxy port '{}'", port).into()),
+            },
+            _ => (address, 1080),
+        };
+
+        Ok(Socks5Proxy {
+            host: String::from(host.trim_start_matches('[').trim_end_matches(']')),
+            port,
+            credentials,
+        })
+    }
+
+    /// Connects to the proxy and asks it to open a connection to `host:port`. The policy's
+    /// timeout applies to reaching the proxy and to each of its answers.
+    pub fn connect(
+        &self,
+        host: &str,
+        port: u16,
+        policy: &ConnectPolicy,
+    ) -> Result<TcpStream, ClientError> {
+        let mut stream: TcpStream =
+            helper::connect_timeout(&self.host, self.port, policy.timeout, policy.prefer)?;
+        stream.set_read_timeout(Some(policy.timeout))?;
+
+        // Greeting: offer "no auth" and, if we have credentials, username/password
+        match self.credentials {
+            Some(_) => stream.write_all(&[0x05, 0x02, 0x00, 0x02])?,
+            None => stream.write_all(&[0x05, 0x01, 0x00])?,
+        }
+
+        let mut method: [u8; 2] = [0u8; 2];
+        stream.read_exact(&mut method)?;
+
+        match (method[1], &self.credentials) {
+            (0x00, _) => {}
+            (0x02, Some((user, pass))) => {
+                let mut auth: Vec<u8> = vec![0x01, user.len() as u8];
+                auth.extend_from_slice(user.as_bytes());
+                auth.push(pass.len() as u8);
+                auth.extend_from_slice(pass.as_bytes());
+                stream.write_all(&auth)?;
+
+                let mut status: [u8; 2] = [0u8; 2];
+                stream.read_exact(&mut status)?;
+                if status[1] != 0x00 {
+                    return Err(ClientError::AuthFailed(String::from(
+                        "The SOCKS5 proxy rejected the username/password",
+                    )));
+                }
+            }
+            _ => {
+                return Err(ClientError::AuthFailed(String::from(
+                    "The SOCKS5 proxy doesn't accept any of the offered auth methods",
+                )));
+            }
+        }
+
+        // CONNECT request
+        let mut request: Vec<u8> = vec![0x05, 0x01, 0x00];
+        match host.parse::<IpAddr>() {
+            Ok(IpAddr::V4(ip)) => {
+                request.push(0x01);
+                request.extend_from_slice(&ip.octets());
+            }
+            Ok(IpAddr::V6(ip)) => {
+                request.push(0x04);
+                request.extend_from_slice(&ip.octets());
+            }
+            Err(_) => {
+                // Let the proxy resolve the hostname
+                request.push(0x03);
+                request.push(host.len() as u8);
+                request.extend_from_slice(host.as_bytes());
+            }
+        }
+        request.extend_from_slice(&port.to_be_bytes());
+        stream.write_all(&request)?;
+
+        let mut reply: [u8; 4] = [0u8; 4];
+        stream.read_exact(&mut reply)?;
+        if reply[1] != 0x00 {
+            return Err(format!(
+                "The SOCKS5 proxy refused the connection (code {})",
+                reply[1]
+            )
+            .into());
+        }
+
+        // Skip the bound address the proxy sends back
+        let address_len: usize = match reply[3] {
+            0x01 => 4,
+            0x04 => 16,
+            0x03 => {
+                let mut len: [u8; 1] = [0u8; 1];
+                stream.read_exact(&mut len)?;
+                len[0] as usize
+            }
+            _ => return Err("Invalid SOCKS5 reply".into()),
+        };
+        let mut bound_address: Vec<u8> = vec![0u8; address_len + 2];
+        stream.read_exact(&mut bound_address)?;
+
+        stream.set_read_timeout(None)?;
+        Ok(stream)
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::net::UdpSocket;
+use std::time::Duration;
+
+use crate::error::ClientError;
+
+// GameSpy4 (UDP) query, answered by servers with `enable-query=true`. Unlike the status
+// ping it lists every online player plus the plugins and map name.
+
+const MAGIC: [u8; 2] = [0xFE, 0xFD];
+const TYPE_HANDSHAKE: u8 = 0x09;
+const TYPE_STAT: u8 = 0x00;
+
+#[derive(Clone, Debug)]
+pub struct QueryResponse {
+    pub motd: String,
+    pub game_type: String,
+    pub version: String,
+    /// Server software followed by its plugins, e.g. `Paper on 1.16.5: WorldEdit 7.2.5; ...`.
+    pub plugins: String,
+    pub map: String,
+    pub online_players: u32,
+    pub max_players: u32,
+    pub host_port: u16,
+    pub host_ip: String,
+    pub players: Vec<String>,
+    /// Every key/value pair the server sent, including ones not covered above.
+    pub values: HashMap<String, String>,
+}
+
+fn request(
+    socket: &UdpSocket,
+    kind: u8,
+    session: i32,
+    payload: &[u8],
+) -> Result<Vec<u8>, ClientError> {
+    let mut packet: Vec<u8> = MAGIC.to_vec();
+    packet.push(kind);
+    packet.extend_from_slice(&session.to_be_bytes());
+    packet.extend_from_slice(payload);
+    socket.send(&packet)?;
+
+    let mut buffer: [u8; 65535] = [0u8; 65535];
+    let len: usize = socket.recv(&mut buffer)?;
+
+    // type (1) + session (4)
+    if len < 5 || buffer[0] != kind || buffer[1..5] != session.to_be_bytes() {
+        return Err("Invalid query response".into());
+    }
+    Ok(buffer[5..len].to_vec())
+}
+
+// Splits null-terminated strings, stopping at the first empty one.
+fn read_strings(data: &[u8]) -> (Vec<String>, usize) {
+    let mut strings: Vec<String> = Vec::new();
+    let mut pos: usize = 0;
+
+    while let Some(len) = data[pos..].iter().position(|byte| *byte == 0) {
+        let end: usize = pos + len;
+        pos = end + 1;
+        if len == 0 {
+            break;
+        }
+        strings.push(String::from_utf8_lossy(&data[end - len..end]).into_owned());
+    }
+
+    (strings, pos)
+}
+
+/// Sends a full stat query to `host:port` (the server's `query.port`).
+pub fn query(host: &str, port: u16) -> Result<QueryResponse, ClientError> {
+    let socket: UdpSocket = UdpSocket::bind("0.0.0.0:0")?;
+    socket.set_read_timeout(Some(Duration::from_secs(5)))?;
+    socket.connect((host, port))?;
+
+    let session: i32 = std::process::id() as i32 & 0x0F0F0F0F;
+
+    let handshake: Vec<u8> = request(&socket, TYPE_HANDSHAKE, session, &[])?;
+    let (token, _) = read_strings(&handshake);
+    let Some(challenge) = token.first().and_then(|token| token.parse::<i32>().ok()) else {
+        return Err("Invalid query challenge token".into());
+    };
+
+    let mut payload: Vec<u8> = challenge.to_be_bytes().to_vec();
+    payload.extend_from_slice(&[0u8; 4]); // asks for the full stat
+    let stat: Vec<u8> = request(&socket, TYPE_STAT, session, &payload)?;
+
+    // "splitnum\0\x80\0" padding before the key/value section
+    let Some(data) = stat.get(11..) else {
+        return Err("Truncated query response".into());
+    };
+    let (pairs, end) = read_strings(data);
+    let values: HashMap<String, String> = pairs
+        .chunks(2)
+        .filter_map(|pair| Some((pair[0].clone(), pair.get(1)?.clone())))
+        .collect();
+
+    // "\x01player_\0\0" padding before the player names
+    let players: Vec<String> = match data.get(end + 10..) {
+        Some(names) => read_strings(names).0,
+        None => Vec::new(),
+    };
+
+    let value = |key: &str| -> String { values.get(key).cloned().unwrap_or_default() };
+
+    Ok(QueryResponse {
+        motd: value("hostname"),
+        game_type: value("gametype"),
+        version: value("version"),
+        plugins: value("plugins"),
+        map: value("map"),
+        online_players: value("numplayers").parse().unwrap_or(0),
+        max_players: value("maxplayers").parse().unwrap_or(0),
+        host_port: value("hostport").parse().unwrap_or(0),
+        host_ip: value("hostip"),
+        players,
+        values,
+    })
+}
+
+impl fmt::Display for QueryResponse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "MOTD: {}", self.motd)?;
+        writeln!(f, "Version: {} ({})", self.version, self.game_type)?;
+        writeln!(f, "Map: {}", self.map)?;
+        writeln!(f, "Address: {}:{}", self.host_ip, self.host_port)?;
+        writeln!(
+            f,
+            "Players ({}/{}): [{}]",
+            self.online_players,
+            self.max_players,
+            self.players.join(", ")
+        )?;
+        write!(
+            f,
+            "Plugins: {}",
+            if self.plugins.is_empty() {
+                "none"
+            } else {
+                &self.plugins
+            }
+        )
+    }
+}
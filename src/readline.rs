@@ -0,0 +1,426 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{IsTerminal, Read, Write, stdin, stdout};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::{Mutex, OnceLock};
+
+// Line editor for the interactive input: arrow-key history, cursor movement,
+// Ctrl-R reverse search, Tab completion, and a history file shared between sessions.
+// The terminal is switched to non-canonical mode with `stty` while it's active.
+
+const MAX_HISTORY: usize = 1000;
+const PROMPT: &str = "> ";
+
+type Completer = Box<dyn Fn(&str) -> Vec<String> + Send + Sync>;
+
+struct EditState {
+    buffer: Vec<char>,
+    cursor: usize,
+    search: Option<String>,
+    search_match: Option<usize>,
+    // Lines that repeated Tab presses cycle through
+    completions: Vec<String>,
+    completion_index: usize,
+}
+
+pub struct LineEditor {
+    history: Mutex<Vec<String>>,
+    history_path: Option<PathBuf>,
+    saved_tty: String,
+    state: Mutex<EditState>,
+    completer: OnceLock<Completer>,
+}
+
+static EDITOR: OnceLock<LineEditor> = OnceLock::new();
+
+/// Returns the line editor if the input is an interactive terminal.
+pub fn get() -> Option<&'static LineEditor> {
+    EDITOR.get()
+}
+
+pub fn default_history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".mc_chat_history"))
+}
+
+fn stty(args: &[&str]) -> Option<String> {
+    let tty: File = File::open("/dev/tty").ok()?;
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::from(tty))
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn read_byte() -> Option<u8> {
+    let mut byte: [u8; 1] = [0u8; 1];
+    match stdin().lock().read(&mut byte) {
+        Ok(1) => Some(byte[0]),
+        _ => None,
+    }
+}
+
+fn read_char(first: u8) -> Option<char> {
+    let len: usize = match first {
+        0x00..=0x7F => 1,
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        _ => 4,
+    };
+
+    let mut bytes: Vec<u8> = vec![first];
+    for _ in 1..len {
+        bytes.push(read_byte()?);
+    }
+    String::from_utf8(bytes).ok()?.chars().next()
+}
+
+enum Key {
+    Char(char),
+    Enter,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    Search,
+    Tab,
+    Cancel,
+    Interrupt,
+    Eof,
+    Closed,
+    Ignored,
+}
+
+fn read_key() -> Key {
+    let Some(byte) = read_byte() else {
+        return Key::Closed;
+    };
+
+    match byte {
+        b'\r' | b'\n' => Key::Enter,
+        b'\t' => Key::Tab,
+        0x7F | 0x08 => Key::Backspace,
+        0x01 => Key::Home,      // Ctrl-A
+        0x05 => Key::End,       // Ctrl-E
+        0x02 => Key::Left,      // Ctrl-B
+        0x06 => Key::Right,     // Ctrl-F
+        0x10 => Key::Up,        // Ctrl-P
+        0x0E => Key::Down,      // Ctrl-N
+        0x12 => Key::Search,    // Ctrl-R
+        0x07 => Key::Cancel,    // Ctrl-G
+        0x03 => Key::Interrupt, // Ctrl-C
+        0x04 => Key::Eof,       // Ctrl-D
+        0x1B => match read_byte() {
+            Some(b'[') | Some(b'O') => match read_byte() {
+                Some(b'A') => Key::Up,
+                Some(b'B') => Key::Down,
+                Some(b'C') => Key::Right,
+                Some(b'D') => Key::Left,
+                Some(b'H') => Key::Home,
+                Some(b'F') => Key::End,
+                Some(digit @ b'0'..=b'9') => {
+                    // ESC [ n ~
+                    let mut code: u8 = digit;
+                    while let Some(next) = read_byte() {
+                        if next == b'~' {
+                            break;
+                        }
+                        code = next;
+                    }
+                    match code {
+                        b'1' | b'7' => Key::Home,
+                        b'4' | b'8' => Key::End,
+                        b'3' => Key::Delete,
+                        _ => Key::Ignored,
+                    }
+                }
+                _ => Key::Ignored,
+            },
+            _ => Key::Cancel,
+        },
+        byte if byte < 0x20 => Key::Ignored,
+        byte => match read_char(byte) {
+            Some(c) => Key::Char(c),
+            None => Key::Ignored,
+        },
+    }
+}
+
+impl LineEditor {
+    /// Switches the terminal to non-canonical mode. Returns `None` when stdin isn't a
+    /// terminal, in which case input should be read line by line as usual.
+    pub fn start(history_path: Option<PathBuf>) -> Option<&'static LineEditor> {
+        if !stdin().is_terminal() {
+            return None;
+        }
+
+        let saved_tty: String = stty(&["-g"])?;
+        stty(&["-icanon", "-echo", "-isig", "min", "1"])?;
+
+        let mut history: Vec<String> = Vec::new();
+        if let Some(path) = &history_path
+            && let Ok(text) = fs::read_to_string(path)
+        {
+            history = text.lines().map(String::from).collect();
+            let excess: usize = history.len().saturating_sub(MAX_HISTORY);
+            history.drain(..excess);
+        }
+
+        let editor: LineEditor = LineEditor {
+            history: Mutex::new(history),
+            history_path,
+            saved_tty,
+            state: Mutex::new(EditState {
+                buffer: Vec::new(),
+                cursor: 0,
+                search: None,
+                search_match: None,
+                completions: Vec::new(),
+                completion_index: 0,
+            }),
+            completer: OnceLock::new(),
+        };
+
+        Some(EDITOR.get_or_init(|| editor))
+    }
+
+    /// Sets what Tab completes the line with. `completer` gets the whole line and returns
+    /// the candidates for it; it's called without holding the editor's lock.
+    pub fn set_completer<F>(&self, completer: F)
+    where
+        F: Fn(&str) -> Vec<String> + Send + Sync + 'static,
+    {
+        let _ = self.completer.set(Box::new(completer));
+    }
+
+    /// Puts the terminal back the way it was.
+    pub fn stop(&self) {
+        stty(&[self.saved_tty.as_str()]);
+    }
+
+    fn render(&self, state: &EditState) {
+        let mut line: String = String::from("\r\x1b[2K");
+
+        match &state.search {
+            Some(query) => {
+                let found: String = match state.search_match {
+                    Some(i) => self.history.lock().unwrap()[i].clone(),
+                    None => String::new(),
+                };
+                line += &format!("(reverse-i-search)`{}': {}", query, found);
+            }
+            None => {
+                let text: String = state.buffer.iter().collect();
+                line += PROMPT;
+                line += &text;
+                let back: usize = state.buffer.len() - state.cursor;
+                if back > 0 {
+                    line += &format!("\x1b[{}D", back);
+                }
+            }
+        }
+
+        let mut out = stdout().lock();
+        let _ = out.write_all(line.as_bytes());
+        let _ = out.flush();
+    }
+
+    /// Prints a line above the one being edited, then redraws the input.
+    pub fn print_above(&self, text: &str) {
+        let state = self.state.lock().unwrap();
+        print!("\r\x1b[2K{}\n", text);
+        self.render(&state);
+    }
+
+    fn find_match(&self, query: &str, before: usize) -> Option<usize> {
+        let history = self.history.lock().unwrap();
+        history[..before.min(history.len())]
+            .iter()
+            .rposition(|entry| entry.contains(query))
+    }
+
+    fn remember(&self, line: &str) {
+        let mut history = self.history.lock().unwrap();
+        if line.is_empty() || history.last().map(String::as_str) == Some(line) {
+            return;
+        }
+
+        history.push(String::from(line));
+        if history.len() > MAX_HISTORY {
+            history.remove(0);
+        }
+
+        if let Some(path) = &self.history_path
+            && let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path)
+        {
+            let _ = writeln!(file, "{}", line);
+        }
+    }
+
+    /// Reads one line of input. Returns `None` on Ctrl-C, or Ctrl-D on an empty line.
+    pub fn read_line(&self) -> Option<String> {
+        let mut history_pos: usize = self.history.lock().unwrap().len();
+        {
+            let mut state = self.state.lock().unwrap();
+            state.buffer.clear();
+            state.cursor = 0;
+            state.search = None;
+            state.search_match = None;
+            state.completions.clear();
+            self.render(&state);
+        }
+
+        loop {
+            let key: Key = read_key();
+            let mut state = self.state.lock().unwrap();
+
+            if let Some(query) = state.search.clone() {
+                // Reverse search mode
+                match key {
+                    Key::Char(c) => {
+                        let query: String = query + &c.to_string();
+                        let history_len: usize = self.history.lock().unwrap().len();
+                        state.search_match = self.find_match(&query, history_len);
+                        state.search = Some(query);
+                    }
+                    Key::Backspace => {
+                        let mut query: String = query;
+                        query.pop();
+                        let history_len: usize = self.history.lock().unwrap().len();
+                        state.search_match = self.find_match(&query, history_len);
+                        state.search = Some(query);
+                    }
+                    Key::Search => {
+                        if let Some(i) = state.search_match {
+                            state.search_match = self.find_match(&query, i).or(Some(i));
+                        }
+                    }
+                    Key::Cancel | Key::Interrupt => {
+                        state.search = None;
+                    }
+                    _ => {
+                        // Any other key accepts the match and goes back to editing
+                        if let Some(i) = state.search_match {
+                            state.buffer = self.history.lock().unwrap()[i].chars().collect();
+                            state.cursor = state.buffer.len();
+                        }
+                        state.search = None;
+                        if matches!(key, Key::Enter) {
+                            let line: String = state.buffer.iter().collect();
+                            print!("\r\n");
+                            self.remember(line.trim());
+                            return Some(line);
+                        }
+                    }
+                }
+                self.render(&state);
+                continue;
+            }
+
+            if !matches!(key, Key::Tab) {
+                state.completions.clear();
+            }
+
+            match key {
+                Key::Char(c) => {
+                    let cursor: usize = state.cursor;
+                    state.buffer.insert(cursor, c);
+                    state.cursor += 1;
+                }
+                Key::Enter => {
+                    let line: String = state.buffer.iter().collect();
+                    print!("\r\n");
+                    let _ = stdout().flush();
+                    self.remember(line.trim());
+                    return Some(line);
+                }
+                Key::Backspace => {
+                    if state.cursor > 0 {
+                        state.cursor -= 1;
+                        let cursor: usize = state.cursor;
+                        state.buffer.remove(cursor);
+                    }
+                }
+                Key::Delete => {
+                    let cursor: usize = state.cursor;
+                    if cursor < state.buffer.len() {
+                        state.buffer.remove(cursor);
+                    }
+                }
+                Key::Left => state.cursor = state.cursor.saturating_sub(1),
+                Key::Right => state.cursor = (state.cursor + 1).min(state.buffer.len()),
+                Key::Home => state.cursor = 0,
+                Key::End => state.cursor = state.buffer.len(),
+                Key::Up | Key::Down => {
+                    let history = self.history.lock().unwrap();
+                    history_pos = match key {
+                        Key::Up => history_pos.saturating_sub(1),
+                        _ => (history_pos + 1).min(history.len()),
+                    };
+                    state.buffer = match history.get(history_pos) {
+                        Some(entry) => entry.chars().collect(),
+                        None => Vec::new(),
+                    };
+                    state.cursor = state.buffer.len();
+                }
+                Key::Search => {
+                    state.search = Some(String::new());
+                    state.search_match = None;
+                }
+                Key::Tab => {
+                    if state.completions.is_empty() {
+                        let line: String = state.buffer.iter().collect();
+                        drop(state);
+                        // The completer may wait on the server, let other threads print meanwhile
+                        let completions: Vec<String> = match self.completer.get() {
+                            Some(completer) => completer(&line),
+                            None => Vec::new(),
+                        };
+                        state = self.state.lock().unwrap();
+                        state.completions = completions;
+                        state.completion_index = 0;
+                    } else {
+                        state.completion_index =
+                            (state.completion_index + 1) % state.completions.len();
+                    }
+
+                    if let Some(completion) = state.completions.get(state.completion_index) {
+                        state.buffer = completion.chars().collect();
+                        state.cursor = state.buffer.len();
+                    }
+                }
+                Key::Cancel => {
+                    state.buffer.clear();
+                    state.cursor = 0;
+                }
+                Key::Interrupt => {
+                    print!("\r\n");
+                    // -isig turns Ctrl-C into a key, so it's passed on like the signal
+                    crate::shutdown::request();
+                    return None;
+                }
+                Key::Closed => {
+                    print!("\r\n");
+                    return None;
+                }
+                Key::Eof => {
+                    if state.buffer.is_empty() {
+                        print!("\r\n");
+                        return None;
+                    }
+                }
+                Key::Ignored => {}
+            }
+
+            self.render(&state);
+        }
+    }
+}
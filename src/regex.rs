@@ -0,0 +1,749 @@
+use std::error::Error;
+
+use tracing::debug;
+
+// A small backtracking regex engine for the chat filters and scripts, covering what
+// people usually write for them: literals, `.`, classes like `[a-z]` and `\d`, `^`/`$`,
+// capturing and `(?:...)` groups with `|`, the `* + ? {n,m}` quantifiers and a
+// leading `(?i)` for case-insensitive matching.
+//
+// Patterns are compiled to a small program that is run with an explicit backtracking
+// stack, so long lines can't overflow the thread's stack. Patterns like `(.*)*z`
+// backtrack exponentially, and matching runs on the reader thread for every chat line,
+// so a search gives up (as no match) after `MAX_STEPS`.
+
+const MAX_STEPS: usize = 1_000_000;
+// Counted repetitions are written out, `(...){1000}` would be huge
+const MAX_PROGRAM: usize = 10_000;
+
+#[derive(Clone, Debug)]
+enum ClassItem {
+    Char(char),
+    Range(char, char),
+    Digit(bool),
+    Word(bool),
+    Space(bool),
+}
+
+impl ClassItem {
+    fn matches(&self, c: char) -> bool {
+        match *self {
+            ClassItem::Char(expected) => c == expected,
+            ClassItem::Range(from, to) => (from..=to).contains(&c),
+            ClassItem::Digit(negated) => c.is_ascii_digit() != negated,
+            ClassItem::Word(negated) => (c.is_alphanumeric() || c == '_') != negated,
+            ClassItem::Space(negated) => c.is_whitespace() != negated,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Node {
+    Char(char),
+    Any,
+    Class {
+        items: Vec<ClassItem>,
+        negated: bool,
+    },
+    Start,
+    End,
+    /// The alternatives of a group, each a sequence. `index` is the number of a
+    /// capturing group.
+    Group {
+        index: Option<usize>,
+        alternatives: Vec<Vec<Node>>,
+    },
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: Option<usize>,
+    },
+}
+
+// One step of a compiled pattern. `Split` tries its first target and comes back to the
+// second if that fails.
+#[derive(Debug)]
+enum Inst {
+    /// A node that matches one character.
+    Char(Node),
+    Start,
+    End,
+    Split(usize, usize),
+    Jump(usize),
+    /// Remembers the position in a capture slot: two per group, the whole match first.
+    Save(usize),
+    /// Remembers where a round of a repetition started, in a loop register.
+    Mark(usize),
+    /// Fails if the round since the `Mark` matched nothing. Such rounds would loop
+    /// forever.
+    Progress(usize),
+    Match,
+}
+
+pub struct Regex {
+    program: Vec<Inst>,
+    ignore_case: bool,
+    groups: usize,
+    registers: usize,
+}
+
+struct Compiler {
+    program: Vec<Inst>,
+    registers: usize,
+}
+
+impl Compiler {
+    fn emit(&mut self, inst: Inst) -> Result<usize, String> {
+        if self.program.len() >= MAX_PROGRAM {
+            return Err(String::from("Pattern too large"));
+        }
+        self.program.push(inst);
+        Ok(self.program.len() - 1)
+    }
+
+    // Placeholders are filled in once the targets are known
+    fn patch(&mut self, at: usize, inst: Inst) {
+        self.program[at] = inst;
+    }
+
+    fn alternatives(&mut self, alternatives: &[Vec<Node>]) -> Result<(), String> {
+        let mut jumps: Vec<usize> = Vec::new();
+        for (i, alternative) in alternatives.iter().enumerate() {
+            if i == alternatives.len() - 1 {
+                self.sequence(alternative)?;
+                break;
+            }
+            let split: usize = self.emit(Inst::Match)?;
+            self.sequence(alternative)?;
+            jumps.push(self.emit(Inst::Match)?);
+            let next: usize = self.program.len();
+            self.patch(split, Inst::Split(split + 1, next));
+        }
+        let end: usize = self.program.len();
+        for jump in jumps {
+            self.patch(jump, Inst::Jump(end));
+        }
+        Ok(())
+    }
+
+    fn sequence(&mut self, nodes: &[Node]) -> Result<(), String> {
+        for node in nodes {
+            self.node(node)?;
+        }
+        Ok(())
+    }
+
+    fn node(&mut self, node: &Node) -> Result<(), String> {
+        match node {
+            Node::Start => self.emit(Inst::Start).map(|_| ()),
+            Node::End => self.emit(Inst::End).map(|_| ()),
+            Node::Group {
+                index: None,
+                alternatives,
+            } => self.alternatives(alternatives),
+            Node::Group {
+                index: Some(index),
+                alternatives,
+            } => {
+                self.emit(Inst::Save(index * 2))?;
+                self.alternatives(alternatives)?;
+                self.emit(Inst::Save(index * 2 + 1)).map(|_| ())
+            }
+            Node::Repeat { node, min, max } => self.repeat(node, *min, *max),
+            node => self.emit(Inst::Char(node.clone())).map(|_| ()),
+        }
+    }
+
+    // The first `min` rounds may match nothing, the ones after that have to move on
+    fn repeat(&mut self, node: &Node, min: usize, max: Option<usize>) -> Result<(), String> {
+        for _ in 0..min {
+            self.node(node)?;
+        }
+
+        let register: usize = self.registers;
+        self.registers += 1;
+
+        match max {
+            None => {
+                let split: usize = self.emit(Inst::Match)?;
+                self.emit(Inst::Mark(register))?;
+                self.node(node)?;
+                self.emit(Inst::Progress(register))?;
+                self.emit(Inst::Jump(split))?;
+                let end: usize = self.program.len();
+                self.patch(split, Inst::Split(split + 1, end));
+            }
+            Some(max) => {
+                let mut splits: Vec<usize> = Vec::new();
+                for _ in min..max {
+                    splits.push(self.emit(Inst::Match)?);
+                    self.emit(Inst::Mark(register))?;
+                    self.node(node)?;
+                    self.emit(Inst::Progress(register))?;
+                }
+                let end: usize = self.program.len();
+                for split in splits {
+                    self.patch(split, Inst::Split(split + 1, end));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    // Capturing groups so far
+    groups: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c: Option<char> = self.peek();
+        self.pos += 1;
+        c
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn alternatives(&mut self) -> Result<Vec<Vec<Node>>, String> {
+        let mut res: Vec<Vec<Node>> = vec![self.sequence()?];
+        while self.eat('|') {
+            res.push(self.sequence()?);
+        }
+        Ok(res)
+    }
+
+    fn sequence(&mut self) -> Result<Vec<Node>, String> {
+        let mut res: Vec<Node> = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom: Node = self.atom()?;
+            res.push(self.quantifier(atom)?);
+        }
+        Ok(res)
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        match self.next() {
+            Some('.') => Ok(Node::Any),
+            Some('^') => Ok(Node::Start),
+            Some('$') => Ok(Node::End),
+            Some('(') => {
+                let index: Option<usize> = match self.eat('?') {
+                    true if self.eat(':') => None,
+                    true => {
+                        return Err(String::from(
+                            "Only (?:...) groups and a leading (?i) are supported",
+                        ));
+                    }
+                    false => {
+                        self.groups += 1;
+                        Some(self.groups)
+                    }
+                };
+                let alternatives: Vec<Vec<Node>> = self.alternatives()?;
+                if !self.eat(')') {
+                    return Err(String::from("Missing )"));
+                }
+                Ok(Node::Group {
+                    index,
+                    alternatives,
+                })
+            }
+            Some('[') => self.class(),
+            Some('\\') => match self.escape()? {
+                ClassItem::Char(c) => Ok(Node::Char(c)),
+                item => Ok(Node::Class {
+                    items: vec![item],
+                    negated: false,
+                }),
+            },
+            Some(c @ ('*' | '+' | '?')) => Err(format!("Nothing to repeat before {}", c)),
+            Some(c) => Ok(Node::Char(c)),
+            None => Err(String::from("Unexpected end of pattern")),
+        }
+    }
+
+    fn escape(&mut self) -> Result<ClassItem, String> {
+        match self.next() {
+            Some('d') => Ok(ClassItem::Digit(false)),
+            Some('D') => Ok(ClassItem::Digit(true)),
+            Some('w') => Ok(ClassItem::Word(false)),
+            Some('W') => Ok(ClassItem::Word(true)),
+            Some('s') => Ok(ClassItem::Space(false)),
+            Some('S') => Ok(ClassItem::Space(true)),
+            Some('n') => Ok(ClassItem::Char('\n')),
+            Some('t') => Ok(ClassItem::Char('\t')),
+            Some(c) if !c.is_alphanumeric() => Ok(ClassItem::Char(c)),
+            Some(c) => Err(format!("Unknown escape \\{}", c)),
+            None => Err(String::from("Pattern ends with \\")),
+        }
+    }
+
+    fn class(&mut self) -> Result<Node, String> {
+        let negated: bool = self.eat('^');
+        let mut items: Vec<ClassItem> = Vec::new();
+
+        loop {
+            let item: ClassItem = match self.next() {
+                None => return Err(String::from("Missing ]")),
+                // A ] right at the start is a literal
+                Some(']') if !items.is_empty() => break,
+                Some('\\') => self.escape()?,
+                Some(c) => ClassItem::Char(c),
+            };
+
+            // `a-z`, unless the - is the last character
+            if let ClassItem::Char(from) = item
+                && self.peek() == Some('-')
+                && self.chars.get(self.pos + 1).is_some_and(|c| *c != ']')
+            {
+                self.pos += 1;
+                let to: char = match self.next() {
+                    Some('\\') => match self.escape()? {
+                        ClassItem::Char(c) => c,
+                        _ => return Err(String::from("Invalid range in []")),
+                    },
+                    Some(c) => c,
+                    None => return Err(String::from("Missing ]")),
+                };
+                if to < from {
+                    return Err(format!("Invalid range {}-{}", from, to));
+                }
+                items.push(ClassItem::Range(from, to));
+            } else {
+                items.push(item);
+            }
+        }
+
+        Ok(Node::Class { items, negated })
+    }
+
+    fn number(&mut self) -> Option<usize> {
+        let start: usize = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos]
+            .iter()
+            .collect::<String>()
+            .parse()
+            .ok()
+    }
+
+    fn quantifier(&mut self, node: Node) -> Result<Node, String> {
+        let (min, max): (usize, Option<usize>) = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => {
+                // Not a quantifier unless it looks like one, like in most engines
+                let start: usize = self.pos;
+                self.pos += 1;
+                let min: Option<usize> = self.number();
+                let max: Option<Option<usize>> = if self.eat(',') {
+                    Some(self.number())
+                } else {
+                    Some(min)
+                };
+                match (min, max) {
+                    (Some(min), Some(max)) if self.peek() == Some('}') => {
+                        if let Some(max) = max
+                            && max < min
+                        {
+                            return Err(format!("Invalid repetition {{{},{}}}", min, max));
+                        }
+                        (min, max)
+                    }
+                    _ => {
+                        self.pos = start;
+                        return Ok(node);
+                    }
+                }
+            }
+            _ => return Ok(node),
+        };
+        self.pos += 1;
+
+        if matches!(node, Node::Start | Node::End) {
+            return Err(String::from("Anchors can't be repeated"));
+        }
+        // Lazy quantifiers only change what a match covers, not whether there is one
+        self.eat('?');
+        Ok(Node::Repeat {
+            node: Box::new(node),
+            min,
+            max,
+        })
+    }
+}
+
+impl Regex {
+    pub fn new(pattern: &str) -> Result<Regex, Box<dyn Error>> {
+        let (pattern, ignore_case): (&str, bool) = match pattern.strip_prefix("(?i)") {
+            Some(rest) => (rest, true),
+            None => (pattern, false),
+        };
+
+        let mut parser: Parser = Parser {
+            chars: pattern.chars().collect(),
+            pos: 0,
+            groups: 0,
+        };
+        let alternatives: Vec<Vec<Node>> = parser.alternatives()?;
+        if parser.pos < parser.chars.len() {
+            return Err("Unmatched )".into());
+        }
+
+        let mut compiler: Compiler = Compiler {
+            program: Vec::new(),
+            registers: 0,
+        };
+        compiler.emit(Inst::Save(0))?;
+        compiler.alternatives(&alternatives)?;
+        compiler.emit(Inst::Save(1))?;
+        compiler.emit(Inst::Match)?;
+
+        Ok(Regex {
+            program: compiler.program,
+            ignore_case,
+            groups: parser.groups,
+            registers: compiler.registers,
+        })
+    }
+
+    /// Whether the pattern matches anywhere in `text`.
+    pub fn is_match(&self, text: &str) -> bool {
+        self.captures(text).is_some()
+    }
+
+    /// The first match in `text`, followed by what each capturing group matched (`None`
+    /// for groups that weren't part of it).
+    pub fn captures(&self, text: &str) -> Option<Vec<Option<String>>> {
+        let text: Vec<char> = text.chars().collect();
+        let mut matcher: Matcher = Matcher {
+            regex: self,
+            text: &text,
+            slots: vec![None; (self.groups + 1) * 2],
+            registers: vec![0; self.registers],
+            stack: Vec::new(),
+            steps: 0,
+        };
+
+        let found: bool = (0..=text.len()).any(|start| matcher.run(start));
+        if matcher.steps > MAX_STEPS {
+            debug!(
+                "A pattern gave up after {} steps on a line of {} characters",
+                MAX_STEPS,
+                text.len()
+            );
+        }
+        if !found {
+            return None;
+        }
+        Some(
+            matcher
+                .slots
+                .chunks(2)
+                .map(|span| match span {
+                    [Some(start), Some(end)] => Some(text[*start..*end].iter().collect()),
+                    _ => None,
+                })
+                .collect(),
+        )
+    }
+}
+
+// What to undo or retry when the current path fails
+enum Backtrack {
+    Retry {
+        pc: usize,
+        pos: usize,
+    },
+    Slot {
+        slot: usize,
+        previous: Option<usize>,
+    },
+    Register {
+        register: usize,
+        previous: usize,
+    },
+}
+
+struct Matcher<'a> {
+    regex: &'a Regex,
+    text: &'a [char],
+    slots: Vec<Option<usize>>,
+    registers: Vec<usize>,
+    stack: Vec<Backtrack>,
+    // Shared by all start positions
+    steps: usize,
+}
+
+impl Matcher<'_> {
+    fn char_matches(&self, node: &Node, c: char) -> bool {
+        let ignore_case: bool = self.regex.ignore_case;
+        match node {
+            Node::Char(expected) if ignore_case => expected.to_lowercase().eq(c.to_lowercase()),
+            Node::Char(expected) => *expected == c,
+            Node::Any => c != '\n',
+            Node::Class { items, negated } => {
+                let found: bool = items.iter().any(|item| {
+                    item.matches(c)
+                        || (ignore_case
+                            && (c.to_uppercase().any(|c| item.matches(c))
+                                || c.to_lowercase().any(|c| item.matches(c))))
+                });
+                found != *negated
+            }
+            _ => false,
+        }
+    }
+
+    // Runs the program from `start`. On a match the slots hold the captures.
+    fn run(&mut self, start: usize) -> bool {
+        self.stack.clear();
+        let (mut pc, mut pos): (usize, usize) = (0, start);
+
+        loop {
+            self.steps += 1;
+            if self.steps > MAX_STEPS {
+                return false;
+            }
+
+            let ok: bool = match &self.regex.program[pc] {
+                Inst::Char(node) => {
+                    let ok: bool = self
+                        .text
+                        .get(pos)
+                        .is_some_and(|c| self.char_matches(node, *c));
+                    pos += 1;
+                    ok
+                }
+                Inst::Start => pos == 0,
+                Inst::End => pos == self.text.len(),
+                Inst::Split(first, second) => {
+                    self.stack.push(Backtrack::Retry { pc: *second, pos });
+                    pc = *first;
+                    continue;
+                }
+                Inst::Jump(target) => {
+                    pc = *target;
+                    continue;
+                }
+                Inst::Save(slot) => {
+                    let previous: Option<usize> = self.slots[*slot].replace(pos);
+                    self.stack.push(Backtrack::Slot {
+                        slot: *slot,
+                        previous,
+                    });
+                    true
+                }
+                Inst::Mark(register) => {
+                    let previous: usize = std::mem::replace(&mut self.registers[*register], pos);
+                    self.stack.push(Backtrack::Register {
+                        register: *register,
+                        previous,
+                    });
+                    true
+                }
+                Inst::Progress(register) => self.registers[*register] != pos,
+                Inst::Match => return true,
+            };
+            if ok {
+                pc += 1;
+                continue;
+            }
+
+            // Undo back to the last choice and take its other way
+            loop {
+                match self.stack.pop() {
+                    None => return false,
+                    Some(Backtrack::Slot { slot, previous }) => self.slots[slot] = previous,
+                    Some(Backtrack::Register { register, previous }) => {
+                        self.registers[register] = previous
+                    }
+                    Some(Backtrack::Retry { pc: retry, pos: at }) => {
+                        (pc, pos) = (retry, at);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    fn matches(pattern: &str, text: &str) -> bool {
+        Regex::new(pattern).unwrap().is_match(text)
+    }
+
+    fn groups(pattern: &str, text: &str) -> Option<Vec<Option<String>>> {
+        Regex::new(pattern).unwrap().captures(text)
+    }
+
+    #[test]
+    fn literals_and_any() {
+        assert!(matches("vote", "please vote for us"));
+        assert!(!matches("vote", "please Vote for us"));
+        assert!(matches("v.te", "vote"));
+        assert!(!matches("a.b", "a\nb"));
+        assert!(matches("", "anything"));
+    }
+
+    #[test]
+    fn classes() {
+        assert!(matches("[abc]x", "bx"));
+        assert!(!matches("[abc]x", "dx"));
+        assert!(matches("[a-z]+[0-9]", "abc7"));
+        assert!(matches("[^a-z]", "abc!"));
+        assert!(!matches("^[^a-z]+$", "abc"));
+        assert!(matches("[]]", "]"));
+        assert!(matches("[a-]", "-"));
+        assert!(matches(r"\d\d", "in 42 ways"));
+        assert!(!matches(r"\D", "123"));
+        assert!(matches(r"^\w+$", "Player_1"));
+        assert!(matches(r"\W", "a b"));
+        assert!(matches(r"a\sb", "a\tb"));
+        assert!(!matches(r"\S", "  "));
+        assert!(matches(r"[\d.]+", "1.5"));
+        assert!(matches(r"\[Ad\]", "[Ad] buy"));
+    }
+
+    #[test]
+    fn anchors() {
+        assert!(matches("^Hello", "Hello world"));
+        assert!(!matches("^world", "Hello world"));
+        assert!(matches("world$", "Hello world"));
+        assert!(!matches("Hello$", "Hello world"));
+        assert!(matches("^$", ""));
+    }
+
+    #[test]
+    fn quantifiers() {
+        assert!(matches("^ab*c$", "ac"));
+        assert!(matches("^ab*c$", "abbbc"));
+        assert!(!matches("^ab+c$", "ac"));
+        assert!(matches("^colou?r$", "color"));
+        assert!(matches("^colou?r$", "colour"));
+        assert!(matches("^a{2,3}$", "aaa"));
+        assert!(!matches("^a{2,3}$", "aaaa"));
+        assert!(matches("^a{2}$", "aa"));
+        assert!(matches("^a{2,}$", "aaaaa"));
+        assert!(!matches("^a{2,}$", "a"));
+        // Not a quantifier, so the braces are literal
+        assert!(matches("^a{x}$", "a{x}"));
+        assert!(matches("^a+?b$", "aaab"));
+        assert!(matches("^(a*)*b$", "aab"));
+    }
+
+    #[test]
+    fn groups_and_alternation() {
+        assert!(matches("^(cat|dog)s?$", "dogs"));
+        assert!(!matches("^(cat|dog)$", "cow"));
+        assert!(matches("^(?:ab)+$", "ababab"));
+        assert!(matches("^a|b$", "ax"));
+        assert_eq!(
+            groups(r"<(\w+)> (.*)", "[12:00] <Steve> hi there"),
+            Some(vec![
+                Some(String::from("<Steve> hi there")),
+                Some(String::from("Steve")),
+                Some(String::from("hi there")),
+            ])
+        );
+        assert_eq!(
+            groups("(a)|(b)", "b"),
+            Some(vec![Some(String::from("b")), None, Some(String::from("b"))])
+        );
+        assert_eq!(groups("(x)", "y"), None);
+    }
+
+    #[test]
+    fn ignore_case() {
+        assert!(matches("(?i)vote for us", "VOTE for US"));
+        assert!(matches("(?i)[a-z]+$", "ABC"));
+        assert!(matches("(?i)^steve$", "Steve"));
+        assert!(!matches("(?i)^steve$", "Steven"));
+    }
+
+    #[test]
+    fn parse_errors() {
+        for pattern in [
+            "(ab",
+            "ab)",
+            "[ab",
+            "*a",
+            "a++",
+            "a{3,1}",
+            r"\q",
+            "a\\",
+            "[z-a]",
+            "(?=a)",
+            "^*",
+            "(abc){5000}",
+        ] {
+            assert!(
+                Regex::new(pattern).is_err(),
+                "{} should be an error",
+                pattern
+            );
+        }
+    }
+
+    #[test]
+    fn exponential_patterns_give_up() {
+        let start: Instant = Instant::now();
+        assert!(!matches("(.*)*z", &"a".repeat(40)));
+        assert!(!matches("(a|aa)+$", &format!("{}b", "a".repeat(60))));
+        assert!(start.elapsed() < Duration::from_secs(5));
+        // Matches that are found quickly still are
+        assert!(matches("(.*)*a", &"a".repeat(40)));
+    }
+
+    #[test]
+    fn long_lines() {
+        let line: String = "a".repeat(100_000);
+        assert!(!matches("^.*z", &line));
+        assert!(matches("^.*z$", &format!("{}z", line)));
+        assert!(matches(r"^(\w)+$", &line));
+        assert_eq!(
+            groups("^(a+)(b?)$", &line).map(|groups| groups[1].as_ref().map(String::len)),
+            Some(Some(100_000))
+        );
+    }
+
+    #[test]
+    fn repeated_groups_keep_the_last_round() {
+        assert_eq!(
+            groups("^(?:(a)|(b))+$", "ab"),
+            Some(vec![
+                Some(String::from("ab")),
+                Some(String::from("a")),
+                Some(String::from("b")),
+            ])
+        );
+        assert_eq!(
+            groups("^(a|b){2}$", "ab"),
+            Some(vec![Some(String::from("ab")), Some(String::from("b"))])
+        );
+    }
+}
@@ -0,0 +1,95 @@
+use std::path::Path;
+
+use mclient_project::packet::{ClientboundPacket, ConfigurationPacket, LoginPacket};
+use mclient_project::protocol::{self, PacketTable};
+use mclient_project::{CaptureReader, CaptureState, CapturedFrame, ClientError};
+
+use crate::clock::LocalTime;
+
+/// Runs every frame of a `--capture` file through the client's receiving code, and its
+/// packet through the parser for its state, printing a line for each. Returns how many
+/// failed.
+pub fn replay(path: &Path, print: fn(&str)) -> Result<usize, ClientError> {
+    let capture: CaptureReader = CaptureReader::open(path)?;
+    let capture_protocol: i32 = capture.protocol;
+    let table: &PacketTable = protocol::require_table(capture_protocol)?;
+    let started: LocalTime = LocalTime::at(capture.started_at);
+    print(&format!(
+        "Replaying {}: protocol {} (parsed like {}), captured {} {}",
+        path.display(),
+        capture_protocol,
+        table.version,
+        started.date(),
+        started.time()
+    ));
+
+    let (mut frames, mut play, mut failed): (usize, usize, usize) = (0, 0, 0);
+    for frame in capture {
+        let frame: CapturedFrame = frame?;
+        frames += 1;
+        let (id, data): (i32, &[u8]) = frame.split()?;
+
+        let name: &str = match frame.state {
+            CaptureState::Play => table.clientbound_name(id).unwrap_or("(unknown)"),
+            _ => "",
+        };
+        let result: Result<&str, String> = match frame.verify() {
+            Err(e) => Err(format!("frame doesn't unpack: {}", e)),
+            Ok(()) => match parse(&frame, capture_protocol, table, id, data) {
+                Ok(true) => Ok("parsed"),
+                Ok(false) => Ok("ignored"),
+                Err(e) => Err(format!("malformed: {}", e)),
+            },
+        };
+        if frame.state == CaptureState::Play {
+            play += 1;
+        }
+        let note: String = match result {
+            Ok(note) => String::from(note),
+            Err(e) => {
+                failed += 1;
+                e
+            }
+        };
+
+        print(&format!(
+            "{:>10}  {:<13} 0x{:02X}  {:<38} {:>8} B  {}",
+            format!("+{:.3}s", frame.time.as_secs_f64()),
+            frame.state,
+            id,
+            name,
+            data.len(),
+            note
+        ));
+    }
+
+    print(&format!(
+        "{} frames, {} of them play packets; {} failed",
+        frames, play, failed
+    ));
+    Ok(failed)
+}
+
+// Whether the client understands the packet, as opposed to ignoring it
+fn parse(
+    frame: &CapturedFrame,
+    protocol: i32,
+    table: &PacketTable,
+    id: i32,
+    data: &[u8],
+) -> Result<bool, ClientError> {
+    Ok(match frame.state {
+        CaptureState::Login => !matches!(
+            LoginPacket::decode(protocol, id, data)?,
+            LoginPacket::Unknown { .. }
+        ),
+        CaptureState::Configuration => !matches!(
+            ConfigurationPacket::decode(protocol, id, data)?,
+            ConfigurationPacket::Unknown { .. }
+        ),
+        CaptureState::Play => !matches!(
+            ClientboundPacket::decode(table, id, data)?,
+            ClientboundPacket::Unknown { .. }
+        ),
+    })
+}
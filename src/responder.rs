@@ -0,0 +1,152 @@
+use std::error::Error;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::regex::Regex;
+
+// Answers chat lines that match a rule from the [responders.<name>] tables of the
+// config file, for simple FAQ or support bots. Rules are tried in the order of their
+// names and the first one that matches answers.
+
+/// A `[responders.<name>]` table.
+#[derive(Clone, Debug)]
+pub struct ResponderRule {
+    pub name: String,
+    pub pattern: String,
+    /// Sent as typed, after `$1`..`$9`, `$0` (the whole match) and `$sender` are
+    /// replaced. `$$` is a single `$`.
+    pub response: String,
+    /// How long the rule stays quiet after answering.
+    pub cooldown: Duration,
+    /// Players the rule answers (in any case); everyone if it's empty. Lines with an
+    /// unknown author are only answered by rules without a list.
+    pub allow: Vec<String>,
+}
+
+struct Rule {
+    spec: ResponderRule,
+    regex: Regex,
+    last_answer: Mutex<Option<Instant>>,
+}
+
+pub struct AutoResponder {
+    rules: Vec<Rule>,
+}
+
+impl AutoResponder {
+    pub fn new(rules: &[ResponderRule]) -> Result<AutoResponder, Box<dyn Error>> {
+        let mut compiled: Vec<Rule> = Vec::new();
+        for rule in rules {
+            let regex: Regex = match Regex::new(&rule.pattern) {
+                Ok(regex) => regex,
+                Err(e) => {
+                    return Err(
+                        format!("Invalid pattern in [responders.{}]: {}", rule.name, e).into(),
+                    );
+                }
+            };
+            compiled.push(Rule {
+                spec: rule.clone(),
+                regex,
+                last_answer: Mutex::new(None),
+            });
+        }
+        Ok(AutoResponder { rules: compiled })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// The answer to a chat line, if a rule matches it and isn't cooling down.
+    /// `sender` is the player who wrote it, if known.
+    pub fn answer(&self, sender: Option<&str>, text: &str) -> Option<String> {
+        for rule in &self.rules {
+            let allowed: bool = rule.spec.allow.is_empty()
+                || sender.is_some_and(|sender| {
+                    rule.spec
+                        .allow
+                        .iter()
+                        .any(|name| name.eq_ignore_ascii_case(sender))
+                });
+            if !allowed {
+                continue;
+            }
+            let Some(captures) = rule.regex.captures(text) else {
+                continue;
+            };
+
+            let mut last_answer = rule.last_answer.lock().unwrap();
+            if last_answer.is_some_and(|last_answer| last_answer.elapsed() < rule.spec.cooldown) {
+                continue;
+            }
+            *last_answer = Some(Instant::now());
+            return Some(expand(&rule.spec.response, &captures, sender));
+        }
+        None
+    }
+}
+
+fn expand(response: &str, captures: &[Option<String>], sender: Option<&str>) -> String {
+    let mut res: String = String::new();
+    let mut rest: &str = response;
+    while let Some(start) = rest.find('$') {
+        res.push_str(&rest[..start]);
+        rest = &rest[start + 1..];
+
+        if let Some(after) = rest.strip_prefix('$') {
+            res.push('$');
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("sender") {
+            res.push_str(sender.unwrap_or_default());
+            rest = after;
+        } else if let Some(digit) = rest.chars().next().and_then(|c| c.to_digit(10)) {
+            let group: Option<&Option<String>> = captures.get(digit as usize);
+            res.push_str(group.and_then(Option::as_deref).unwrap_or_default());
+            rest = &rest[1..];
+        } else {
+            res.push('$');
+        }
+    }
+    res.push_str(rest);
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn responder(pattern: &str, response: &str) -> AutoResponder {
+        AutoResponder::new(&[ResponderRule {
+            name: String::from("test"),
+            pattern: String::from(pattern),
+            response: String::from(response),
+            cooldown: Duration::ZERO,
+            allow: Vec::new(),
+        }])
+        .unwrap()
+    }
+
+    #[test]
+    fn answers_with_captures() {
+        let responder: AutoResponder =
+            responder(r"(?i)^!ip\s*(\w*)", "$sender: play.example.com $1 $$");
+        assert_eq!(
+            responder.answer(Some("Steve"), "!IP java"),
+            Some(String::from("Steve: play.example.com java $"))
+        );
+        assert_eq!(responder.answer(Some("Steve"), "what's the ip?"), None);
+    }
+
+    // Server messages can be very long, the reader thread has to survive them
+    #[test]
+    fn long_lines() {
+        let responder: AutoResponder = responder("^.*z", "found");
+        let line: String = "a".repeat(100_000);
+        assert_eq!(responder.answer(None, &line), None);
+        assert_eq!(
+            responder.answer(None, &format!("{}z", line)),
+            Some(String::from("found"))
+        );
+    }
+}
@@ -0,0 +1,352 @@
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::bots::Bots;
+use crate::clock::LocalTime;
+
+// Sends messages or commands through the active bot at fixed intervals or on cron
+// schedules, e.g. hourly announcements. Schedules come from the [schedules.<name>]
+// tables of the config file and `.schedule add`.
+
+const TICK: Duration = Duration::from_secs(1);
+
+/// A standard five-field cron expression (minute, hour, day of month, month, day of
+/// week) in local time. Fields can be `*`, numbers, ranges, lists and `/step`s.
+#[derive(Clone, Debug)]
+pub struct Cron {
+    text: String,
+    // One bit per allowed value
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    // Like in cron, a day matches either field when both are restricted
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl Cron {
+    pub fn parse(text: &str) -> Result<Cron, Box<dyn Error>> {
+        let fields: Vec<&str> = text.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(format!(
+                "'{}' needs five fields: minute hour day month weekday",
+                text
+            )
+            .into());
+        };
+
+        Ok(Cron {
+            text: fields.join(" "),
+            minutes: parse_field(minutes, 0, 59)?,
+            hours: parse_field(hours, 0, 23)?,
+            days: parse_field(days, 1, 31)?,
+            months: parse_field(months, 1, 12)?,
+            // 7 is Sunday too
+            weekdays: {
+                let weekdays: u64 = parse_field(weekdays, 0, 7)?;
+                (weekdays | weekdays >> 7) & 0x7F
+            },
+            any_day: days == "*",
+            any_weekday: weekdays == "*",
+        })
+    }
+
+    fn matches(&self, time: &LocalTime) -> bool {
+        let has = |set: u64, value: u32| set & (1 << value) != 0;
+        let day: bool = match (self.any_day, self.any_weekday) {
+            (false, false) => has(self.days, time.day) || has(self.weekdays, time.weekday),
+            _ => has(self.days, time.day) && has(self.weekdays, time.weekday),
+        };
+        has(self.minutes, time.minute)
+            && has(self.hours, time.hour)
+            && has(self.months, time.month)
+            && day
+    }
+}
+
+// `*`, `5`, `1-5`, `*/15`, `0-30/10` and comma-separated lists of those
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, Box<dyn Error>> {
+    let mut set: u64 = 0;
+    for part in field.split(',') {
+        let (range, step): (&str, u32) = match part.split_once('/') {
+            Some((range, step)) => match step.parse() {
+                Ok(step) if step > 0 => (range, step),
+                _ => return Err(format!("Invalid step in '{}'", field).into()),
+            },
+            None => (part, 1),
+        };
+        let (from, to): (u32, u32) = match range {
+            "*" => (min, max),
+            range => {
+                let parse = |value: &str| -> Result<u32, Box<dyn Error>> {
+                    match value.parse() {
+                        Ok(value) if (min..=max).contains(&value) => Ok(value),
+                        _ => Err(format!("'{}' isn't between {} and {}", value, min, max).into()),
+                    }
+                };
+                match range.split_once('-') {
+                    Some((from, to)) => (parse(from)?, parse(to)?),
+                    // A single value with a step runs to the end, like in cron
+                    None if step > 1 => (parse(range)?, max),
+                    None => (parse(range)?, parse(range)?),
+                }
+            }
+        };
+        for value in (from..=to).step_by(step as usize) {
+            set |= 1 << value;
+        }
+    }
+    Ok(set)
+}
+
+/// `90`, `90s`, `15m`, `2h` or `1d`.
+pub fn parse_interval(text: &str) -> Result<Duration, Box<dyn Error>> {
+    let (number, unit): (&str, u64) = match text.char_indices().last() {
+        Some((i, 's')) => (&text[..i], 1),
+        Some((i, 'm')) => (&text[..i], 60),
+        Some((i, 'h')) => (&text[..i], 60 * 60),
+        Some((i, 'd')) => (&text[..i], 24 * 60 * 60),
+        _ => (text, 1),
+    };
+    // try_from also refuses negative intervals and ones too long for a Duration
+    match number
+        .parse::<f64>()
+        .map(|number| Duration::try_from_secs_f64(number * unit as f64))
+    {
+        Ok(Ok(interval)) if !interval.is_zero() => Ok(interval),
+        _ => Err(format!("Invalid interval '{}', expected e.g. 90s, 15m or 2h", text).into()),
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum When {
+    Every(Duration),
+    Cron(Cron),
+}
+
+impl When {
+    pub fn describe(&self) -> String {
+        match self {
+            When::Every(interval) => format!("every {}", format_interval(*interval)),
+            When::Cron(cron) => format!("cron {}", cron.text),
+        }
+    }
+}
+
+fn format_interval(interval: Duration) -> String {
+    let seconds: u64 = interval.as_secs();
+    match seconds {
+        _ if interval.subsec_nanos() != 0 => format!("{}s", interval.as_secs_f64()),
+        _ if seconds.is_multiple_of(24 * 60 * 60) => format!("{}d", seconds / (24 * 60 * 60)),
+        _ if seconds.is_multiple_of(60 * 60) => format!("{}h", seconds / (60 * 60)),
+        _ if seconds.is_multiple_of(60) => format!("{}m", seconds / 60),
+        _ => format!("{}s", seconds),
+    }
+}
+
+/// A `[schedules.<name>]` table, or a schedule added with `.schedule add`.
+#[derive(Clone, Debug)]
+pub struct ScheduleSpec {
+    /// The table's name; schedules added at runtime don't have one.
+    pub name: Option<String>,
+    pub when: When,
+    /// Sent like a typed line, so commands work too.
+    pub message: String,
+}
+
+struct Entry {
+    spec: ScheduleSpec,
+    // For intervals
+    next: Instant,
+    // For cron schedules, the minute it last ran in
+    last_minute: Option<(String, u32, u32)>,
+}
+
+pub struct Scheduler {
+    entries: Mutex<Vec<Entry>>,
+}
+
+impl Scheduler {
+    pub fn new(specs: &[ScheduleSpec]) -> Scheduler {
+        let scheduler: Scheduler = Scheduler {
+            entries: Mutex::new(Vec::new()),
+        };
+        for spec in specs {
+            scheduler.add(spec.clone());
+        }
+        scheduler
+    }
+
+    /// Intervals start counting now, and cron schedules first run in the next minute
+    /// that matches.
+    pub fn add(&self, spec: ScheduleSpec) {
+        let now: LocalTime = LocalTime::now();
+        let next: Instant = match &spec.when {
+            When::Every(interval) => Instant::now() + *interval,
+            When::Cron(_) => Instant::now(),
+        };
+        self.entries.lock().unwrap().push(Entry {
+            spec,
+            next,
+            last_minute: Some((now.date(), now.hour, now.minute)),
+        });
+    }
+
+    pub fn list(&self) -> Vec<ScheduleSpec> {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|entry| entry.spec.clone())
+            .collect()
+    }
+
+    /// Removes the schedule at `index` (counting from 1, like `.schedule list` shows them).
+    pub fn remove(&self, index: usize) -> Result<ScheduleSpec, Box<dyn Error>> {
+        let mut entries = self.entries.lock().unwrap();
+        if index == 0 || index > entries.len() {
+            return Err(format!("There is no schedule {}", index).into());
+        }
+        Ok(entries.remove(index - 1).spec)
+    }
+
+    /// Checks the schedules every second on a new thread and sends what's due through
+    /// the active bot. Errors are passed to `print`.
+    pub fn start(self: &Arc<Self>, bots: &Arc<Bots>, print: fn(&str)) {
+        let scheduler: Arc<Scheduler> = Arc::clone(self);
+        let bots: Arc<Bots> = Arc::clone(bots);
+        thread::spawn(move || {
+            loop {
+                thread::sleep(TICK);
+                for message in scheduler.due() {
+                    if let Err(e) = bots.client().send_chat(&message) {
+                        print(&format!("[MClient] Couldn't send scheduled message: {}", e));
+                    }
+                }
+            }
+        });
+    }
+
+    fn due(&self) -> Vec<String> {
+        let now: LocalTime = LocalTime::now();
+        let minute: (String, u32, u32) = (now.date(), now.hour, now.minute);
+        let mut due: Vec<String> = Vec::new();
+
+        for entry in self.entries.lock().unwrap().iter_mut() {
+            match &entry.spec.when {
+                When::Every(interval) => {
+                    if entry.next > Instant::now() {
+                        continue;
+                    }
+                    // Runs that were missed while the client was stuck are skipped
+                    while entry.next <= Instant::now() {
+                        entry.next += *interval;
+                    }
+                }
+                When::Cron(cron) => {
+                    if !cron.matches(&now) || entry.last_minute.as_ref() == Some(&minute) {
+                        continue;
+                    }
+                    entry.last_minute = Some(minute.clone());
+                }
+            }
+            due.push(entry.spec.message.clone());
+        }
+        due
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(month: u32, day: u32, weekday: u32, hour: u32, minute: u32) -> LocalTime {
+        LocalTime {
+            year: 2025,
+            month,
+            day,
+            hour,
+            minute,
+            second: 0,
+            weekday,
+        }
+    }
+
+    #[test]
+    fn fields() {
+        assert_eq!(parse_field("*", 0, 5).unwrap(), 0b111111);
+        assert_eq!(parse_field("3", 0, 59).unwrap(), 1 << 3);
+        assert_eq!(parse_field("1-3,5", 0, 59).unwrap(), 0b101110);
+        assert_eq!(
+            parse_field("*/15", 0, 59).unwrap(),
+            1 | 1 << 15 | 1 << 30 | 1 << 45
+        );
+        assert_eq!(
+            parse_field("0-30/10", 0, 59).unwrap(),
+            1 | 1 << 10 | 1 << 20 | 1 << 30
+        );
+        // A single value with a step runs to the end
+        assert_eq!(parse_field("50/5", 0, 59).unwrap(), 1 << 50 | 1 << 55);
+
+        assert!(parse_field("60", 0, 59).is_err());
+        assert!(parse_field("0", 1, 31).is_err());
+        assert!(parse_field("*/0", 0, 59).is_err());
+        assert!(parse_field("a", 0, 59).is_err());
+        assert!(parse_field("", 0, 59).is_err());
+    }
+
+    #[test]
+    fn cron_expressions() {
+        assert!(Cron::parse("* * * *").is_err());
+        assert!(Cron::parse("* * * * * *").is_err());
+        assert!(Cron::parse("0 24 * * *").is_err());
+
+        let hourly: Cron = Cron::parse("0 * * * *").unwrap();
+        assert!(hourly.matches(&at(6, 15, 0, 13, 0)));
+        assert!(!hourly.matches(&at(6, 15, 0, 13, 1)));
+
+        let weekdays: Cron = Cron::parse("30 9 * * 1-5").unwrap();
+        assert!(weekdays.matches(&at(6, 16, 1, 9, 30)));
+        assert!(!weekdays.matches(&at(6, 15, 0, 9, 30)));
+
+        // 7 is Sunday too
+        let sundays: Cron = Cron::parse("0 12 * * 7").unwrap();
+        assert!(sundays.matches(&at(6, 15, 0, 12, 0)));
+    }
+
+    #[test]
+    fn restricted_day_and_weekday_match_either() {
+        // The 1st of the month or any Monday
+        let cron: Cron = Cron::parse("0 0 1 * 1").unwrap();
+        assert!(cron.matches(&at(6, 1, 0, 0, 0)));
+        assert!(cron.matches(&at(6, 16, 1, 0, 0)));
+        assert!(!cron.matches(&at(6, 17, 2, 0, 0)));
+
+        // Only one restricted: both have to match
+        let cron: Cron = Cron::parse("0 0 1 * *").unwrap();
+        assert!(!cron.matches(&at(6, 16, 1, 0, 0)));
+    }
+
+    #[test]
+    fn intervals() {
+        assert_eq!(parse_interval("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_interval("90s").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_interval("15m").unwrap(), Duration::from_secs(15 * 60));
+        assert_eq!(
+            parse_interval("1.5h").unwrap(),
+            Duration::from_secs(90 * 60)
+        );
+        assert_eq!(
+            parse_interval("1d").unwrap(),
+            Duration::from_secs(24 * 60 * 60)
+        );
+
+        for invalid in ["", "s", "0", "0m", "-5m", "5x", "NaN", "inf", "1e300d"] {
+            assert!(parse_interval(invalid).is_err(), "{}", invalid);
+        }
+    }
+}
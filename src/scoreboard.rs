@@ -0,0 +1,243 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+use azalea_chat::FormattedText;
+use azalea_chat::style::{ChatFormatting, Style, TextColor};
+use azalea_chat::text_component::TextComponent;
+
+use crate::packet::{ClientboundPacket, NumberFormat, ObjectiveAction, TeamAction, TeamInfo};
+
+const SIDEBAR_POSITION: i32 = 1;
+const MAX_SIDEBAR_LINES: usize = 15;
+
+/// The scoreboard objective shown on the right side of the screen.
+#[derive(Clone, Debug)]
+pub struct Sidebar {
+    pub title: FormattedText,
+    /// Highest score first, like in game.
+    pub lines: Vec<SidebarLine>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SidebarLine {
+    pub text: FormattedText,
+    /// The number (or fixed text) drawn on the right. `None` if the server hides it.
+    pub score: Option<FormattedText>,
+}
+
+struct Objective {
+    display_name: FormattedText,
+    number_format: Option<NumberFormat>,
+}
+
+struct Score {
+    value: i32,
+    display_name: Option<FormattedText>,
+    number_format: Option<NumberFormat>,
+}
+
+struct Team {
+    info: TeamInfo,
+    members: HashSet<String>,
+}
+
+// Objectives, scores and teams as the server sent them. Sidebar lines are usually
+// made of a placeholder entry name and the prefix and suffix of the team it's in.
+pub(crate) struct Scoreboard {
+    legacy: bool,
+    objectives: HashMap<String, Objective>,
+    // objective -> entity -> score
+    scores: HashMap<String, HashMap<String, Score>>,
+    teams: HashMap<String, Team>,
+    sidebar: Option<String>,
+}
+
+impl Scoreboard {
+    pub(crate) fn new(protocol: i32) -> Scoreboard {
+        Scoreboard {
+            legacy: protocol < 393,
+            objectives: HashMap::new(),
+            scores: HashMap::new(),
+            teams: HashMap::new(),
+            sidebar: None,
+        }
+    }
+
+    /// Applies a scoreboard or team packet, ignoring anything else.
+    pub(crate) fn handle(&mut self, packet: ClientboundPacket) {
+        match packet {
+            ClientboundPacket::DisplayObjective {
+                position,
+                objective,
+            } if position == SIDEBAR_POSITION => {
+                self.sidebar = Some(objective).filter(|objective| !objective.is_empty());
+            }
+            ClientboundPacket::UpdateObjective { name, action } => match action {
+                ObjectiveAction::Set {
+                    display_name,
+                    number_format,
+                } => {
+                    self.objectives.insert(
+                        name,
+                        Objective {
+                            display_name,
+                            number_format,
+                        },
+                    );
+                }
+                ObjectiveAction::Remove => {
+                    self.objectives.remove(&name);
+                    self.scores.remove(&name);
+                    if self.sidebar.as_ref() == Some(&name) {
+                        self.sidebar = None;
+                    }
+                }
+            },
+            ClientboundPacket::UpdateScore {
+                entity,
+                objective,
+                value,
+                display_name,
+                number_format,
+            } => {
+                self.scores.entry(objective).or_default().insert(
+                    entity,
+                    Score {
+                        value,
+                        display_name,
+                        number_format,
+                    },
+                );
+            }
+            ClientboundPacket::ResetScore { entity, objective } => match objective {
+                Some(objective) => {
+                    if let Some(scores) = self.scores.get_mut(&objective) {
+                        scores.remove(&entity);
+                    }
+                }
+                None => {
+                    for scores in self.scores.values_mut() {
+                        scores.remove(&entity);
+                    }
+                }
+            },
+            ClientboundPacket::UpdateTeam { name, action } => match action {
+                TeamAction::Create { info, members } => {
+                    self.teams.insert(
+                        name,
+                        Team {
+                            info,
+                            members: members.into_iter().collect(),
+                        },
+                    );
+                }
+                TeamAction::Remove => {
+                    self.teams.remove(&name);
+                }
+                TeamAction::Update(info) => {
+                    if let Some(team) = self.teams.get_mut(&name) {
+                        team.info = info;
+                    }
+                }
+                TeamAction::AddMembers(members) => {
+                    if let Some(team) = self.teams.get_mut(&name) {
+                        team.members.extend(members);
+                    }
+                }
+                TeamAction::RemoveMembers(members) => {
+                    if let Some(team) = self.teams.get_mut(&name) {
+                        for member in members {
+                            team.members.remove(&member);
+                        }
+                    }
+                }
+            },
+            _ => {}
+        }
+    }
+
+    pub(crate) fn team_of(&self, entity: &str) -> Option<&TeamInfo> {
+        self.teams
+            .values()
+            .find(|team| team.members.contains(entity))
+            .map(|team| &team.info)
+    }
+
+    /// The entity (a player name or sidebar entry) decorated by its team, the way the
+    /// game draws it. `None` if it isn't on a team.
+    pub(crate) fn decorate(&self, entity: &str) -> Option<FormattedText> {
+        let team: &TeamInfo = self.team_of(entity)?;
+
+        // Legacy prefixes are plain strings whose formatting codes carry over
+        if self.legacy {
+            return Some(FormattedText::from(TextComponent::new(format!(
+                "{}{}{}",
+                team.prefix, entity, team.suffix
+            ))));
+        }
+
+        let color: Option<TextColor> = "0123456789abcdef"
+            .chars()
+            .nth(team.color as usize)
+            .and_then(ChatFormatting::from_code)
+            .and_then(|formatting| TextColor::try_from(formatting).ok());
+
+        let mut line: TextComponent = TextComponent::new("");
+        line.base.siblings.push(team.prefix.clone());
+        line.base.siblings.push(FormattedText::from(
+            TextComponent::new(entity).with_style(Style::default().color(color)),
+        ));
+        line.base.siblings.push(team.suffix.clone());
+        Some(FormattedText::from(line))
+    }
+
+    pub(crate) fn sidebar(&self) -> Option<Sidebar> {
+        let name: &String = self.sidebar.as_ref()?;
+        let objective: &Objective = self.objectives.get(name)?;
+
+        // Entries starting with # are hidden in game
+        let mut scores: Vec<(&String, &Score)> = match self.scores.get(name) {
+            Some(scores) => scores
+                .iter()
+                .filter(|(entity, _)| !entity.starts_with('#'))
+                .collect(),
+            None => Vec::new(),
+        };
+        scores.sort_by(|a, b| match b.1.value.cmp(&a.1.value) {
+            Ordering::Equal => a.0.to_lowercase().cmp(&b.0.to_lowercase()),
+            ordering => ordering,
+        });
+        scores.truncate(MAX_SIDEBAR_LINES);
+
+        let lines: Vec<SidebarLine> = scores
+            .into_iter()
+            .map(|(entity, score)| {
+                let number_format: Option<&NumberFormat> = score
+                    .number_format
+                    .as_ref()
+                    .or(objective.number_format.as_ref());
+
+                SidebarLine {
+                    text: match &score.display_name {
+                        Some(display_name) => display_name.clone(),
+                        None => self
+                            .decorate(entity)
+                            .unwrap_or_else(|| FormattedText::from(TextComponent::new(entity))),
+                    },
+                    score: match number_format {
+                        Some(NumberFormat::Blank) => None,
+                        Some(NumberFormat::Fixed(text)) => Some(*text.clone()),
+                        Some(NumberFormat::Styled) | None => {
+                            Some(FormattedText::from(score.value.to_string()))
+                        }
+                    },
+                }
+            })
+            .collect();
+
+        Some(Sidebar {
+            title: objective.display_name.clone(),
+            lines,
+        })
+    }
+}
@@ -0,0 +1,536 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use mclient_project::{Client, Event};
+
+use crate::bots::JOIN_GRACE;
+use crate::filter;
+use crate::lang::Language;
+use crate::regex::Regex;
+use crate::whisper::{self, Direction};
+
+// Scripts react to events with a few statements, for auto-replies, greetings and the
+// like without recompiling:
+//
+//     # Answer !ping in chat, at most every 5 seconds
+//     on chat /^<(\w+)> !ping$/ cooldown 5 {
+//         say "pong, $1!"
+//     }
+//
+//     on join {
+//         wait 2
+//         say "Welcome, $name!"
+//     }
+//
+// A handler is `on <event> [/pattern/] [cooldown <seconds>] {`, then one statement per
+// line and a closing `}`. The pattern is matched against the event's text (the name for
+// joins and leaves), and its groups become $1 to $9. Statements are `say "..."` (sent
+// like a typed line), `print "..."` (only shown locally) and `wait <seconds>`. Strings
+// can use the event's variables as `$name` or `${name}`; `\"`, `\\` and `\$` are
+// escapes.
+
+// Names and values, looked up by templates
+type Variables = Vec<(String, String)>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Trigger {
+    Chat,
+    Whisper,
+    Join,
+    Leave,
+    Death,
+    Title,
+    ActionBar,
+    Connect,
+}
+
+impl Trigger {
+    fn parse(name: &str) -> Option<Trigger> {
+        match name {
+            "chat" => Some(Trigger::Chat),
+            "whisper" => Some(Trigger::Whisper),
+            "join" => Some(Trigger::Join),
+            "leave" => Some(Trigger::Leave),
+            "death" => Some(Trigger::Death),
+            "title" => Some(Trigger::Title),
+            "action_bar" => Some(Trigger::ActionBar),
+            "connect" => Some(Trigger::Connect),
+            _ => None,
+        }
+    }
+
+    // Besides $bot and the pattern's groups
+    fn variables(self) -> &'static [&'static str] {
+        match self {
+            Trigger::Chat | Trigger::Whisper => &["text", "sender"],
+            Trigger::Join | Trigger::Leave => &["name"],
+            Trigger::Death | Trigger::Title | Trigger::ActionBar => &["text"],
+            Trigger::Connect => &[],
+        }
+    }
+}
+
+enum Piece {
+    Text(String),
+    Variable(String),
+}
+
+// A string with variables in it
+struct Template(Vec<Piece>);
+
+impl Template {
+    fn parse(literal: &str, trigger: Trigger) -> Result<Template, String> {
+        let Some(inner) = literal
+            .strip_prefix('"')
+            .and_then(|rest| rest.strip_suffix('"'))
+        else {
+            return Err(format!("Expected a string in quotes, got {}", literal));
+        };
+
+        let mut pieces: Vec<Piece> = Vec::new();
+        let mut text: String = String::new();
+        let mut chars = inner.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some(escaped @ ('"' | '\\' | '$')) => text.push(escaped),
+                    Some(other) => return Err(format!("Unknown escape \\{}", other)),
+                    None => return Err(String::from("The string ends with \\")),
+                },
+                '"' => return Err(String::from("Quotes inside a string need a \\")),
+                '$' => {
+                    let mut name: String = String::new();
+                    if chars.next_if_eq(&'{').is_some() {
+                        for c in chars.by_ref() {
+                            if c == '}' {
+                                break;
+                            }
+                            name.push(c);
+                        }
+                    } else if let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                        name.push(digit);
+                    } else {
+                        while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_') {
+                            name.push(c);
+                        }
+                    }
+
+                    let known: bool = name == "bot"
+                        || trigger.variables().contains(&name.as_str())
+                        || (name.len() == 1 && name.chars().all(|c| c.is_ascii_digit()));
+                    if !known {
+                        return Err(format!(
+                            "There's no ${} in {} handlers",
+                            name,
+                            trigger_name(trigger)
+                        ));
+                    }
+                    if !text.is_empty() {
+                        pieces.push(Piece::Text(std::mem::take(&mut text)));
+                    }
+                    pieces.push(Piece::Variable(name));
+                }
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            pieces.push(Piece::Text(text));
+        }
+        Ok(Template(pieces))
+    }
+
+    fn render(&self, variables: &[(String, String)]) -> String {
+        self.0
+            .iter()
+            .map(|piece| match piece {
+                Piece::Text(text) => text.as_str(),
+                Piece::Variable(name) => variables
+                    .iter()
+                    .find(|(key, _)| key == name)
+                    .map_or("", |(_, value)| value.as_str()),
+            })
+            .collect()
+    }
+}
+
+fn trigger_name(trigger: Trigger) -> &'static str {
+    match trigger {
+        Trigger::Chat => "chat",
+        Trigger::Whisper => "whisper",
+        Trigger::Join => "join",
+        Trigger::Leave => "leave",
+        Trigger::Death => "death",
+        Trigger::Title => "title",
+        Trigger::ActionBar => "action_bar",
+        Trigger::Connect => "connect",
+    }
+}
+
+enum Statement {
+    Say(Template),
+    Print(Template),
+    Wait(Duration),
+}
+
+struct Handler {
+    trigger: Trigger,
+    pattern: Option<Regex>,
+    cooldown: Option<Duration>,
+    last_run: Mutex<Option<Instant>>,
+    body: Vec<Statement>,
+}
+
+impl Handler {
+    // Starts the cooldown if the handler may run now
+    fn ready(&self) -> bool {
+        let mut last_run = self.last_run.lock().unwrap();
+        if let (Some(cooldown), Some(last_run)) = (self.cooldown, *last_run)
+            && last_run.elapsed() < cooldown
+        {
+            return false;
+        }
+        *last_run = Some(Instant::now());
+        true
+    }
+}
+
+struct Script {
+    path: PathBuf,
+    handlers: Vec<Handler>,
+}
+
+fn parse_script(text: &str) -> Result<Vec<Handler>, String> {
+    let mut handlers: Vec<Handler> = Vec::new();
+    let mut current: Option<Handler> = None;
+
+    for (i, line) in text.lines().enumerate() {
+        let line: &str = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = |message: String| format!("line {}: {}", i + 1, message);
+
+        let Some(handler) = current.as_mut() else {
+            let header: &str = line
+                .strip_prefix("on ")
+                .ok_or_else(|| error(format!("Expected `on <event> {{`, got {}", line)))?;
+            current = Some(parse_header(header).map_err(error)?);
+            continue;
+        };
+
+        if line == "}" {
+            handlers.extend(current.take());
+            continue;
+        }
+        let (keyword, rest): (&str, &str) = line.split_once(' ').unwrap_or((line, ""));
+        let rest: &str = rest.trim();
+        handler.body.push(match keyword {
+            "say" => Statement::Say(Template::parse(rest, handler.trigger).map_err(error)?),
+            "print" => Statement::Print(Template::parse(rest, handler.trigger).map_err(error)?),
+            "wait" => Statement::Wait(parse_seconds(rest).map_err(error)?),
+            _ => return Err(error(format!("Unknown statement {}", keyword))),
+        });
+    }
+
+    if current.is_some() {
+        return Err(String::from("The last handler is missing its }"));
+    }
+    Ok(handlers)
+}
+
+// `<event> [/pattern/] [cooldown <seconds>] {`, after the `on`
+fn parse_header(header: &str) -> Result<Handler, String> {
+    let Some(header) = header.strip_suffix('{') else {
+        return Err(String::from("The handler's line has to end with {"));
+    };
+    let (event, mut rest): (&str, &str) =
+        header.trim().split_once(' ').unwrap_or((header.trim(), ""));
+    let Some(trigger) = Trigger::parse(event) else {
+        return Err(format!("Unknown event {}", event));
+    };
+
+    let mut pattern: Option<Regex> = None;
+    if let Some(after_slash) = rest.trim_start().strip_prefix('/') {
+        // The pattern ends at the first / that isn't escaped
+        let mut end: Option<usize> = None;
+        let mut escaped: bool = false;
+        for (i, c) in after_slash.char_indices() {
+            match c {
+                '/' if !escaped => {
+                    end = Some(i);
+                    break;
+                }
+                '\\' => escaped = !escaped,
+                _ => escaped = false,
+            }
+        }
+        let Some(end) = end else {
+            return Err(String::from("The pattern is missing its closing /"));
+        };
+        if trigger == Trigger::Connect {
+            return Err(String::from("connect handlers can't have a pattern"));
+        }
+        let source: String = after_slash[..end].replace("\\/", "/");
+        pattern = Some(Regex::new(&source).map_err(|e| format!("Invalid pattern: {}", e))?);
+        rest = &after_slash[end + 1..];
+    }
+
+    let cooldown: Option<Duration> = match rest.split_whitespace().collect::<Vec<&str>>()[..] {
+        [] => None,
+        ["cooldown", seconds] => Some(parse_seconds(seconds)?),
+        _ => return Err(format!("Unexpected {} before {{", rest.trim())),
+    };
+
+    Ok(Handler {
+        trigger,
+        pattern,
+        cooldown,
+        last_run: Mutex::new(None),
+        body: Vec::new(),
+    })
+}
+
+fn parse_seconds(text: &str) -> Result<Duration, String> {
+    match text.parse::<f64>().map(Duration::try_from_secs_f64) {
+        Ok(Ok(duration)) => Ok(duration),
+        _ => Err(format!("Expected a number of seconds, got {}", text)),
+    }
+}
+
+/// The bot a script handler was triggered by.
+pub struct Source {
+    pub name: String,
+    pub uuid: u128,
+    pub client: Weak<Client>,
+    connected_at: Mutex<Instant>,
+}
+
+impl Source {
+    pub fn new(name: &str, uuid: u128, client: Weak<Client>) -> Source {
+        Source {
+            name: String::from(name),
+            uuid,
+            client,
+            connected_at: Mutex::new(Instant::now()),
+        }
+    }
+}
+
+/// The loaded scripts. Loading a file again replaces its handlers.
+pub struct Scripts {
+    scripts: Mutex<Vec<Arc<Script>>>,
+    print: fn(&str),
+}
+
+impl Scripts {
+    /// `print` shows the output of `print` statements and errors of `say`.
+    pub fn new(print: fn(&str)) -> Scripts {
+        Scripts {
+            scripts: Mutex::new(Vec::new()),
+            print,
+        }
+    }
+
+    /// Loads or reloads a script. Returns how many handlers it has.
+    pub fn load(&self, path: &Path) -> Result<usize, Box<dyn Error>> {
+        let text: String = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) => return Err(format!("Couldn't read {}: {}", path.display(), e).into()),
+        };
+        let handlers: Vec<Handler> =
+            parse_script(&text).map_err(|e| format!("{}, {}", path.display(), e))?;
+        let count: usize = handlers.len();
+
+        let mut scripts = self.scripts.lock().unwrap();
+        let script: Arc<Script> = Arc::new(Script {
+            path: path.to_path_buf(),
+            handlers,
+        });
+        match scripts.iter().position(|loaded| loaded.path == path) {
+            Some(index) => scripts[index] = script,
+            None => scripts.push(script),
+        }
+        Ok(count)
+    }
+
+    /// Returns whether the script was loaded.
+    pub fn unload(&self, path: &Path) -> bool {
+        let mut scripts = self.scripts.lock().unwrap();
+        let count: usize = scripts.len();
+        scripts.retain(|script| script.path != path);
+        scripts.len() != count
+    }
+
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.scripts
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|script| script.path.clone())
+            .collect()
+    }
+
+    /// Runs the handlers for an event. Each one runs on its own thread, so `wait`
+    /// doesn't hold anything else up.
+    pub fn dispatch(&self, source: &Source, event: &Event, language: &Language) {
+        let Some((trigger, subject, mut variables)) = describe(source, event, language) else {
+            return;
+        };
+        variables.push((String::from("bot"), source.name.clone()));
+
+        for script in self.scripts.lock().unwrap().iter() {
+            for (index, handler) in script.handlers.iter().enumerate() {
+                if handler.trigger != trigger {
+                    continue;
+                }
+                let mut variables: Variables = variables.clone();
+                if let Some(pattern) = &handler.pattern {
+                    let Some(captures) = pattern.captures(&subject) else {
+                        continue;
+                    };
+                    for (group, capture) in captures.into_iter().enumerate().take(10) {
+                        variables.push((group.to_string(), capture.unwrap_or_default()));
+                    }
+                }
+                if !handler.ready() {
+                    continue;
+                }
+
+                let script: Arc<Script> = Arc::clone(script);
+                let client: Weak<Client> = Weak::clone(&source.client);
+                let print: fn(&str) = self.print;
+                thread::spawn(move || {
+                    run(&script.handlers[index], &variables, &client, print);
+                });
+            }
+        }
+    }
+}
+
+// The trigger, the text a pattern is matched against and the variables for an event,
+// or `None` if scripts don't see it
+fn describe(
+    source: &Source,
+    event: &Event,
+    language: &Language,
+) -> Option<(Trigger, String, Variables)> {
+    let variable = |name: &str, value: &str| (String::from(name), String::from(value));
+    match event {
+        Event::ChatReceived {
+            message, sender, ..
+        } => {
+            let text: String = language.translate(message).to_string();
+            // Answering the bot's own messages could go on forever
+            if *sender == Some(source.uuid) || text.starts_with(&format!("<{}>", source.name)) {
+                return None;
+            }
+            let (trigger, sender_name): (Trigger, Option<String>) = match whisper::detect(message) {
+                Some((Direction::Incoming, partner)) => (Trigger::Whisper, Some(partner)),
+                Some((Direction::Outgoing, _)) => return None,
+                None => {
+                    let known: Option<String> = match (sender, source.client.upgrade()) {
+                        (Some(uuid), Some(client)) => client
+                            .online_players()
+                            .get(uuid)
+                            .map(|player| player.name.clone()),
+                        _ => None,
+                    };
+                    (
+                        Trigger::Chat,
+                        known.or_else(|| filter::tagged_author(&text).map(String::from)),
+                    )
+                }
+            };
+            let variables = vec![
+                variable("text", &text),
+                variable("sender", sender_name.as_deref().unwrap_or_default()),
+            ];
+            Some((trigger, text, variables))
+        }
+        Event::PlayerJoined { name, .. } => {
+            if source.connected_at.lock().unwrap().elapsed() < JOIN_GRACE {
+                return None;
+            }
+            Some((Trigger::Join, name.clone(), vec![variable("name", name)]))
+        }
+        Event::PlayerLeft { name, .. } => {
+            Some((Trigger::Leave, name.clone(), vec![variable("name", name)]))
+        }
+        Event::Died(message) => {
+            let text: String = language.translate(message).to_string();
+            Some((Trigger::Death, text.clone(), vec![variable("text", &text)]))
+        }
+        Event::Title { title, .. } => {
+            let text: String = language.translate(title).to_string();
+            Some((Trigger::Title, text.clone(), vec![variable("text", &text)]))
+        }
+        Event::ActionBar(message) => {
+            let text: String = language.translate(message).to_string();
+            Some((
+                Trigger::ActionBar,
+                text.clone(),
+                vec![variable("text", &text)],
+            ))
+        }
+        Event::Connected => {
+            *source.connected_at.lock().unwrap() = Instant::now();
+            Some((Trigger::Connect, String::new(), Vec::new()))
+        }
+        _ => None,
+    }
+}
+
+fn run(handler: &Handler, variables: &[(String, String)], client: &Weak<Client>, print: fn(&str)) {
+    for statement in &handler.body {
+        match statement {
+            Statement::Say(template) => {
+                let Some(client) = client.upgrade() else {
+                    return;
+                };
+                if let Err(e) = client.send_chat(&template.render(variables)) {
+                    print(&format!("[Script] {}", e));
+                }
+            }
+            Statement::Print(template) => {
+                print(&format!("[Script] {}", template.render(variables)))
+            }
+            Statement::Wait(duration) => thread::sleep(*duration),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_handlers() {
+        let handlers: Vec<Handler> = parse_script(
+            "# Answer !ping\non chat /^<(\\w+)> !ping$/ cooldown 5 {\n    say \"pong, $1!\"\n}\n\non join {\n    wait 2\n    print \"hi\"\n}\n",
+        )
+        .unwrap();
+        assert_eq!(handlers.len(), 2);
+        assert_eq!(handlers[0].trigger, Trigger::Chat);
+        assert_eq!(handlers[0].cooldown, Some(Duration::from_secs(5)));
+        assert_eq!(handlers[1].body.len(), 2);
+
+        assert!(parse_script("on chat {\n say \"x\"\n").is_err());
+        assert!(parse_script("on nothing {\n}").is_err());
+        assert!(parse_script("on chat /(/ {\n}").is_err());
+        assert!(parse_script("on join {\n wait 1e300\n}").is_err());
+    }
+
+    // Chat lines come from the server, a very long one mustn't take the client down
+    #[test]
+    fn patterns_on_long_lines() {
+        let handlers: Vec<Handler> = parse_script("on chat /^.*z/ {\n print \"z\"\n}").unwrap();
+        let pattern: &Regex = handlers[0].pattern.as_ref().unwrap();
+        let line: String = "a".repeat(100_000);
+        assert!(pattern.captures(&line).is_none());
+        assert!(pattern.captures(&format!("{}z", line)).is_some());
+    }
+}
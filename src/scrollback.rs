@@ -0,0 +1,103 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use mclient_project::Interaction;
+
+use crate::regex::Regex;
+
+pub const DEFAULT_SCROLLBACK: usize = 5000;
+
+struct Entry {
+    // Counts up from 1 and doesn't change when older lines are dropped
+    number: usize,
+    // As it was printed, with the chat format and colors
+    line: String,
+    // What .search looks at
+    text: String,
+    interactions: Vec<Interaction>,
+}
+
+/// The last chat lines that were shown, for `.history`, `.search` and `.inspect`. The
+/// oldest ones are dropped once `capacity` is reached.
+pub struct Scrollback {
+    entries: Mutex<VecDeque<Entry>>,
+    capacity: usize,
+}
+
+impl Scrollback {
+    pub fn new(capacity: usize) -> Scrollback {
+        Scrollback {
+            entries: Mutex::new(VecDeque::new()),
+            capacity,
+        }
+    }
+
+    pub fn push(&self, line: &str, text: &str, interactions: Vec<Interaction>) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        let number: usize = entries.back().map_or(1, |entry| entry.number + 1);
+        if entries.len() == self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(Entry {
+            number,
+            line: String::from(line),
+            text: String::from(text),
+            interactions,
+        });
+    }
+
+    /// The last `count` lines with their numbers, oldest first.
+    pub fn last(&self, count: usize) -> Vec<(usize, String)> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .iter()
+            .skip(entries.len().saturating_sub(count))
+            .map(|entry| (entry.number, entry.line.clone()))
+            .collect()
+    }
+
+    /// The lines whose plain text matches `regex` with their numbers, oldest first.
+    pub fn search(&self, regex: &Regex) -> Vec<(usize, String)> {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|entry| regex.is_match(&entry.text))
+            .map(|entry| (entry.number, entry.line.clone()))
+            .collect()
+    }
+
+    /// The hover and click parts of line `number`, or of the latest line that has any.
+    /// `None` if that line isn't kept anymore.
+    pub fn interactions(&self, number: Option<usize>) -> Option<(usize, Vec<Interaction>)> {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(|entry| match number {
+                Some(number) => entry.number == number,
+                None => !entry.interactions.is_empty(),
+            })
+            .map(|entry| (entry.number, entry.interactions.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn search_long_lines() {
+        let scrollback: Scrollback = Scrollback::new(10);
+        let line: String = "a".repeat(100_000);
+        scrollback.push(&line, &line, Vec::new());
+        scrollback.push("z", &format!("{}z", line), Vec::new());
+
+        let found: Vec<(usize, String)> = scrollback.search(&Regex::new("^.*z").unwrap());
+        assert_eq!(found, [(2, String::from("z"))]);
+    }
+}
@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use mclient_project::mojang;
+use serde::{Deserialize, Serialize};
+
+use crate::clock::LocalTime;
+
+// When each player was first and last seen in the player list, per server (`host:port`),
+// and who was online when the last session there ended. Kept as JSON between sessions:
+//
+//   {"play.example.com:25565": {"players": {"bob": {"name": "Bob", "uuid": "...",
+//     "first_seen": 1760522400000, "last_seen": 1760526000000}}, "online": ["Bob"],
+//     "ended": 1760526000000}}
+
+/// A player's first and last appearance in the player list, in milliseconds since the
+/// Unix epoch.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Sighting {
+    pub name: String,
+    pub uuid: String,
+    pub first_seen: u64,
+    pub last_seen: u64,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct Server {
+    // Keyed by the lowercase name, names aren't case-sensitive in game
+    players: HashMap<String, Sighting>,
+    #[serde(default)]
+    online: Vec<String>,
+    #[serde(default)]
+    ended: Option<u64>,
+}
+
+/// The players seen so far, loaded from and saved to a file if there is one.
+pub struct PlayerHistory {
+    path: Option<PathBuf>,
+    servers: Mutex<HashMap<String, Server>>,
+    // The sessions before this one, as they were when the file was loaded
+    previous: HashMap<String, Server>,
+}
+
+impl PlayerHistory {
+    /// Loads the history from `path`, starting an empty one if the file doesn't exist.
+    /// Without a path it's only kept for this session.
+    pub fn open(path: Option<PathBuf>) -> Result<PlayerHistory, Box<dyn Error>> {
+        let servers: HashMap<String, Server> = match path.as_ref().map(fs::read_to_string) {
+            Some(Ok(json)) => serde_json::from_str(&json).map_err(|e| {
+                format!("Couldn't parse {}: {}", path.as_ref().unwrap().display(), e)
+            })?,
+            Some(Err(e)) if e.kind() != io::ErrorKind::NotFound => {
+                let path: &PathBuf = path.as_ref().unwrap();
+                return Err(format!("Couldn't read {}: {}", path.display(), e).into());
+            }
+            _ => HashMap::new(),
+        };
+
+        Ok(PlayerHistory {
+            path,
+            previous: servers.clone(),
+            servers: Mutex::new(servers),
+        })
+    }
+
+    /// An empty history that isn't saved anywhere.
+    pub fn session_only() -> PlayerHistory {
+        PlayerHistory {
+            path: None,
+            servers: Mutex::new(HashMap::new()),
+            previous: HashMap::new(),
+        }
+    }
+
+    /// Notes that `name` is in the player list of `server` right now.
+    pub fn saw(&self, server: &str, uuid: u128, name: &str) {
+        let now: u64 = now();
+        let mut servers = self.servers.lock().unwrap();
+        let sighting: &mut Sighting = servers
+            .entry(String::from(server))
+            .or_default()
+            .players
+            .entry(name.to_lowercase())
+            .or_insert_with(|| Sighting {
+                name: String::from(name),
+                uuid: mojang::format_uuid(uuid),
+                first_seen: now,
+                last_seen: now,
+            });
+        sighting.name = String::from(name);
+        sighting.last_seen = now;
+    }
+
+    pub fn find(&self, server: &str, name: &str) -> Option<Sighting> {
+        let servers = self.servers.lock().unwrap();
+        servers
+            .get(server)?
+            .players
+            .get(&name.to_lowercase())
+            .cloned()
+    }
+
+    /// When the last session on `server` ended and who was online then.
+    pub fn last_session(&self, server: &str) -> Option<(u64, Vec<String>)> {
+        let previous: &Server = self.previous.get(server)?;
+        Some((previous.ended?, previous.online.clone()))
+    }
+
+    /// Ends the session on `server` with `online` in the player list, and writes
+    /// everything to the file.
+    pub fn save(&self, server: &str, online: &[(u128, String)]) -> io::Result<()> {
+        for (uuid, name) in online {
+            self.saw(server, *uuid, name);
+        }
+        let mut servers = self.servers.lock().unwrap();
+        let entry: &mut Server = servers.entry(String::from(server)).or_default();
+        entry.online = online.iter().map(|(_, name)| name.clone()).collect();
+        entry.online.sort_by_key(|name| name.to_lowercase());
+        entry.ended = Some(now());
+
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let json: String = serde_json::to_string(&*servers)?;
+        // Written next to the file and renamed over it, so a crash mid-write (or another
+        // client saving at the same time) can't leave half a file behind
+        let mut temp: OsString = path.clone().into_os_string();
+        temp.push(format!(".{}.tmp", std::process::id()));
+        let temp: PathBuf = PathBuf::from(temp);
+        if let Err(e) = fs::write(&temp, json).and_then(|_| fs::rename(&temp, path)) {
+            let _ = fs::remove_file(&temp);
+            return Err(e);
+        }
+        Ok(())
+    }
+}
+
+pub fn default_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".mc_chat_seen.json"))
+}
+
+/// `YYYY-MM-DD HH:MM (3 hours ago)`
+pub fn describe(time: u64) -> String {
+    let local: LocalTime = LocalTime::at(UNIX_EPOCH + Duration::from_millis(time));
+    let seconds: u64 = now().saturating_sub(time) / 1000;
+    let ago: String = match seconds {
+        0..60 => String::from("just now"),
+        60..3600 => plural(seconds / 60, "minute"),
+        3600..86400 => plural(seconds / 3600, "hour"),
+        _ => plural(seconds / 86400, "day"),
+    };
+    format!("{} {} ({})", local.date(), &local.time()[..5], ago)
+}
+
+fn plural(count: u64, unit: &str) -> String {
+    match count {
+        1 => format!("1 {} ago", unit),
+        count => format!("{} {}s ago", count, unit),
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saves_replace_the_file() {
+        let dir: PathBuf =
+            std::env::temp_dir().join(format!("mc_chat_seen_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path: PathBuf = dir.join("seen.json");
+
+        let history: PlayerHistory = PlayerHistory::open(Some(path.clone())).unwrap();
+        history
+            .save("example.com:25565", &[(1, String::from("Steve"))])
+            .unwrap();
+        history
+            .save("example.com:25565", &[(2, String::from("Alex"))])
+            .unwrap();
+
+        let history: PlayerHistory = PlayerHistory::open(Some(path)).unwrap();
+        assert!(history.find("example.com:25565", "steve").is_some());
+        assert_eq!(
+            history
+                .last_session("example.com:25565")
+                .map(|(_, online)| online),
+            Some(vec![String::from("Alex")])
+        );
+        // Only the file itself is left
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,152 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::thread::{self, JoinHandle};
+
+use mclient_project::{ClientError, ConnectPolicy, ServerStatus, Socks5Proxy, VirtualHost};
+
+use crate::chatformat;
+
+// A server list file has one `host` or `host:port` per line. Empty lines and lines
+// starting with `#` are skipped.
+
+pub struct ServerEntry {
+    pub host: String,
+    /// `None` if the line had no port, the SRV record is looked up then.
+    pub port: Option<u16>,
+}
+
+impl ServerEntry {
+    pub fn address(&self) -> String {
+        let port: u16 = self.port.unwrap_or(mclient_project::DEFAULT_PORT);
+        match self.host.contains(':') {
+            true => format!("[{}]:{}", self.host, port),
+            false => format!("{}:{}", self.host, port),
+        }
+    }
+}
+
+pub fn read_server_list(path: &Path) -> Result<Vec<ServerEntry>, Box<dyn Error>> {
+    let text: String = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) => return Err(format!("Couldn't read {}: {}", path.display(), e).into()),
+    };
+
+    let mut servers: Vec<ServerEntry> = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line: &str = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        // Bare IPv6 addresses have colons too, those need brackets to take a port
+        let (host, port): (&str, Option<u16>) = match line.rsplit_once(':') {
+            Some((host, port)) if !host.contains(':') || host.ends_with(']') => {
+                let Ok(port) = port.parse() else {
+                    return Err(format!("Invalid port on line {}: {}", i + 1, line).into());
+                };
+                (host, Some(port))
+            }
+            _ => (line, None),
+        };
+        servers.push(ServerEntry {
+            host: String::from(host.trim_start_matches('[').trim_end_matches(']')),
+            port,
+        });
+    }
+    Ok(servers)
+}
+
+/// Asks every server for its status at the same time. The results are in the order of
+/// `servers`.
+pub fn check_all(
+    servers: &[ServerEntry],
+    proxy: Option<&Socks5Proxy>,
+    policy: &ConnectPolicy,
+) -> Vec<Result<ServerStatus, ClientError>> {
+    let handles: Vec<JoinHandle<Result<ServerStatus, ClientError>>> = servers
+        .iter()
+        .map(|server| {
+            let (host, port): (String, Option<u16>) = (server.host.clone(), server.port);
+            let proxy: Option<Socks5Proxy> = proxy.cloned();
+            let policy: ConnectPolicy = policy.clone();
+            thread::spawn(move || {
+                // Every server gets its own address in the handshake
+                mclient_project::fetch_status(
+                    &host,
+                    port,
+                    proxy.as_ref(),
+                    &policy,
+                    &VirtualHost::default(),
+                )
+            })
+        })
+        .collect();
+
+    handles
+        .into_iter()
+        .map(|handle| match handle.join() {
+            Ok(status) => status,
+            Err(_) => Err("The status request crashed".into()),
+        })
+        .collect()
+}
+
+/// One row per server: address, online or offline, players, version and latency.
+pub fn create_table(
+    servers: &[ServerEntry],
+    results: &[Result<ServerStatus, ClientError>],
+) -> String {
+    let mut rows: Vec<[String; 5]> = vec![[
+        String::from("Server"),
+        String::from("State"),
+        String::from("Players"),
+        String::from("Version"),
+        String::from("Latency"),
+    ]];
+    for (server, result) in servers.iter().zip(results) {
+        rows.push(match result {
+            Ok(status) => [
+                server.address(),
+                String::from("\x1b[32monline\x1b[0m"),
+                match &status.players {
+                    Some(players) => format!("{}/{}", players.online, players.max),
+                    None => String::from("?"),
+                },
+                status.version.name.clone(),
+                match status.latency {
+                    Some(latency) => format!("{} ms", latency.as_millis()),
+                    None => String::from("?"),
+                },
+            ],
+            Err(_) => [
+                server.address(),
+                String::from("\x1b[31moffline\x1b[0m"),
+                String::from("-"),
+                String::from("-"),
+                String::from("-"),
+            ],
+        });
+    }
+
+    let mut widths: [usize; 5] = [0; 5];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(chatformat::visible_width(cell));
+        }
+    }
+
+    rows.iter()
+        .map(|row| {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(widths)
+                .map(|(cell, width)| {
+                    let padding: usize = width - chatformat::visible_width(cell);
+                    format!("{}{}", cell, " ".repeat(padding))
+                })
+                .collect();
+            cells.join("  ").trim_end().to_string()
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
@@ -0,0 +1,43 @@
+use std::os::raw::c_int;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Ctrl-C and SIGTERM only set a flag; the main loop notices it and disconnects
+// properly. A second signal while that's going on exits right away.
+
+const SIGINT: c_int = 2;
+const SIGTERM: c_int = 15;
+
+// 128 + SIGINT, what shells report for a process stopped by Ctrl-C
+const EXIT_INTERRUPTED: c_int = 130;
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+unsafe extern "C" {
+    fn signal(signum: c_int, handler: extern "C" fn(c_int)) -> usize;
+    fn _exit(status: c_int) -> !;
+}
+
+extern "C" fn handle_signal(_: c_int) {
+    if REQUESTED.swap(true, Ordering::SeqCst) {
+        // SAFETY: _exit is async-signal-safe
+        unsafe { _exit(EXIT_INTERRUPTED) }
+    }
+}
+
+/// Installs the handlers for SIGINT and SIGTERM.
+pub fn install() {
+    // SAFETY: the handler only touches an atomic and calls _exit
+    unsafe {
+        signal(SIGINT, handle_signal);
+        signal(SIGTERM, handle_signal);
+    }
+}
+
+/// Asks the main loop to shut down, like a signal would.
+pub fn request() {
+    REQUESTED.store(true, Ordering::SeqCst);
+}
+
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
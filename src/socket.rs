@@ -0,0 +1,140 @@
+use std::io;
+use std::net::TcpStream;
+use std::time::Duration;
+
+/// Options for the connection to the server, applied right after connecting.
+#[derive(Clone, Debug)]
+pub struct SocketOptions {
+    /// How long reads may block while logging in. In the play state the keep alive
+    /// timeout takes over. `None` waits forever.
+    pub read_timeout: Option<Duration>,
+    /// How long a write may block before the connection counts as lost. `None` waits
+    /// forever.
+    pub write_timeout: Option<Duration>,
+    /// Sends small packets like chat right away instead of batching them (TCP_NODELAY).
+    pub nodelay: bool,
+    /// Lets the OS probe idle connections (SO_KEEPALIVE), so a dead one is noticed even
+    /// when the server sends nothing.
+    pub keepalive: bool,
+}
+
+impl Default for SocketOptions {
+    fn default() -> Self {
+        SocketOptions {
+            read_timeout: Some(Duration::from_secs(30)),
+            write_timeout: Some(Duration::from_secs(30)),
+            nodelay: true,
+            keepalive: false,
+        }
+    }
+}
+
+impl SocketOptions {
+    pub(crate) fn apply(&self, stream: &TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(self.read_timeout)?;
+        stream.set_write_timeout(self.write_timeout)?;
+        stream.set_nodelay(self.nodelay)?;
+        if self.keepalive {
+            set_keepalive(stream)?;
+        }
+        Ok(())
+    }
+}
+
+// The standard library has no setter for SO_KEEPALIVE, so it's set with setsockopt.
+// The option's numbers differ between platforms, and even between architectures on Linux
+// (MIPS, SPARC, Alpha and PA-RISC have their own), so only the known ones are used.
+#[cfg(any(
+    all(
+        any(target_os = "linux", target_os = "android"),
+        any(
+            target_arch = "x86",
+            target_arch = "x86_64",
+            target_arch = "arm",
+            target_arch = "aarch64",
+            target_arch = "riscv32",
+            target_arch = "riscv64",
+            target_arch = "powerpc",
+            target_arch = "powerpc64",
+            target_arch = "s390x",
+            target_arch = "loongarch64",
+        ),
+    ),
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "dragonfly",
+))]
+fn set_keepalive(stream: &TcpStream) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+    use std::os::raw::{c_int, c_void};
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    const SOL_SOCKET: c_int = 1;
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    const SO_KEEPALIVE: c_int = 9;
+    // macOS and the BSDs
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    const SOL_SOCKET: c_int = 0xFFFF;
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    const SO_KEEPALIVE: c_int = 0x0008;
+
+    unsafe extern "C" {
+        fn setsockopt(
+            socket: c_int,
+            level: c_int,
+            name: c_int,
+            value: *const c_void,
+            length: u32,
+        ) -> c_int;
+    }
+
+    let enabled: c_int = 1;
+    // SAFETY: the descriptor belongs to `stream`, and the value pointer and length
+    // describe `enabled`
+    let res: c_int = unsafe {
+        setsockopt(
+            stream.as_raw_fd(),
+            SOL_SOCKET,
+            SO_KEEPALIVE,
+            &enabled as *const c_int as *const c_void,
+            size_of::<c_int>() as u32,
+        )
+    };
+    match res {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+#[cfg(not(any(
+    all(
+        any(target_os = "linux", target_os = "android"),
+        any(
+            target_arch = "x86",
+            target_arch = "x86_64",
+            target_arch = "arm",
+            target_arch = "aarch64",
+            target_arch = "riscv32",
+            target_arch = "riscv64",
+            target_arch = "powerpc",
+            target_arch = "powerpc64",
+            target_arch = "s390x",
+            target_arch = "loongarch64",
+        ),
+    ),
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "dragonfly",
+)))]
+fn set_keepalive(_: &TcpStream) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "SO_KEEPALIVE can't be set on this platform",
+    ))
+}
@@ -0,0 +1,154 @@
+use std::fmt;
+use std::time::Duration;
+
+use azalea_chat::FormattedText;
+use base64::{engine::Engine, prelude::BASE64_STANDARD};
+use serde::Deserialize;
+
+use crate::error::ClientError;
+
+/// The server's answer to a status request, as shown in the multiplayer server list.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ServerStatus {
+    pub version: StatusVersion,
+    pub players: Option<StatusPlayers>,
+    #[serde(default)]
+    pub description: FormattedText,
+    /// `data:image/png;base64,...`
+    pub favicon: Option<String>,
+    /// Forge 1.12 and older.
+    #[serde(rename = "modinfo")]
+    pub mod_info: Option<ModInfo>,
+    /// Forge 1.13 and newer.
+    #[serde(rename = "forgeData")]
+    pub forge_data: Option<ForgeData>,
+    #[serde(rename = "enforcesSecureChat", default)]
+    pub enforces_secure_chat: bool,
+    /// Round-trip time of the ping that followed the status request, `None` if the
+    /// server answered the status but not the ping.
+    #[serde(skip)]
+    pub latency: Option<Duration>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct StatusVersion {
+    pub name: String,
+    pub protocol: i32,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct StatusPlayers {
+    pub max: i32,
+    pub online: i32,
+    #[serde(default)]
+    pub sample: Vec<StatusPlayer>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct StatusPlayer {
+    pub name: String,
+    pub id: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ModInfo {
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(rename = "modList", default)]
+    pub mods: Vec<ModInfoEntry>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ModInfoEntry {
+    #[serde(rename = "modid")]
+    pub id: String,
+    pub version: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ForgeData {
+    #[serde(default)]
+    pub mods: Vec<ForgeMod>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ForgeMod {
+    #[serde(rename = "modId")]
+    pub id: String,
+    #[serde(rename = "modmarker")]
+    pub version: String,
+}
+
+impl ServerStatus {
+    /// The server icon as PNG data, if the server has one.
+    pub fn favicon_png(&self) -> Result<Option<Vec<u8>>, ClientError> {
+        let Some(favicon) = &self.favicon else {
+            return Ok(None);
+        };
+        let Some((_, data)) = favicon.split_once(',') else {
+            return Err("The favicon isn't a data URL".into());
+        };
+        Ok(Some(BASE64_STANDARD.decode(data)?))
+    }
+
+    /// Mod IDs and versions from whichever Forge format the server uses.
+    pub fn mods(&self) -> Vec<(&str, &str)> {
+        if let Some(mod_info) = &self.mod_info {
+            return mod_info
+                .mods
+                .iter()
+                .map(|entry| (entry.id.as_str(), entry.version.as_str()))
+                .collect();
+        }
+        match &self.forge_data {
+            Some(forge_data) => forge_data
+                .mods
+                .iter()
+                .map(|entry| (entry.id.as_str(), entry.version.as_str()))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+impl fmt::Display for ServerStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Server status: {}", self.description.to_ansi())?;
+        writeln!(
+            f,
+            "Server version: {} (protocol {})",
+            self.version.name, self.version.protocol
+        )?;
+
+        if let Some(players) = &self.players {
+            write!(f, "Players: {}/{}", players.online, players.max)?;
+            if !players.sample.is_empty() {
+                let names: Vec<&str> = players
+                    .sample
+                    .iter()
+                    .map(|player| player.name.as_str())
+                    .collect();
+                write!(f, " [{}]", names.join(", "))?;
+            }
+            writeln!(f)?;
+        }
+
+        let mods: Vec<(&str, &str)> = self.mods();
+        if !mods.is_empty() {
+            let list: Vec<String> = mods
+                .iter()
+                .map(|(id, version)| format!("{} {}", id, version))
+                .collect();
+            writeln!(f, "Mods ({}): {}", mods.len(), list.join(", "))?;
+        }
+
+        if self.enforces_secure_chat {
+            writeln!(f, "Secure chat: enforced (unsigned chat will be rejected)")?;
+        }
+
+        match self.latency {
+            Some(latency) => write!(f, "Latency: {} ms", latency.as_millis()),
+            None => write!(f, "Latency: unknown (no answer to the ping)"),
+        }
+    }
+}
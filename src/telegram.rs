@@ -0,0 +1,133 @@
+use std::thread;
+use std::time::Duration;
+
+use serde_json::{Value, json};
+
+use crate::error::ClientError;
+use crate::http;
+use crate::textlimit::truncate;
+
+// The parts of the Telegram Bot API a chat relay needs: sending text to a chat and
+// long polling for new messages with getUpdates.
+
+const API_URL: &str = "https://api.telegram.org";
+
+/// Telegram rejects longer messages.
+pub const MAX_MESSAGE_LENGTH: usize = 4096;
+
+// How long getUpdates waits for a message before answering with none. Stays below
+// the HTTP timeout.
+const LONG_POLL: Duration = Duration::from_secs(8);
+
+// Telegram answers 429 with how long to wait; this caps it in case it's absurd
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// A message sent to the bot, or in a group it's in.
+#[derive(Clone, Debug)]
+pub struct TelegramMessage {
+    pub chat_id: i64,
+    pub user_id: i64,
+    /// The @username without the @, if the user has one.
+    pub username: Option<String>,
+    /// The first name, which every user has.
+    pub first_name: String,
+    pub text: String,
+}
+
+/// A bot, authenticated by the token from @BotFather.
+#[derive(Debug)]
+pub struct TelegramBot {
+    token: String,
+    // The update_id after the last one seen
+    offset: Option<i64>,
+}
+
+impl TelegramBot {
+    pub fn new(token: &str) -> TelegramBot {
+        TelegramBot {
+            token: String::from(token),
+            offset: None,
+        }
+    }
+
+    /// Sends `text` to a chat, as plain text. Waits and tries again when rate limited.
+    pub fn send_message(&self, chat_id: i64, text: &str) -> Result<(), ClientError> {
+        let body: Value = json!({
+            "chat_id": chat_id,
+            "text": truncate(text, MAX_MESSAGE_LENGTH),
+        });
+        self.call("sendMessage", Some(&body.to_string()))?;
+        Ok(())
+    }
+
+    /// Waits a few seconds for new text messages and returns them, oldest first. The
+    /// first call only skips what was sent while the bot wasn't listening and returns
+    /// nothing.
+    pub fn updates(&mut self) -> Result<Vec<TelegramMessage>, ClientError> {
+        let first: bool = self.offset.is_none();
+        let body: Value = json!({
+            // -1 is the newest update, only to find out where the queue ends
+            "offset": self.offset.unwrap_or(-1),
+            "timeout": if first { 0 } else { LONG_POLL.as_secs() },
+            "allowed_updates": ["message"],
+        });
+        let result: Value = self.call("getUpdates", Some(&body.to_string()))?;
+        let Some(updates) = result.as_array() else {
+            return Err("Telegram didn't send a list of updates".into());
+        };
+
+        let mut messages: Vec<TelegramMessage> = Vec::new();
+        for update in updates {
+            if let Some(id) = update["update_id"].as_i64() {
+                self.offset = Some(self.offset.unwrap_or(0).max(id + 1));
+            }
+            if let Some(message) = parse_message(&update["message"]) {
+                messages.push(message);
+            }
+        }
+        if first {
+            self.offset.get_or_insert(0);
+            return Ok(Vec::new());
+        }
+        Ok(messages)
+    }
+
+    // Calls a Bot API method and returns its `result`
+    fn call(&self, method: &str, body: Option<&str>) -> Result<Value, ClientError> {
+        let url: String = format!("{}/bot{}/{}", API_URL, self.token, method);
+        let headers: [(&str, &str); 1] = [("Content-Type", "application/json")];
+
+        loop {
+            let (status, response): (u16, Vec<u8>) = http::request("POST", &url, &headers, body)?;
+            let json: Value = serde_json::from_slice(&response).unwrap_or_default();
+            match status {
+                200 if json["ok"] == true => return Ok(json["result"].clone()),
+                429 => {
+                    let retry_after: u64 = json["parameters"]["retry_after"].as_u64().unwrap_or(1);
+                    thread::sleep(Duration::from_secs(retry_after).min(MAX_RETRY_AFTER));
+                }
+                _ => {
+                    let description: &str = json["description"].as_str().unwrap_or_default();
+                    return Err(format!(
+                        "Telegram answered with status {} {}",
+                        status, description
+                    )
+                    .trim_end()
+                    .into());
+                }
+            }
+        }
+    }
+}
+
+fn parse_message(message: &Value) -> Option<TelegramMessage> {
+    let from: &Value = &message["from"];
+    Some(TelegramMessage {
+        chat_id: message["chat"]["id"].as_i64()?,
+        user_id: from["id"].as_i64()?,
+        username: from["username"].as_str().map(String::from),
+        first_name: String::from(from["first_name"].as_str().unwrap_or_default()),
+        // Photos, stickers and the like have no text
+        text: String::from(message["text"].as_str()?),
+    })
+}
@@ -0,0 +1,182 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use mclient_project::telegram::{self, TelegramBot, TelegramMessage};
+use mclient_project::{Client, Event};
+
+use crate::bots::{Bot, Bots, JOIN_GRACE};
+use crate::lang::Language;
+
+// Relays a bot's chat, joins, leaves and kicks to a Telegram chat (built with the
+// `telegram` feature), and sends what allowed Telegram users write back to the server.
+
+pub const DEFAULT_TO_TELEGRAM: &str = "%msg";
+pub const DEFAULT_TO_MINECRAFT: &str = "[Telegram] %user: %msg";
+
+/// The `[telegram]` table.
+#[derive(Clone, Debug)]
+pub struct TelegramConfig {
+    pub token: String,
+    /// Where the chat goes.
+    pub chat: i64,
+    /// Usernames (without @) or user IDs of the people whose messages are sent in game,
+    /// from the chat or sent to the bot directly. Nobody if empty.
+    pub allow: Vec<String>,
+    /// The bot's name; the one logged in with `--username` if not given.
+    pub bot: Option<String>,
+    /// How a chat line is sent to Telegram: %msg is the line and %bot the bot's name.
+    pub to_telegram: String,
+    /// How a Telegram message is sent in game: %user is the author and %msg the text.
+    pub to_minecraft: String,
+    /// Also send joins, leaves and kicks.
+    pub events: bool,
+}
+
+impl TelegramConfig {
+    fn allows(&self, message: &TelegramMessage) -> bool {
+        self.allow.iter().any(|allowed| {
+            let allowed: &str = allowed.trim_start_matches('@');
+            allowed == message.user_id.to_string()
+                || message
+                    .username
+                    .as_deref()
+                    .is_some_and(|username| username.eq_ignore_ascii_case(allowed))
+        })
+    }
+}
+
+/// Starts relaying. Problems are passed to `print`.
+pub fn start(config: &TelegramConfig, bots: &Bots, language: &Arc<Language>, print: fn(&str)) {
+    let bots: Vec<Arc<Bot>> = bots.all();
+    let bot: Option<&Arc<Bot>> = match &config.bot {
+        Some(name) => bots.iter().find(|bot| bot.name.eq_ignore_ascii_case(name)),
+        None => bots.first(),
+    };
+    let Some(bot) = bot else {
+        print(&format!(
+            "[MClient] [telegram]: there's no bot called {}",
+            config.bot.as_deref().unwrap_or_default()
+        ));
+        return;
+    };
+
+    relay_to_telegram(config, bot, language, print);
+    relay_to_minecraft(config, Arc::downgrade(&bot.client), print);
+}
+
+fn relay_to_telegram(
+    config: &TelegramConfig,
+    bot: &Bot,
+    language: &Arc<Language>,
+    print: fn(&str),
+) {
+    // Sending takes a moment, so lines that come in meanwhile are sent together
+    let (queue, lines): (Sender<String>, Receiver<String>) = mpsc::channel();
+    let telegram: TelegramBot = TelegramBot::new(&config.token);
+    let chat: i64 = config.chat;
+    thread::spawn(move || {
+        while let Ok(line) = lines.recv() {
+            let mut text: String = line;
+            while let Ok(line) = lines.try_recv() {
+                if text.chars().count() + line.chars().count() >= telegram::MAX_MESSAGE_LENGTH {
+                    send(&telegram, chat, &text, print);
+                    text = line;
+                } else {
+                    text = format!("{}\n{}", text, line);
+                }
+            }
+            send(&telegram, chat, &text, print);
+        }
+    });
+
+    let format: String = config.to_telegram.replace("%bot", &bot.name);
+    let events: bool = config.events;
+    let language: Arc<Language> = Arc::clone(language);
+    let own_uuid: u128 = bot.client.identity().uuid;
+    let own_tag: String = format!("<{}>", bot.name);
+    let connected_at: Mutex<Instant> = Mutex::new(Instant::now());
+    bot.client.on_event(move |event| {
+        let line: String = match event {
+            Event::ChatReceived {
+                message, sender, ..
+            } => {
+                let text: String = language.translate(message).to_string();
+                // That includes what it sent for Telegram users, which would show up twice
+                if *sender == Some(own_uuid) || text.starts_with(&own_tag) {
+                    return;
+                }
+                format.replace("%msg", &text)
+            }
+            Event::PlayerJoined { name, .. }
+                if events && connected_at.lock().unwrap().elapsed() > JOIN_GRACE =>
+            {
+                format!("{} joined", name)
+            }
+            Event::PlayerLeft { name, .. } if events => format!("{} left", name),
+            Event::Kicked(reason) if events => {
+                format!("Kicked from the server: {}", language.translate(reason))
+            }
+            Event::Connected => {
+                *connected_at.lock().unwrap() = Instant::now();
+                return;
+            }
+            _ => return,
+        };
+        let _ = queue.send(line);
+    });
+}
+
+fn send(telegram: &TelegramBot, chat: i64, text: &str, print: fn(&str)) {
+    if let Err(e) = telegram.send_message(chat, text) {
+        print(&format!("[MClient] Couldn't send to Telegram: {}", e));
+    }
+}
+
+fn relay_to_minecraft(config: &TelegramConfig, client: Weak<Client>, print: fn(&str)) {
+    let config: TelegramConfig = config.clone();
+    let mut telegram: TelegramBot = TelegramBot::new(&config.token);
+    thread::spawn(move || {
+        loop {
+            let messages: Vec<TelegramMessage> = match telegram.updates() {
+                Ok(messages) => messages,
+                Err(e) => {
+                    print(&format!("[MClient] Couldn't read Telegram messages: {}", e));
+                    // Not hammering the API while it's down or the token is wrong
+                    thread::sleep(Duration::from_secs(10));
+                    Vec::new()
+                }
+            };
+            let Some(client) = client.upgrade() else {
+                return;
+            };
+            for message in messages {
+                let direct: bool = message.chat_id == message.user_id;
+                if (message.chat_id != config.chat && !direct) || !config.allows(&message) {
+                    continue;
+                }
+                let text: String = message
+                    .text
+                    .split_whitespace()
+                    .collect::<Vec<&str>>()
+                    .join(" ");
+                if text.is_empty() {
+                    continue;
+                }
+                let user: &str = message.username.as_deref().unwrap_or(&message.first_name);
+                let line: String = config
+                    .to_minecraft
+                    .replace("%user", user)
+                    .replace("%msg", &text);
+                // Telegram users shouldn't be able to run commands as the bot
+                if let Err(e) = client.send_chat(line.trim_start_matches('/')) {
+                    print(&format!(
+                        "[MClient] Couldn't relay a Telegram message: {}",
+                        e
+                    ));
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,432 @@
+use std::io::{Cursor, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use mc_varint::{VarInt, VarIntWrite};
+use serde_json::json;
+
+use crate::configuration::has_configuration;
+use crate::error::ClientError;
+use crate::helper::{self, PacketReader};
+use crate::mcstring::McString;
+use crate::mojang;
+use crate::protocol::{self, Clientbound, PacketTable, Serverbound};
+use crate::types::McRead;
+
+// A fake server on localhost for end-to-end tests: it answers status requests and pings,
+// logs offline-mode players in (compressing packets if asked to), sends keep alives and
+// echoes chat back as `<name> message`. Nothing of the world is simulated, so clients
+// never spawn. Versions with a configuration state (1.20.2+) aren't supported.
+
+// How long a connection may stay silent before the server drops it
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Debug)]
+pub struct TestServerConfig {
+    /// The version the server runs. Clients logging in with another one are kicked, like
+    /// vanilla does.
+    pub protocol: i32,
+    pub motd: String,
+    pub max_players: u32,
+    /// Packets this many bytes long or longer are compressed. `None` leaves compression
+    /// off.
+    pub compression_threshold: Option<i32>,
+    pub keep_alive_interval: Duration,
+}
+
+impl Default for TestServerConfig {
+    fn default() -> Self {
+        TestServerConfig {
+            protocol: helper::DEFAULT_PROTOCOL,
+            motd: String::from("A Minecraft Server"),
+            max_players: 20,
+            compression_threshold: Some(256),
+            keep_alive_interval: Duration::from_secs(15),
+        }
+    }
+}
+
+// A logged in player
+struct Connection {
+    name: String,
+    uuid: u128,
+    // Shared by the reading thread, the keep alive thread and broadcasts
+    stream: Mutex<TcpStream>,
+    threshold: i32,
+    // The keep alive waiting for its answer
+    keep_alive: AtomicI64,
+}
+
+impl Connection {
+    fn send(&self, id: i32, data: Vec<u8>) -> Result<(), ClientError> {
+        helper::send_packet(&mut self.stream.lock().unwrap(), id, data, self.threshold)
+    }
+}
+
+struct State {
+    config: TestServerConfig,
+    packets: &'static PacketTable,
+    connections: Mutex<Vec<Arc<Connection>>>,
+    // Chat messages and commands from every player, in the order they arrived
+    chat: Mutex<Vec<String>>,
+    keep_alives_answered: AtomicUsize,
+    closing: AtomicBool,
+}
+
+/// A server listening on a free port of 127.0.0.1 until it's stopped or dropped.
+pub struct TestServer {
+    address: SocketAddr,
+    state: Arc<State>,
+}
+
+impl TestServer {
+    pub fn start(config: TestServerConfig) -> Result<TestServer, ClientError> {
+        if has_configuration(config.protocol) {
+            return Err(format!(
+                "The test server doesn't support protocol {} (1.20.2+ needs the configuration state)",
+                config.protocol
+            )
+            .into());
+        }
+
+        let packets: &'static PacketTable = protocol::require_table(config.protocol)?;
+        let listener: TcpListener = TcpListener::bind("127.0.0.1:0")?;
+        let address: SocketAddr = listener.local_addr()?;
+        let state: Arc<State> = Arc::new(State {
+            packets,
+            config,
+            connections: Mutex::new(Vec::new()),
+            chat: Mutex::new(Vec::new()),
+            keep_alives_answered: AtomicUsize::new(0),
+            closing: AtomicBool::new(false),
+        });
+
+        {
+            let state: Arc<State> = Arc::clone(&state);
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if state.closing.load(Ordering::SeqCst) {
+                        return;
+                    }
+                    let Ok(stream) = stream else {
+                        continue;
+                    };
+                    let state: Arc<State> = Arc::clone(&state);
+                    // A client that misbehaves only loses its own connection
+                    thread::spawn(move || {
+                        let _ = handle(stream, &state);
+                    });
+                }
+            });
+        }
+
+        Ok(TestServer { address, state })
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    pub fn port(&self) -> u16 {
+        self.address.port()
+    }
+
+    /// The names of the players logged in right now.
+    pub fn players(&self) -> Vec<String> {
+        let connections = self.state.connections.lock().unwrap();
+        connections
+            .iter()
+            .map(|connection| connection.name.clone())
+            .collect()
+    }
+
+    /// Every chat message received so far. Commands keep their `/`.
+    pub fn chat_messages(&self) -> Vec<String> {
+        self.state.chat.lock().unwrap().clone()
+    }
+
+    /// How many keep alives were answered with the right ID.
+    pub fn keep_alives_answered(&self) -> usize {
+        self.state.keep_alives_answered.load(Ordering::SeqCst)
+    }
+
+    /// Sends a system message to every player.
+    pub fn broadcast(&self, text: &str) {
+        let connections: Vec<Arc<Connection>> = self.state.connections.lock().unwrap().clone();
+        for connection in connections {
+            if let Ok(data) = chat_packet(&self.state, text, None) {
+                let _ = connection.send(chat_id(&self.state), data);
+            }
+        }
+    }
+
+    /// Creates a team for every player: `members` get `prefix` and `suffix` (plain text)
+    /// around their names, and the color with formatting code index `color` (0-15).
+    pub fn add_team(&self, name: &str, prefix: &str, suffix: &str, color: u8, members: &[&str]) {
+        let connections: Vec<Arc<Connection>> = self.state.connections.lock().unwrap().clone();
+        let Some(id) = self.state.packets.clientbound_id(Clientbound::UpdateTeam) else {
+            return;
+        };
+        for connection in connections {
+            if let Ok(data) = team_packet(&self.state, name, prefix, suffix, color, members) {
+                let _ = connection.send(id, data);
+            }
+        }
+    }
+
+    /// Closes every connection and stops accepting new ones.
+    pub fn stop(&self) {
+        if self.state.closing.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        for connection in self.state.connections.lock().unwrap().drain(..) {
+            let _ = connection.stream.lock().unwrap().shutdown(Shutdown::Both);
+        }
+        // Wakes the accept loop up so it sees `closing`
+        let _ = TcpStream::connect(self.address);
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn handle(mut stream: TcpStream, state: &Arc<State>) -> Result<(), ClientError> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader: PacketReader = PacketReader::new();
+
+    // Handshake
+    let (_, data): (i32, &[u8]) = reader.receive(&mut stream, -1)?;
+    let mut cursor: Cursor<&[u8]> = Cursor::new(data);
+    let client_protocol: i32 = cursor.read_varint()?;
+    let _host: String = McString::max(255).read(&mut cursor)?;
+    let _port: u16 = cursor.read_u16()?;
+    match cursor.read_varint()? {
+        1 => answer_status(&mut stream, &mut reader, state),
+        2 => log_in(stream, &mut reader, state, client_protocol),
+        intent => Err(format!("Unknown handshake intent {}", intent).into()),
+    }
+}
+
+fn answer_status(
+    stream: &mut TcpStream,
+    reader: &mut PacketReader,
+    state: &State,
+) -> Result<(), ClientError> {
+    reader.receive(stream, -1)?; // Status Request packet
+    let status: String = json!({
+        "version": {
+            "name": state.packets.version,
+            "protocol": state.config.protocol,
+        },
+        "players": {
+            "max": state.config.max_players,
+            "online": state.connections.lock().unwrap().len(),
+            "sample": [],
+        },
+        "description": { "text": state.config.motd },
+    })
+    .to_string();
+    let mut packet_buffer: Vec<u8> = Vec::new();
+    McString::DEFAULT.write(&mut packet_buffer, &status)?;
+    helper::send_packet(stream, 0x00, packet_buffer, -1)?; // Status Response packet
+
+    // The Ping packet's payload goes back unchanged
+    let (id, payload): (i32, &[u8]) = reader.receive(stream, -1)?;
+    if id == 0x01 {
+        helper::send_packet(stream, 0x01, payload.to_vec(), -1)?; // Pong packet
+    }
+    Ok(())
+}
+
+fn log_in(
+    mut stream: TcpStream,
+    reader: &mut PacketReader,
+    state: &Arc<State>,
+    client_protocol: i32,
+) -> Result<(), ClientError> {
+    let (_, data): (i32, &[u8]) = reader.receive(&mut stream, -1)?; // Login Start packet
+    let name: String = McString::USERNAME.read(&mut Cursor::new(data))?;
+
+    if client_protocol != state.config.protocol {
+        let reason: String = json!({
+            "text": format!("Outdated client! Please use {}", state.packets.version),
+        })
+        .to_string();
+        let mut packet_buffer: Vec<u8> = Vec::new();
+        McString::DEFAULT.write(&mut packet_buffer, &reason)?;
+        helper::send_packet(&mut stream, 0x00, packet_buffer, -1)?; // Disconnect (login) packet
+        return Ok(());
+    }
+
+    let threshold: i32 = state.config.compression_threshold.unwrap_or(-1);
+    if threshold >= 0 {
+        let mut packet_buffer: Vec<u8> = Vec::new();
+        packet_buffer.write_var_int(VarInt::from(threshold))?;
+        helper::send_packet(&mut stream, 0x03, packet_buffer, -1)?; // Set Compression packet
+    }
+
+    // Login Success: 1.16+ sends the UUID as a number, 1.19+ adds (no) properties
+    let uuid: u128 = mojang::offline_uuid(&name);
+    let mut packet_buffer: Vec<u8> = Vec::new();
+    match state.config.protocol {
+        735.. => packet_buffer.write_all(&uuid.to_be_bytes())?,
+        _ => McString::max(36).write(&mut packet_buffer, &mojang::format_uuid(uuid))?,
+    }
+    McString::USERNAME.write(&mut packet_buffer, &name)?;
+    if state.config.protocol >= 759 {
+        packet_buffer.write_var_int(VarInt::from(0))?;
+    }
+    helper::send_packet(&mut stream, 0x02, packet_buffer, threshold)?;
+
+    let connection: Arc<Connection> = Arc::new(Connection {
+        name,
+        uuid,
+        stream: Mutex::new(stream.try_clone()?),
+        threshold,
+        keep_alive: AtomicI64::new(-1),
+    });
+    state
+        .connections
+        .lock()
+        .unwrap()
+        .push(Arc::clone(&connection));
+
+    {
+        let state: Arc<State> = Arc::clone(state);
+        let connection: Arc<Connection> = Arc::clone(&connection);
+        thread::spawn(move || send_keep_alives(&state, &connection));
+    }
+
+    let res: Result<(), ClientError> = play(&mut stream, reader, state, &connection);
+    state
+        .connections
+        .lock()
+        .unwrap()
+        .retain(|other| !Arc::ptr_eq(other, &connection));
+    let _ = stream.shutdown(Shutdown::Both);
+    res
+}
+
+fn play(
+    stream: &mut TcpStream,
+    reader: &mut PacketReader,
+    state: &State,
+    connection: &Connection,
+) -> Result<(), ClientError> {
+    let packets: &PacketTable = state.packets;
+    loop {
+        let (id, data): (i32, &[u8]) = reader.receive(stream, connection.threshold)?;
+        let mut cursor: Cursor<&[u8]> = Cursor::new(data);
+
+        if Some(id) == packets.serverbound_id(Serverbound::KeepAlive) {
+            let keep_alive: i64 = cursor.read_long()?;
+            if connection
+                .keep_alive
+                .compare_exchange(keep_alive, -1, Ordering::SeqCst, Ordering::SeqCst)
+                .is_ok()
+            {
+                state.keep_alives_answered.fetch_add(1, Ordering::SeqCst);
+            }
+        } else if Some(id) == packets.serverbound_id(Serverbound::ChatCommand) {
+            let command: String = McString::CHAT.read(&mut cursor)?;
+            state.chat.lock().unwrap().push(format!("/{}", command));
+        } else if Some(id) == packets.serverbound_id(Serverbound::ChatMessage) {
+            // Only the text is read, 1.19+ signature fields after it are ignored
+            let message: String = McString::CHAT.read(&mut cursor)?;
+            state.chat.lock().unwrap().push(message.clone());
+            if !message.starts_with('/') {
+                let text: String = format!("<{}> {}", connection.name, message);
+                connection.send(
+                    chat_id(state),
+                    chat_packet(state, &text, Some(connection.uuid))?,
+                )?;
+            }
+        }
+    }
+}
+
+fn send_keep_alives(state: &State, connection: &Connection) {
+    let mut next_id: i64 = 0;
+    loop {
+        thread::sleep(state.config.keep_alive_interval);
+        if state.closing.load(Ordering::SeqCst) {
+            return;
+        }
+        let Some(id) = state.packets.clientbound_id(Clientbound::KeepAlive) else {
+            return;
+        };
+        connection.keep_alive.store(next_id, Ordering::SeqCst);
+        if connection.send(id, next_id.to_be_bytes().to_vec()).is_err() {
+            return;
+        }
+        next_id += 1;
+    }
+}
+
+fn chat_id(state: &State) -> i32 {
+    state
+        .packets
+        .clientbound_id(Clientbound::ChatMessage)
+        .unwrap_or_default()
+}
+
+// An Update Teams packet creating the team. 1.13 turned the texts into components and
+// moved the prefix and suffix after the color
+fn team_packet(
+    state: &State,
+    name: &str,
+    prefix: &str,
+    suffix: &str,
+    color: u8,
+    members: &[&str],
+) -> Result<Vec<u8>, ClientError> {
+    let mut packet_buffer: Vec<u8> = Vec::new();
+    McString::DEFAULT.write(&mut packet_buffer, name)?;
+    packet_buffer.write_all(&[0u8])?; // create
+    if state.config.protocol < 393 {
+        for text in [name, prefix, suffix] {
+            McString::DEFAULT.write(&mut packet_buffer, text)?;
+        }
+        packet_buffer.write_all(&[0u8])?; // no friendly fire flags
+        McString::DEFAULT.write(&mut packet_buffer, "always")?; // name tag visibility
+        McString::DEFAULT.write(&mut packet_buffer, "always")?; // collision rule
+        packet_buffer.write_all(&[color])?;
+    } else {
+        McString::COMPONENT.write(&mut packet_buffer, &json!({ "text": name }).to_string())?;
+        packet_buffer.write_all(&[0u8])?; // no friendly fire flags
+        McString::DEFAULT.write(&mut packet_buffer, "always")?; // name tag visibility
+        McString::DEFAULT.write(&mut packet_buffer, "always")?; // collision rule
+        packet_buffer.write_var_int(VarInt::from(i32::from(color)))?;
+        for text in [prefix, suffix] {
+            McString::COMPONENT.write(&mut packet_buffer, &json!({ "text": text }).to_string())?;
+        }
+    }
+    packet_buffer.write_var_int(VarInt::from(members.len() as i32))?;
+    for member in members {
+        McString::DEFAULT.write(&mut packet_buffer, member)?;
+    }
+    Ok(packet_buffer)
+}
+
+// A chat packet with plain `text`: a player message from `sender` before 1.19, where
+// System Chat took over, a system message otherwise
+fn chat_packet(state: &State, text: &str, sender: Option<u128>) -> Result<Vec<u8>, ClientError> {
+    let protocol: i32 = state.config.protocol;
+    let mut packet_buffer: Vec<u8> = Vec::new();
+    McString::COMPONENT.write(&mut packet_buffer, &json!({ "text": text }).to_string())?;
+    if protocol >= 759 {
+        packet_buffer.write_all(&[0u8])?; // not in the action bar
+        return Ok(packet_buffer);
+    }
+    packet_buffer.write_all(&[if sender.is_some() { 0u8 } else { 1u8 }])?; // chat or system
+    if protocol >= 735 {
+        packet_buffer.write_all(&sender.unwrap_or(0).to_be_bytes())?;
+    }
+    Ok(packet_buffer)
+}
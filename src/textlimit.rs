@@ -0,0 +1,23 @@
+// Chat services cap the length of a message, the bridges cut what they relay to fit.
+
+/// Cuts `text` to at most `max` characters.
+pub(crate) fn truncate(text: &str, max: usize) -> &str {
+    match text.char_indices().nth(max) {
+        Some((i, _)) => &text[..i],
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cuts_characters_not_bytes() {
+        assert_eq!(truncate("hello", 10), "hello");
+        assert_eq!(truncate("hello", 5), "hello");
+        assert_eq!(truncate("hello", 2), "he");
+        assert_eq!(truncate("äöü", 2), "äö");
+        assert_eq!(truncate("abc", 0), "");
+    }
+}
@@ -0,0 +1,8 @@
+/// How many packets with one ID the server sent, from `Client::packet_counts`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PacketCount {
+    pub id: i32,
+    pub count: u64,
+    /// The data after the ID, decompressed.
+    pub bytes: u64,
+}
@@ -0,0 +1,308 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{Write, stdout};
+use std::process::{Command, Stdio};
+use std::sync::{Mutex, OnceLock};
+
+// Full-screen terminal layout drawn with plain ANSI escapes:
+//
+//   header
+//   chat history                 | players
+//   separator
+//   > input line
+//
+// The input line is left to the terminal's own line editing; only the rows above it
+// are redrawn, with the cursor saved and restored around every redraw.
+
+const SIDEBAR_WIDTH: usize = 20;
+const MAX_HISTORY: usize = 5000;
+
+struct TuiState {
+    title: String,
+    history: VecDeque<String>,
+    players: Vec<String>,
+    tab_header: Vec<String>,
+    tab_footer: Vec<String>,
+    action_bar: String,
+    boss_bars: Vec<String>,
+    scroll: usize,
+    width: usize,
+    height: usize,
+}
+
+pub struct Tui {
+    state: Mutex<TuiState>,
+}
+
+static TUI: OnceLock<Tui> = OnceLock::new();
+
+/// Returns the TUI if it was started.
+pub fn get() -> Option<&'static Tui> {
+    TUI.get()
+}
+
+fn terminal_size() -> (usize, usize) {
+    let tty: Option<File> = File::open("/dev/tty").ok();
+    let output = tty.and_then(|tty| {
+        Command::new("stty")
+            .arg("size")
+            .stdin(Stdio::from(tty))
+            .output()
+            .ok()
+    });
+
+    if let Some(output) = output {
+        let size: String = String::from_utf8_lossy(&output.stdout).into_owned();
+        let mut parts = size.split_whitespace().map(|part| part.parse::<usize>());
+        if let (Some(Ok(rows)), Some(Ok(columns))) = (parts.next(), parts.next()) {
+            return (columns.max(40), rows.max(8));
+        }
+    }
+
+    (80, 24)
+}
+
+/// Splits a line containing ANSI escapes into rows of at most `width` visible
+/// characters, re-applying the active style at the start of every continuation row.
+fn wrap_ansi(line: &str, width: usize) -> Vec<String> {
+    let mut rows: Vec<String> = Vec::new();
+    let mut current: String = String::new();
+    let mut active_style: String = String::new();
+    let mut visible: usize = 0;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            let mut escape: String = String::from(c);
+            for next in chars.by_ref() {
+                escape.push(next);
+                if next.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+            if escape == "\x1b[m" || escape == "\x1b[0m" {
+                active_style.clear();
+            } else {
+                active_style += &escape;
+            }
+            current += &escape;
+            continue;
+        }
+
+        if visible == width {
+            rows.push(std::mem::take(&mut current) + "\x1b[0m");
+            current += &active_style;
+            visible = 0;
+        }
+        current.push(c);
+        visible += 1;
+    }
+
+    rows.push(current + "\x1b[0m");
+    rows
+}
+
+fn pad(text: &str, width: usize) -> String {
+    let mut res: String = text.chars().take(width).collect();
+    let len: usize = res.chars().count();
+    res += &" ".repeat(width - len);
+    res
+}
+
+impl Tui {
+    pub fn start(title: &str) -> &'static Tui {
+        let (width, height): (usize, usize) = terminal_size();
+        let tui: Tui = Tui {
+            state: Mutex::new(TuiState {
+                title: String::from(title),
+                history: VecDeque::new(),
+                players: Vec::new(),
+                tab_header: Vec::new(),
+                tab_footer: Vec::new(),
+                action_bar: String::new(),
+                boss_bars: Vec::new(),
+                scroll: 0,
+                width,
+                height,
+            }),
+        };
+
+        let tui: &'static Tui = TUI.get_or_init(|| tui);
+
+        // Clear the screen and keep scrolling confined to the rows above the input line
+        print!("\x1b[2J\x1b[1;{}r", height - 1);
+        tui.redraw();
+        tui.prompt();
+        tui
+    }
+
+    pub fn push_line(&self, line: &str) {
+        {
+            let mut state = self.state.lock().unwrap();
+            for part in line.split('\n') {
+                state.history.push_back(String::from(part));
+            }
+            while state.history.len() > MAX_HISTORY {
+                state.history.pop_front();
+            }
+        }
+        self.redraw();
+    }
+
+    pub fn set_players(&self, mut players: Vec<String>) {
+        players.sort_by_key(|name| name.to_lowercase());
+        self.state.lock().unwrap().players = players;
+        self.redraw();
+    }
+
+    /// Shows the tab list header and footer (as plain text lines) around the players
+    /// in the sidebar.
+    pub fn set_tab_list(&self, header: Vec<String>, footer: Vec<String>) {
+        {
+            let mut state = self.state.lock().unwrap();
+            if state.tab_header == header && state.tab_footer == footer {
+                return;
+            }
+            state.tab_header = header;
+            state.tab_footer = footer;
+        }
+        self.redraw();
+    }
+
+    /// Shows the boss bars (as plain text) in the header.
+    pub fn set_boss_bars(&self, boss_bars: Vec<String>) {
+        {
+            let mut state = self.state.lock().unwrap();
+            if state.boss_bars == boss_bars {
+                return;
+            }
+            state.boss_bars = boss_bars;
+        }
+        self.redraw();
+    }
+
+    /// Shows the latest action bar text in the separator above the input line.
+    pub fn set_action_bar(&self, text: &str) {
+        {
+            let mut state = self.state.lock().unwrap();
+            if state.action_bar == text {
+                return;
+            }
+            state.action_bar = String::from(text);
+        }
+        self.redraw();
+    }
+
+    /// Scrolls the chat history back (positive) or forward (negative) by `lines`.
+    pub fn scroll(&self, lines: isize) {
+        {
+            let mut state = self.state.lock().unwrap();
+            let max_scroll: usize = state.history.len();
+            state.scroll = state.scroll.saturating_add_signed(lines).min(max_scroll);
+        }
+        self.redraw();
+    }
+
+    /// Re-reads the terminal size, e.g. after the window was resized.
+    pub fn resize(&self) {
+        let (width, height): (usize, usize) = terminal_size();
+        {
+            let mut state = self.state.lock().unwrap();
+            if state.width == width && state.height == height {
+                return;
+            }
+            state.width = width;
+            state.height = height;
+        }
+        print!("\x1b[2J\x1b[1;{}r", height - 1);
+        self.redraw();
+        self.prompt();
+    }
+
+    /// Moves the cursor to an empty input line.
+    pub fn prompt(&self) {
+        let height: usize = self.state.lock().unwrap().height;
+        print!("\x1b[{};1H\x1b[2K> ", height);
+        let _ = stdout().flush();
+    }
+
+    pub fn redraw(&self) {
+        let state = self.state.lock().unwrap();
+        let chat_width: usize = state.width - SIDEBAR_WIDTH - 1;
+        let chat_height: usize = state.height - 3;
+
+        let mut rows: Vec<String> = Vec::new();
+        for line in state.history.iter() {
+            rows.extend(wrap_ansi(line, chat_width));
+        }
+        let end: usize = rows.len().saturating_sub(state.scroll);
+        let start: usize = end.saturating_sub(chat_height);
+        let visible: &[String] = &rows[start..end];
+
+        let mut frame: String = String::from("\x1b7"); // save cursor
+
+        let mut header: String = format!(" {} | {} online", state.title, state.players.len());
+        for boss_bar in state.boss_bars.iter() {
+            header += &format!(" | {}", boss_bar);
+        }
+        if state.scroll > 0 {
+            header += &format!(" | scrolled back {} lines", state.scroll);
+        }
+        frame += &format!(
+            "\x1b[1;1H\x1b[2K\x1b[7m{}\x1b[0m",
+            pad(&header, state.width)
+        );
+
+        let sidebar: Vec<&String> = state
+            .tab_header
+            .iter()
+            .chain(state.players.iter())
+            .chain(state.tab_footer.iter())
+            .collect();
+
+        for i in 0..chat_height {
+            let chat_row: String = match visible.get(i) {
+                Some(row) => row.clone(),
+                None => String::new(),
+            };
+            let player: &str = match sidebar.get(i) {
+                Some(name) => name,
+                None => "",
+            };
+
+            frame += &format!(
+                "\x1b[{};1H\x1b[2K{}\x1b[{}G\x1b[2m|\x1b[0m{}",
+                i + 2,
+                chat_row,
+                chat_width + 1,
+                pad(player, SIDEBAR_WIDTH)
+            );
+        }
+
+        let separator: String = if state.action_bar.is_empty() {
+            "-".repeat(state.width)
+        } else {
+            let text: String = format!("-- {} ", state.action_bar)
+                .chars()
+                .take(state.width)
+                .collect();
+            let len: usize = text.chars().count();
+            text + &"-".repeat(state.width - len)
+        };
+        frame += &format!(
+            "\x1b[{};1H\x1b[2K\x1b[2m{}\x1b[0m",
+            state.height - 1,
+            separator
+        );
+        frame += "\x1b8"; // restore cursor
+
+        let mut out = stdout().lock();
+        let _ = out.write_all(frame.as_bytes());
+        let _ = out.flush();
+    }
+
+    pub fn stop(&self) {
+        print!("\x1b[r\x1b[2J\x1b[1;1H");
+        let _ = stdout().flush();
+    }
+}
@@ -0,0 +1,172 @@
+use std::io::{self, Read};
+
+use crate::error::ClientError;
+use crate::mcstring::McString;
+
+// The protocol's data types, read from anything that implements `Read`: a connection, or
+// a `Cursor` over a packet. Lengths come from the server, so arrays only grow as their
+// bytes actually arrive instead of being allocated up front.
+
+/// A block's coordinates, packed into a long on the wire.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct BlockPosition {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+/// Reads the protocol's data types. Implemented for every `Read`, so
+/// `Cursor::new(data).read_varint()` and `stream.read_varint()` are the same code.
+pub trait McRead: Read {
+    fn read_varint(&mut self) -> Result<i32, ClientError> {
+        let mut value: u32 = 0;
+        for i in 0..5 {
+            let byte: u8 = read_varint_byte(self)?;
+            value |= u32::from(byte & 0x7F) << (7 * i);
+            if byte & 0x80 == 0 {
+                return Ok(value as i32);
+            }
+        }
+        Err("VarInt is longer than 5 bytes".into())
+    }
+
+    fn read_varlong(&mut self) -> Result<i64, ClientError> {
+        let mut value: u64 = 0;
+        for i in 0..10 {
+            let byte: u8 = read_varint_byte(self)?;
+            value |= u64::from(byte & 0x7F) << (7 * i);
+            if byte & 0x80 == 0 {
+                return Ok(value as i64);
+            }
+        }
+        Err("VarLong is longer than 10 bytes".into())
+    }
+
+    fn read_bool(&mut self) -> Result<bool, ClientError> {
+        Ok(read_fixed::<1>(self)?[0] == 1)
+    }
+
+    fn read_byte(&mut self) -> Result<i8, ClientError> {
+        Ok(i8::from_be_bytes(read_fixed(self)?))
+    }
+
+    /// An Unsigned Byte.
+    fn read_u8(&mut self) -> Result<u8, ClientError> {
+        Ok(read_fixed::<1>(self)?[0])
+    }
+
+    fn read_short(&mut self) -> Result<i16, ClientError> {
+        Ok(i16::from_be_bytes(read_fixed(self)?))
+    }
+
+    /// An Unsigned Short, like the port in the handshake.
+    fn read_u16(&mut self) -> Result<u16, ClientError> {
+        Ok(u16::from_be_bytes(read_fixed(self)?))
+    }
+
+    fn read_int(&mut self) -> Result<i32, ClientError> {
+        Ok(i32::from_be_bytes(read_fixed(self)?))
+    }
+
+    fn read_long(&mut self) -> Result<i64, ClientError> {
+        Ok(i64::from_be_bytes(read_fixed(self)?))
+    }
+
+    fn read_float(&mut self) -> Result<f32, ClientError> {
+        Ok(f32::from_be_bytes(read_fixed(self)?))
+    }
+
+    fn read_double(&mut self) -> Result<f64, ClientError> {
+        Ok(f64::from_be_bytes(read_fixed(self)?))
+    }
+
+    fn read_uuid(&mut self) -> Result<u128, ClientError> {
+        Ok(u128::from_be_bytes(read_fixed(self)?))
+    }
+
+    /// 1.14 moved y to the low bits, it used to be between x and z.
+    fn read_position(&mut self, protocol: i32) -> Result<BlockPosition, ClientError> {
+        let value: i64 = self.read_long()?;
+        let (y, z): (i64, i64) = match protocol {
+            477.. => (value << 52 >> 52, value << 26 >> 38),
+            _ => (value << 26 >> 52, value << 38 >> 38),
+        };
+        Ok(BlockPosition {
+            x: (value >> 38) as i32,
+            y: y as i32,
+            z: z as i32,
+        })
+    }
+
+    /// A rotation in steps of 1/256 of a turn, returned in degrees.
+    fn read_angle(&mut self) -> Result<f32, ClientError> {
+        Ok(f32::from(self.read_u8()?) * 360.0 / 256.0)
+    }
+
+    /// `length` bytes.
+    fn read_bytes(&mut self, length: usize) -> Result<Vec<u8>, ClientError> {
+        let mut bytes: Vec<u8> = Vec::new();
+        <&mut Self as Read>::take(self, length as u64).read_to_end(&mut bytes)?;
+        if bytes.len() != length {
+            return Err(too_short(length, bytes.len()));
+        }
+        Ok(bytes)
+    }
+
+    /// Bytes after their length as a VarInt.
+    fn read_byte_array(&mut self) -> Result<Vec<u8>, ClientError> {
+        let length: i32 = self.read_varint()?;
+        if length < 0 {
+            return Err(format!("Negative array length {}", length).into());
+        }
+        self.read_bytes(length as usize)
+    }
+
+    /// A string of up to `max_length` characters.
+    fn read_string(&mut self, max_length: usize) -> Result<String, ClientError> {
+        McString::max(max_length).read(self)
+    }
+
+    /// A boolean, followed by the value if it's true.
+    fn read_optional<T>(
+        &mut self,
+        read: impl FnOnce(&mut Self) -> Result<T, ClientError>,
+    ) -> Result<Option<T>, ClientError> {
+        match self.read_bool()? {
+            true => Ok(Some(read(self)?)),
+            false => Ok(None),
+        }
+    }
+}
+
+impl<R: Read + ?Sized> McRead for R {}
+
+// Running out of bytes in the middle of a VarInt is an I/O error like with `read_exact`,
+// since reading the frame length from a closed connection ends up here
+fn read_varint_byte<R: Read + ?Sized>(reader: &mut R) -> Result<u8, ClientError> {
+    let mut byte: [u8; 1] = [0; 1];
+    reader.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+fn read_fixed<const N: usize>(reader: &mut (impl Read + ?Sized)) -> Result<[u8; N], ClientError> {
+    let mut bytes: [u8; N] = [0; N];
+    let mut filled: usize = 0;
+    while filled < N {
+        match reader.read(&mut bytes[filled..]) {
+            Ok(0) => return Err(too_short(N, filled)),
+            Ok(read) => filled += read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(bytes)
+}
+
+fn too_short(length: usize, remaining: usize) -> ClientError {
+    format!(
+        "Array of {} bytes with only {} left in the packet",
+        length, remaining
+    )
+    .into()
+}
@@ -0,0 +1,55 @@
+use std::io::Write;
+use std::net::IpAddr;
+
+use mc_varint::{VarInt, VarIntWrite};
+
+use crate::client::LoginPlugins;
+use crate::error::ClientError;
+use crate::hash::hmac_sha256;
+use crate::mcstring::McString;
+use crate::mojang::offline_uuid;
+
+// Velocity's "modern" forwarding: the backend asks for the player's details on this
+// channel, and the answer is signed with the secret it shares with the proxy. Only the
+// first version of the payload is sent, which every Velocity backend accepts.
+
+pub const PLAYER_INFO_CHANNEL: &str = "velocity:player_info";
+
+const MODERN_DEFAULT: i32 = 1;
+
+/// Builds the signed answer to a `velocity:player_info` request for an offline-mode
+/// player called `username`, connecting from `address`.
+pub fn player_info_response(
+    secret: &[u8],
+    username: &str,
+    address: IpAddr,
+) -> Result<Vec<u8>, ClientError> {
+    let mut data: Vec<u8> = Vec::new();
+    data.write_var_int(VarInt::from(MODERN_DEFAULT))?;
+    McString::DEFAULT.write(&mut data, &address.to_string())?;
+    data.write_all(&offline_uuid(username).to_be_bytes())?;
+    McString::USERNAME.write(&mut data, username)?;
+    data.write_var_int(VarInt::from(0))?; // no properties
+
+    let mut res: Vec<u8> = hmac_sha256(secret, &data).to_vec();
+    res.extend_from_slice(&data);
+    Ok(res)
+}
+
+/// Answers Velocity's forwarding request during login, so the client can join a backend
+/// server directly. `secret` is the `forwarding.secret` shared with the proxy.
+pub fn register(plugins: &mut LoginPlugins, secret: &str, username: &str, address: IpAddr) {
+    let secret: Vec<u8> = secret.as_bytes().to_vec();
+    let username: String = String::from(username);
+
+    plugins.register(PLAYER_INFO_CHANNEL, move |data| {
+        // The request carries the newest version the backend understands, if any
+        if data
+            .first()
+            .is_some_and(|version| i32::from(*version) < MODERN_DEFAULT)
+        {
+            return None;
+        }
+        player_info_response(&secret, &username, address).ok()
+    });
+}
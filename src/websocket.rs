@@ -0,0 +1,176 @@
+use std::io::{self, Read, Write};
+
+use base64::{engine::Engine, prelude::BASE64_STANDARD};
+
+// The server side of WebSocket framing (RFC 6455), enough for the API's event stream:
+// text messages, pings and closing. SHA-1 is only needed for the handshake, so it's
+// written out here instead of pulling in a crate.
+
+// Appended to the client's key before hashing it
+const HANDSHAKE_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+pub const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+pub const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+pub const OPCODE_PONG: u8 = 0xa;
+
+/// A message from the client.
+pub enum Message {
+    Text(String),
+    Binary,
+    /// Has to be answered with a pong carrying the same payload.
+    Ping(Vec<u8>),
+    Close,
+}
+
+/// The Sec-WebSocket-Accept header value for the client's Sec-WebSocket-Key.
+pub fn accept_key(key: &str) -> String {
+    BASE64_STANDARD.encode(sha1(format!("{}{}", key.trim(), HANDSHAKE_GUID).as_bytes()))
+}
+
+/// Reads the next message, putting fragmented ones back together. Messages longer than
+/// `max_length` bytes are an error.
+pub fn read(reader: &mut impl Read, max_length: usize) -> io::Result<Message> {
+    let mut message: Vec<u8> = Vec::new();
+    let mut message_opcode: Option<u8> = None;
+    loop {
+        let mut header: [u8; 2] = [0; 2];
+        reader.read_exact(&mut header)?;
+        let fin: bool = header[0] & 0x80 != 0;
+        let opcode: u8 = header[0] & 0x0f;
+        let masked: bool = header[1] & 0x80 != 0;
+        let length: u64 = match header[1] & 0x7f {
+            126 => {
+                let mut length: [u8; 2] = [0; 2];
+                reader.read_exact(&mut length)?;
+                u16::from_be_bytes(length) as u64
+            }
+            127 => {
+                let mut length: [u8; 8] = [0; 8];
+                reader.read_exact(&mut length)?;
+                u64::from_be_bytes(length)
+            }
+            length => length as u64,
+        };
+        if length > (max_length - message.len()) as u64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "WebSocket message too long",
+            ));
+        }
+
+        let mut mask: [u8; 4] = [0; 4];
+        if masked {
+            reader.read_exact(&mut mask)?;
+        }
+        let mut payload: Vec<u8> = vec![0; length as usize];
+        reader.read_exact(&mut payload)?;
+        if masked {
+            for (i, byte) in payload.iter_mut().enumerate() {
+                *byte ^= mask[i % 4];
+            }
+        }
+
+        // Control frames can come between the fragments of a message
+        match opcode {
+            OPCODE_CLOSE => return Ok(Message::Close),
+            OPCODE_PING => return Ok(Message::Ping(payload)),
+            OPCODE_PONG => continue,
+            OPCODE_TEXT | OPCODE_BINARY => message_opcode = Some(opcode),
+            OPCODE_CONTINUATION if message_opcode.is_some() => {}
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Unexpected WebSocket frame",
+                ));
+            }
+        }
+        message.extend(payload);
+        if !fin {
+            continue;
+        }
+        return match message_opcode {
+            Some(OPCODE_TEXT) => match String::from_utf8(message) {
+                Ok(text) => Ok(Message::Text(text)),
+                Err(_) => Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "WebSocket text isn't UTF-8",
+                )),
+            },
+            _ => Ok(Message::Binary),
+        };
+    }
+}
+
+/// Writes one unfragmented frame. Servers don't mask what they send.
+pub fn write(writer: &mut impl Write, opcode: u8, payload: &[u8]) -> io::Result<()> {
+    let mut frame: Vec<u8> = vec![0x80 | opcode];
+    match payload.len() {
+        0..=125 => frame.push(payload.len() as u8),
+        126..=0xffff => {
+            frame.push(126);
+            frame.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+        }
+        _ => {
+            frame.push(127);
+            frame.extend_from_slice(&(payload.len() as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    writer.write_all(&frame)
+}
+
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut state: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+
+    let bit_len: u64 = (data.len() as u64).wrapping_mul(8);
+    let mut padded: Vec<u8> = data.to_vec();
+    padded.push(0x80);
+    while padded.len() % 64 != 56 {
+        padded.push(0);
+    }
+    padded.extend_from_slice(&bit_len.to_be_bytes());
+
+    for block in padded.chunks(64) {
+        let mut w: [u32; 80] = [0; 80];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes(word.try_into().unwrap());
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = state;
+        for (i, word) in w.iter().enumerate() {
+            let (f, k): (u32, u32) = match i / 20 {
+                0 => ((b & c) | (!b & d), 0x5a827999),
+                1 => (b ^ c ^ d, 0x6ed9eba1),
+                2 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+                _ => (b ^ c ^ d, 0xca62c1d6),
+            };
+            let temp: u32 = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+
+        for (value, added) in state.iter_mut().zip([a, b, c, d, e]) {
+            *value = value.wrapping_add(added);
+        }
+    }
+
+    let mut res: [u8; 20] = [0; 20];
+    for (i, value) in state.iter().enumerate() {
+        res[i * 4..i * 4 + 4].copy_from_slice(&value.to_be_bytes());
+    }
+    res
+}
@@ -0,0 +1,64 @@
+use std::sync::Mutex;
+
+use azalea_chat::FormattedText;
+use serde::Deserialize;
+use serde_json::Value;
+
+// Private messages are recognized by the vanilla translation keys, which servers
+// without a chat plugin use for /msg, /tell and /w on every version.
+
+const INCOMING_KEY: &str = "commands.message.display.incoming";
+const OUTGOING_KEY: &str = "commands.message.display.outgoing";
+
+pub enum Direction {
+    Incoming,
+    Outgoing,
+}
+
+/// Returns whether `message` is a private message and the name of the other player.
+pub fn detect(message: &FormattedText) -> Option<(Direction, String)> {
+    let json: Value = serde_json::to_value(message).ok()?;
+    let direction: Direction = match json.get("translate")?.as_str()? {
+        INCOMING_KEY => Direction::Incoming,
+        OUTGOING_KEY => Direction::Outgoing,
+        _ => return None,
+    };
+
+    let name: &Value = json.get("with")?.as_array()?.first()?;
+    let name: String = match name {
+        Value::String(name) => name.clone(),
+        component => FormattedText::deserialize(component).ok()?.to_string(),
+    };
+    Some((direction, name))
+}
+
+/// The text of a private message, without the name and "whispers to you".
+pub fn text(message: &FormattedText) -> Option<String> {
+    let json: Value = serde_json::to_value(message).ok()?;
+    let text: &Value = json.get("with")?.as_array()?.get(1)?;
+    match text {
+        Value::String(text) => Some(text.clone()),
+        component => Some(FormattedText::deserialize(component).ok()?.to_string()),
+    }
+}
+
+/// Remembers who the last private message came from or went to, for `.r`.
+pub struct Conversation {
+    partner: Mutex<Option<String>>,
+}
+
+impl Conversation {
+    pub fn new() -> Conversation {
+        Conversation {
+            partner: Mutex::new(None),
+        }
+    }
+
+    pub fn partner(&self) -> Option<String> {
+        self.partner.lock().unwrap().clone()
+    }
+
+    pub fn set_partner(&self, name: &str) {
+        *self.partner.lock().unwrap() = Some(String::from(name));
+    }
+}
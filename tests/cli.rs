@@ -0,0 +1,76 @@
+// Runs the mc-chat binary the way scripts and cron jobs do, checking what ends up on
+// stdout and the exit code.
+
+use std::net::TcpListener;
+use std::process::{Command, Output};
+
+// A port nothing listens on
+fn closed_port() -> u16 {
+    let listener: TcpListener = TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap().port()
+}
+
+fn mc_chat(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_mc-chat"))
+        .args(args)
+        .args(["--connect-attempts", "1", "--no-color"])
+        .output()
+        .expect("mc-chat should run")
+}
+
+#[test]
+fn json_status_of_an_unreachable_server_is_a_down_object() {
+    let port: String = closed_port().to_string();
+    let output: Output = mc_chat(&["status", "--json", "-p", &port]);
+
+    assert_eq!(output.status.code(), Some(1));
+    let stdout: String = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 1, "{}", stdout);
+    assert!(lines[0].starts_with('{') && lines[0].contains("\"event\":\"down\""));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Status request failed"));
+}
+
+#[test]
+fn json_output_ends_with_a_disconnected_object_only() {
+    let port: String = closed_port().to_string();
+    let output: Output = mc_chat(&[
+        "--json",
+        "--skip-status",
+        "--no-history",
+        "--no-seen",
+        "-p",
+        &port,
+    ]);
+
+    assert_eq!(output.status.code(), Some(1));
+    let stdout: String = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout
+            .lines()
+            .all(|line| line.starts_with('{') && line.ends_with('}')),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("\"event\":\"disconnected\""));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("[MClient] Connection closed"));
+}
+
+#[test]
+fn send_to_an_unreachable_server_fails_with_the_connection_code() {
+    let port: String = closed_port().to_string();
+    let output: Output = mc_chat(&[
+        "send",
+        "--message",
+        "hello",
+        "--no-history",
+        "--no-seen",
+        "-p",
+        &port,
+    ]);
+
+    // Not a panic (101)
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("[MClient] Connection closed"));
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("panicked"));
+}
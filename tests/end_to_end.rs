@@ -0,0 +1,323 @@
+// Runs the client against the built-in test server, over real TCP connections on
+// localhost. Needs `--features testserver`.
+
+use std::net::TcpListener;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use mclient_project::mitm::{self, ConnectionState, Direction, ProxyEvent};
+use mclient_project::testserver::{TestServer, TestServerConfig};
+use mclient_project::{
+    Client, ClientConfig, ClientError, ConnectPolicy, ServerStatus, VirtualHost, mojang,
+};
+
+const WAIT: Duration = Duration::from_secs(5);
+
+fn start(config: TestServerConfig) -> TestServer {
+    TestServer::start(config).expect("the test server should start")
+}
+
+fn connect(server: &TestServer, username: &str, protocol: i32) -> Result<Client, ClientError> {
+    let mut config: ClientConfig = ClientConfig::new("127.0.0.1", Some(server.port()), username);
+    config.protocol = protocol;
+    config.connect.attempts = 1;
+    Client::connect_with(config)
+}
+
+// The plain text of every chat message the client receives
+fn messages(client: &Client) -> Receiver<String> {
+    let (sender, receiver): (Sender<String>, Receiver<String>) = mpsc::channel();
+    client.on_message(move |message| {
+        let _ = sender.send(message.to_string());
+    });
+    receiver
+}
+
+fn wait_until(condition: impl Fn() -> bool) -> bool {
+    let start: Instant = Instant::now();
+    while start.elapsed() < WAIT {
+        if condition() {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+    false
+}
+
+#[test]
+fn status_has_the_configured_motd_version_and_players() {
+    let server: TestServer = start(TestServerConfig {
+        motd: String::from("Integration tests"),
+        max_players: 7,
+        ..TestServerConfig::default()
+    });
+
+    let status: ServerStatus = mclient_project::fetch_status(
+        "127.0.0.1",
+        Some(server.port()),
+        None,
+        &ConnectPolicy::default(),
+        &VirtualHost::default(),
+    )
+    .unwrap();
+    assert_eq!(status.description.to_string(), "Integration tests");
+    assert_eq!(status.version.name, "1.16.5");
+    assert_eq!(status.version.protocol, 754);
+    let players = status.players.unwrap();
+    assert_eq!((players.online, players.max), (0, 7));
+}
+
+#[test]
+fn request_status_counts_logged_in_players() {
+    let server: TestServer = start(TestServerConfig::default());
+    let _client: Client = connect(&server, "Counted", 754).unwrap();
+    assert!(wait_until(|| server.players() == ["Counted"]));
+
+    let status: ServerStatus = mclient_project::request_status(
+        "127.0.0.1",
+        Some(server.port()),
+        None,
+        &ConnectPolicy::default(),
+        &VirtualHost::default(),
+    )
+    .unwrap();
+    assert_eq!(status.players.unwrap().online, 1);
+}
+
+#[test]
+fn logs_in_with_the_offline_uuid() {
+    let server: TestServer = start(TestServerConfig::default());
+    let client: Client = connect(&server, "Alice", 754).unwrap();
+
+    let identity = client.identity();
+    assert_eq!(identity.username, "Alice");
+    assert_eq!(identity.uuid, mojang::offline_uuid("Alice"));
+}
+
+#[test]
+fn chat_is_echoed_through_compression() {
+    let server: TestServer = start(TestServerConfig {
+        compression_threshold: Some(64),
+        ..TestServerConfig::default()
+    });
+    let client: Client = connect(&server, "Bob", 754).unwrap();
+    let received: Receiver<String> = messages(&client);
+
+    // Long enough to be compressed on the way there and back
+    let message: String = ["compress me"; 10].join(" ");
+    client.send_chat(&message).unwrap();
+    assert_eq!(
+        received.recv_timeout(WAIT).unwrap(),
+        format!("<Bob> {}", message)
+    );
+    assert_eq!(server.chat_messages(), [message]);
+}
+
+#[test]
+fn handlers_can_register_handlers() {
+    let server: TestServer = start(TestServerConfig::default());
+    let client: Arc<Client> = Arc::new(connect(&server, "Dave", 754).unwrap());
+
+    let (sender, received): (Sender<String>, Receiver<String>) = mpsc::channel();
+    let registered: AtomicBool = AtomicBool::new(false);
+    let inner: Arc<Client> = Arc::clone(&client);
+    client.on_message(move |_| {
+        if !registered.swap(true, Ordering::SeqCst) {
+            let sender: Sender<String> = sender.clone();
+            inner.on_message(move |message| {
+                let _ = sender.send(message.to_string());
+            });
+        }
+    });
+
+    client.send_chat("first").unwrap();
+    client.send_chat("second").unwrap();
+    assert_eq!(received.recv_timeout(WAIT).unwrap(), "<Dave> second");
+}
+
+#[test]
+fn chat_works_without_compression_on_1_12_2() {
+    let server: TestServer = start(TestServerConfig {
+        protocol: 340,
+        compression_threshold: None,
+        ..TestServerConfig::default()
+    });
+    let client: Client = connect(&server, "Carol", 340).unwrap();
+    let received: Receiver<String> = messages(&client);
+
+    assert_eq!(client.identity().uuid, mojang::offline_uuid("Carol"));
+    client.send_chat("hi").unwrap();
+    assert_eq!(received.recv_timeout(WAIT).unwrap(), "<Carol> hi");
+}
+
+#[test]
+fn commands_reach_the_server_without_an_echo() {
+    let server: TestServer = start(TestServerConfig::default());
+    let client: Client = connect(&server, "Dave", 754).unwrap();
+    let received: Receiver<String> = messages(&client);
+
+    client.send_chat("/help").unwrap();
+    assert!(wait_until(|| server.chat_messages() == ["/help"]));
+    assert!(received.recv_timeout(Duration::from_millis(200)).is_err());
+}
+
+#[test]
+fn broadcasts_arrive_as_system_messages() {
+    let server: TestServer = start(TestServerConfig::default());
+    let client: Client = connect(&server, "Erin", 754).unwrap();
+    let received: Receiver<String> = messages(&client);
+    assert!(wait_until(|| server.players() == ["Erin"]));
+
+    server.broadcast("Server restarting");
+    assert_eq!(received.recv_timeout(WAIT).unwrap(), "Server restarting");
+}
+
+#[test]
+fn team_prefixes_decorate_player_names() {
+    for protocol in [340, 754] {
+        let server: TestServer = start(TestServerConfig {
+            protocol,
+            ..TestServerConfig::default()
+        });
+        let client: Client = connect(&server, "Gwen", protocol).unwrap();
+        assert!(wait_until(|| server.players() == ["Gwen"]));
+
+        server.add_team("admins", "[Admin] ", " *", 12, &["Gwen"]);
+        assert!(wait_until(|| client.team_of("Gwen").is_some()));
+        assert_eq!(
+            client.decorated_name("Gwen").unwrap().to_string(),
+            "[Admin] Gwen *"
+        );
+        assert!(client.team_of("Hank").is_none());
+        assert!(client.decorated_name("Hank").is_none());
+    }
+}
+
+#[test]
+fn keep_alives_are_answered() {
+    let server: TestServer = start(TestServerConfig {
+        keep_alive_interval: Duration::from_millis(50),
+        ..TestServerConfig::default()
+    });
+    let _client: Client = connect(&server, "Frank", 754).unwrap();
+
+    assert!(wait_until(|| server.keep_alives_answered() >= 3));
+}
+
+#[test]
+fn another_version_is_kicked() {
+    let server: TestServer = start(TestServerConfig::default());
+
+    match connect(&server, "Grace", 340) {
+        Err(ClientError::Kicked(reason)) => {
+            assert_eq!(reason.to_string(), "Outdated client! Please use 1.16.5")
+        }
+        Err(e) => panic!("expected a kick, got {}", e),
+        Ok(_) => panic!("expected a kick, the client logged in"),
+    }
+}
+
+#[test]
+fn versions_without_a_packet_table_are_refused() {
+    let server: TestServer = start(TestServerConfig::default());
+
+    match connect(&server, "Grace", 762) {
+        Err(e) => assert!(e.to_string().contains("Unsupported protocol 762"), "{}", e),
+        Ok(_) => panic!("expected an error, the client logged in on 1.19.4"),
+    }
+    assert!(server.players().is_empty());
+}
+
+#[test]
+fn stopping_disconnects_the_client() {
+    let server: TestServer = start(TestServerConfig::default());
+    let client: Client = connect(&server, "Heidi", 754).unwrap();
+    let (sender, disconnected): (Sender<()>, Receiver<()>) = mpsc::channel();
+    client.on_disconnect(move |_| {
+        let _ = sender.send(());
+    });
+    assert!(wait_until(|| server.players() == ["Heidi"]));
+
+    server.stop();
+    assert!(disconnected.recv_timeout(WAIT).is_ok());
+}
+
+#[test]
+fn versions_with_configuration_are_refused() {
+    assert!(
+        TestServer::start(TestServerConfig {
+            protocol: 765,
+            ..TestServerConfig::default()
+        })
+        .is_err()
+    );
+}
+
+#[test]
+fn the_proxy_follows_compression_both_ways() {
+    let server: TestServer = start(TestServerConfig {
+        compression_threshold: Some(64),
+        ..TestServerConfig::default()
+    });
+    let listener: TcpListener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let proxy_port: u16 = listener.local_addr().unwrap().port();
+
+    type Seen = (Direction, ConnectionState, i32, Option<&'static str>, usize);
+    let seen: Arc<Mutex<Vec<Seen>>> = Arc::new(Mutex::new(Vec::new()));
+    let handler_seen: Arc<Mutex<Vec<Seen>>> = Arc::clone(&seen);
+    let server_port: u16 = server.port();
+    thread::spawn(move || {
+        mitm::run(
+            listener,
+            "127.0.0.1",
+            Some(server_port),
+            None,
+            &ConnectPolicy::default(),
+            move |event| {
+                if let ProxyEvent::Packet(packet) = event {
+                    handler_seen.lock().unwrap().push((
+                        packet.direction,
+                        packet.state,
+                        packet.id,
+                        packet.name,
+                        packet.data.len(),
+                    ));
+                }
+            },
+        )
+    });
+
+    let mut config: ClientConfig = ClientConfig::new("127.0.0.1", Some(proxy_port), "Eve");
+    config.protocol = 754;
+    config.connect.attempts = 1;
+    let client: Client = Client::connect_with(config).unwrap();
+    let received: Receiver<String> = messages(&client);
+
+    let message: String = ["compress me"; 10].join(" ");
+    client.send_chat(&message).unwrap();
+    assert_eq!(
+        received.recv_timeout(WAIT).unwrap(),
+        format!("<Eve> {}", message)
+    );
+    let has = |expected: &dyn Fn(&Seen) -> bool| seen.lock().unwrap().iter().any(expected);
+    assert!(has(
+        &|packet| packet.1 == ConnectionState::Handshaking && packet.3 == Some("Handshake")
+    ));
+    assert!(has(&|packet| packet.3 == Some("Set Compression")));
+    // Chat Message is 0x03 both ways in 1.16.5, and long enough to be compressed
+    assert!(has(&|packet| *packet
+        == (
+            Direction::Serverbound,
+            ConnectionState::Play,
+            0x03,
+            None,
+            message.len() + 1
+        )));
+    assert_eq!(server.chat_messages(), [message]);
+    assert!(has(&|packet| packet.0 == Direction::Clientbound
+        && packet.1 == ConnectionState::Play
+        && packet.3 == Some("Chat Message")));
+}